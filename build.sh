@@ -0,0 +1,25 @@
+#!/bin/sh
+# Builds the npm package into `pkg/`, along with the JavaScript wrappers that
+# wasm-pack doesn't generate.
+set -e
+
+wasm-pack build --release --scope=mongodb-rust "$@" -- --features wasm
+
+# Node can't load the bundler build above, so the Node stream wrapper comes
+# with a build for Node in `pkg/node/`, which is CommonJS whatever the type of
+# the package around it
+wasm-pack build --release --target nodejs --out-dir pkg/node "$@" -- \
+  --features wasm
+rm -f pkg/node/.gitignore pkg/node/README.md pkg/node/LICENSE*
+echo '{ "type": "commonjs" }' > pkg/node/package.json
+cp js/stream.js pkg/node/stream.js
+
+# wasm-pack only lists its own output in the files to publish
+node -e '
+  var fs = require("fs")
+  var pkg = JSON.parse(fs.readFileSync("pkg/package.json"))
+  pkg.files = pkg.files.concat(["node/"])
+  fs.writeFileSync("pkg/package.json", JSON.stringify(pkg, null, 2) + "\n")
+'
+
+node js/test/stream.js
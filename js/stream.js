@@ -0,0 +1,46 @@
+// Node stream wrapper around the wasm SchemaParser, mirroring the stream API
+// of the npm `mongodb-schema` package. Copied into `pkg/node/` by `build.sh`,
+// next to the build for Node, as Node can't load the bundler build at the
+// root of the package. It's CommonJS, so it can be both required and
+// imported.
+var Transform = require('stream').Transform
+var SchemaParser = require('./mongodb_schema_parser.js').SchemaParser
+
+// Returns a Transform stream that takes documents, one per write, and emits
+// the schema of all of them once the input ends. Documents can be json
+// strings or Buffers, i.e. lines from `split()`, raw BSON as a Uint8Array
+// with `{ raw: true }`, or plain objects.
+function schemaStream (options) {
+  var raw = Boolean(options && options.raw)
+  var schemaParser = new SchemaParser()
+
+  return new Transform({
+    writableObjectMode: true,
+    readableObjectMode: true,
+    transform (doc, encoding, callback) {
+      try {
+        if (raw) {
+          schemaParser.writeRaw(doc)
+        } else if (typeof doc === 'string' || Buffer.isBuffer(doc)) {
+          var json = doc.toString()
+          if (json.trim() !== '') schemaParser.writeJson(json)
+        } else {
+          schemaParser.writeObject(doc)
+        }
+      } catch (e) {
+        return callback(new Error('schema-parser: ' + e))
+      }
+      callback()
+    },
+    flush (callback) {
+      try {
+        callback(null, schemaParser.toObject())
+      } catch (e) {
+        callback(new Error('schema-parser: ' + e))
+      }
+    }
+  })
+}
+
+module.exports = schemaStream
+module.exports.schemaStream = schemaStream
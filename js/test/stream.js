@@ -0,0 +1,38 @@
+// Smoke test of the stream wrapper in plain Node, run by `build.sh` once the
+// package is built: pipes a file of newline delimited json through it.
+var assert = require('assert')
+var fs = require('fs')
+var path = require('path')
+var Transform = require('stream').Transform
+var schemaStream = require('../../pkg/node/stream.js')
+
+// writes one line at a time, as `split()` does
+function split () {
+  var rest = ''
+  return new Transform({
+    readableObjectMode: true,
+    transform (chunk, encoding, callback) {
+      var lines = (rest + chunk).split('\n')
+      rest = lines.pop()
+      lines.forEach(line => this.push(line))
+      callback()
+    },
+    flush (callback) {
+      callback(null, rest)
+    }
+  })
+}
+
+fs.createReadStream(path.join(__dirname, 'pets.json'))
+  .pipe(split())
+  .pipe(schemaStream())
+  .on('error', e => {
+    console.error(e)
+    process.exit(1)
+  })
+  .on('data', schema => {
+    assert.strictEqual(schema.count, 3)
+    assert.deepStrictEqual(Object.keys(schema.fields), ['name', 'type', 'age'])
+    assert.strictEqual(schema.fields.age.count, 2)
+    console.log('stream.js: ok')
+  })
@@ -0,0 +1,177 @@
+use super::{Bson, Field, FieldType, HashMap, IndexMap, ValueType};
+use crate::field_type;
+use bson::Document;
+
+/// Scores how unusual a document is compared to finalised fields, from 0.0
+/// for a document like most others to 1.0 for one that has nothing in common
+/// with them. Every field the document has or is expected to have gets a
+/// score, and the document's score is their mean:
+/// - fields the schema has never seen score 1.0,
+/// - fields the document has score how rare they are, how rare their type is
+///   and 1.0 if a number is outside the range of the sampled values,
+/// - fields the document is missing score how common they are.
+///
+/// Subdocuments and documents in arrays are scored field by field too.
+pub(crate) fn anomaly_score(
+  fields: &IndexMap<String, Field>,
+  doc: &Document,
+) -> f64 {
+  let mut scores = Vec::new();
+  score_document(fields, doc, None, &mut scores);
+  if scores.is_empty() {
+    return 0.0;
+  }
+  scores.iter().sum::<f64>() / scores.len() as f64
+}
+
+fn score_document(
+  fields: &IndexMap<String, Field>,
+  doc: &Document,
+  path: Option<String>,
+  scores: &mut Vec<f64>,
+) {
+  for (key, value) in doc {
+    let current_path = Field::get_path(key.to_owned(), path.to_owned());
+    let field = match fields.get(&current_path) {
+      Some(field) => field,
+      None => {
+        scores.push(1.0);
+        continue;
+      }
+    };
+    let present = f64::from(field.probability);
+    let rarity = 1.0 - present;
+    // type probabilities are relative to all documents, including the ones
+    // the field is missing from
+    let type_score = score_value(&field.types, present, value, scores);
+    scores.push(rarity.max(type_score));
+  }
+  for field in fields.values() {
+    if !doc.contains_key(&field.name) {
+      scores.push(f64::from(field.probability));
+    }
+  }
+}
+
+// Scores how rare a value's type is, and whether it's out of range. The
+// fields of subdocuments get scores of their own.
+fn score_value(
+  types: &HashMap<String, FieldType>,
+  total: f64,
+  value: &Bson,
+  scores: &mut Vec<f64>,
+) -> f64 {
+  let bson_type = FieldType::get_type(value);
+  let field_type = match field_type::counted_type(types, &bson_type) {
+    Some(field_type) => field_type,
+    None => return 1.0,
+  };
+  let share = if total > 0.0 {
+    f64::from(field_type.probability) / total
+  } else {
+    0.0
+  };
+  let mut score = 1.0 - share.min(1.0);
+
+  match value {
+    // documents past the max depth weren't analysed
+    Bson::Document(subdoc) => {
+      if let Some(schema) = field_type.schema() {
+        let path = Some(field_type.path.to_string());
+        score_document(&schema.fields, subdoc, path, scores);
+      }
+    }
+    // element type probabilities are relative to all elements seen
+    Bson::Array(elements) => {
+      for element in elements {
+        let element_score =
+          score_value(&field_type.types, 1.0, element, scores);
+        score = score.max(element_score);
+      }
+    }
+    _ => {
+      if is_out_of_range(field_type, value) {
+        score = 1.0;
+      }
+    }
+  }
+  score
+}
+
+// Whether a number is smaller or larger than the numbers seen for its type,
+// or for any numeric type if numbers were coalesced.
+fn is_out_of_range(field_type: &FieldType, value: &Bson) -> bool {
+  let value = match FieldType::get_value(value).as_ref().and_then(as_f64) {
+    Some(value) => value,
+    None => return false,
+  };
+  let seen = std::iter::once(field_type)
+    .chain(field_type.types())
+    .flat_map(|t| t.values().iter().chain(t.min.iter()).chain(t.max.iter()))
+    .filter_map(as_f64)
+    .filter(|num| !num.is_nan());
+  let (min, max) = seen.fold((None, None), |(min, max), num| {
+    (
+      Some(min.map_or(num, |min: f64| min.min(num))),
+      Some(max.map_or(num, |max: f64| max.max(num))),
+    )
+  });
+  match (min, max) {
+    (Some(min), Some(max)) => value < min || value > max,
+    _ => false,
+  }
+}
+
+fn as_f64(value: &ValueType) -> Option<f64> {
+  match value {
+    ValueType::I32(num) => Some(f64::from(*num)),
+    ValueType::I64(num) => Some(*num as f64),
+    ValueType::FloatingPoint(num) => Some(*num),
+    ValueType::Decimal128(num) => num.parse().ok(),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::SchemaParser;
+  use bson::{bson, doc};
+
+  fn fields() -> IndexMap<String, Field> {
+    let mut schema_parser = SchemaParser::new();
+    for age in 1..10 {
+      let json = format!(r#"{{"name": "Nori", "age": {}}}"#, age);
+      schema_parser.write_json(&json).unwrap();
+    }
+    schema_parser
+      .write_json(r#"{"name": "Rey", "age": "10", "owner": {"name": "Ana"}}"#)
+      .unwrap();
+    schema_parser.flush().fields
+  }
+
+  #[test]
+  fn it_scores_typical_documents_low() {
+    let doc = doc! { "name": "Chashu", "age": 5 };
+    // a tenth of ages are strings, and a tenth of documents have an owner
+    let score = anomaly_score(&fields(), &doc);
+    assert!((score - 0.2 / 3.0).abs() < 1e-6);
+  }
+
+  #[test]
+  fn it_scores_unusual_documents_high() {
+    let fields = fields();
+    let typical = anomaly_score(&fields, &doc! { "name": "Chashu", "age": 5 });
+    let rare = anomaly_score(
+      &fields,
+      &doc! { "name": "Chashu", "age": "5", "owner": { "name": "Ben" } },
+    );
+    let out_of_range =
+      anomaly_score(&fields, &doc! { "name": "Chashu", "age": 500 });
+    let unknown = anomaly_score(&fields, &doc! { "color": "black" });
+    assert!(rare > typical);
+    assert!(out_of_range > typical);
+    assert!(unknown > rare);
+    assert!(unknown <= 1.0);
+  }
+}
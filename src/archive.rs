@@ -0,0 +1,159 @@
+use crate::dump::{read_body, read_length};
+use crate::{ParseError, ParserOptions, SchemaParser};
+
+use bson::{decode_document, Document};
+use indexmap::IndexMap;
+use std::io::Read;
+
+// First four bytes of every archive, as a little endian u32.
+static MAGIC: u32 = 0x8199_e26d;
+// Length prefix that ends the collection list and each namespace block.
+static TERMINATOR: i32 = -1;
+
+/// Reads a `mongodump --archive` and writes the documents of each namespace
+/// to a SchemaParser of its own, keyed by namespace.
+///
+/// An archive starts with its magic number and a header document, then lists
+/// its collections, one metadata document each, up to a terminator. The rest
+/// are namespace blocks, in any order and interleaved between collections:
+/// a document naming the namespace, its documents and a terminator. A
+/// namespace's last block is marked `EOF` and has no documents.
+pub(crate) fn read<R: Read>(
+  mut reader: R,
+  options: &ParserOptions,
+) -> Result<IndexMap<String, SchemaParser>, ParseError> {
+  let mut magic = [0; 4];
+  reader
+    .read_exact(&mut magic)
+    .map_err(|_| invalid("missing magic number"))?;
+  if u32::from_le_bytes(magic) != MAGIC {
+    return Err(invalid("missing magic number"));
+  }
+  next_document(&mut reader, "header")?
+    .ok_or_else(|| invalid("missing header"))?;
+
+  let mut parsers = IndexMap::new();
+  // Collections are listed up front, so empty ones get a schema too.
+  while let Some(metadata) = next_document(&mut reader, "collection list")? {
+    parsers
+      .entry(namespace(&decode(&metadata)?)?)
+      .or_insert_with(|| SchemaParser::with_options(options.clone()));
+  }
+
+  while let Some(length) = read_length(&mut reader)? {
+    let header = decode(&read_body(&mut reader, length)?)?;
+    let namespace = namespace(&header)?;
+    let schema_parser = parsers
+      .entry(namespace.clone())
+      .or_insert_with(|| SchemaParser::with_options(options.clone()));
+    while let Some(bytes) = next_document(&mut reader, &namespace)? {
+      schema_parser.write_raw(&bytes)?;
+    }
+  }
+  parsers.sort_keys();
+  Ok(parsers)
+}
+
+// Reads the next document of a section, or None at its terminator.
+fn next_document<R: Read>(
+  reader: &mut R,
+  section: &str,
+) -> Result<Option<Vec<u8>>, ParseError> {
+  match read_length(reader)? {
+    Some(length) if length == TERMINATOR => Ok(None),
+    Some(length) => Ok(Some(read_body(reader, length)?)),
+    None => Err(invalid(&format!("archive ends inside {}", section))),
+  }
+}
+
+fn namespace(doc: &Document) -> Result<String, ParseError> {
+  match (doc.get_str("db"), doc.get_str("collection")) {
+    (Ok(db), Ok(collection)) => Ok(format!("{}.{}", db, collection)),
+    _ => Err(invalid("namespace without db or collection")),
+  }
+}
+
+fn decode(mut bytes: &[u8]) -> Result<Document, ParseError> {
+  Ok(decode_document(&mut bytes)?)
+}
+
+fn invalid(message: &str) -> ParseError {
+  ParseError::InvalidArchive(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use bson::{bson, doc, encode_document};
+
+  fn push(archive: &mut Vec<u8>, doc: Document) {
+    encode_document(archive, &doc).unwrap();
+  }
+
+  fn terminate(archive: &mut Vec<u8>) {
+    archive.extend_from_slice(&TERMINATOR.to_le_bytes());
+  }
+
+  fn block(archive: &mut Vec<u8>, collection: &str, docs: Vec<Document>) {
+    let eof = docs.is_empty();
+    push(
+      archive,
+      doc! { "db": "shelter", "collection": collection, "EOF": eof },
+    );
+    for doc in docs {
+      push(archive, doc);
+    }
+    terminate(archive);
+  }
+
+  fn archive() -> Vec<u8> {
+    let mut archive = MAGIC.to_le_bytes().to_vec();
+    push(&mut archive, doc! { "version": "0.1", "server": "4.2.0" });
+    for collection in &["pets", "owners", "visits"] {
+      push(
+        &mut archive,
+        doc! { "db": "shelter", "collection": *collection, "metadata": "" },
+      );
+    }
+    terminate(&mut archive);
+    block(&mut archive, "pets", vec![doc! { "name": "Nori" }]);
+    block(&mut archive, "owners", vec![doc! { "name": "Ana" }]);
+    block(&mut archive, "pets", vec![doc! { "name": "Rey", "age": 4 }]);
+    block(&mut archive, "pets", vec![]);
+    block(&mut archive, "owners", vec![]);
+    block(&mut archive, "visits", vec![]);
+    archive
+  }
+
+  #[test]
+  fn it_reads_interleaved_namespaces() {
+    let parsers = read(archive().as_slice(), &ParserOptions::default());
+    let parsers = parsers.unwrap();
+    let namespaces: Vec<&String> = parsers.keys().collect();
+    assert_eq!(
+      namespaces,
+      vec!["shelter.owners", "shelter.pets", "shelter.visits"]
+    );
+    assert_eq!(parsers["shelter.pets"].count, 2);
+    assert_eq!(parsers["shelter.pets"].fields().count(), 2);
+    assert_eq!(parsers["shelter.owners"].count, 1);
+    assert_eq!(parsers["shelter.visits"].count, 0);
+  }
+
+  #[test]
+  fn it_rejects_input_without_magic_number() {
+    let mut bson = Vec::new();
+    push(&mut bson, doc! { "name": "Nori" });
+    match read(bson.as_slice(), &ParserOptions::default()) {
+      Err(ParseError::InvalidArchive(_)) => (),
+      other => panic!("expected InvalidArchive, got {:?}", other.err()),
+    }
+  }
+
+  #[test]
+  fn it_fails_on_truncated_archives() {
+    let mut archive = archive();
+    archive.truncate(archive.len() - 4);
+    assert!(read(archive.as_slice(), &ParserOptions::default()).is_err());
+  }
+}
@@ -0,0 +1,313 @@
+use super::{Field, FieldType};
+use crate::field_type;
+use serde_json::{json, Value};
+
+/// Converts finalised fields into an Apache Arrow schema, in the JSON format
+/// Arrow uses for its integration tests. Subdocuments become structs, arrays
+/// become lists and fields with several types become dense unions. Fields
+/// that are missing or null in some documents are nullable.
+pub(crate) fn to_arrow_schema<'a>(
+  fields: impl Iterator<Item = &'a Field>,
+) -> Value {
+  json!({ "fields": arrow_fields(fields) })
+}
+
+/// Converts finalised fields into a Parquet schema message called `name`.
+/// Parquet has no unions, so fields with several types are stored as
+/// strings.
+pub(crate) fn to_parquet_schema<'a>(
+  fields: impl Iterator<Item = &'a Field>,
+  name: &str,
+) -> String {
+  let mut parquet = format!("message {} {{\n", parquet_name(name));
+  parquet_fields(&mut parquet, fields, 1);
+  parquet.push_str("}\n");
+  parquet
+}
+
+fn arrow_fields<'a>(fields: impl Iterator<Item = &'a Field>) -> Vec<Value> {
+  fields
+    .map(|field| {
+      let nullable = field.probability < 1.0 || is_nullable(field.types());
+      arrow_field(&field.name, nullable, field.types())
+    })
+    .collect()
+}
+
+fn arrow_field<'a>(
+  name: &str,
+  nullable: bool,
+  types: impl Iterator<Item = &'a FieldType>,
+) -> Value {
+  let types = value_types(types);
+  let (arrow_type, children) = match types.len() {
+    0 => (json!({ "name": "null" }), Vec::new()),
+    1 => arrow_type(types[0]),
+    _ => {
+      let type_ids: Vec<usize> = (0..types.len()).collect();
+      let children = types
+        .iter()
+        .map(|t| arrow_field(&t.bson_type, true, std::iter::once(*t)))
+        .collect();
+      let union =
+        json!({ "name": "union", "mode": "DENSE", "typeIds": type_ids });
+      (union, children)
+    }
+  };
+  json!({
+    "name": name,
+    "nullable": nullable,
+    "type": arrow_type,
+    "children": children
+  })
+}
+
+fn arrow_type(field_type: &FieldType) -> (Value, Vec<Value>) {
+  let arrow_type = match field_type.bson_type.as_str() {
+    t if t == field_type::DOCUMENT => match field_type.schema() {
+      Some(schema) => {
+        return (json!({ "name": "struct" }), arrow_fields(schema.fields()))
+      }
+      // documents past the max depth are kept as json strings
+      None => json!({ "name": "utf8" }),
+    },
+    t if t == field_type::ARRAY => {
+      let nullable = is_nullable(field_type.types());
+      let item = arrow_field("item", nullable, field_type.types());
+      return (json!({ "name": "list" }), vec![item]);
+    }
+    t if t == field_type::FLOATING_POINT || t == field_type::NUMBER => {
+      json!({ "name": "floatingpoint", "precision": "DOUBLE" })
+    }
+    t if t == field_type::I32 => {
+      json!({ "name": "int", "bitWidth": 32, "isSigned": true })
+    }
+    t if t == field_type::I64 => {
+      json!({ "name": "int", "bitWidth": 64, "isSigned": true })
+    }
+    t if t == field_type::TIMESTAMP => {
+      json!({ "name": "int", "bitWidth": 64, "isSigned": false })
+    }
+    t if t == field_type::UTCDATE_TIME => {
+      json!({ "name": "timestamp", "unit": "MILLISECOND", "timezone": "UTC" })
+    }
+    t if t == field_type::BOOLEAN => json!({ "name": "bool" }),
+    t if t == field_type::BINARY => json!({ "name": "binary" }),
+    // ObjectIds, Decimal128s without a fixed scale, regular expressions, code
+    // and symbols are kept as strings
+    _ => json!({ "name": "utf8" }),
+  };
+  (arrow_type, Vec::new())
+}
+
+fn parquet_fields<'a>(
+  parquet: &mut String,
+  fields: impl Iterator<Item = &'a Field>,
+  depth: usize,
+) {
+  for field in fields {
+    let optional = field.probability < 1.0 || is_nullable(field.types());
+    let repetition = if optional { "optional" } else { "required" };
+    let name = parquet_name(&field.name);
+    parquet_node(parquet, depth, repetition, &name, field.types());
+  }
+}
+
+// Arrays use Parquet's three level LIST structure.
+fn parquet_node<'a>(
+  parquet: &mut String,
+  depth: usize,
+  repetition: &str,
+  name: &str,
+  types: impl Iterator<Item = &'a FieldType>,
+) {
+  let indent = "  ".repeat(depth);
+  let types = value_types(types);
+  let nested = match types.as_slice() {
+    [field_type] => Some(*field_type),
+    _ => None,
+  };
+
+  match nested {
+    Some(field_type) if field_type.bson_type == field_type::ARRAY => {
+      let element = if is_nullable(field_type.types()) {
+        "optional"
+      } else {
+        "required"
+      };
+      parquet.push_str(&format!(
+        "{}{} group {} (LIST) {{\n{}  repeated group list {{\n",
+        indent, repetition, name, indent
+      ));
+      parquet_node(parquet, depth + 2, element, "element", field_type.types());
+      parquet.push_str(&format!("{}  }}\n{}}}\n", indent, indent));
+    }
+    Some(field_type) if field_type.schema().is_some() => {
+      parquet
+        .push_str(&format!("{}{} group {} {{\n", indent, repetition, name));
+      if let Some(schema) = field_type.schema() {
+        parquet_fields(parquet, schema.fields(), depth + 1);
+      }
+      parquet.push_str(&format!("{}}}\n", indent));
+    }
+    _ => {
+      let (primitive, annotation) = match nested {
+        Some(field_type) => (
+          parquet_primitive(&field_type.bson_type),
+          parquet_annotation(&field_type.bson_type),
+        ),
+        None => ("binary", Some("STRING")),
+      };
+      let annotation = match annotation {
+        Some(annotation) => format!(" ({})", annotation),
+        None => String::new(),
+      };
+      parquet.push_str(&format!(
+        "{}{} {} {}{};\n",
+        indent, repetition, primitive, name, annotation
+      ));
+    }
+  }
+}
+
+fn parquet_primitive(bson_type: &str) -> &'static str {
+  match bson_type {
+    t if t == field_type::FLOATING_POINT || t == field_type::NUMBER => "double",
+    t if t == field_type::I32 => "int32",
+    t if t == field_type::I64
+      || t == field_type::TIMESTAMP
+      || t == field_type::UTCDATE_TIME =>
+    {
+      "int64"
+    }
+    t if t == field_type::BOOLEAN => "boolean",
+    _ => "binary",
+  }
+}
+
+fn parquet_annotation(bson_type: &str) -> Option<&'static str> {
+  match bson_type {
+    t if t == field_type::UTCDATE_TIME => Some("TIMESTAMP(MILLIS,true)"),
+    t if t == field_type::TIMESTAMP => Some("INT(64,false)"),
+    t if t == field_type::BINARY => None,
+    // documents past the max depth are stored as json
+    t if t == field_type::DOCUMENT => Some("JSON"),
+    _ if parquet_primitive(bson_type) == "binary" => Some("STRING"),
+    _ => None,
+  }
+}
+
+// Types of the values a field has, most common first. Nulls and missing
+// values only make a field nullable.
+fn value_types<'a>(
+  types: impl Iterator<Item = &'a FieldType>,
+) -> Vec<&'a FieldType> {
+  let mut types: Vec<&FieldType> = types
+    .filter(|t| {
+      t.bson_type != field_type::NULL && t.bson_type != field_type::UNDEFINED
+    })
+    .collect();
+  types.sort_by(|a, b| {
+    b.count
+      .cmp(&a.count)
+      .then_with(|| a.bson_type.cmp(&b.bson_type))
+  });
+  types
+}
+
+fn is_nullable<'a>(mut types: impl Iterator<Item = &'a FieldType>) -> bool {
+  types.any(|t| {
+    t.bson_type == field_type::NULL || t.bson_type == field_type::UNDEFINED
+  })
+}
+
+// Parquet's schema syntax has no quoting, so names may only contain letters,
+// digits and underscores.
+fn parquet_name(name: &str) -> String {
+  name
+    .chars()
+    .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::SchemaParser;
+
+  fn schema_parser() -> SchemaParser {
+    let mut schema_parser = SchemaParser::new();
+    let json_str1 = r#"{"name": "Nori", "age": 5, "tags": ["cat"]}"#;
+    let json_str2 = r#"{"name": null, "age": "3", "owner": {"name": "Ana"}}"#;
+    schema_parser.write_json(&json_str1).unwrap();
+    schema_parser.write_json(&json_str2).unwrap();
+    schema_parser
+  }
+
+  #[test]
+  fn it_converts_to_arrow_schema() {
+    let schema = schema_parser().flush();
+    let arrow = to_arrow_schema(schema.fields());
+    let utf8 = json!({ "name": "utf8" });
+    let int32 = json!({ "name": "int", "bitWidth": 32, "isSigned": true });
+    let field = |name: &str, nullable: bool, arrow_type: &Value| {
+      json!({
+        "name": name,
+        "nullable": nullable,
+        "type": arrow_type,
+        "children": []
+      })
+    };
+    assert_eq!(
+      arrow,
+      json!({
+        "fields": [
+          field("name", true, &utf8),
+          {
+            "name": "age",
+            "nullable": false,
+            "type": { "name": "union", "mode": "DENSE", "typeIds": [0, 1] },
+            "children": [
+              field("Int32", true, &int32),
+              field("String", true, &utf8)
+            ]
+          },
+          {
+            "name": "tags",
+            "nullable": true,
+            "type": { "name": "list" },
+            "children": [field("item", false, &utf8)]
+          },
+          {
+            "name": "owner",
+            "nullable": true,
+            "type": { "name": "struct" },
+            "children": [field("name", false, &utf8)]
+          }
+        ]
+      })
+    );
+  }
+
+  #[test]
+  fn it_converts_to_parquet_schema() {
+    let schema = schema_parser().flush();
+    let parquet = to_parquet_schema(schema.fields(), "pets");
+    assert_eq!(
+      parquet,
+      r#"message pets {
+  optional binary name (STRING);
+  required binary age (STRING);
+  optional group tags (LIST) {
+    repeated group list {
+      required binary element (STRING);
+    }
+  }
+  optional group owner {
+    required binary name (STRING);
+  }
+}
+"#
+    );
+  }
+}
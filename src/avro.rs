@@ -0,0 +1,177 @@
+use super::{Field, FieldType};
+use crate::field_type;
+use serde_json::{json, Map, Value};
+
+/// Converts finalised fields into an Avro record schema named `name`. Fields
+/// with several types become unions, and fields that are missing or null in
+/// some documents become optional unions with "null" and a null default.
+pub(crate) fn to_avro<'a>(
+  fields: impl Iterator<Item = &'a Field>,
+  name: &str,
+) -> Value {
+  record(fields, &avro_name(name))
+}
+
+fn record<'a>(fields: impl Iterator<Item = &'a Field>, name: &str) -> Value {
+  let fields: Vec<Value> = fields
+    .map(|field| {
+      let mut avro_field = Map::new();
+      let name = avro_name(&field.name);
+      avro_field.insert("name".to_string(), Value::from(name));
+      let mut types = union(field.types());
+      if field.probability < 1.0 && !types.contains(&Value::from("null")) {
+        types.insert(0, Value::from("null"));
+      }
+      let optional = types.first() == Some(&Value::from("null"));
+      avro_field.insert("type".to_string(), from_union(types));
+      if optional {
+        avro_field.insert("default".to_string(), Value::Null);
+      }
+      Value::Object(avro_field)
+    })
+    .collect();
+  json!({ "type": "record", "name": name, "fields": fields })
+}
+
+// Avro types of all field types, without duplicates. "null" always goes first,
+// so it can be used as the default value.
+fn union<'a>(types: impl Iterator<Item = &'a FieldType>) -> Vec<Value> {
+  let mut types: Vec<&FieldType> = types.collect();
+  types.sort_by(|a, b| a.bson_type.cmp(&b.bson_type));
+
+  let mut union = Vec::new();
+  for field_type in types {
+    let avro_type = match avro_type(field_type) {
+      Some(avro_type) => avro_type,
+      None => continue,
+    };
+    if union.contains(&avro_type) {
+      continue;
+    }
+    if avro_type == "null" {
+      union.insert(0, avro_type);
+    } else {
+      union.push(avro_type);
+    }
+  }
+  union
+}
+
+fn from_union(mut union: Vec<Value>) -> Value {
+  match union.len() {
+    0 => Value::from("null"),
+    1 => union.remove(0),
+    _ => Value::Array(union),
+  }
+}
+
+fn avro_type(field_type: &FieldType) -> Option<Value> {
+  let bson_type = field_type.bson_type.as_str();
+  let avro_type = match bson_type {
+    t if t == field_type::DOCUMENT => match field_type.schema() {
+      Some(schema) => record(schema.fields(), &avro_name(&field_type.path)),
+      // documents past the max depth weren't analysed, so only their values'
+      // primitive types can be assumed
+      None => json!({
+        "type": "map",
+        "values": ["null", "boolean", "int", "long", "double", "string"]
+      }),
+    },
+    t if t == field_type::ARRAY => {
+      let items = from_union(union(field_type.types()));
+      json!({ "type": "array", "items": items })
+    }
+    t if t == field_type::UTCDATE_TIME => {
+      json!({ "type": "long", "logicalType": "timestamp-millis" })
+    }
+    t if t == field_type::FLOATING_POINT || t == field_type::NUMBER => {
+      json!("double")
+    }
+    t if t == field_type::I32 => json!("int"),
+    t if t == field_type::I64 || t == field_type::TIMESTAMP => json!("long"),
+    t if t == field_type::BOOLEAN => json!("boolean"),
+    t if t == field_type::BINARY => json!("bytes"),
+    t if t == field_type::NULL => json!("null"),
+    t if t == field_type::UNDEFINED => return None,
+    // Avro decimals need a fixed scale, which Decimal128 values don't have,
+    // so they are kept as strings along with ObjectIds, regular expressions,
+    // code and symbols.
+    _ => json!("string"),
+  };
+  Some(avro_type)
+}
+
+// Avro names may only contain letters, digits and underscores, and can't
+// start with a digit.
+fn avro_name(name: &str) -> String {
+  let mut avro_name: String = name
+    .chars()
+    .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+    .collect();
+  let starts_with_digit = avro_name.starts_with(|c: char| c.is_ascii_digit());
+  if avro_name.is_empty() || starts_with_digit {
+    avro_name.insert(0, '_');
+  }
+  avro_name
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::SchemaParser;
+
+  #[test]
+  fn it_converts_to_avro() {
+    let mut schema_parser = SchemaParser::new();
+    let json_str1 = r#"{"name": "Nori", "age": 5, "tags": ["cat"]}"#;
+    let json_str2 = r#"{"name": "Rey", "age": "3", "owner": {"name": "Ana"}}"#;
+    schema_parser.write_json(&json_str1).unwrap();
+    schema_parser.write_json(&json_str2).unwrap();
+    let schema = schema_parser.flush();
+    let avro = to_avro(schema.fields(), "pets");
+    assert_eq!(
+      avro,
+      json!({
+        "type": "record",
+        "name": "pets",
+        "fields": [
+          { "name": "name", "type": "string" },
+          { "name": "age", "type": ["int", "string"] },
+          {
+            "name": "tags",
+            "type": ["null", { "type": "array", "items": "string" }],
+            "default": null
+          },
+          {
+            "name": "owner",
+            "type": ["null", {
+              "type": "record",
+              "name": "owner",
+              "fields": [{ "name": "name", "type": "string" }]
+            }],
+            "default": null
+          }
+        ]
+      })
+    );
+  }
+
+  #[test]
+  fn it_makes_null_fields_optional() {
+    let mut schema_parser = SchemaParser::new();
+    schema_parser.write_json(r#"{"name": "Nori"}"#).unwrap();
+    schema_parser.write_json(r#"{"name": null}"#).unwrap();
+    let schema = schema_parser.flush();
+    let avro = to_avro(schema.fields(), "pets");
+    assert_eq!(
+      avro["fields"][0],
+      json!({ "name": "name", "type": ["null", "string"], "default": null })
+    );
+  }
+
+  #[test]
+  fn it_sanitises_names() {
+    assert_eq!(avro_name("owner.first-name"), "owner_first_name");
+    assert_eq!(avro_name("2fa"), "_2fa");
+  }
+}
@@ -0,0 +1,85 @@
+use mongodb_schema_parser::{Schema, SchemaDiff};
+use std::error::Error;
+use std::fs;
+
+/// Reads a schema previously written by `mongodb-schema` or `to_json()`.
+pub fn read_schema(path: &str) -> Result<Schema, Box<dyn Error>> {
+  let json =
+    fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+  let schema =
+    serde_json::from_str(&json).map_err(|e| format!("{}: {}", path, e))?;
+  Ok(schema)
+}
+
+/// Describes the structural changes between two schemas, one line each:
+/// `+ path` for added fields, `- path` for removed fields, and
+/// `~ path: +Type -Type` for fields that gained or lost types. Changes in how
+/// common fields and types are aren't listed, as they shift with every
+/// sample.
+pub fn changes(diff: &SchemaDiff) -> Vec<String> {
+  let mut lines = Vec::new();
+  for path in &diff.added {
+    lines.push(format!("+ {}", path));
+  }
+  for path in &diff.removed {
+    lines.push(format!("- {}", path));
+  }
+  for field in &diff.changed {
+    let types: Vec<String> = field
+      .types
+      .iter()
+      .filter_map(|t| {
+        if t.probability_before == 0.0 {
+          Some(format!("+{}", t.bson_type))
+        } else if t.probability_after == 0.0 {
+          Some(format!("-{}", t.bson_type))
+        } else {
+          None
+        }
+      })
+      .collect();
+    if !types.is_empty() {
+      lines.push(format!("~ {}: {}", field.path, types.join(" ")));
+    }
+  }
+  lines
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use mongodb_schema_parser::SchemaParser;
+
+  fn schema(documents: &[&str]) -> Schema {
+    let mut schema_parser = SchemaParser::new();
+    for json in documents {
+      schema_parser.write_json(json).unwrap();
+    }
+    schema_parser.flush()
+  }
+
+  #[test]
+  fn it_lists_added_removed_and_retyped_fields() {
+    let before = schema(&[r#"{"name": "Nori", "age": 5, "nick": "N"}"#]);
+    let after = schema(&[
+      r#"{"name": "Rey", "age": "4", "phone": "555"}"#,
+      r#"{"name": "Ana", "age": 3, "phone": "556"}"#,
+    ]);
+    assert_eq!(
+      changes(&before.diff(&after)),
+      vec!["+ phone", "- nick", "~ age: +String"]
+    );
+  }
+
+  #[test]
+  fn it_ignores_shifts_in_probability() {
+    let before = schema(&[r#"{"name": "Nori"}"#, r#"{"name": null}"#]);
+    let after = schema(&[
+      r#"{"name": "Nori"}"#,
+      r#"{"name": null}"#,
+      r#"{"name": "Rey"}"#,
+    ]);
+    assert!(!before.diff(&after).is_empty());
+    assert!(changes(&before.diff(&after)).is_empty());
+  }
+}
@@ -0,0 +1,40 @@
+use mongodb_schema_parser::{ParseError, SchemaParser};
+use std::fs::File;
+use std::path::Path;
+
+/// Writes the documents of an input file to `schema_parser`: Bson documents
+/// one after another, as written by `mongodump`, for `.bson` files, and
+/// newline delimited json for anything else. Gzip and zstd compressed files
+/// are decompressed on the fly, i.e. `users.bson.gz`.
+pub fn read_file(
+  schema_parser: &mut SchemaParser,
+  path: &Path,
+) -> Result<(), ParseError> {
+  let file = File::open(path)?;
+  match extension(path) {
+    Some("bson") => schema_parser.read_bson_from(file),
+    _ => schema_parser.read_from(file),
+  }
+}
+
+// Extension of the file before it was compressed.
+fn extension(path: &Path) -> Option<&str> {
+  let extension = path.extension()?.to_str()?;
+  match extension {
+    "gz" | "zst" => Path::new(path.file_stem()?).extension()?.to_str(),
+    _ => Some(extension),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_finds_extensions_of_compressed_files() {
+    assert_eq!(extension(Path::new("dump/users.bson")), Some("bson"));
+    assert_eq!(extension(Path::new("dump/users.bson.gz")), Some("bson"));
+    assert_eq!(extension(Path::new("users.json.zst")), Some("json"));
+    assert_eq!(extension(Path::new("users.gz")), None);
+  }
+}
@@ -0,0 +1,247 @@
+//! Command line interface of the schema parser, built with the `cli` feature:
+//! ```sh
+//! mongodb-schema users.json more-users.bson
+//! mongodb-schema analyze mongodb://localhost:27017 --db app --coll users
+//! mongodb-schema diff old.json new.json
+//! mongodb-schema users.json --format typescript
+//! mongodb-schema watch dump/*.json --interval 30s
+//! ```
+
+use clap::{crate_version, App, AppSettings, Arg, ArgMatches, SubCommand};
+use mongodb::Client;
+use mongodb_schema_parser::{
+  AnalyzeOptions, Schema, SchemaParser, DEFAULT_SAMPLE_SIZE,
+};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::process;
+use std::thread;
+
+mod diff;
+mod input;
+mod output;
+mod watch;
+
+// Exit codes follow diff(1): 1 if schemas differ, 2 if anything went wrong.
+static EXIT_DIFFERENT: i32 = 1;
+static EXIT_ERROR: i32 = 2;
+
+fn main() {
+  let default_sample_size = DEFAULT_SAMPLE_SIZE.to_string();
+  let matches = App::new("mongodb-schema")
+    .version(crate_version!())
+    .about("Infers a probabilistic schema for a MongoDB collection")
+    .setting(AppSettings::SubcommandsNegateReqs)
+    .arg(
+      Arg::with_name("files")
+        .help("Newline delimited json or .bson files of the collection")
+        .required(true)
+        .multiple(true),
+    )
+    .arg(
+      Arg::with_name("out")
+        .help("File to write the schema to instead of printing it")
+        .long("out")
+        .short("o")
+        .takes_value(true)
+        .global(true),
+    )
+    .arg(
+      Arg::with_name("format")
+        .help("Format to write the schema in")
+        .long("format")
+        .short("f")
+        .takes_value(true)
+        .possible_values(output::FORMATS)
+        .default_value(output::FORMATS[0])
+        .global(true),
+    )
+    .arg(
+      Arg::with_name("name")
+        .help("Type name for typescript and avro, i.e. the collection name")
+        .long("name")
+        .takes_value(true)
+        .global(true),
+    )
+    .subcommand(
+      SubCommand::with_name("analyze")
+        .about("Samples a collection of a running MongoDB deployment")
+        .arg(
+          Arg::with_name("uri")
+            .help("Connection string, i.e. mongodb://localhost:27017")
+            .required(true),
+        )
+        .arg(
+          Arg::with_name("db")
+            .help("Database of the collection")
+            .long("db")
+            .takes_value(true)
+            .required(true),
+        )
+        .arg(
+          Arg::with_name("coll")
+            .help("Collection to sample")
+            .long("coll")
+            .takes_value(true)
+            .required(true),
+        )
+        .arg(
+          Arg::with_name("sample")
+            .help("Number of documents to sample")
+            .long("sample")
+            .takes_value(true)
+            .default_value(&default_sample_size),
+        ),
+    )
+    .subcommand(
+      SubCommand::with_name("diff")
+        .about("Lists fields and types added or removed between two schemas")
+        .arg(
+          Arg::with_name("before")
+            .help("Schema json written before, i.e. by an earlier run")
+            .required(true),
+        )
+        .arg(
+          Arg::with_name("after")
+            .help("Schema json to compare it with")
+            .required(true),
+        ),
+    )
+    .subcommand(
+      SubCommand::with_name("watch")
+        .about("Writes the schema of files again whenever they change")
+        .arg(
+          Arg::with_name("files")
+            .help("Newline delimited json or .bson files of the collection")
+            .required(true)
+            .multiple(true),
+        )
+        .arg(
+          Arg::with_name("interval")
+            .help("How often to check files for changes, i.e. 30s or 5m")
+            .long("interval")
+            .takes_value(true)
+            .default_value("30s"),
+        ),
+    )
+    .get_matches();
+
+  let result = match matches.subcommand() {
+    ("analyze", Some(matches)) => analyze(matches),
+    ("diff", Some(matches)) => diff(matches),
+    ("watch", Some(matches)) => watch(matches),
+    _ => read(&matches),
+  };
+  if let Err(e) = result {
+    eprintln!("mongodb-schema: {}", e);
+    process::exit(EXIT_ERROR);
+  }
+}
+
+fn read(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+  let files = files(matches);
+  let schema = read_files(&files)?;
+  write(matches, &schema, collection_name(&files))
+}
+
+// Reads every file again once any of them has changed, as files can be
+// rewritten as well as appended to. Errors are reported without stopping, as
+// a file being written can be incomplete until the next check.
+fn watch(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+  let interval = watch::parse_interval(matches.value_of("interval").unwrap())?;
+  let files = files(matches);
+  let mut read_stamps = None;
+  loop {
+    let stamps = watch::stamps(&files);
+    if read_stamps.as_ref() != Some(&stamps) {
+      let written = read_files(&files)
+        .and_then(|schema| write(matches, &schema, collection_name(&files)));
+      match written {
+        Ok(()) => read_stamps = Some(stamps),
+        Err(e) => eprintln!("mongodb-schema: {}", e),
+      }
+    }
+    thread::sleep(interval);
+  }
+}
+
+fn files<'a>(matches: &'a ArgMatches) -> Vec<&'a Path> {
+  matches
+    .values_of("files")
+    .into_iter()
+    .flatten()
+    .map(Path::new)
+    .collect()
+}
+
+// Every file is part of the same collection, so they're written to a single
+// SchemaParser.
+fn read_files(files: &[&Path]) -> Result<Schema, Box<dyn Error>> {
+  let mut schema_parser = SchemaParser::new();
+  for file in files {
+    input::read_file(&mut schema_parser, file)
+      .map_err(|e| format!("{}: {}", file.display(), e))?;
+  }
+  Ok(schema_parser.into_schema())
+}
+
+// The collection is named after the first file, i.e. users.json.
+fn collection_name<'a>(files: &[&'a Path]) -> &'a str {
+  files
+    .first()
+    .and_then(|file| file.file_stem())
+    .and_then(|stem| stem.to_str())
+    .unwrap_or("collection")
+}
+
+fn analyze(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+  let sample_size = matches
+    .value_of("sample")
+    .unwrap_or_default()
+    .parse()
+    .map_err(|e| format!("--sample: {}", e))?;
+  let name = matches.value_of("coll").unwrap();
+  let client = Client::with_uri_str(matches.value_of("uri").unwrap())?;
+  let collection = client
+    .database(matches.value_of("db").unwrap())
+    .collection(name);
+  let options = AnalyzeOptions {
+    sample_size: Some(sample_size),
+    ..AnalyzeOptions::default()
+  };
+  let schema = SchemaParser::analyze_collection(&collection, options)?;
+  write(matches, &schema, name)
+}
+
+fn diff(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+  let before = diff::read_schema(matches.value_of("before").unwrap())?;
+  let after = diff::read_schema(matches.value_of("after").unwrap())?;
+  let changes = diff::changes(&before.diff(&after));
+  for change in &changes {
+    println!("{}", change);
+  }
+  if !changes.is_empty() {
+    process::exit(EXIT_DIFFERENT);
+  }
+  Ok(())
+}
+
+// Writes the schema in the format from `--format`, with the type name from
+// `--name` or else `name`.
+fn write(
+  matches: &ArgMatches,
+  schema: &Schema,
+  name: &str,
+) -> Result<(), Box<dyn Error>> {
+  let format = matches.value_of("format").unwrap_or(output::FORMATS[0]);
+  let name = matches.value_of("name").unwrap_or(name);
+  let output = output::render(schema, format, name)?;
+  match matches.value_of("out") {
+    Some(out) => {
+      fs::write(out, output + "\n").map_err(|e| format!("{}: {}", out, e))?
+    }
+    None => println!("{}", output),
+  }
+  Ok(())
+}
@@ -0,0 +1,78 @@
+use mongodb_schema_parser::{ParserOptions, Schema};
+use std::error::Error;
+
+/// Values of `--format`, the first of which is the default.
+pub static FORMATS: &[&str] = &[
+  "json",
+  "json-schema",
+  "validator",
+  "typescript",
+  "avro",
+  "markdown",
+];
+
+/// Renders a schema with the library's exporter for `format`. Required
+/// fields and unknown fields are treated as with default parser options.
+///
+/// # Arguments
+/// * `format` - One of `FORMATS`.
+/// * `name` - Name of the collection, for formats that name the type.
+pub fn render(
+  schema: &Schema,
+  format: &str,
+  name: &str,
+) -> Result<String, Box<dyn Error>> {
+  let options = ParserOptions::default();
+  let output = match format {
+    "json" => schema.to_json()?,
+    "json-schema" => serde_json::to_string_pretty(
+      &schema.to_json_schema(options.required_threshold),
+    )?,
+    "validator" => serde_json::to_string_pretty(&schema.to_mongodb_validator(
+      options.required_threshold,
+      options.additional_properties,
+    ))?,
+    "typescript" => schema.to_typescript(name, options.required_threshold),
+    "avro" => serde_json::to_string_pretty(&schema.to_avro(name))?,
+    "markdown" => schema.to_markdown(),
+    format => return Err(format!("unknown format {}", format).into()),
+  };
+  Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use mongodb_schema_parser::SchemaParser;
+
+  fn schema() -> Schema {
+    let mut schema_parser = SchemaParser::new();
+    schema_parser
+      .write_json(r#"{"name": "Nori", "age": 5}"#)
+      .unwrap();
+    schema_parser.flush()
+  }
+
+  #[test]
+  fn it_renders_every_format() {
+    let schema = schema();
+    for format in FORMATS {
+      let output = render(&schema, format, "pets").unwrap();
+      assert!(output.contains("name"), "{} output: {}", format, output);
+    }
+  }
+
+  #[test]
+  fn it_routes_to_the_exporters() {
+    let schema = schema();
+    let validator = render(&schema, "validator", "pets").unwrap();
+    assert!(validator.contains("$jsonSchema"));
+    let avro = render(&schema, "avro", "pets").unwrap();
+    assert!(avro.contains(r#""type": "record""#));
+  }
+
+  #[test]
+  fn it_rejects_unknown_formats() {
+    assert!(render(&schema(), "yaml", "pets").is_err());
+  }
+}
@@ -0,0 +1,72 @@
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// Size and modification time of each watched file, or None for files that
+/// can't be read right now, i.e. while they're being replaced.
+pub type Stamps = Vec<Option<(u64, Option<SystemTime>)>>;
+
+/// Returns the current stamps of `files`. Inputs have changed when their
+/// stamps differ from the ones taken when they were last read.
+pub fn stamps(files: &[&Path]) -> Stamps {
+  files
+    .iter()
+    .map(|file| {
+      let metadata = fs::metadata(file).ok()?;
+      Some((metadata.len(), metadata.modified().ok()))
+    })
+    .collect()
+}
+
+/// Parses an `--interval` such as `30s`, `5m` or `500ms`. Numbers without a
+/// unit are seconds.
+pub fn parse_interval(interval: &str) -> Result<Duration, String> {
+  let split = interval
+    .find(|c: char| !c.is_ascii_digit())
+    .unwrap_or_else(|| interval.len());
+  let (number, unit) = interval.split_at(split);
+  let number: u64 = number
+    .parse()
+    .map_err(|_| format!("invalid interval {}", interval))?;
+  let duration = match unit {
+    "ms" => Duration::from_millis(number),
+    "" | "s" => Duration::from_secs(number),
+    "m" => Duration::from_secs(number * 60),
+    "h" => Duration::from_secs(number * 60 * 60),
+    _ => return Err(format!("invalid interval {}", interval)),
+  };
+  if duration == Duration::from_secs(0) {
+    return Err(format!("interval {} needs to be longer than 0", interval));
+  }
+  Ok(duration)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::env;
+
+  #[test]
+  fn it_parses_intervals() {
+    assert_eq!(parse_interval("30s"), Ok(Duration::from_secs(30)));
+    assert_eq!(parse_interval("30"), Ok(Duration::from_secs(30)));
+    assert_eq!(parse_interval("5m"), Ok(Duration::from_secs(300)));
+    assert_eq!(parse_interval("1h"), Ok(Duration::from_secs(3600)));
+    assert_eq!(parse_interval("500ms"), Ok(Duration::from_millis(500)));
+    assert!(parse_interval("0s").is_err());
+    assert!(parse_interval("s").is_err());
+    assert!(parse_interval("30 seconds").is_err());
+  }
+
+  #[test]
+  fn it_stamps_files_by_size() {
+    let path = env::temp_dir().join("mongodb-schema-watch-test.json");
+    fs::write(&path, "{\"name\": \"Nori\"}\n").unwrap();
+    let before = stamps(&[&path]);
+    fs::write(&path, "{\"name\": \"Nori\"}\n{\"name\": \"Rey\"}\n").unwrap();
+    let after = stamps(&[&path]);
+    fs::remove_file(&path).unwrap();
+    assert_ne!(before, after);
+    assert_eq!(stamps(&[&path]), vec![None]);
+  }
+}
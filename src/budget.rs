@@ -0,0 +1,65 @@
+use crate::ValueType;
+use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Estimate of the memory taken up by fields, stored values and distinct
+/// value hashes, kept against `ParserOptions::memory_budget_bytes`. Clones
+/// share the same count, so a SchemaParser and the parsers of its
+/// subdocuments are charged together. The estimate only ever grows.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MemoryUsage(Arc<AtomicUsize>);
+
+impl MemoryUsage {
+  pub fn charge(&self, bytes: usize) {
+    self.0.fetch_add(bytes, Ordering::Relaxed);
+  }
+
+  pub fn used(&self) -> usize {
+    self.0.load(Ordering::Relaxed)
+  }
+}
+
+// Usage isn't an option in itself, so it doesn't tell options apart.
+impl PartialEq for MemoryUsage {
+  fn eq(&self, _: &MemoryUsage) -> bool {
+    true
+  }
+}
+
+/// Approximate size of a stored value, including what it holds on the heap.
+pub(crate) fn value_size(value: &ValueType) -> usize {
+  let heap = match value {
+    ValueType::Str(string)
+    | ValueType::Decimal128(string)
+    | ValueType::Null(string) => string.len(),
+    ValueType::Array(values) => values.iter().map(value_size).sum(),
+    ValueType::RegExp { pattern, options } => pattern.len() + options.len(),
+    _ => 0,
+  };
+  mem::size_of::<ValueType>() + heap
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_shares_usage_between_clones() {
+    let usage = MemoryUsage::default();
+    usage.clone().charge(10);
+    usage.charge(5);
+    assert_eq!(usage.used(), 15);
+  }
+
+  #[test]
+  fn it_sizes_values_with_their_contents() {
+    let name = ValueType::Str("Nori".to_string());
+    let names = ValueType::Array(vec![name.clone(), name.clone()]);
+    assert_eq!(value_size(&name), mem::size_of::<ValueType>() + 4);
+    assert_eq!(
+      value_size(&names),
+      mem::size_of::<ValueType>() + 2 * value_size(&name)
+    );
+  }
+}
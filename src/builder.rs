@@ -0,0 +1,440 @@
+use super::{
+  DateBucket, FieldOrder, HistogramOptions, HistogramScale, SchemaHistory,
+  SchemaParser, SnapshotInterval,
+};
+use crate::budget::MemoryUsage;
+
+/// Number of values sampled per field type unless configured otherwise.
+pub static DEFAULT_MAX_VALUES: usize = 100;
+
+/// Analysis options used by a SchemaParser. These are usually set up with
+/// `SchemaParser::builder()`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ParserOptions {
+  /// How many levels of nested documents to descend into. Documents past this
+  /// depth are still counted as a Document type, but their fields are not
+  /// analysed. `None` means no limit.
+  pub max_depth: Option<usize>,
+  /// Maximum number of fields tracked per document level. Fields first seen
+  /// after this limit is reached are ignored. `None` means no limit.
+  pub max_fields: Option<usize>,
+  /// Maximum number of values stored per field type. Past this limit values
+  /// are reservoir sampled, so the stored values stay representative of all
+  /// values seen. Defaults to `DEFAULT_MAX_VALUES`, `None` means no limit.
+  pub max_values: Option<usize>,
+  /// Whether to store sampled values at all.
+  pub store_values: bool,
+  /// Field name to wrap top level scalars and arrays under, i.e. `42` is
+  /// analysed as `{"<root_field>": 42}`. `None` means such input is rejected
+  /// with `ParseError::NotADocument`.
+  pub root_field: Option<String>,
+  /// Probability from 0.0 to 1.0 a field needs to be listed as required in
+  /// `to_json_schema()` and `to_mongodb_validator()`. Defaults to 1.0, i.e. fields present in every
+  /// document.
+  pub required_threshold: f32,
+  /// Whether `to_mongodb_validator()` allows fields that weren't seen during
+  /// analysis. Defaults to true.
+  pub additional_properties: bool,
+  /// Probability from 0.0 to 1.0 a field needs to be kept when the schema is
+  /// finalised, i.e. to drop one-off keys that drown out the real schema.
+  /// `None` keeps every field.
+  pub min_probability: Option<f32>,
+  /// Number of sibling fields with the same types above which they're taken
+  /// to be values used as keys, i.e. `votes.<userId>: 1`, and coalesced into
+  /// a single `$*` field when the schema is finalised. `None` never coalesces
+  /// fields.
+  pub wildcard_threshold: Option<usize>,
+  /// Whether keys that only differ in ASCII case, i.e. `userId` and
+  /// `userID`, are merged into one field named after the first spelling
+  /// seen. The other spellings are listed in the field's `spellings`.
+  /// Defaults to false.
+  pub case_insensitive_keys: bool,
+  /// Whether Int32, Long and Double values are counted as a single `Number`
+  /// type when the schema is finalised, with a breakdown by type in its
+  /// `types`. Defaults to false.
+  pub coalesce_numbers: bool,
+  /// Whether Decimal128 values are counted as a `Number` too, when
+  /// `coalesce_numbers` is set. Defaults to false.
+  pub coalesce_decimal128: bool,
+  /// Order fields are listed in once the schema is finalised. Defaults to
+  /// `FieldOrder::FirstSeen`.
+  pub field_order: FieldOrder,
+  /// Approximate number of bytes analysis may take up. Once it's exceeded,
+  /// samples stop growing, though stored values are still replaced so they
+  /// stay representative, and distinct values are estimated with fixed size
+  /// sketches instead of being counted exactly. `None` means no limit.
+  pub memory_budget_bytes: Option<usize>,
+  /// Whether distinct values are estimated with a fixed size HyperLogLog
+  /// sketch per field type, rather than counted exactly, i.e. for UUIDs and
+  /// other fields where almost every value is distinct. Types with only a few
+  /// distinct values are still counted exactly. Defaults to false.
+  pub approximate_distinct: bool,
+  /// Whether `to_json_schema()` and `to_mongodb_validator()` bound numbers by
+  /// the `minimum` and `maximum` seen. Defaults to false.
+  pub numeric_bounds: bool,
+  /// Whether `to_json_schema()` and `to_mongodb_validator()` bound strings by
+  /// the `minLength` and `maxLength` seen, in characters. Defaults to false.
+  pub length_bounds: bool,
+  /// Size of the buckets dates are counted in, per field type, to show the
+  /// time coverage of a collection. `None` only records the range of dates.
+  pub date_histogram: Option<DateBucket>,
+  /// Number of most frequent values reported per field type, with their
+  /// counts, i.e. to make sense of enum-like fields. `None` doesn't count
+  /// values.
+  pub top_values: Option<usize>,
+  /// Number and scale of the buckets the numbers of each numeric type are
+  /// counted in, i.e. for distribution charts. `None` doesn't build
+  /// histograms.
+  pub numeric_histogram: Option<HistogramOptions>,
+  /// Most distinct values a String, Int32 or Long type can have to be
+  /// flagged as an enum candidate, with its values listed in `enum_values`.
+  /// `None` doesn't look for enums.
+  pub enum_threshold: Option<usize>,
+  #[serde(skip)]
+  pub(crate) memory: MemoryUsage,
+}
+
+impl Default for ParserOptions {
+  fn default() -> Self {
+    ParserOptions {
+      max_depth: None,
+      max_fields: None,
+      max_values: Some(DEFAULT_MAX_VALUES),
+      store_values: true,
+      root_field: None,
+      required_threshold: 1.0,
+      additional_properties: true,
+      min_probability: None,
+      wildcard_threshold: None,
+      case_insensitive_keys: false,
+      coalesce_numbers: false,
+      coalesce_decimal128: false,
+      field_order: FieldOrder::FirstSeen,
+      memory_budget_bytes: None,
+      approximate_distinct: false,
+      numeric_bounds: false,
+      length_bounds: false,
+      date_histogram: None,
+      top_values: None,
+      numeric_histogram: None,
+      enum_threshold: None,
+      memory: MemoryUsage::default(),
+    }
+  }
+}
+
+impl ParserOptions {
+  // Options for a nested document's SchemaParser, with one less level of
+  // depth left. Returns None when max depth has been reached.
+  pub(crate) fn for_subdocument(&self) -> Option<ParserOptions> {
+    let max_depth = match self.max_depth {
+      Some(0) => return None,
+      Some(depth) => Some(depth - 1),
+      None => None,
+    };
+    Some(ParserOptions {
+      max_depth,
+      ..self.clone()
+    })
+  }
+
+  // Adds to the estimated memory used, when there's a budget to keep to.
+  pub(crate) fn charge_memory(&self, bytes: usize) {
+    if self.memory_budget_bytes.is_some() {
+      self.memory.charge(bytes);
+    }
+  }
+
+  pub(crate) fn over_memory_budget(&self) -> bool {
+    match self.memory_budget_bytes {
+      Some(budget) => self.memory.used() > budget,
+      None => false,
+    }
+  }
+}
+
+/// Builder for a SchemaParser with custom analysis options.
+///
+/// # Examples
+/// ```
+/// use mongodb_schema_parser::SchemaParser;
+///
+/// let mut schema_parser = SchemaParser::builder()
+///   .max_depth(3)
+///   .max_values(100)
+///   .build();
+/// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SchemaParserBuilder {
+  options: ParserOptions,
+  snapshot_interval: Option<SnapshotInterval>,
+}
+
+impl SchemaParserBuilder {
+  /// Returns a new builder with default options.
+  pub fn new() -> Self {
+    SchemaParserBuilder {
+      options: ParserOptions::default(),
+      snapshot_interval: None,
+    }
+  }
+
+  /// Sets how many levels of nested documents to descend into.
+  pub fn max_depth(mut self, max_depth: usize) -> Self {
+    self.options.max_depth = Some(max_depth);
+    self
+  }
+
+  /// Sets the maximum number of fields tracked per document level.
+  pub fn max_fields(mut self, max_fields: usize) -> Self {
+    self.options.max_fields = Some(max_fields);
+    self
+  }
+
+  /// Sets the maximum number of values sampled per field type.
+  pub fn max_values(mut self, max_values: usize) -> Self {
+    self.options.max_values = Some(max_values);
+    self
+  }
+
+  /// Stores every value seen instead of sampling them.
+  pub fn unlimited_values(mut self) -> Self {
+    self.options.max_values = None;
+    self
+  }
+
+  /// Sets whether sampled values are stored at all.
+  pub fn store_values(mut self, store_values: bool) -> Self {
+    self.options.store_values = store_values;
+    self
+  }
+
+  /// Sets a field name to wrap top level scalars and arrays under, instead of
+  /// rejecting them.
+  pub fn root_field<T: Into<String>>(mut self, root_field: T) -> Self {
+    self.options.root_field = Some(root_field.into());
+    self
+  }
+
+  /// Sets the probability a field needs to be listed as required in
+  /// `to_json_schema()` and `to_mongodb_validator()`.
+  pub fn required_threshold(mut self, required_threshold: f32) -> Self {
+    self.options.required_threshold = required_threshold;
+    self
+  }
+
+  /// Sets whether `to_mongodb_validator()` allows fields that weren't seen
+  /// during analysis.
+  pub fn additional_properties(mut self, additional_properties: bool) -> Self {
+    self.options.additional_properties = additional_properties;
+    self
+  }
+
+  /// Sets the probability a field needs to be kept when the schema is
+  /// finalised. Rarer fields, including those of subdocuments, are dropped.
+  pub fn min_probability(mut self, min_probability: f32) -> Self {
+    self.options.min_probability = Some(min_probability);
+    self
+  }
+
+  /// Sets how many sibling fields with the same types there can be before
+  /// they're coalesced into a single `$*` wildcard field.
+  pub fn wildcard_threshold(mut self, wildcard_threshold: usize) -> Self {
+    self.options.wildcard_threshold = Some(wildcard_threshold);
+    self
+  }
+
+  /// Sets whether keys that only differ in case are merged into one field.
+  pub fn case_insensitive_keys(mut self, case_insensitive_keys: bool) -> Self {
+    self.options.case_insensitive_keys = case_insensitive_keys;
+    self
+  }
+
+  /// Sets whether numbers of any type are counted as a single `Number` type.
+  pub fn coalesce_numbers(mut self, coalesce_numbers: bool) -> Self {
+    self.options.coalesce_numbers = coalesce_numbers;
+    self
+  }
+
+  /// Sets whether Decimal128 values are counted as a `Number` too.
+  pub fn coalesce_decimal128(mut self, coalesce_decimal128: bool) -> Self {
+    self.options.coalesce_decimal128 = coalesce_decimal128;
+    self
+  }
+
+  /// Sets the order fields are listed in, i.e. `FieldOrder::Name` for output
+  /// that diffs cleanly between runs.
+  pub fn field_order(mut self, field_order: FieldOrder) -> Self {
+    self.options.field_order = field_order;
+    self
+  }
+
+  /// Sets the approximate number of bytes analysis may take up, past which
+  /// values stop being sampled and distinct values are estimated.
+  pub fn memory_budget_bytes(mut self, memory_budget_bytes: usize) -> Self {
+    self.options.memory_budget_bytes = Some(memory_budget_bytes);
+    self
+  }
+
+  /// Sets whether distinct values are estimated with a HyperLogLog sketch
+  /// instead of being counted exactly.
+  pub fn approximate_distinct(mut self, approximate_distinct: bool) -> Self {
+    self.options.approximate_distinct = approximate_distinct;
+    self
+  }
+
+  /// Sets whether `to_json_schema()` and `to_mongodb_validator()` bound
+  /// numbers by the minimum and maximum seen.
+  pub fn numeric_bounds(mut self, numeric_bounds: bool) -> Self {
+    self.options.numeric_bounds = numeric_bounds;
+    self
+  }
+
+  /// Sets whether `to_json_schema()` and `to_mongodb_validator()` bound
+  /// string lengths by the shortest and longest strings seen.
+  pub fn length_bounds(mut self, length_bounds: bool) -> Self {
+    self.options.length_bounds = length_bounds;
+    self
+  }
+
+  /// Sets the size of the buckets dates are counted in, i.e. by
+  /// `DateBucket::Month`.
+  pub fn date_histogram(mut self, bucket: DateBucket) -> Self {
+    self.options.date_histogram = Some(bucket);
+    self
+  }
+
+  /// Sets how many of the most frequent values are reported per field type.
+  pub fn top_values(mut self, top_values: usize) -> Self {
+    self.options.top_values = Some(top_values);
+    self
+  }
+
+  /// Sets the number of buckets numbers are counted in per numeric type, and
+  /// whether they're of equal width on a linear or log scale.
+  pub fn numeric_histogram(
+    mut self,
+    buckets: usize,
+    scale: HistogramScale,
+  ) -> Self {
+    self.options.numeric_histogram = Some(HistogramOptions { buckets, scale });
+    self
+  }
+
+  /// Sets the most distinct values a type can have to be an enum candidate,
+  /// i.e. 20 for status or country fields.
+  pub fn enum_threshold(mut self, enum_threshold: usize) -> Self {
+    self.options.enum_threshold = Some(enum_threshold);
+    self
+  }
+
+  /// Sets how often to record a snapshot of the schema to the SchemaParser's
+  /// history, i.e. every 10000 documents or every hour.
+  pub fn snapshot_every(mut self, interval: SnapshotInterval) -> Self {
+    self.snapshot_interval = Some(interval);
+    self
+  }
+
+  /// Returns a SchemaParser using the configured options.
+  pub fn build(self) -> SchemaParser {
+    let mut options = self.options;
+    // parsers built from the same builder don't share their memory usage
+    options.memory = MemoryUsage::default();
+    let mut schema_parser = SchemaParser::with_options(options);
+    if let Some(interval) = self.snapshot_interval {
+      schema_parser.history = Some(SchemaHistory::with_interval(interval));
+    }
+    schema_parser
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_builds_with_options() {
+    let schema_parser = SchemaParserBuilder::new()
+      .max_depth(2)
+      .max_fields(10)
+      .max_values(5)
+      .store_values(false)
+      .min_probability(0.001)
+      .wildcard_threshold(100)
+      .case_insensitive_keys(true)
+      .coalesce_numbers(true)
+      .coalesce_decimal128(true)
+      .field_order(FieldOrder::Name)
+      .memory_budget_bytes(1024)
+      .approximate_distinct(true)
+      .numeric_bounds(true)
+      .length_bounds(true)
+      .date_histogram(DateBucket::Month)
+      .top_values(5)
+      .numeric_histogram(20, HistogramScale::Log)
+      .enum_threshold(20)
+      .build();
+    let options = schema_parser.options();
+    assert_eq!(options.max_depth, Some(2));
+    assert_eq!(options.max_fields, Some(10));
+    assert_eq!(options.max_values, Some(5));
+    assert!(!options.store_values);
+    assert_eq!(options.min_probability, Some(0.001));
+    assert_eq!(options.wildcard_threshold, Some(100));
+    assert!(options.case_insensitive_keys);
+    assert!(options.coalesce_numbers);
+    assert!(options.coalesce_decimal128);
+    assert_eq!(options.field_order, FieldOrder::Name);
+    assert_eq!(options.memory_budget_bytes, Some(1024));
+    assert!(options.approximate_distinct);
+    assert!(options.numeric_bounds);
+    assert!(options.length_bounds);
+    assert_eq!(options.date_histogram, Some(DateBucket::Month));
+    assert_eq!(options.top_values, Some(5));
+    let histogram = options.numeric_histogram.unwrap();
+    assert_eq!(histogram.buckets, 20);
+    assert_eq!(histogram.scale, HistogramScale::Log);
+    assert_eq!(options.enum_threshold, Some(20));
+  }
+
+  #[test]
+  fn it_samples_values_by_default() {
+    let options = SchemaParserBuilder::new().build().options().clone();
+    assert_eq!(options.max_values, Some(DEFAULT_MAX_VALUES));
+    let options = SchemaParserBuilder::new()
+      .unlimited_values()
+      .build()
+      .options()
+      .clone();
+    assert_eq!(options.max_values, None);
+  }
+
+  #[test]
+  fn it_decrements_depth_for_subdocuments() {
+    let options = ParserOptions {
+      max_depth: Some(1),
+      ..ParserOptions::default()
+    };
+    let nested = options.for_subdocument().unwrap();
+    assert_eq!(nested.max_depth, Some(0));
+    assert!(nested.for_subdocument().is_none());
+  }
+
+  #[test]
+  fn it_shares_memory_usage_with_subdocuments() {
+    let options = ParserOptions {
+      memory_budget_bytes: Some(10),
+      ..ParserOptions::default()
+    };
+    let nested = options.for_subdocument().unwrap();
+    nested.charge_memory(11);
+    assert!(options.over_memory_budget());
+  }
+
+  #[test]
+  fn it_has_no_depth_limit_by_default() {
+    let options = ParserOptions::default();
+    assert!(options.for_subdocument().is_some());
+  }
+}
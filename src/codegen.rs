@@ -0,0 +1,254 @@
+use super::{Field, FieldType};
+use crate::field_type;
+use crate::typescript::pascal_case;
+
+// Keywords that can't be used as field names without `r#`.
+static KEYWORDS: &[&str] = &[
+  "as", "async", "await", "break", "const", "continue", "dyn", "else", "enum",
+  "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match",
+  "mod", "move", "mut", "pub", "ref", "return", "static", "struct", "trait",
+  "true", "type", "unsafe", "use", "where", "while", "abstract", "become",
+  "box", "do", "final", "macro", "override", "priv", "try", "typeof",
+  "unsized", "virtual", "yield",
+];
+
+/// Generates Rust struct definitions with serde attributes for finalised
+/// fields. The top level struct is called `name`, subdocuments get their own
+/// structs named after their path, and fields with several types get an
+/// untagged enum. Fields present in less than `required_threshold` of the
+/// documents, or that are sometimes null, are `Option`s.
+pub(crate) fn to_rust_structs<'a>(
+  fields: impl Iterator<Item = &'a Field>,
+  name: &str,
+  required_threshold: f32,
+) -> String {
+  let mut writer = Writer {
+    root: pascal_case(name),
+    items: Vec::new(),
+    required_threshold,
+  };
+  let root = writer.root.clone();
+  writer.write_struct(fields, &root);
+
+  let mut rust = String::from("use serde::{Deserialize, Serialize};\n\n");
+  rust.push_str(&writer.items.join("\n"));
+  rust
+}
+
+struct Writer {
+  root: String,
+  items: Vec<String>,
+  required_threshold: f32,
+}
+
+impl Writer {
+  // Nested structs and enums are written after the struct that uses them.
+  fn write_struct<'a>(
+    &mut self,
+    fields: impl Iterator<Item = &'a Field>,
+    name: &str,
+  ) {
+    let index = self.items.len();
+    self.items.push(String::new());
+
+    let mut item = derive();
+    item.push_str(&format!("pub struct {} {{\n", name));
+    for field in fields {
+      let ident = field_ident(&field.name);
+      if ident.trim_start_matches("r#") != &*field.name {
+        item.push_str(&format!("    #[serde(rename = {:?})]\n", field.name));
+      }
+      let optional = field.probability < self.required_threshold;
+      let rust_type = self.field_type(field.types(), &field.path, optional);
+      item.push_str(&format!("    pub {}: {},\n", ident, rust_type));
+    }
+    item.push_str("}\n");
+    self.items[index] = item;
+  }
+
+  fn field_type<'a>(
+    &mut self,
+    types: impl Iterator<Item = &'a FieldType>,
+    path: &str,
+    mut optional: bool,
+  ) -> String {
+    let mut types: Vec<&FieldType> = types.collect();
+    types.sort_by(|a, b| a.bson_type.cmp(&b.bson_type));
+    // nulls and missing values make a field optional, rather than being a
+    // type of their own
+    optional |= types.iter().any(|t| is_nullable(&t.bson_type));
+    types.retain(|t| !is_nullable(&t.bson_type));
+
+    let rust_type = match types.len() {
+      0 => return "Option<bson::Bson>".to_string(),
+      1 => self.rust_type(types[0]),
+      _ => self.write_enum(&types, path),
+    };
+    if optional {
+      format!("Option<{}>", rust_type)
+    } else {
+      rust_type
+    }
+  }
+
+  // Fields with several types get an untagged enum with a variant per type,
+  // unless one of the types can only be represented as `bson::Bson`.
+  fn write_enum(&mut self, types: &[&FieldType], path: &str) -> String {
+    let variants: Vec<(String, String)> = types
+      .iter()
+      .map(|t| (pascal_case(&t.bson_type), self.rust_type(t)))
+      .collect();
+    if variants
+      .iter()
+      .any(|(_, rust_type)| rust_type == "bson::Bson")
+    {
+      return "bson::Bson".to_string();
+    }
+
+    let name = format!("{}{}", self.root, pascal_case(path));
+    let mut item = derive();
+    item.push_str("#[serde(untagged)]\n");
+    item.push_str(&format!("pub enum {} {{\n", name));
+    for (variant, rust_type) in variants {
+      item.push_str(&format!("    {}({}),\n", variant, rust_type));
+    }
+    item.push_str("}\n");
+    self.items.push(item);
+    name
+  }
+
+  fn rust_type(&mut self, field_type: &FieldType) -> String {
+    let rust_type = match field_type.bson_type.as_str() {
+      t if t == field_type::DOCUMENT => match field_type.schema() {
+        Some(schema) => {
+          let path = pascal_case(&field_type.path);
+          let name = format!("{}{}", self.root, path);
+          self.write_struct(schema.fields(), &name);
+          return name;
+        }
+        None => "bson::Document",
+      },
+      t if t == field_type::ARRAY => {
+        let items =
+          self.field_type(field_type.types(), &field_type.path, false);
+        return format!("Vec<{}>", items);
+      }
+      t if t == field_type::FLOATING_POINT || t == field_type::NUMBER => "f64",
+      t if t == field_type::I32 => "i32",
+      t if t == field_type::I64 => "i64",
+      t if t == field_type::STRING || t == field_type::SYMBOL => "String",
+      t if t == field_type::BOOLEAN => "bool",
+      t if t == field_type::OBJECTID => "bson::oid::ObjectId",
+      t if t == field_type::UTCDATE_TIME => "bson::UtcDateTime",
+      // Decimal128, binaries, timestamps, regular expressions and code
+      _ => "bson::Bson",
+    };
+    rust_type.to_string()
+  }
+}
+
+fn derive() -> String {
+  "#[derive(Debug, Clone, Serialize, Deserialize)]\n".to_string()
+}
+
+fn is_nullable(bson_type: &str) -> bool {
+  bson_type == field_type::NULL || bson_type == field_type::UNDEFINED
+}
+
+// snake_case identifier for a field name. Keywords become raw identifiers,
+// which serde already (de)serializes without the `r#`.
+fn field_ident(name: &str) -> String {
+  let ident = snake_case(name);
+  if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+    format!("_{}", ident)
+  } else if KEYWORDS.contains(&ident.as_str()) {
+    format!("r#{}", ident)
+  } else {
+    ident
+  }
+}
+
+pub(crate) fn snake_case(name: &str) -> String {
+  let mut ident = String::new();
+  let mut previous_lowercase = false;
+  for c in name.chars() {
+    if c.is_ascii_uppercase() {
+      if previous_lowercase {
+        ident.push('_');
+      }
+      ident.push(c.to_ascii_lowercase());
+      previous_lowercase = false;
+    } else if c.is_ascii_alphanumeric() {
+      ident.push(c);
+      previous_lowercase = true;
+    } else if !ident.is_empty() && !ident.ends_with('_') {
+      ident.push('_');
+      previous_lowercase = false;
+    }
+  }
+  ident.trim_end_matches('_').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::SchemaParser;
+
+  #[test]
+  fn it_generates_rust_structs() {
+    let mut schema_parser = SchemaParser::new();
+    let json_str1 = r#"{
+      "_id": {"$oid": "5d505646cf6d4fe581014ab2"},
+      "type": "Cat",
+      "age": 5,
+      "tags": ["cat"]
+    }"#;
+    let json_str2 = r#"{
+      "_id": {"$oid": "5d505646cf6d4fe581014ab3"},
+      "type": null,
+      "age": "3",
+      "owner": {"firstName": "Ana"}
+    }"#;
+    schema_parser.write_json(&json_str1).unwrap();
+    schema_parser.write_json(&json_str2).unwrap();
+    let schema = schema_parser.flush();
+    let rust = to_rust_structs(schema.fields(), "pets", 1.0);
+    assert_eq!(
+      rust,
+      r#"use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pets {
+    #[serde(rename = "_id")]
+    pub id: bson::oid::ObjectId,
+    pub r#type: Option<String>,
+    pub age: PetsAge,
+    pub tags: Option<Vec<String>>,
+    pub owner: Option<PetsOwner>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PetsAge {
+    Int32(i32),
+    String(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PetsOwner {
+    #[serde(rename = "firstName")]
+    pub first_name: String,
+}
+"#
+    );
+  }
+
+  #[test]
+  fn it_converts_field_names_to_snake_case() {
+    assert_eq!(field_ident("firstName"), "first_name");
+    assert_eq!(field_ident("first-name"), "first_name");
+    assert_eq!(field_ident("_id"), "id");
+    assert_eq!(field_ident("2fa"), "_2fa");
+    assert_eq!(field_ident("match"), "r#match");
+  }
+}
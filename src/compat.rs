@@ -0,0 +1,170 @@
+use super::{Field, FieldType};
+use crate::field_type;
+use serde_json::{json, Map, Value};
+
+/// Converts finalised fields into the JSON structure the npm `mongodb-schema`
+/// package produces. Fields are sorted by name and types by probability, type
+/// names are those of the js bson library, subdocuments list their `fields`
+/// and arrays list the `types` of their elements.
+pub(crate) fn to_json_compat<'a>(
+  fields: impl Iterator<Item = &'a Field>,
+  count: usize,
+) -> Value {
+  json!({ "count": count, "fields": compat_fields(fields, count) })
+}
+
+fn compat_fields<'a>(
+  fields: impl Iterator<Item = &'a Field>,
+  parent_count: usize,
+) -> Vec<Value> {
+  let mut fields: Vec<&Field> = fields.collect();
+  fields.sort_by_key(|field| field.name.to_lowercase());
+  fields
+    .into_iter()
+    .map(|field| {
+      let types = compat_types(field.types());
+      let names: Vec<Value> = types.iter().map(|t| t["name"].clone()).collect();
+      let field_type = match names.len() {
+        1 => names[0].clone(),
+        _ => Value::Array(names),
+      };
+      let has_duplicates = field.types().any(|t| t.has_duplicates);
+      json!({
+        "name": field.name,
+        "path": field.path,
+        "count": field.count,
+        "type": field_type,
+        "probability": field.probability,
+        "has_duplicates": has_duplicates,
+        "types": types,
+        "total_count": parent_count
+      })
+    })
+    .collect()
+}
+
+fn compat_types<'a>(types: impl Iterator<Item = &'a FieldType>) -> Vec<Value> {
+  let mut types: Vec<&FieldType> = types.collect();
+  types.sort_by(|a, b| {
+    b.probability
+      .partial_cmp(&a.probability)
+      .unwrap_or(std::cmp::Ordering::Equal)
+      .then_with(|| a.bson_type.cmp(&b.bson_type))
+  });
+  types.into_iter().map(compat_type).collect()
+}
+
+fn compat_type(field_type: &FieldType) -> Value {
+  let name = js_type_name(&field_type.bson_type);
+  let mut compat = Map::new();
+  compat.insert("name".to_string(), Value::from(name));
+  compat.insert("bsonType".to_string(), Value::from(name));
+  compat.insert("path".to_string(), Value::from(&*field_type.path));
+  compat.insert("count".to_string(), Value::from(field_type.count));
+  compat.insert("probability".to_string(), json!(field_type.probability));
+
+  if let Some(schema) = field_type.schema() {
+    let fields = compat_fields(schema.fields(), field_type.count);
+    compat.insert("fields".to_string(), Value::Array(fields));
+  } else if field_type.bson_type == field_type::ARRAY {
+    let lengths = &field_type.lengths;
+    let total_count: usize = lengths.iter().sum();
+    let average_length = if lengths.is_empty() {
+      0.0
+    } else {
+      total_count as f64 / lengths.len() as f64
+    };
+    let types = compat_types(field_type.types());
+    compat.insert("types".to_string(), Value::Array(types));
+    compat.insert("lengths".to_string(), json!(lengths));
+    compat.insert("average_length".to_string(), json!(average_length));
+    compat.insert("total_count".to_string(), Value::from(total_count));
+  } else {
+    let values = serde_json::to_value(field_type.values()).unwrap_or_default();
+    compat.insert("values".to_string(), values);
+    compat.insert("total_count".to_string(), Value::from(0));
+    compat.insert(
+      "unique".to_string(),
+      Value::from(field_type.unique.unwrap_or(0)),
+    );
+  }
+  compat.insert(
+    "has_duplicates".to_string(),
+    Value::from(field_type.has_duplicates),
+  );
+  Value::Object(compat)
+}
+
+// Name of the js bson library's class for a bson type.
+fn js_type_name(bson_type: &str) -> &str {
+  match bson_type {
+    t if t == field_type::OBJECTID => "ObjectID",
+    t if t == field_type::UTCDATE_TIME => "Date",
+    t if t == field_type::BINARY => "Binary",
+    t if t == field_type::REGEXP => "BSONRegExp",
+    t if t == field_type::JAVASCRIPT_CODE
+      || t == field_type::JAVASCRIPT_CODE_WITH_SCOPE =>
+    {
+      "Code"
+    }
+    // Double, Int32, Long, Decimal128, String, Boolean, Timestamp, Symbol,
+    // Null, Undefined, Document and Array have the same names
+    t => t,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::SchemaParser;
+
+  #[test]
+  #[allow(clippy::float_cmp)]
+  fn it_converts_to_json_compat() {
+    let mut schema_parser = SchemaParser::new();
+    let json_str1 = r#"{
+      "name": "Nori",
+      "_id": {"$oid": "5d505646cf6d4fe581014ab2"},
+      "tags": ["cat", "fluffy"]
+    }"#;
+    let json_str2 = r#"{
+      "name": "Nori",
+      "_id": {"$oid": "5d505646cf6d4fe581014ab3"},
+      "owner": {"name": "Ana"}
+    }"#;
+    schema_parser.write_json(&json_str1).unwrap();
+    schema_parser.write_json(&json_str2).unwrap();
+    let schema = schema_parser.flush();
+    let compat = to_json_compat(schema.fields(), schema.count);
+
+    assert_eq!(compat["count"], 2);
+    let names: Vec<&Value> = compat["fields"]
+      .as_array()
+      .unwrap()
+      .iter()
+      .map(|f| &f["name"])
+      .collect();
+    assert_eq!(names, vec!["_id", "name", "owner", "tags"]);
+
+    let id = &compat["fields"][0];
+    assert_eq!(id["type"], "ObjectID");
+    assert_eq!(id["total_count"], 2);
+    assert_eq!(id["types"][0]["bsonType"], "ObjectID");
+    assert_eq!(id["types"][0]["unique"], 2);
+
+    let name = &compat["fields"][1];
+    assert_eq!(name["has_duplicates"], true);
+    assert_eq!(name["types"][0]["values"], json!(["Nori", "Nori"]));
+
+    let owner = &compat["fields"][2];
+    assert_eq!(owner["type"], json!(["Document", "Undefined"]));
+    assert_eq!(owner["types"][0]["fields"][0]["path"], "owner.name");
+
+    let tags = &compat["fields"][3]["types"][0];
+    assert_eq!(tags["name"], "Array");
+    assert_eq!(tags["lengths"], json!([2]));
+    assert_eq!(tags["average_length"], 2.0);
+    assert_eq!(tags["total_count"], 2);
+    assert_eq!(tags["types"][0]["name"], "String");
+  }
+}
@@ -0,0 +1,78 @@
+use flate2::read::MultiGzDecoder;
+use std::io::{self, BufRead, BufReader, Read};
+
+static GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+static ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Wraps a reader of gzip or zstd compressed input, recognised by its magic
+/// bytes, in a decoder that decompresses it on the fly. Anything else is read
+/// as it is. Only available with the `compression` feature.
+///
+/// # Arguments
+/// * `reader` - Compressed or uncompressed input, i.e. an export file.
+///
+/// # Examples
+/// ```no_run
+/// use mongodb_schema_parser::decompress;
+/// use std::fs::File;
+/// use std::io::Read;
+///
+/// let file = File::open("users.bson.gz").unwrap();
+/// let mut bytes = Vec::new();
+/// decompress(file).unwrap().read_to_end(&mut bytes).unwrap();
+/// ```
+pub fn decompress<'a, R: Read + 'a>(
+  reader: R,
+) -> io::Result<Box<dyn Read + 'a>> {
+  let mut reader = BufReader::new(reader);
+  let (gzip, zstd) = {
+    let magic = reader.fill_buf()?;
+    (magic.starts_with(GZIP_MAGIC), magic.starts_with(ZSTD_MAGIC))
+  };
+  if gzip {
+    // exports can be several gzip members written one after another
+    Ok(Box::new(MultiGzDecoder::new(reader)))
+  } else if zstd {
+    Ok(Box::new(zstd::stream::read::Decoder::with_buffer(reader)?))
+  } else {
+    Ok(Box::new(reader))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use flate2::write::GzEncoder;
+  use flate2::Compression;
+  use std::io::Write;
+
+  static JSON: &str = "{\"name\": \"Nori\"}\n{\"name\": \"Rey\"}\n";
+
+  fn decompressed(bytes: &[u8]) -> String {
+    let mut json = String::new();
+    decompress(bytes)
+      .unwrap()
+      .read_to_string(&mut json)
+      .unwrap();
+    json
+  }
+
+  #[test]
+  fn it_decompresses_gzip() {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(JSON.as_bytes()).unwrap();
+    assert_eq!(decompressed(&encoder.finish().unwrap()), JSON);
+  }
+
+  #[test]
+  fn it_decompresses_zstd() {
+    let bytes = zstd::encode_all(JSON.as_bytes(), 0).unwrap();
+    assert_eq!(decompressed(&bytes), JSON);
+  }
+
+  #[test]
+  fn it_reads_uncompressed_input_as_it_is() {
+    assert_eq!(decompressed(JSON.as_bytes()), JSON);
+    assert_eq!(decompressed(b""), "");
+  }
+}
@@ -0,0 +1,147 @@
+use super::{Field, FieldType, ValueType};
+use crate::field_type;
+use std::cmp::Ordering;
+
+static HEADER: &[&str] = &[
+  "path",
+  "probability",
+  "dominant_type",
+  "types",
+  "distinct",
+  "min",
+  "max",
+];
+
+/// Converts finalised fields into CSV, with a header row and one row per
+/// field path, including the fields of subdocuments.
+pub(crate) fn to_csv<'a>(fields: impl Iterator<Item = &'a Field>) -> String {
+  let mut csv = row(HEADER.iter().map(|column| column.to_string()));
+  write_rows(&mut csv, fields);
+  csv
+}
+
+fn write_rows<'a>(csv: &mut String, fields: impl Iterator<Item = &'a Field>) {
+  for field in fields {
+    let mut types: Vec<&FieldType> = field
+      .types()
+      .filter(|t| t.bson_type != field_type::UNDEFINED)
+      .collect();
+    types.sort_by(|a, b| {
+      b.count
+        .cmp(&a.count)
+        .then_with(|| a.bson_type.cmp(&b.bson_type))
+    });
+
+    let breakdown: Vec<String> = types
+      .iter()
+      .map(|t| format!("{}:{}", t.bson_type, t.probability))
+      .collect();
+    let distinct: Option<usize> = types
+      .iter()
+      .filter(|t| is_scalar(t))
+      .filter_map(|t| t.unique)
+      .fold(None, |sum, unique| Some(sum.unwrap_or(0) + unique));
+    let dominant = types.first();
+    let (min, max) = match dominant {
+      Some(dominant) => range(dominant),
+      None => (None, None),
+    };
+
+    csv.push_str(&row(vec![
+      field.path.to_string(),
+      field.probability.to_string(),
+      dominant.map(|t| t.bson_type.clone()).unwrap_or_default(),
+      breakdown.join(";"),
+      distinct.map(|d| d.to_string()).unwrap_or_default(),
+      min.map(cell).unwrap_or_default(),
+      max.map(cell).unwrap_or_default(),
+    ]));
+
+    for field_type in types {
+      write_subdocuments(csv, field_type);
+    }
+  }
+}
+
+// Fields of subdocuments, including those in arrays, follow their parent.
+fn write_subdocuments(csv: &mut String, field_type: &FieldType) {
+  if let Some(schema) = field_type.schema() {
+    write_rows(csv, schema.fields());
+  }
+  for element_type in field_type.types() {
+    write_subdocuments(csv, element_type);
+  }
+}
+
+fn is_scalar(field_type: &FieldType) -> bool {
+  field_type.bson_type != field_type::DOCUMENT
+    && field_type.bson_type != field_type::ARRAY
+}
+
+// The range tracked for the type, or else the range of its sampled values.
+fn range(field_type: &FieldType) -> (Option<&ValueType>, Option<&ValueType>) {
+  if field_type.min.is_some() || field_type.max.is_some() {
+    return (field_type.min.as_ref(), field_type.max.as_ref());
+  }
+  if !is_scalar(field_type) {
+    return (None, None);
+  }
+  let compare = |a: &&ValueType, b: &&ValueType| {
+    a.partial_cmp(b).unwrap_or(Ordering::Equal)
+  };
+  let values = field_type.values().iter();
+  (values.clone().min_by(compare), values.max_by(compare))
+}
+
+fn cell(value: &ValueType) -> String {
+  match value {
+    ValueType::Str(value) | ValueType::Decimal128(value) => value.clone(),
+    _ => serde_json::to_string(value).unwrap_or_default(),
+  }
+}
+
+// Cells with commas, quotes or line breaks are quoted, doubling any quotes.
+fn row(cells: impl IntoIterator<Item = String>) -> String {
+  let cells: Vec<String> = cells
+    .into_iter()
+    .map(|cell| {
+      if cell.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+      } else {
+        cell
+      }
+    })
+    .collect();
+  format!("{}\n", cells.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::SchemaParser;
+
+  #[test]
+  fn it_converts_to_csv() {
+    let mut schema_parser = SchemaParser::new();
+    let json_str1 = r#"{"name": "Nori", "age": 5, "owner": {"name": "Ana"}}"#;
+    let json_str2 = r#"{"name": "Rey, Jr.", "age": 3}"#;
+    let json_str3 = r#"{"name": "Chashu", "age": "2"}"#;
+    schema_parser.write_json(&json_str1).unwrap();
+    schema_parser.write_json(&json_str2).unwrap();
+    schema_parser.write_json(&json_str3).unwrap();
+    let schema = schema_parser.flush();
+    let csv = to_csv(schema.fields());
+    let (one_third, two_thirds) = (1.0_f32 / 3.0, 2.0_f32 / 3.0);
+    assert_eq!(
+      csv,
+      format!(
+        "path,probability,dominant_type,types,distinct,min,max\n\
+         name,1,String,String:1,3,Chashu,\"Rey, Jr.\"\n\
+         age,1,Int32,Int32:{1};String:{0},3,3,5\n\
+         owner,{0},Document,Document:{0},,,\n\
+         owner.name,1,String,String:1,1,Ana,Ana\n",
+        one_third, two_thirds
+      )
+    );
+  }
+}
@@ -0,0 +1,146 @@
+use super::{
+  AnalyzeOptions, Bson, Document, IndexMap, ParseError, Schema, SchemaParser,
+};
+use mongodb::Database;
+use std::collections::HashMap;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+/// Number of collections a DatabaseAnalyzer samples at the same time unless
+/// configured otherwise.
+pub static DEFAULT_CONCURRENCY: usize = 4;
+
+/// Analyses every collection of a database, i.e. to build a data catalog,
+/// with a SchemaParser per collection. Collections are sampled as with
+/// `SchemaParser::analyze_collection()`, a few at a time. Only available with
+/// the `driver` feature.
+///
+/// # Examples
+/// ```no_run
+/// use mongodb::Client;
+/// use mongodb_schema_parser::DatabaseAnalyzer;
+///
+/// let client = Client::with_uri_str("mongodb://localhost:27017").unwrap();
+/// let schemas = DatabaseAnalyzer::new(client.database("shelter"))
+///   .concurrency(8)
+///   .analyze()
+///   .unwrap();
+/// for (namespace, schema) in schemas {
+///   println!("{}: {} documents", namespace, schema.count);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DatabaseAnalyzer {
+  database: Database,
+  options: AnalyzeOptions,
+  concurrency: usize,
+}
+
+impl DatabaseAnalyzer {
+  /// Returns an analyzer for all collections of `database`, sampled with
+  /// default options.
+  pub fn new(database: Database) -> Self {
+    DatabaseAnalyzer {
+      database,
+      options: AnalyzeOptions::default(),
+      concurrency: DEFAULT_CONCURRENCY,
+    }
+  }
+
+  /// Sets the sample size, filter and analysis options used for every
+  /// collection.
+  pub fn options(mut self, options: AnalyzeOptions) -> Self {
+    self.options = options;
+    self
+  }
+
+  /// Sets how many collections are sampled at the same time, each on its own
+  /// thread and connection. At least one is.
+  pub fn concurrency(mut self, concurrency: usize) -> Self {
+    self.concurrency = concurrency.max(1);
+    self
+  }
+
+  /// Samples every collection, leaving out views and `system.` collections,
+  /// and returns their schemas by namespace, i.e. `shelter.pets`, in the
+  /// order the server listed them in. Returns the first error any collection
+  /// fails with.
+  pub fn analyze(&self) -> Result<IndexMap<String, Schema>, ParseError> {
+    let mut filter = Document::new();
+    filter.insert("type", Bson::String("collection".to_string()));
+    let names =
+      analyzed_collections(self.database.list_collection_names(filter)?);
+
+    let queue = Arc::new(Mutex::new(names.clone().into_iter()));
+    let (sender, receiver) = mpsc::channel();
+    for _ in 0..self.concurrency.min(names.len()) {
+      let queue = Arc::clone(&queue);
+      let sender = sender.clone();
+      let database = self.database.clone();
+      let options = self.options.clone();
+      thread::spawn(move || loop {
+        let name = queue.lock().unwrap().next();
+        let name = match name {
+          Some(name) => name,
+          None => break,
+        };
+        let collection = database.collection(&name);
+        let schema =
+          SchemaParser::analyze_collection(&collection, options.clone());
+        // the receiver is gone once another collection failed
+        if sender.send((name, schema)).is_err() {
+          break;
+        }
+      });
+    }
+    // the loop below ends once every worker has dropped its sender
+    drop(sender);
+
+    let mut schemas = HashMap::new();
+    for (name, schema) in receiver {
+      schemas.insert(name, schema?);
+    }
+    Ok(
+      names
+        .into_iter()
+        .filter_map(|name| {
+          let schema = schemas.remove(&name)?;
+          Some((namespace(self.database.name(), &name), schema))
+        })
+        .collect(),
+    )
+  }
+}
+
+// System collections hold the server's own metadata, not application data.
+fn analyzed_collections(names: Vec<String>) -> Vec<String> {
+  names
+    .into_iter()
+    .filter(|name| !name.starts_with("system."))
+    .collect()
+}
+
+fn namespace(database: &str, collection: &str) -> String {
+  format!("{}.{}", database, collection)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_leaves_out_system_collections() {
+    let names = vec![
+      "pets".to_string(),
+      "system.views".to_string(),
+      "owners".to_string(),
+      "system.profile".to_string(),
+    ];
+    assert_eq!(analyzed_collections(names), vec!["pets", "owners"]);
+  }
+
+  #[test]
+  fn it_names_namespaces() {
+    assert_eq!(namespace("shelter", "pets"), "shelter.pets");
+  }
+}
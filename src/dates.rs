@@ -0,0 +1,44 @@
+use chrono::{DateTime, Datelike, Duration, Utc};
+
+/// Size of the buckets of a date histogram, set with
+/// `SchemaParser::builder().date_histogram()`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum DateBucket {
+  /// One bucket per day, labelled by the day, i.e. `2019-08-11`.
+  Day,
+  /// One bucket per ISO week, labelled by the Monday it starts on.
+  Week,
+  /// One bucket per month, labelled by the month, i.e. `2019-08`.
+  Month,
+}
+
+impl DateBucket {
+  /// Label of the bucket `date` falls into.
+  pub(crate) fn label(self, date: &DateTime<Utc>) -> String {
+    match self {
+      DateBucket::Day => date.format("%Y-%m-%d").to_string(),
+      DateBucket::Week => {
+        let day = date.naive_utc().date();
+        let since_monday = day.weekday().num_days_from_monday();
+        let monday = day - Duration::days(i64::from(since_monday));
+        monday.format("%Y-%m-%d").to_string()
+      }
+      DateBucket::Month => date.format("%Y-%m").to_string(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::TimeZone;
+
+  #[test]
+  fn it_labels_buckets() {
+    // a Sunday
+    let date = Utc.timestamp(1_565_546_054, 0);
+    assert_eq!(DateBucket::Day.label(&date), "2019-08-11");
+    assert_eq!(DateBucket::Week.label(&date), "2019-08-05");
+    assert_eq!(DateBucket::Month.label(&date), "2019-08");
+  }
+}
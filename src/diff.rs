@@ -0,0 +1,214 @@
+use super::{Field, FieldType, IndexMap, Schema};
+use crate::field_type;
+
+/// Differences between two finalised Schemas, i.e. the schemas of the same
+/// collection analysed at different times.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SchemaDiff {
+  /// Paths of fields only present in the newer Schema.
+  pub added: Vec<String>,
+  /// Paths of fields only present in the older Schema.
+  pub removed: Vec<String>,
+  /// Fields present in both Schemas whose probability or types changed.
+  pub changed: Vec<FieldDiff>,
+}
+
+/// Changes to a field present in both Schemas.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FieldDiff {
+  pub path: String,
+  pub probability_before: f32,
+  pub probability_after: f32,
+  /// Types whose probability changed, including types that were added or
+  /// removed, which have a probability of 0.0 on the side they're missing
+  /// from.
+  pub types: Vec<TypeDiff>,
+}
+
+/// Change in how often a field has a type.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TypeDiff {
+  pub bson_type: String,
+  pub probability_before: f32,
+  pub probability_after: f32,
+}
+
+impl SchemaDiff {
+  /// Whether both Schemas have the same fields, with the same types and
+  /// probabilities.
+  pub fn is_empty(&self) -> bool {
+    self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+  }
+}
+
+/// Compares every field of two Schemas by path, including the fields of
+/// subdocuments and of documents in arrays.
+pub(crate) fn diff(before: &Schema, after: &Schema) -> SchemaDiff {
+  let before = fields_by_path(before.fields());
+  let after = fields_by_path(after.fields());
+
+  let added = after
+    .keys()
+    .filter(|path| !before.contains_key(*path))
+    .map(|path| path.to_string())
+    .collect();
+  let removed = before
+    .keys()
+    .filter(|path| !after.contains_key(*path))
+    .map(|path| path.to_string())
+    .collect();
+  let changed = before
+    .iter()
+    .filter_map(|(path, field)| Some((*field, *after.get(path)?)))
+    .filter_map(|(before, after)| field_diff(before, after))
+    .collect();
+
+  SchemaDiff {
+    added,
+    removed,
+    changed,
+  }
+}
+
+fn field_diff(before: &Field, after: &Field) -> Option<FieldDiff> {
+  let mut bson_types: Vec<&String> = before
+    .types
+    .keys()
+    .chain(after.types.keys())
+    .filter(|bson_type| *bson_type != field_type::UNDEFINED)
+    .collect();
+  bson_types.sort();
+  bson_types.dedup();
+
+  let probability = |field: &Field, bson_type: &str| {
+    field.types.get(bson_type).map_or(0.0, |t| t.probability)
+  };
+  let types: Vec<TypeDiff> = bson_types
+    .into_iter()
+    .map(|bson_type| TypeDiff {
+      bson_type: bson_type.clone(),
+      probability_before: probability(before, bson_type),
+      probability_after: probability(after, bson_type),
+    })
+    .filter(|t| {
+      (t.probability_before - t.probability_after).abs() > std::f32::EPSILON
+    })
+    .collect();
+
+  let shifted =
+    (before.probability - after.probability).abs() > std::f32::EPSILON;
+  if !shifted && types.is_empty() {
+    return None;
+  }
+  Some(FieldDiff {
+    path: before.path.to_string(),
+    probability_before: before.probability,
+    probability_after: after.probability,
+    types,
+  })
+}
+
+// Fields keyed by their path, in the order they are first seen, with the
+// fields of subdocuments following their parent.
+pub(crate) fn fields_by_path<'a>(
+  fields: impl Iterator<Item = &'a Field>,
+) -> IndexMap<&'a str, &'a Field> {
+  let mut by_path = IndexMap::new();
+  collect_fields(fields, &mut by_path);
+  by_path
+}
+
+fn collect_fields<'a>(
+  fields: impl Iterator<Item = &'a Field>,
+  by_path: &mut IndexMap<&'a str, &'a Field>,
+) {
+  for field in fields {
+    by_path.entry(&*field.path).or_insert(field);
+    for field_type in field.types() {
+      collect_nested(field_type, by_path);
+    }
+  }
+}
+
+fn collect_nested<'a>(
+  field_type: &'a FieldType,
+  by_path: &mut IndexMap<&'a str, &'a Field>,
+) {
+  if let Some(schema) = field_type.schema() {
+    collect_fields(schema.fields(), by_path);
+  }
+  for element_type in field_type.types() {
+    collect_nested(element_type, by_path);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::SchemaParser;
+
+  fn schema(documents: &[&str]) -> Schema {
+    let mut schema_parser = SchemaParser::new();
+    for json in documents {
+      schema_parser.write_json(json).unwrap();
+    }
+    schema_parser.flush()
+  }
+
+  #[test]
+  fn it_finds_no_changes() {
+    let before = schema(&[r#"{"name": "Nori", "owner": {"name": "Ana"}}"#]);
+    let after = schema(&[r#"{"name": "Rey", "owner": {"name": "Ben"}}"#]);
+    assert!(diff(&before, &after).is_empty());
+  }
+
+  #[test]
+  fn it_finds_added_and_removed_fields() {
+    let before = schema(&[r#"{"name": "Nori", "owner": {"name": "Ana"}}"#]);
+    let after = schema(&[r#"{"name": "Rey", "owner": {"age": 30}}"#]);
+    let diff = diff(&before, &after);
+    assert_eq!(diff.added, vec!["owner.age"]);
+    assert_eq!(diff.removed, vec!["owner.name"]);
+    assert!(diff.changed.is_empty());
+  }
+
+  #[test]
+  #[allow(clippy::float_cmp)]
+  fn it_finds_changed_types_and_probabilities() {
+    let before = schema(&[r#"{"name": "Nori", "age": 5}"#]);
+    let after = schema(&[
+      r#"{"name": "Rey", "age": "3"}"#,
+      r#"{"name": "Chashu", "age": 2}"#,
+      r#"{"age": 4}"#,
+      r#"{"age": 1}"#,
+    ]);
+    let diff = diff(&before, &after);
+    assert_eq!(diff.changed.len(), 2);
+
+    let name = &diff.changed[0];
+    assert_eq!(name.path, "name");
+    assert_eq!(name.probability_before, 1.0);
+    assert_eq!(name.probability_after, 0.5);
+    assert_eq!(name.types[0].bson_type, "String");
+    assert_eq!(name.types[0].probability_after, 0.5);
+
+    let age = &diff.changed[1];
+    assert_eq!(age.path, "age");
+    assert_eq!(age.probability_after, 1.0);
+    assert_eq!(
+      age.types,
+      vec![
+        TypeDiff {
+          bson_type: "Int32".to_string(),
+          probability_before: 1.0,
+          probability_after: 0.75,
+        },
+        TypeDiff {
+          bson_type: "String".to_string(),
+          probability_before: 0.0,
+          probability_after: 0.25,
+        },
+      ]
+    );
+  }
+}
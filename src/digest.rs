@@ -0,0 +1,272 @@
+use std::cmp::Ordering;
+use std::f64::consts::PI;
+use std::mem;
+
+// How many centroids a digest keeps, roughly. Centroids are smallest at the
+// tails, where percentiles like p99 need the most accuracy.
+const COMPRESSION: f64 = 100.0;
+// Values buffered before they're merged into the centroids.
+const BUFFER: usize = 100;
+// Centroids a digest is restored to from its percentiles.
+const RESTORED_CENTROIDS: usize = 100;
+
+/// Percentiles of the numbers seen for a numeric type, estimated with a
+/// t-digest. `min` and `max` are exact.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Percentiles {
+  pub min: f64,
+  pub p50: f64,
+  pub p90: f64,
+  pub p99: f64,
+  pub max: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Centroid {
+  mean: f64,
+  weight: f64,
+}
+
+/// Merging t-digest: a sorted list of centroids, each the mean of a run of
+/// neighbouring values, that stays small however many values are inserted.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TDigest {
+  centroids: Vec<Centroid>,
+  buffer: Vec<Centroid>,
+  count: f64,
+  min: f64,
+  max: f64,
+}
+
+impl Default for TDigest {
+  fn default() -> Self {
+    TDigest {
+      centroids: Vec::new(),
+      buffer: Vec::new(),
+      count: 0.0,
+      min: f64::INFINITY,
+      max: f64::NEG_INFINITY,
+    }
+  }
+}
+
+impl TDigest {
+  /// Restores a digest from the percentiles of `count` numbers, with the
+  /// numbers spread out evenly between them.
+  pub fn restore(percentiles: &Percentiles, count: usize) -> Self {
+    let points = [
+      (0.0, percentiles.min),
+      (0.5, percentiles.p50),
+      (0.9, percentiles.p90),
+      (0.99, percentiles.p99),
+      (1.0, percentiles.max),
+    ];
+    let weight = count as f64 / RESTORED_CENTROIDS as f64;
+    let centroids = (0..RESTORED_CENTROIDS)
+      .map(|i| {
+        let q = (i as f64 + 0.5) / RESTORED_CENTROIDS as f64;
+        let upper = points.iter().position(|&(x, _)| x >= q).unwrap_or(4);
+        let mean = interpolate(points[upper - 1], points[upper], q);
+        Centroid { mean, weight }
+      })
+      .collect();
+    TDigest {
+      centroids,
+      buffer: Vec::new(),
+      count: count as f64,
+      min: percentiles.min,
+      max: percentiles.max,
+    }
+  }
+
+  pub fn insert(&mut self, value: f64) {
+    if value.is_nan() {
+      return;
+    }
+    self.min = self.min.min(value);
+    self.max = self.max.max(value);
+    self.buffer.push(Centroid {
+      mean: value,
+      weight: 1.0,
+    });
+    if self.buffer.len() >= BUFFER {
+      self.compress();
+    }
+  }
+
+  pub fn merge(&mut self, mut other: TDigest) {
+    self.min = self.min.min(other.min);
+    self.max = self.max.max(other.max);
+    self.buffer.append(&mut other.centroids);
+    self.buffer.append(&mut other.buffer);
+    self.compress();
+  }
+
+  /// Returns the percentiles of the numbers inserted, if there were any.
+  pub fn percentiles(&mut self) -> Option<Percentiles> {
+    self.compress();
+    if self.count == 0.0 {
+      return None;
+    }
+    Some(Percentiles {
+      min: self.min,
+      p50: self.quantile(0.5),
+      p90: self.quantile(0.9),
+      p99: self.quantile(0.99),
+      max: self.max,
+    })
+  }
+
+  /// Number of numbers inserted.
+  pub fn count(&mut self) -> usize {
+    self.compress();
+    self.count as usize
+  }
+
+  /// Estimates how many of the numbers inserted are at most `value`, the
+  /// inverse of a quantile.
+  pub fn rank(&mut self, value: f64) -> f64 {
+    self.compress();
+    if value < self.min {
+      return 0.0;
+    }
+    if value >= self.max {
+      return self.count;
+    }
+    let mut before = 0.0;
+    let mut previous = (self.min, 0.0);
+    for centroid in &self.centroids {
+      let centre = before + centroid.weight / 2.0;
+      if value < centroid.mean {
+        return interpolate(previous, (centroid.mean, centre), value);
+      }
+      previous = (centroid.mean, centre);
+      before += centroid.weight;
+    }
+    interpolate(previous, (self.max, self.count), value)
+  }
+
+  // Merges buffered values into the centroids. Neighbouring centroids are
+  // merged for as long as they stay within one unit of the scale function,
+  // which allows for larger centroids around the median than at the tails.
+  fn compress(&mut self) {
+    if self.buffer.is_empty() {
+      return;
+    }
+    let mut all = mem::replace(&mut self.centroids, Vec::new());
+    all.append(&mut self.buffer);
+    all.sort_by(|a, b| a.mean.partial_cmp(&b.mean).unwrap_or(Ordering::Equal));
+    let total: f64 = all.iter().map(|c| c.weight).sum();
+
+    let mut centroids = Vec::new();
+    let mut before = 0.0;
+    let mut current = all[0];
+    for next in all.into_iter().skip(1) {
+      let q_left = before / total;
+      let q_right = (before + current.weight + next.weight) / total;
+      if scale(q_right) - scale(q_left) <= 1.0 {
+        let weight = current.weight + next.weight;
+        current.mean += (next.mean - current.mean) * next.weight / weight;
+        current.weight = weight;
+      } else {
+        before += current.weight;
+        centroids.push(current);
+        current = next;
+      }
+    }
+    centroids.push(current);
+    self.centroids = centroids;
+    self.count = total;
+  }
+
+  // Interpolates between the centres of the centroids around the `q`th
+  // value, and the exact min and max at either end.
+  fn quantile(&self, q: f64) -> f64 {
+    let target = q * self.count;
+    let mut before = 0.0;
+    let mut previous = (0.0, self.min);
+    for centroid in &self.centroids {
+      let centre = before + centroid.weight / 2.0;
+      if target < centre {
+        return interpolate(previous, (centre, centroid.mean), target);
+      }
+      previous = (centre, centroid.mean);
+      before += centroid.weight;
+    }
+    interpolate(previous, (self.count, self.max), target)
+  }
+}
+
+// k1 scale function of the t-digest paper, mapping a quantile to the index
+// of the centroid it belongs to.
+fn scale(q: f64) -> f64 {
+  COMPRESSION / (2.0 * PI) * (2.0 * q.min(1.0) - 1.0).asin()
+}
+
+fn interpolate((x0, y0): (f64, f64), (x1, y1): (f64, f64), x: f64) -> f64 {
+  if x1 <= x0 {
+    y1
+  } else {
+    y0 + (y1 - y0) * (x - x0) / (x1 - x0)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn digest_of(values: impl Iterator<Item = f64>) -> TDigest {
+    let mut digest = TDigest::default();
+    for value in values {
+      digest.insert(value);
+    }
+    digest
+  }
+
+  #[test]
+  fn it_estimates_percentiles() {
+    let mut digest = digest_of((1..=10_000).map(f64::from));
+    let percentiles = digest.percentiles().unwrap();
+    assert_eq!(percentiles.min, 1.0);
+    assert_eq!(percentiles.max, 10_000.0);
+    assert!((percentiles.p50 - 5000.0).abs() < 50.0);
+    assert!((percentiles.p90 - 9000.0).abs() < 50.0);
+    assert!((percentiles.p99 - 9900.0).abs() < 10.0);
+    assert!(digest.centroids.len() < 2 * COMPRESSION as usize);
+  }
+
+  #[test]
+  fn it_has_no_percentiles_without_values() {
+    assert_eq!(TDigest::default().percentiles(), None);
+    assert_eq!(digest_of(vec![f64::NAN].into_iter()).percentiles(), None);
+  }
+
+  #[test]
+  fn it_merges() {
+    let mut digest = digest_of((1..=5000).map(f64::from));
+    digest.merge(digest_of((5001..=10_000).map(f64::from)));
+    let percentiles = digest.percentiles().unwrap();
+    assert_eq!(percentiles.max, 10_000.0);
+    assert!((percentiles.p90 - 9000.0).abs() < 50.0);
+  }
+
+  #[test]
+  fn it_restores_from_percentiles() {
+    let mut digest = digest_of((1..=10_000).map(f64::from));
+    let percentiles = digest.percentiles().unwrap();
+    let mut restored = TDigest::restore(&percentiles, 10_000);
+    let restored = restored.percentiles().unwrap();
+    assert_eq!(restored.min, percentiles.min);
+    assert!((restored.p50 - percentiles.p50).abs() < 100.0);
+    assert!((restored.p99 - percentiles.p99).abs() < 100.0);
+  }
+
+  #[test]
+  fn it_estimates_ranks() {
+    let mut digest = digest_of((1..=10_000).map(f64::from));
+    assert_eq!(digest.rank(0.0), 0.0);
+    assert!((digest.rank(2500.0) - 2500.0).abs() < 50.0);
+    assert_eq!(digest.rank(10_000.0), 10_000.0);
+    assert_eq!(digest.count(), 10_000);
+  }
+}
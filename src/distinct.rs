@@ -0,0 +1,309 @@
+use super::Bson;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::mem;
+
+// Sketches have 2^PRECISION one byte registers, for a standard error of
+// about 3%.
+const PRECISION: u32 = 10;
+const REGISTERS: usize = 1 << PRECISION;
+// Rough size of a hash in `seen`, including the set's overhead.
+const HASH_SIZE: usize = 16;
+// Number of hashes past which a set takes up more memory than a sketch.
+const SKETCH_AFTER: usize = REGISTERS / HASH_SIZE;
+
+/// Keeps track of how many distinct values a FieldType has seen. This is
+/// independent from the values that get stored, so `unique` and
+/// `has_duplicates` stay correct when values are capped or not stored at all.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct DistinctValues {
+  seen: HashSet<u64>,
+  // replaces `seen` once the memory budget is exceeded
+  sketch: Option<Sketch>,
+  total: usize,
+  // distinct values counted before the schema was serialized, whose hashes
+  // are no longer known
+  restored: usize,
+}
+
+impl DistinctValues {
+  /// Counts a value, and returns the bytes its hash newly takes up. When
+  /// `approximate`, i.e. past the memory budget, hashes that would take up
+  /// more memory than a sketch are replaced by one, and values are only
+  /// estimated from then on.
+  pub fn insert(&mut self, value: &Bson, approximate: bool) -> usize {
+    let mut hasher = DefaultHasher::new();
+    hash_value(value, &mut hasher);
+    let hash = hasher.finish();
+    self.total += 1;
+    let outgrown = self.seen.len() >= SKETCH_AFTER;
+    if approximate && outgrown && self.sketch.is_none() {
+      self.sketch = Some(self.take_sketch());
+    }
+    match self.sketch.as_mut() {
+      Some(sketch) => {
+        sketch.insert(hash);
+        0
+      }
+      None if self.seen.insert(hash) => HASH_SIZE,
+      None => 0,
+    }
+  }
+
+  pub fn unique(&self) -> usize {
+    match &self.sketch {
+      // estimates can be off either way, but never above the values seen
+      Some(sketch) => {
+        let seen = self.total.saturating_sub(self.restored);
+        self.restored + sketch.estimate().min(seen)
+      }
+      None => self.restored + self.seen.len(),
+    }
+  }
+
+  /// Whether distinct values are estimated rather than counted exactly.
+  pub fn is_approximate(&self) -> bool {
+    self.sketch.is_some()
+  }
+
+  pub fn has_duplicates(&self) -> bool {
+    self.unique() < self.total
+  }
+
+  pub fn merge(&mut self, other: DistinctValues) {
+    self.total += other.total;
+    self.restored += other.restored;
+    if self.sketch.is_none() && other.sketch.is_some() {
+      self.sketch = Some(self.take_sketch());
+    }
+    match self.sketch.as_mut() {
+      Some(sketch) => {
+        sketch.extend(other.seen);
+        if let Some(other_sketch) = other.sketch {
+          sketch.merge(&other_sketch);
+        }
+      }
+      None => self.seen.extend(other.seen),
+    }
+  }
+
+  // Moves the hashes seen so far into a new sketch, freeing up their set.
+  fn take_sketch(&mut self) -> Sketch {
+    let mut sketch = Sketch::default();
+    sketch.extend(mem::replace(&mut self.seen, HashSet::new()));
+    sketch
+  }
+
+  /// Restores the counts of a deserialized FieldType. Values seen from then on
+  /// can't be compared with the restored ones, so each new distinct value is
+  /// counted as unique. Counts that are still known are kept as they are.
+  pub fn restore(&mut self, unique: usize, has_duplicates: bool) {
+    if self.total > 0 {
+      return;
+    }
+    self.restored = unique;
+    self.total = unique + has_duplicates as usize;
+  }
+}
+
+/// HyperLogLog sketch, estimating the number of distinct hashes inserted in
+/// a fixed amount of memory. The first bits of a hash pick a register, which
+/// keeps the longest run of leading zeros seen in the remaining bits.
+#[derive(Debug, Clone, PartialEq)]
+struct Sketch {
+  registers: Vec<u8>,
+}
+
+impl Default for Sketch {
+  fn default() -> Self {
+    Sketch {
+      registers: vec![0; REGISTERS],
+    }
+  }
+}
+
+impl Sketch {
+  fn insert(&mut self, hash: u64) {
+    let register = (hash >> (64 - PRECISION)) as usize;
+    let rank = (hash << PRECISION).leading_zeros().min(64 - PRECISION) + 1;
+    let rank = rank as u8;
+    if rank > self.registers[register] {
+      self.registers[register] = rank;
+    }
+  }
+
+  fn extend<I: IntoIterator<Item = u64>>(&mut self, hashes: I) {
+    for hash in hashes {
+      self.insert(hash);
+    }
+  }
+
+  fn merge(&mut self, other: &Sketch) {
+    for (register, other) in self.registers.iter_mut().zip(&other.registers) {
+      *register = (*register).max(*other);
+    }
+  }
+
+  fn estimate(&self) -> usize {
+    let m = REGISTERS as f64;
+    let alpha = 0.7213 / (1.0 + 1.079 / m);
+    let sum: f64 = self
+      .registers
+      .iter()
+      .map(|&rank| 2f64.powi(-i32::from(rank)))
+      .sum();
+    let estimate = alpha * m * m / sum;
+    let empty = self.registers.iter().filter(|&&rank| rank == 0).count();
+    // linear counting is more accurate while few values have been seen
+    if estimate <= 2.5 * m && empty > 0 {
+      (m * (m / empty as f64).ln()).round() as usize
+    } else {
+      estimate.round() as usize
+    }
+  }
+}
+
+// Bson values can't be hashed directly because of floats and documents.
+// Values are hashed as a whole, even when only a summary of them is stored,
+// i.e. two binaries of the same length are still told apart. Floats are
+// hashed by their bits, which also keeps NaN values countable.
+fn hash_value<H: Hasher>(value: &Bson, state: &mut H) {
+  mem::discriminant(value).hash(state);
+  match value {
+    Bson::FloatingPoint(num) => num.to_bits().hash(state),
+    Bson::String(string)
+    | Bson::Symbol(string)
+    | Bson::JavaScriptCode(string) => string.hash(state),
+    Bson::JavaScriptCodeWithScope(code, scope) => {
+      code.hash(state);
+      for (key, value) in scope {
+        key.hash(state);
+        hash_value(value, state);
+      }
+    }
+    Bson::Array(values) => {
+      values.len().hash(state);
+      for value in values {
+        hash_value(value, state);
+      }
+    }
+    Bson::Document(doc) => {
+      for (key, value) in doc {
+        key.hash(state);
+        hash_value(value, state);
+      }
+    }
+    Bson::Boolean(boolean) => boolean.hash(state),
+    Bson::Null => (),
+    Bson::RegExp(pattern, options) => {
+      pattern.hash(state);
+      options.hash(state);
+    }
+    Bson::I32(num) => num.hash(state),
+    Bson::I64(num) | Bson::TimeStamp(num) => num.hash(state),
+    Bson::Binary(sub_type, bytes) => {
+      u8::from(*sub_type).hash(state);
+      bytes.hash(state);
+    }
+    Bson::ObjectId(id) => id.bytes().hash(state),
+    Bson::UtcDatetime(date) => date.hash(state),
+    Bson::Decimal128(d128) => d128.to_string().hash(state),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use bson::spec::BinarySubtype;
+
+  #[test]
+  fn it_counts_unique_values() {
+    let mut distinct = DistinctValues::default();
+    distinct.insert(&Bson::String("Berlin".to_string()), false);
+    distinct.insert(&Bson::String("Hamburg".to_string()), false);
+    assert_eq!(distinct.unique(), 2);
+    assert!(!distinct.has_duplicates());
+  }
+
+  #[test]
+  fn it_finds_duplicates() {
+    let mut distinct = DistinctValues::default();
+    distinct.insert(&Bson::FloatingPoint(f64::NAN), false);
+    distinct.insert(&Bson::FloatingPoint(f64::NAN), false);
+    assert_eq!(distinct.unique(), 1);
+    assert!(distinct.has_duplicates());
+  }
+
+  #[test]
+  fn it_tells_types_apart() {
+    let mut distinct = DistinctValues::default();
+    distinct.insert(&Bson::I32(1), false);
+    distinct.insert(&Bson::I64(1), false);
+    assert_eq!(distinct.unique(), 2);
+  }
+
+  #[test]
+  fn it_tells_binaries_of_same_length_apart() {
+    let mut distinct = DistinctValues::default();
+    distinct.insert(&Bson::Binary(BinarySubtype::Generic, vec![1, 2]), false);
+    distinct.insert(&Bson::Binary(BinarySubtype::Generic, vec![3, 4]), false);
+    assert_eq!(distinct.unique(), 2);
+  }
+
+  #[test]
+  fn it_merges() {
+    let mut distinct = DistinctValues::default();
+    distinct.insert(&Bson::Boolean(true), false);
+    let mut other = DistinctValues::default();
+    other.insert(&Bson::Boolean(true), false);
+    distinct.merge(other);
+    assert_eq!(distinct.unique(), 1);
+    assert!(distinct.has_duplicates());
+  }
+
+  #[test]
+  fn it_restores() {
+    let mut distinct = DistinctValues::default();
+    distinct.restore(3, true);
+    assert_eq!(distinct.unique(), 3);
+    assert!(distinct.has_duplicates());
+    distinct.insert(&Bson::Boolean(true), false);
+    assert_eq!(distinct.unique(), 4);
+  }
+
+  #[test]
+  fn it_estimates_past_the_memory_budget() {
+    let mut distinct = DistinctValues::default();
+    for num in 0..10_000 {
+      distinct.insert(&Bson::I32(num % 5000), true);
+    }
+    assert!(distinct.is_approximate());
+    assert!(distinct.seen.is_empty());
+    let unique = distinct.unique() as f64;
+    assert!((unique - 5000.0).abs() < 5000.0 * 0.1);
+    assert!(distinct.has_duplicates());
+  }
+
+  #[test]
+  fn it_counts_small_sets_exactly_past_the_memory_budget() {
+    let mut distinct = DistinctValues::default();
+    distinct.insert(&Bson::Boolean(true), true);
+    distinct.insert(&Bson::Boolean(false), true);
+    assert!(!distinct.is_approximate());
+    assert_eq!(distinct.unique(), 2);
+  }
+
+  #[test]
+  fn it_merges_sketches() {
+    let mut distinct = DistinctValues::default();
+    let mut other = DistinctValues::default();
+    for num in 0..2000 {
+      distinct.insert(&Bson::I32(num), true);
+      other.insert(&Bson::I32(num + 1000), false);
+    }
+    distinct.merge(other);
+    let unique = distinct.unique() as f64;
+    assert!((unique - 3000.0).abs() < 3000.0 * 0.1);
+  }
+}
@@ -0,0 +1,282 @@
+use super::{ParseError, Schema, SchemaParser, Violation};
+use crate::diff;
+use bson::Document;
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// How much a probability may shift before it's reported, unless configured
+/// otherwise.
+pub static DEFAULT_DRIFT_THRESHOLD: f32 = 0.1;
+
+/// A change in documents compared to a baseline Schema.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum DriftEvent {
+  /// A field the baseline has never seen.
+  NewField { path: String },
+  /// A type the baseline has never seen for a field.
+  NewType { path: String, bson_type: String },
+  /// The probability of a field, or of one of its types if `bson_type` is
+  /// set, moved further than the threshold from the baseline.
+  ProbabilityShift {
+    path: String,
+    bson_type: Option<String>,
+    baseline: f32,
+    current: f32,
+  },
+}
+
+/// Watches documents for drift from a baseline Schema, i.e. the schema of a
+/// collection's production data analysed last week. New fields and types are
+/// reported as soon as a document has them, and probability shifts are
+/// checked on demand, once enough documents were seen for them to mean
+/// something.
+#[derive(Debug, Clone)]
+pub struct DriftDetector {
+  baseline: Schema,
+  threshold: f32,
+  schema_parser: SchemaParser,
+  // (path, bson_type) of new fields and types that were already reported
+  reported: HashSet<(String, Option<String>)>,
+}
+
+impl DriftDetector {
+  /// Returns a DriftDetector comparing documents to `baseline`.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::{DriftDetector, SchemaParser};
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let detector = DriftDetector::new(schema_parser.flush()).threshold(0.2);
+  /// ```
+  pub fn new(baseline: Schema) -> Self {
+    DriftDetector {
+      baseline,
+      threshold: DEFAULT_DRIFT_THRESHOLD,
+      schema_parser: SchemaParser::new(),
+      reported: HashSet::new(),
+    }
+  }
+
+  /// Sets how far from 0.0 to 1.0 a probability may move from the baseline
+  /// before it's reported as a `ProbabilityShift`.
+  pub fn threshold(mut self, threshold: f32) -> Self {
+    self.threshold = threshold;
+    self
+  }
+
+  /// Returns the Schema documents are compared to.
+  pub fn baseline(&self) -> &Schema {
+    &self.baseline
+  }
+
+  /// Returns the Schema of all documents written since the baseline.
+  pub fn schema(&self) -> Schema {
+    self.schema_parser.flush()
+  }
+
+  /// Writes a json document and returns the new fields and types it has.
+  /// Each new field or type is only reported the first time it's seen.
+  ///
+  /// # Arguments
+  /// * `json` - A json-like string slice. i.e `{ "name": "Nori" }`
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::{DriftDetector, DriftEvent, SchemaParser};
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let mut detector = DriftDetector::new(schema_parser.flush());
+  /// let events = detector
+  ///   .write_json(r#"{ "name": "Rey", "type": "Dog", "age": 4 }"#)
+  ///   .unwrap();
+  /// let age = DriftEvent::NewField {
+  ///   path: "age".to_string(),
+  /// };
+  /// assert_eq!(events, vec![age]);
+  /// ```
+  pub fn write_json(
+    &mut self,
+    json: &str,
+  ) -> Result<Vec<DriftEvent>, ParseError> {
+    let val: Value =
+      serde_json::from_str(json).map_err(ParseError::InvalidJson)?;
+    match crate::extended_json::to_bson(val)? {
+      bson::Bson::Document(doc) => self.write_document(doc),
+      _ => Err(ParseError::NotADocument),
+    }
+  }
+
+  /// Writes a document and returns the new fields and types it has. Each new
+  /// field or type is only reported the first time it's seen.
+  ///
+  /// # Arguments
+  /// * `doc` - A bson Document.
+  pub fn write_document(
+    &mut self,
+    doc: Document,
+  ) -> Result<Vec<DriftEvent>, ParseError> {
+    let mut events = Vec::new();
+    for violation in self.baseline.validate(&doc) {
+      let (event, key) = match violation {
+        Violation::UnknownField { path } => {
+          let key = (path.clone(), None);
+          (DriftEvent::NewField { path }, key)
+        }
+        Violation::UnexpectedType { path, bson_type } => {
+          let key = (path.clone(), Some(bson_type.clone()));
+          (DriftEvent::NewType { path, bson_type }, key)
+        }
+        // missing fields show up as probability shifts
+        Violation::MissingField { .. } => continue,
+      };
+      if self.reported.insert(key) {
+        events.push(event);
+      }
+    }
+    self.schema_parser.write_document(doc)?;
+    Ok(events)
+  }
+
+  /// Returns the fields, and types of fields, whose probability in documents
+  /// written since the baseline moved further than the threshold from their
+  /// probability in the baseline. Fields that stopped appearing have a
+  /// current probability of 0.0.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::{DriftDetector, SchemaParser};
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let mut detector = DriftDetector::new(schema_parser.flush());
+  /// detector.write_json(r#"{ "name": "Rey" }"#).unwrap();
+  /// let shifts = detector.probability_shifts();
+  /// // "type" is missing from all documents since
+  /// assert_eq!(shifts.len(), 1);
+  /// ```
+  pub fn probability_shifts(&self) -> Vec<DriftEvent> {
+    if self.schema_parser.count == 0 {
+      return Vec::new();
+    }
+    let changes = self.baseline.diff(&self.schema());
+    let mut events = Vec::new();
+    for field in changes.changed {
+      let path = &field.path;
+      let (baseline, current) =
+        (field.probability_before, field.probability_after);
+      self.push_shift(&mut events, path, None, baseline, current);
+      for t in field.types {
+        let (baseline, current) = (t.probability_before, t.probability_after);
+        let bson_type = Some(t.bson_type);
+        self.push_shift(&mut events, path, bson_type, baseline, current);
+      }
+    }
+    let baseline_fields = diff::fields_by_path(self.baseline.fields());
+    for path in changes.removed {
+      if let Some(field) = baseline_fields.get(path.as_str()) {
+        self.push_shift(&mut events, &path, None, field.probability, 0.0);
+      }
+    }
+    events
+  }
+
+  fn push_shift(
+    &self,
+    events: &mut Vec<DriftEvent>,
+    path: &str,
+    bson_type: Option<String>,
+    baseline: f32,
+    current: f32,
+  ) {
+    if (baseline - current).abs() > self.threshold {
+      events.push(DriftEvent::ProbabilityShift {
+        path: path.to_string(),
+        bson_type,
+        baseline,
+        current,
+      });
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn detector() -> DriftDetector {
+    let mut schema_parser = SchemaParser::new();
+    for _ in 0..4 {
+      schema_parser
+        .write_json(r#"{"name": "Nori", "age": 5, "owner": {"name": "Ana"}}"#)
+        .unwrap();
+    }
+    DriftDetector::new(schema_parser.flush())
+  }
+
+  #[test]
+  fn it_reports_new_fields_and_types_once() {
+    let mut detector = detector();
+    let json = r#"{"name": "Rey", "age": "5", "owner": {"phone": "555"}}"#;
+    let events = detector.write_json(json).unwrap();
+    assert_eq!(
+      events,
+      vec![
+        DriftEvent::NewType {
+          path: "age".to_string(),
+          bson_type: "String".to_string(),
+        },
+        DriftEvent::NewField {
+          path: "owner.phone".to_string(),
+        },
+      ]
+    );
+    assert!(detector.write_json(json).unwrap().is_empty());
+  }
+
+  #[test]
+  fn it_reports_probability_shifts() {
+    let mut detector = detector().threshold(0.3);
+    assert!(detector.probability_shifts().is_empty());
+
+    detector.write_json(r#"{"name": "Rey", "age": 5}"#).unwrap();
+    detector
+      .write_json(r#"{"name": "Rey", "age": "5"}"#)
+      .unwrap();
+    detector.write_json(r#"{"name": "Rey", "age": 4}"#).unwrap();
+    detector.write_json(r#"{"name": "Rey", "age": 3}"#).unwrap();
+    // age's String type only shifted by 0.25
+    assert_eq!(
+      detector.probability_shifts(),
+      vec![
+        DriftEvent::ProbabilityShift {
+          path: "owner".to_string(),
+          bson_type: None,
+          baseline: 1.0,
+          current: 0.0,
+        },
+        DriftEvent::ProbabilityShift {
+          path: "owner.name".to_string(),
+          bson_type: None,
+          baseline: 1.0,
+          current: 0.0,
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn it_checks_dynamic_keys_against_their_wildcard() {
+    let mut schema_parser =
+      SchemaParser::builder().wildcard_threshold(3).build();
+    schema_parser
+      .write_json(r#"{"votes": {"u1": 1, "u2": -1, "u3": 1, "u4": 1}}"#)
+      .unwrap();
+    let mut detector = DriftDetector::new(schema_parser.flush());
+    let json = r#"{"votes": {"u5": 1, "u6": -1}}"#;
+    assert!(detector.write_json(json).unwrap().is_empty());
+    assert!(detector.probability_shifts().is_empty());
+  }
+}
@@ -0,0 +1,275 @@
+use super::{Bson, Document, ParseError, ParserOptions, SchemaParser};
+use mongodb::options::AggregateOptions;
+use mongodb::{Collection, Cursor};
+use std::{panic, thread};
+
+/// Number of documents `SchemaParser::analyze_collection()` samples unless
+/// configured otherwise.
+pub static DEFAULT_SAMPLE_SIZE: usize = 1000;
+
+/// Options for analysing a live collection with
+/// `SchemaParser::analyze_collection()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnalyzeOptions {
+  /// Number of documents to pick with a `$sample` stage. `None` analyses
+  /// every document, which can take long on big collections. Defaults to
+  /// `DEFAULT_SAMPLE_SIZE`.
+  pub sample_size: Option<usize>,
+  /// Query documents need to match to be sampled, i.e. to analyse recent
+  /// documents only. `None` samples from the whole collection.
+  pub filter: Option<Document>,
+  /// Analysis options for the SchemaParser documents are written to.
+  pub parser_options: ParserOptions,
+}
+
+impl Default for AnalyzeOptions {
+  fn default() -> Self {
+    AnalyzeOptions {
+      sample_size: Some(DEFAULT_SAMPLE_SIZE),
+      filter: None,
+      parser_options: ParserOptions::default(),
+    }
+  }
+}
+
+/// Returns the aggregation pipeline that picks the documents to analyse. The
+/// filter runs before `$sample`, so the sample is taken from the matching
+/// documents only.
+pub(crate) fn pipeline(options: &AnalyzeOptions) -> Vec<Document> {
+  let mut pipeline = Vec::new();
+  if let Some(filter) = options.filter.as_ref() {
+    let mut stage = Document::new();
+    stage.insert("$match", Bson::Document(filter.clone()));
+    pipeline.push(stage);
+  }
+  if let Some(sample_size) = options.sample_size {
+    let mut size = Document::new();
+    size.insert("size", Bson::I64(sample_size as i64));
+    let mut stage = Document::new();
+    stage.insert("$sample", Bson::Document(size));
+    pipeline.push(stage);
+  }
+  pipeline
+}
+
+/// Writes the documents `options` pick from a collection to a new
+/// SchemaParser, which isn't finalised yet so it can be merged with others.
+pub(crate) fn sample(
+  collection: &Collection,
+  options: &AnalyzeOptions,
+) -> Result<SchemaParser, ParseError> {
+  // samples bigger than 5% of the collection are sorted in memory
+  let aggregate_options =
+    AggregateOptions::builder().allow_disk_use(true).build();
+  let cursor = collection.aggregate(pipeline(options), aggregate_options)?;
+  let mut schema_parser =
+    SchemaParser::with_options(options.parser_options.clone());
+  for doc in cursor {
+    schema_parser.write_document(doc?)?;
+  }
+  Ok(schema_parser)
+}
+
+/// Samples each partition of a collection on its own thread, taking a share
+/// of the sample size proportional to the partition's share of documents, and
+/// merges the results into one SchemaParser.
+pub(crate) fn sample_partitions(
+  partitions: &[Collection],
+  options: &AnalyzeOptions,
+) -> Result<SchemaParser, ParseError> {
+  let sizes = match options.sample_size {
+    Some(sample_size) => {
+      let mut counts = Vec::new();
+      for partition in partitions {
+        counts.push(count(partition, options)?);
+      }
+      sample_sizes(&counts, sample_size)
+        .into_iter()
+        .map(Some)
+        .collect()
+    }
+    None => vec![None; partitions.len()],
+  };
+
+  let workers: Vec<_> = partitions
+    .iter()
+    .zip(sizes)
+    // a $sample of 0 documents is an error
+    .filter(|(_, sample_size)| *sample_size != Some(0))
+    .map(|(partition, sample_size)| {
+      let partition = partition.clone();
+      let options = AnalyzeOptions {
+        sample_size,
+        ..options.clone()
+      };
+      thread::spawn(move || sample(&partition, &options))
+    })
+    .collect();
+
+  let mut schema_parser =
+    SchemaParser::with_options(options.parser_options.clone());
+  for worker in workers {
+    match worker.join() {
+      Ok(sampled) => schema_parser.merge(sampled?),
+      Err(e) => panic::resume_unwind(e),
+    }
+  }
+  Ok(schema_parser)
+}
+
+fn count(
+  collection: &Collection,
+  options: &AnalyzeOptions,
+) -> Result<u64, ParseError> {
+  let count = match options.filter.as_ref() {
+    Some(filter) => collection.count_documents(filter.clone(), None)?,
+    None => collection.estimated_document_count(None)?,
+  };
+  Ok(count.max(0) as u64)
+}
+
+/// Splits `sample_size` between partitions with `counts` documents in
+/// proportion to their counts, rounded to the nearest document. No partition
+/// gets more than all of its documents.
+pub(crate) fn sample_sizes(counts: &[u64], sample_size: usize) -> Vec<usize> {
+  let total: u64 = counts.iter().sum();
+  counts
+    .iter()
+    .map(|&count| {
+      if total == 0 {
+        return 0;
+      }
+      let share = (sample_size as u128 * u128::from(count)
+        + u128::from(total) / 2)
+        / u128::from(total);
+      share.min(u128::from(count)) as usize
+    })
+    .collect()
+}
+
+/// Opens a change stream on a collection to pass to
+/// `SchemaParser::tail_change_stream()`. Updates come with the whole document
+/// as it is after the update, so they can be analysed like inserts. The
+/// stream waits for new changes until it's dropped.
+///
+/// # Arguments
+/// * `collection` - Collection to watch.
+pub fn change_stream(collection: &Collection) -> Result<Cursor, ParseError> {
+  let mut options = Document::new();
+  options.insert("fullDocument", Bson::String("updateLookup".to_string()));
+  let mut stage = Document::new();
+  stage.insert("$changeStream", Bson::Document(options));
+  Ok(collection.aggregate(vec![stage], None)?)
+}
+
+/// Returns the document a change stream event wrote, for inserts, replaces,
+/// and updates looked up with `fullDocument: "updateLookup"`. Deletes and
+/// other events don't add anything to the schema.
+pub(crate) fn changed_document(event: Document) -> Option<Document> {
+  match event.get_str("operationType") {
+    Ok("insert") | Ok("replace") | Ok("update") => (),
+    _ => return None,
+  }
+  match event.get("fullDocument") {
+    Some(Bson::Document(doc)) => Some(doc.clone()),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::SchemaParser;
+  use bson::{bson, doc};
+
+  #[test]
+  fn it_samples_by_default() {
+    let pipeline = pipeline(&AnalyzeOptions::default());
+    assert_eq!(pipeline, vec![doc! { "$sample": { "size": 1000_i64 } }]);
+  }
+
+  #[test]
+  fn it_filters_before_sampling() {
+    let options = AnalyzeOptions {
+      sample_size: Some(50),
+      filter: Some(doc! { "type": "Cat" }),
+      ..AnalyzeOptions::default()
+    };
+    assert_eq!(
+      pipeline(&options),
+      vec![
+        doc! { "$match": { "type": "Cat" } },
+        doc! { "$sample": { "size": 50_i64 } },
+      ]
+    );
+  }
+
+  #[test]
+  fn it_takes_written_documents_from_change_events() {
+    let insert = doc! {
+      "operationType": "insert",
+      "fullDocument": { "name": "Nori" }
+    };
+    assert_eq!(changed_document(insert), Some(doc! { "name": "Nori" }));
+    let update = doc! { "operationType": "update", "fullDocument": Bson::Null };
+    assert_eq!(changed_document(update), None);
+    let delete =
+      doc! { "operationType": "delete", "documentKey": { "_id": 1 } };
+    assert_eq!(changed_document(delete), None);
+  }
+
+  #[test]
+  fn it_tails_a_change_stream() {
+    let events: Vec<Result<Document, ParseError>> = vec![
+      Ok(doc! { "operationType": "insert", "fullDocument": { "a": 1 } }),
+      Ok(doc! { "operationType": "delete" }),
+      Ok(doc! { "operationType": "replace", "fullDocument": { "b": 1 } }),
+      Ok(doc! { "operationType": "insert", "fullDocument": { "a": 2 } }),
+    ];
+    let mut schema_parser = SchemaParser::new();
+    let mut counts = Vec::new();
+    schema_parser
+      .tail_change_stream(events, 2, |schema| counts.push(schema.count))
+      .unwrap();
+    assert_eq!(counts, vec![2]);
+    assert_eq!(schema_parser.count, 3);
+  }
+
+  #[test]
+  fn it_splits_samples_by_document_count() {
+    assert_eq!(sample_sizes(&[100, 300], 40), vec![10, 30]);
+    assert_eq!(sample_sizes(&[5, 995], 100), vec![1, 100]);
+    assert_eq!(sample_sizes(&[2, 2], 100), vec![2, 2]);
+    assert_eq!(sample_sizes(&[0, 0], 100), vec![0, 0]);
+  }
+
+  #[test]
+  #[allow(clippy::float_cmp)]
+  fn it_weighs_merged_partitions_by_their_samples() {
+    // a shard with 300 cats and a shard with 100 dogs, sampled 40 at a time
+    let sizes = sample_sizes(&[300, 100], 40);
+    let mut schema_parser = SchemaParser::new();
+    for (json, &size) in [r#"{"meows": 1}"#, r#"{"barks": 1}"#]
+      .iter()
+      .zip(sizes.iter())
+    {
+      let mut partition = SchemaParser::new();
+      for _ in 0..size {
+        partition.write_json(json).unwrap();
+      }
+      schema_parser.merge(partition);
+    }
+    let schema = schema_parser.flush();
+    assert_eq!(schema.get_field("meows").unwrap().probability, 0.75);
+    assert_eq!(schema.get_field("barks").unwrap().probability, 0.25);
+  }
+
+  #[test]
+  fn it_reads_every_document_without_a_sample_size() {
+    let options = AnalyzeOptions {
+      sample_size: None,
+      ..AnalyzeOptions::default()
+    };
+    assert!(pipeline(&options).is_empty());
+  }
+}
@@ -0,0 +1,151 @@
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Reads the next Bson document of a `.bson` file, or None at the end of the
+/// file.
+pub(crate) fn read_document<R: Read>(
+  reader: &mut R,
+) -> io::Result<Option<Vec<u8>>> {
+  match read_length(reader)? {
+    Some(length) => read_body(reader, length).map(Some),
+    None => Ok(None),
+  }
+}
+
+/// Reads the length prefix of the next Bson document, or None at the end of
+/// the input. Each document starts with its length in bytes, including the
+/// length itself, as a little endian i32.
+pub(crate) fn read_length<R: Read>(reader: &mut R) -> io::Result<Option<i32>> {
+  let mut length = [0; 4];
+  match reader.read_exact(&mut length) {
+    Ok(()) => Ok(Some(i32::from_le_bytes(length))),
+    Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+    Err(e) => Err(e),
+  }
+}
+
+/// Reads the rest of a Bson document whose length prefix was already read,
+/// and returns the whole document, prefix included.
+pub(crate) fn read_body<R: Read>(
+  reader: &mut R,
+  length: i32,
+) -> io::Result<Vec<u8>> {
+  let mut bytes = length.to_le_bytes().to_vec();
+  bytes.resize(length.max(4) as usize, 0);
+  reader.read_exact(&mut bytes[4..])?;
+  Ok(bytes)
+}
+
+/// Finds the collection files of a `mongodump` output directory, and the
+/// namespace of each, sorted by namespace. Collections are in a directory
+/// named after their database, which is either `path` itself or one of its
+/// subdirectories.
+pub(crate) fn collection_files(
+  path: &Path,
+) -> io::Result<Vec<(String, PathBuf)>> {
+  let mut files = Vec::new();
+  collect_files(path, &mut files)?;
+  for entry in fs::read_dir(path)? {
+    let entry = entry?;
+    if entry.file_type()?.is_dir() {
+      collect_files(&entry.path(), &mut files)?;
+    }
+  }
+  files.sort();
+  Ok(files)
+}
+
+fn collect_files(
+  database: &Path,
+  files: &mut Vec<(String, PathBuf)>,
+) -> io::Result<()> {
+  let database_name = match database.file_name().and_then(|n| n.to_str()) {
+    Some(name) => name.to_string(),
+    None => return Ok(()),
+  };
+  for entry in fs::read_dir(database)? {
+    let path = entry?.path();
+    let file_name = match path.file_name().and_then(|n| n.to_str()) {
+      Some(file_name) => file_name,
+      None => continue,
+    };
+    if let Some(collection) = collection_name(file_name) {
+      let namespace = format!("{}.{}", database_name, collection);
+      files.push((namespace, path.clone()));
+    }
+  }
+  Ok(())
+}
+
+// `mongodump --gzip` compresses each collection file on its own.
+fn collection_name(file_name: &str) -> Option<&str> {
+  if cfg!(feature = "compression") && file_name.ends_with(".bson.gz") {
+    return Some(&file_name[..file_name.len() - ".bson.gz".len()]);
+  }
+  if file_name.ends_with(".bson") {
+    return Some(&file_name[..file_name.len() - ".bson".len()]);
+  }
+  None
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{ParserOptions, SchemaParser};
+  use bson::{bson, doc, encode_document};
+  use std::env;
+
+  #[test]
+  fn it_reads_consecutive_documents() {
+    let mut bytes = Vec::new();
+    encode_document(&mut bytes, &doc! { "name": "Nori" }).unwrap();
+    encode_document(&mut bytes, &doc! { "name": "Rey", "age": 4 }).unwrap();
+    let mut schema_parser = SchemaParser::new();
+    schema_parser.read_bson_from(bytes.as_slice()).unwrap();
+    assert_eq!(schema_parser.count, 2);
+    assert_eq!(schema_parser.fields().count(), 2);
+  }
+
+  #[test]
+  fn it_fails_on_truncated_documents() {
+    let mut bytes = Vec::new();
+    encode_document(&mut bytes, &doc! { "name": "Nori" }).unwrap();
+    bytes.pop();
+    let mut schema_parser = SchemaParser::new();
+    assert!(schema_parser.read_bson_from(bytes.as_slice()).is_err());
+  }
+
+  #[test]
+  fn it_reads_dump_directories() {
+    let dump = env::temp_dir().join("mongodb-schema-parser-dump-test");
+    let shelter = dump.join("shelter");
+    fs::create_dir_all(&shelter).unwrap();
+    let mut pets = Vec::new();
+    encode_document(&mut pets, &doc! { "name": "Nori" }).unwrap();
+    encode_document(&mut pets, &doc! { "name": "Rey" }).unwrap();
+    fs::write(shelter.join("pets.bson"), pets).unwrap();
+    let mut owners = Vec::new();
+    encode_document(&mut owners, &doc! { "name": "Ana" }).unwrap();
+    fs::write(shelter.join("owners.bson"), owners).unwrap();
+    fs::write(shelter.join("pets.metadata.json"), "{}").unwrap();
+
+    let schemas = SchemaParser::read_dump(&dump, ParserOptions::default());
+    let from_database =
+      SchemaParser::read_dump(&shelter, ParserOptions::default());
+    fs::remove_dir_all(&dump).unwrap();
+
+    let schemas = schemas.unwrap();
+    let namespaces: Vec<&String> = schemas.keys().collect();
+    assert_eq!(namespaces, vec!["shelter.owners", "shelter.pets"]);
+    assert_eq!(schemas["shelter.pets"].count, 2);
+    assert_eq!(from_database.unwrap().len(), 2);
+  }
+
+  #[test]
+  fn it_names_collections_after_their_files() {
+    assert_eq!(collection_name("pets.bson"), Some("pets"));
+    assert_eq!(collection_name("pets.metadata.json"), None);
+    assert_eq!(collection_name("system.views.bson"), Some("system.views"));
+  }
+}
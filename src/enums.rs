@@ -0,0 +1,136 @@
+use crate::budget::value_size;
+use crate::ValueType;
+use std::cmp::Ordering;
+
+// How many times each distinct value has to be seen on average for a type to
+// be an enum candidate, so a handful of unique values isn't taken for one.
+const MIN_REPEATS: usize = 2;
+
+/// Collects the distinct values of a type for as long as there are at most
+/// `max` of them, to list them when the type looks like an enum. Past `max`
+/// the values are dropped, and the type can no longer be a candidate.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct EnumValues {
+  max: usize,
+  values: Vec<ValueType>,
+  overflowed: bool,
+}
+
+impl EnumValues {
+  pub fn new(max: usize) -> Self {
+    EnumValues {
+      max,
+      values: Vec::new(),
+      overflowed: false,
+    }
+  }
+
+  /// Restores the values of a type deserialized from `to_json()` output,
+  /// if they're all known. Otherwise the type is taken to have had too many
+  /// distinct values to collect.
+  pub fn restore(values: Option<&[ValueType]>, max: usize) -> Self {
+    let mut enums = EnumValues::new(max);
+    match values {
+      Some(values) => {
+        for value in values {
+          enums.insert(value);
+        }
+      }
+      None => enums.overflow(),
+    }
+    enums
+  }
+
+  /// Adds a value, and returns the bytes newly taken up by it.
+  pub fn insert(&mut self, value: &ValueType) -> usize {
+    if self.overflowed || self.values.contains(value) {
+      return 0;
+    }
+    if self.values.len() >= self.max {
+      self.overflow();
+      return 0;
+    }
+    self.values.push(value.clone());
+    value_size(value)
+  }
+
+  pub fn merge(&mut self, other: EnumValues) {
+    if other.overflowed {
+      self.overflow();
+    }
+    for value in other.values {
+      self.insert(&value);
+    }
+  }
+
+  /// Returns the sorted values of a type seen `count` times, if it has few
+  /// enough distinct values to be an enum.
+  pub fn candidates(&self, count: usize) -> Option<Vec<ValueType>> {
+    if self.overflowed
+      || self.values.is_empty()
+      || count < self.values.len() * MIN_REPEATS
+    {
+      return None;
+    }
+    let mut values = self.values.clone();
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    Some(values)
+  }
+
+  fn overflow(&mut self) {
+    self.overflowed = true;
+    self.values = Vec::new();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn status(status: &str) -> ValueType {
+    ValueType::Str(status.to_string())
+  }
+
+  #[test]
+  fn it_finds_enum_candidates() {
+    let mut enums = EnumValues::new(4);
+    for value in &["pending", "active", "active", "deleted", "active", "x"] {
+      enums.insert(&status(value));
+    }
+    let candidates = enums.candidates(10).unwrap();
+    assert_eq!(candidates[0], status("active"));
+    assert_eq!(candidates.len(), 4);
+  }
+
+  #[test]
+  fn it_needs_repeated_values() {
+    let mut enums = EnumValues::new(4);
+    enums.insert(&ValueType::I32(1));
+    enums.insert(&ValueType::I32(2));
+    assert!(enums.candidates(3).is_none());
+    assert_eq!(enums.candidates(4).unwrap().len(), 2);
+  }
+
+  #[test]
+  fn it_overflows_past_max() {
+    let mut enums = EnumValues::new(4);
+    for num in 0..5 {
+      enums.insert(&ValueType::I32(num));
+    }
+    enums.insert(&ValueType::I32(0));
+    assert!(enums.candidates(100).is_none());
+    let mut other = EnumValues::new(4);
+    other.insert(&ValueType::I32(0));
+    other.merge(enums);
+    assert!(other.candidates(100).is_none());
+  }
+
+  #[test]
+  fn it_restores() {
+    let values = vec![status("active"), status("deleted")];
+    let mut restored = EnumValues::restore(Some(&values[..]), 3);
+    restored.insert(&status("pending"));
+    assert_eq!(restored.candidates(6).unwrap().len(), 3);
+    assert!(EnumValues::restore(None, 3).candidates(6).is_none());
+  }
+}
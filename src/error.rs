@@ -1,85 +1,110 @@
-use failure::{Backtrace, Context, Fail};
+use std::error;
 use std::fmt::{self, Display};
 use std::io;
-use std::result;
-
-/// A specialized [`Result`] type for this crate's operations.
-///
-/// This is generally used to avoid writing out [Error] directly and
-/// is otherwise a direct mapping to [`Result`].
-///
-/// [`Result`]: https://doc.rust-lang.org/nightly/std/result/enum.Result.html
-/// [`Error`]: std.struct.Error.html
-pub type Result<T> = result::Result<T, Error>;
 
 /// A list enumerating the categories of errors in this crate.
 ///
-/// This list is intended to grow over time and it is not recommended to
-/// exhaustively match against it.
-///
-/// It is used with the [`Error`] struct.
-///
-/// [`Error`]: std.struct.Error.html
-#[derive(Debug, Fail)]
-pub enum ErrorKind {
-  /// Any error not part of this list.
-  #[fail(display = "Generic error.")]
-  Other,
-  /// An error caused by an IO failure.
-  #[fail(display = "{}", _0)]
-  Io(#[cause] io::Error),
-  /// An error caused by an unknown input type.
-  #[fail(
-    display = "Unknown Input Type for 'docs'. Must be an array or a MongoDB Cursor"
-  )]
-  UnknownInputType,
-  /// An parse error
-  #[fail(display = "Parse Error in input document")]
-  ParseError,
+/// This list is intended to grow over time and can't be exhaustively matched
+/// against. Documents nested deeper than `max_depth` aren't an error: past it
+/// they're only counted as Document types, and not descended into.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ParseError {
+  /// Input could not be parsed as json.
+  InvalidJson(serde_json::Error),
+  /// Input contained a malformed Extended JSON wrapper, i.e. `{"$oid": 1}`.
+  InvalidExtendedJson(String),
+  /// Input could not be decoded as Bson.
+  InvalidBson(bson::DecoderError),
+  /// Input was parsed, but its top level value is not a document.
+  NotADocument,
+  /// Input is not a `mongodump --archive`, or the archive is cut short.
+  InvalidArchive(String),
+  /// An error caused by an IO failure while reading input.
+  Io(io::Error),
+  /// The schema could not be serialized.
+  Serialization(serde_json::Error),
+  /// The MongoDB driver failed to read documents from a collection.
+  #[cfg(feature = "driver")]
+  Driver(mongodb::error::Error),
 }
 
-/// A specialized [`Error`] type for this crate's operations.
-///
-/// [`Error`]: https://doc.rust-lang.org/nightly/std/error/trait.Error.html
-#[derive(Debug)]
-pub struct Error {
-  inner: Context<ErrorKind>,
+impl Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      ParseError::InvalidJson(e) => write!(f, "Invalid json input: {}", e),
+      ParseError::InvalidExtendedJson(e) => {
+        write!(f, "Invalid Extended JSON input: {}", e)
+      }
+      ParseError::InvalidBson(e) => write!(f, "Invalid bson input: {}", e),
+      ParseError::NotADocument => {
+        write!(f, "Input must be a document, i.e. a json object")
+      }
+      ParseError::InvalidArchive(e) => {
+        write!(f, "Invalid mongodump archive: {}", e)
+      }
+      ParseError::Io(e) => write!(f, "{}", e),
+      ParseError::Serialization(e) => {
+        write!(f, "Failed to serialize schema: {}", e)
+      }
+      #[cfg(feature = "driver")]
+      ParseError::Driver(e) => write!(f, "MongoDB driver error: {}", e),
+    }
+  }
 }
 
-impl Error {
-  /// Access the [`ErrorKind`] member.
-  ///
-  /// [`ErrorKind`]: enum.ErrorKind.html
-  pub fn kind(&self) -> &ErrorKind {
-    &*self.inner.get_context()
+impl error::Error for ParseError {
+  fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+    match self {
+      ParseError::InvalidJson(e) | ParseError::Serialization(e) => Some(e),
+      ParseError::InvalidBson(e) => Some(e),
+      ParseError::Io(e) => Some(e),
+      #[cfg(feature = "driver")]
+      ParseError::Driver(e) => Some(e),
+      ParseError::InvalidExtendedJson(_)
+      | ParseError::InvalidArchive(_)
+      | ParseError::NotADocument => None,
+    }
   }
 }
 
-impl Fail for Error {
-  fn cause(&self) -> Option<&Fail> {
-    self.inner.cause()
+impl From<io::Error> for ParseError {
+  fn from(e: io::Error) -> ParseError {
+    ParseError::Io(e)
   }
+}
 
-  fn backtrace(&self) -> Option<&Backtrace> {
-    self.inner.backtrace()
+impl From<bson::DecoderError> for ParseError {
+  fn from(e: bson::DecoderError) -> ParseError {
+    ParseError::InvalidBson(e)
   }
 }
 
-impl Display for Error {
-  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    Display::fmt(&self.inner, f)
+#[cfg(feature = "driver")]
+impl From<mongodb::error::Error> for ParseError {
+  fn from(e: mongodb::error::Error) -> ParseError {
+    ParseError::Driver(e)
   }
 }
 
-impl From<ErrorKind> for Error {
-  fn from(kind: ErrorKind) -> Error {
-    let inner = Context::new(kind);
-    Error { inner }
+/// A line of newline delimited json that could not be written, as reported
+/// by `SchemaParser::write_many`.
+#[derive(Debug)]
+pub struct LineError {
+  /// Number of the line in the input, starting at 1.
+  pub line: usize,
+  /// Why the line could not be written.
+  pub error: ParseError,
+}
+
+impl Display for LineError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "line {}: {}", self.line, self.error)
   }
 }
 
-impl From<Context<ErrorKind>> for Error {
-  fn from(inner: Context<ErrorKind>) -> Error {
-    Error { inner }
+impl error::Error for LineError {
+  fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+    Some(&self.error)
   }
 }
@@ -0,0 +1,387 @@
+use super::{Bson, ParseError};
+use bson::oid::ObjectId;
+use bson::spec::BinarySubtype;
+use bson::{bson, doc, Document};
+use chrono::{DateTime, TimeZone, Utc};
+use failure::format_err;
+use serde_json::{Map, Number, Value};
+use std::convert::TryFrom;
+
+/// Converts a serde_json Value into Bson, turning MongoDB Extended JSON v2
+/// wrappers (canonical and relaxed) into their proper Bson types. Plain json
+/// is converted as is.
+pub fn to_bson(value: Value) -> Result<Bson, ParseError> {
+  convert(value, false)
+    .map_err(|e| ParseError::InvalidExtendedJson(e.to_string()))
+}
+
+/// Same as `to_bson`, but also recognises legacy Extended JSON v1 shell-mode
+/// constructors that older tools wrote out as strings, i.e.
+/// `"NumberLong(\"5\")"` or `"ISODate(\"2015-03-23T21:06:49.506Z\")"`.
+pub fn to_bson_legacy(value: Value) -> Result<Bson, ParseError> {
+  convert(value, true)
+    .map_err(|e| ParseError::InvalidExtendedJson(e.to_string()))
+}
+
+fn convert(value: Value, legacy: bool) -> Result<Bson, failure::Error> {
+  match value {
+    Value::Object(map) => from_object(map, legacy),
+    Value::Array(arr) => {
+      let arr: Result<Vec<Bson>, failure::Error> =
+        arr.into_iter().map(|val| convert(val, legacy)).collect();
+      Ok(Bson::Array(arr?))
+    }
+    Value::Number(num) => Ok(from_number(&num)),
+    Value::String(string) => {
+      if legacy {
+        if let Some(bson) = from_shell_constructor(&string) {
+          return Ok(bson);
+        }
+      }
+      Ok(Bson::String(string))
+    }
+    // booleans and nulls map one to one
+    value => Ok(Bson::from(value)),
+  }
+}
+
+fn from_object(
+  map: Map<String, Value>,
+  legacy: bool,
+) -> Result<Bson, failure::Error> {
+  if let Some(bson) = from_wrapper(&map, legacy)? {
+    return Ok(bson);
+  }
+
+  let mut doc = Document::new();
+  for (key, value) in map {
+    doc.insert(key, convert(value, legacy)?);
+  }
+  Ok(Bson::Document(doc))
+}
+
+// Relaxed Extended JSON represents numbers as plain json numbers, in which case
+// they are Int32 if they fit, Int64 if they don't and Double otherwise.
+fn from_number(num: &Number) -> Bson {
+  match num.as_i64() {
+    Some(int) => match i32::try_from(int) {
+      Ok(int) => Bson::I32(int),
+      Err(_) => Bson::I64(int),
+    },
+    None => Bson::FloatingPoint(num.as_f64().unwrap_or(f64::NAN)),
+  }
+}
+
+// Recognises `$`-prefixed wrapper objects, i.e. `{"$oid": "..."}`. Returns
+// None for regular documents.
+fn from_wrapper(
+  map: &Map<String, Value>,
+  legacy: bool,
+) -> Result<Option<Bson>, failure::Error> {
+  if map.len() == 2 {
+    return from_pair_wrapper(map, legacy);
+  }
+
+  let (key, value) = match map.iter().next() {
+    Some(entry) if map.len() == 1 => entry,
+    _ => return Ok(None),
+  };
+
+  let bson = match key.as_str() {
+    "$oid" => Bson::ObjectId(ObjectId::with_string(as_str(key, value)?)?),
+    "$date" => Bson::UtcDatetime(date(value)?),
+    "$numberInt" => Bson::I32(as_str(key, value)?.parse()?),
+    "$numberLong" => Bson::I64(as_str(key, value)?.parse()?),
+    "$numberDouble" => Bson::FloatingPoint(double(as_str(key, value)?)?),
+    "$numberDecimal" => decimal(as_str(key, value)?),
+    "$binary" => binary(value)?,
+    "$timestamp" => timestamp(value)?,
+    "$regularExpression" => regular_expression(value)?,
+    "$symbol" => Bson::Symbol(as_str(key, value)?.to_string()),
+    "$code" => Bson::JavaScriptCode(as_str(key, value)?.to_string()),
+    _ => return Ok(None),
+  };
+  Ok(Some(bson))
+}
+
+// Wrappers made up of two keys: `$code` with `$scope`, and the v1 forms of
+// binary data and regular expressions.
+fn from_pair_wrapper(
+  map: &Map<String, Value>,
+  legacy: bool,
+) -> Result<Option<Bson>, failure::Error> {
+  if let (Some(code), Some(scope)) = (map.get("$code"), map.get("$scope")) {
+    let code = as_str("$code", code)?.to_string();
+    return match convert(scope.clone(), legacy)? {
+      Bson::Document(scope) => {
+        Ok(Some(Bson::JavaScriptCodeWithScope(code, scope)))
+      }
+      _ => Err(format_err!("Invalid $scope value: {}", scope)),
+    };
+  }
+
+  // `{"$binary": "<base64>", "$type": "<hex byte>"}`
+  if let (Some(payload), Some(subtype)) = (map.get("$binary"), map.get("$type"))
+  {
+    let payload = as_str("$binary", payload)?;
+    let subtype = u8::from_str_radix(as_str("$type", subtype)?, 16)?;
+    return Ok(Some(Bson::Binary(
+      BinarySubtype::from(subtype),
+      base64::decode(payload)?,
+    )));
+  }
+
+  // `{"$regex": "<regex>", "$options": "<flags>"}`
+  if let (Some(pattern), Some(options)) =
+    (map.get("$regex"), map.get("$options"))
+  {
+    return Ok(Some(Bson::RegExp(
+      as_str("$regex", pattern)?.to_string(),
+      as_str("$options", options)?.to_string(),
+    )));
+  }
+
+  Ok(None)
+}
+
+// Shell-mode constructors, i.e. `NumberInt(5)` or `ObjectId("...")`. Strings
+// that merely look like one but don't parse are left as strings.
+fn from_shell_constructor(string: &str) -> Option<Bson> {
+  let open = string.find('(')?;
+  if !string.ends_with(')') {
+    return None;
+  }
+  let arg = string[open + 1..string.len() - 1].trim().trim_matches('"');
+
+  match &string[..open] {
+    "ObjectId" => ObjectId::with_string(arg).ok().map(Bson::ObjectId),
+    "ISODate" => DateTime::parse_from_rfc3339(arg)
+      .ok()
+      .map(|date| Bson::UtcDatetime(date.with_timezone(&Utc))),
+    "NumberInt" => arg.parse().ok().map(Bson::I32),
+    "NumberLong" => arg.parse().ok().map(Bson::I64),
+    "NumberDecimal" => Some(decimal(arg)),
+    _ => None,
+  }
+}
+
+fn as_str<'a>(key: &str, value: &'a Value) -> Result<&'a str, failure::Error> {
+  value
+    .as_str()
+    .ok_or_else(|| format_err!("Invalid {} value: {}", key, value))
+}
+
+// Canonical dates are `{"$date": {"$numberLong": "<millis>"}}`, relaxed ones
+// are `{"$date": "<ISO-8601>"}` and v1 ones `{"$date": <millis>}`. Millis
+// outside of the range of dates chrono can represent are invalid.
+fn date(value: &Value) -> Result<DateTime<Utc>, failure::Error> {
+  let millis = match value {
+    Value::Number(millis) => millis.as_i64(),
+    Value::String(iso) => {
+      return Ok(DateTime::parse_from_rfc3339(iso)?.with_timezone(&Utc));
+    }
+    Value::Object(map) => match map.get("$numberLong") {
+      Some(Value::String(millis)) => Some(millis.parse()?),
+      _ => None,
+    },
+    _ => None,
+  };
+  millis
+    .and_then(|millis| Utc.timestamp_millis_opt(millis).single())
+    .ok_or_else(|| format_err!("Invalid $date value: {}", value))
+}
+
+// let bson's own extended document handling build the Decimal128
+fn decimal(value: &str) -> Bson {
+  Bson::from_extended_document(doc! { "$numberDecimal": value })
+}
+
+fn double(value: &str) -> Result<f64, failure::Error> {
+  match value {
+    "Infinity" => Ok(f64::INFINITY),
+    "-Infinity" => Ok(f64::NEG_INFINITY),
+    "NaN" => Ok(f64::NAN),
+    _ => Ok(value.parse()?),
+  }
+}
+
+// `{"$binary": {"base64": "<payload>", "subType": "<hex byte>"}}`
+fn binary(value: &Value) -> Result<Bson, failure::Error> {
+  let payload = value.get("base64").and_then(Value::as_str);
+  let subtype = value.get("subType").and_then(Value::as_str);
+  match (payload, subtype) {
+    (Some(payload), Some(subtype)) => {
+      let subtype = u8::from_str_radix(subtype, 16)?;
+      Ok(Bson::Binary(
+        BinarySubtype::from(subtype),
+        base64::decode(payload)?,
+      ))
+    }
+    _ => Err(format_err!("Invalid $binary value: {}", value)),
+  }
+}
+
+// `{"$timestamp": {"t": <seconds>, "i": <increment>}}`, where both are
+// unsigned 32 bit integers.
+fn timestamp(value: &Value) -> Result<Bson, failure::Error> {
+  let part = |key: &str| {
+    value
+      .get(key)
+      .and_then(Value::as_u64)
+      .and_then(|num| u32::try_from(num).ok())
+  };
+  match (part("t"), part("i")) {
+    (Some(time), Some(increment)) => {
+      let timestamp = u64::from(time) << 32 | u64::from(increment);
+      Ok(Bson::TimeStamp(timestamp as i64))
+    }
+    _ => Err(format_err!("Invalid $timestamp value: {}", value)),
+  }
+}
+
+// `{"$regularExpression": {"pattern": "<regex>", "options": "<flags>"}}`
+fn regular_expression(value: &Value) -> Result<Bson, failure::Error> {
+  let pattern = value.get("pattern").and_then(Value::as_str);
+  let options = value.get("options").and_then(Value::as_str);
+  match (pattern, options) {
+    (Some(pattern), Some(options)) => {
+      Ok(Bson::RegExp(pattern.to_string(), options.to_string()))
+    }
+    _ => Err(format_err!("Invalid $regularExpression value: {}", value)),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::field_type::FieldType;
+
+  fn parse(json: &str) -> Bson {
+    to_bson(serde_json::from_str(json).unwrap()).unwrap()
+  }
+
+  #[test]
+  fn it_converts_plain_json() {
+    let bson = parse(r#"{"name": "Nori", "age": 4, "weight": 4.5}"#);
+    let doc = bson.as_document().unwrap();
+    assert_eq!(doc.get("name"), Some(&Bson::String("Nori".to_string())));
+    assert_eq!(doc.get("age"), Some(&Bson::I32(4)));
+    assert_eq!(doc.get("weight"), Some(&Bson::FloatingPoint(4.5)));
+  }
+
+  #[test]
+  fn it_converts_canonical_numbers() {
+    assert_eq!(parse(r#"{"$numberInt": "42"}"#), Bson::I32(42));
+    assert_eq!(
+      parse(r#"{"$numberLong": "1427144809506"}"#),
+      Bson::I64(1_427_144_809_506)
+    );
+    assert_eq!(
+      parse(r#"{"$numberDouble": "1.5"}"#),
+      Bson::FloatingPoint(1.5)
+    );
+  }
+
+  #[test]
+  fn it_converts_object_id() {
+    let bson = parse(r#"{"$oid": "5bd761dcae323e45a93ccff1"}"#);
+    assert_eq!(FieldType::get_type(&bson), crate::field_type::OBJECTID);
+  }
+
+  #[test]
+  fn it_converts_canonical_and_relaxed_dates() {
+    let canonical = parse(r#"{"$date": {"$numberLong": "1427144809506"}}"#);
+    let relaxed = parse(r#"{"$date": "2015-03-23T21:06:49.506Z"}"#);
+    assert_eq!(canonical, relaxed);
+    assert_eq!(
+      FieldType::get_type(&canonical),
+      crate::field_type::UTCDATE_TIME
+    );
+  }
+
+  #[test]
+  fn it_errors_on_dates_out_of_range() {
+    let dates = [
+      r#"{"$date": {"$numberLong": "9223372036854775807"}}"#,
+      r#"{"$date": -9223372036854775808}"#,
+    ];
+    for date in dates.iter() {
+      let value = serde_json::from_str(date).unwrap();
+      assert!(to_bson(value).is_err());
+    }
+  }
+
+  #[test]
+  fn it_errors_on_timestamps_out_of_range() {
+    let max = parse(r#"{"$timestamp": {"t": 4294967295, "i": 4294967295}}"#);
+    assert_eq!(max, Bson::TimeStamp(-1));
+    let timestamps = [
+      r#"{"$timestamp": {"t": 4294967296, "i": 1}}"#,
+      r#"{"$timestamp": {"t": 1, "i": 4294967296}}"#,
+    ];
+    for timestamp in timestamps.iter() {
+      let value = serde_json::from_str(timestamp).unwrap();
+      assert!(to_bson(value).is_err());
+    }
+  }
+
+  #[test]
+  fn it_converts_binary() {
+    let bson = parse(r#"{"$binary": {"base64": "AQID", "subType": "00"}}"#);
+    assert_eq!(bson, Bson::Binary(BinarySubtype::Generic, vec![1, 2, 3]));
+  }
+
+  #[test]
+  fn it_converts_nested_wrappers() {
+    let bson = parse(r#"{"items": [{"quantity": {"$numberInt": "2"}}]}"#);
+    let doc = bson.as_document().unwrap();
+    let items = doc.get_array("items").unwrap();
+    let item = items[0].as_document().unwrap();
+    assert_eq!(item.get("quantity"), Some(&Bson::I32(2)));
+  }
+
+  #[test]
+  fn it_converts_v1_wrappers() {
+    let date = parse(r#"{"$date": 1427144809506}"#);
+    let relaxed = parse(r#"{"$date": "2015-03-23T21:06:49.506Z"}"#);
+    assert_eq!(date, relaxed);
+
+    let binary = parse(r#"{"$binary": "AQID", "$type": "00"}"#);
+    assert_eq!(binary, Bson::Binary(BinarySubtype::Generic, vec![1, 2, 3]));
+
+    let regex = parse(r#"{"$regex": "^cat", "$options": "i"}"#);
+    assert_eq!(regex, Bson::RegExp("^cat".to_string(), "i".to_string()));
+  }
+
+  #[test]
+  fn it_converts_shell_constructors_in_legacy_mode() {
+    let json = r#"{"count": "NumberLong(\"5\")", "size": "NumberInt(3)"}"#;
+    let value: Value = serde_json::from_str(json).unwrap();
+
+    let bson = to_bson_legacy(value.clone()).unwrap();
+    let doc = bson.as_document().unwrap();
+    assert_eq!(doc.get("count"), Some(&Bson::I64(5)));
+    assert_eq!(doc.get("size"), Some(&Bson::I32(3)));
+
+    // outside of legacy mode these are just strings
+    let bson = to_bson(value).unwrap();
+    let doc = bson.as_document().unwrap();
+    assert_eq!(
+      doc.get("size"),
+      Some(&Bson::String("NumberInt(3)".to_string()))
+    );
+  }
+
+  #[test]
+  fn it_leaves_unparseable_shell_constructors_as_strings() {
+    let value = Value::String("ObjectId(nope)".to_string());
+    let bson = to_bson_legacy(value).unwrap();
+    assert_eq!(bson, Bson::String("ObjectId(nope)".to_string()));
+  }
+
+  #[test]
+  fn it_errors_on_invalid_wrapper() {
+    let value = serde_json::from_str(r#"{"$oid": 1234}"#).unwrap();
+    assert!(to_bson(value).is_err());
+  }
+}
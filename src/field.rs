@@ -1,21 +1,43 @@
-use super::{Bson, FieldType};
+use super::{Bson, FieldType, IndexMap};
+use crate::builder::ParserOptions;
 use std::collections::HashMap;
+use std::mem;
+use std::sync::Arc;
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Field {
-  pub name: String,
-  pub path: String,
+  /// The field's key. Its name and path are only allocated when the field is
+  /// first seen, and its path is shared with all of its types.
+  pub name: Arc<str>,
+  pub path: Arc<str>,
   pub count: usize,
   pub bson_types: Vec<String>,
   pub probability: f32,
+  /// Whether the field is present in at least `required_threshold` of the
+  /// documents, as set with `SchemaParser::builder()`.
+  #[serde(default)]
+  pub required: bool,
+  #[serde(serialize_with = "crate::field_type::sorted_types")]
   pub types: HashMap<String, FieldType>,
+  /// Other spellings of the field's name that were merged into it, when keys
+  /// are matched case-insensitively.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub spellings: Vec<String>,
+  /// Approximate number of bytes the field takes up in BSON across all
+  /// documents, its key included.
+  #[serde(default)]
+  pub total_bytes: usize,
+  /// Average number of bytes the field takes up in the documents it's
+  /// present in.
+  #[serde(default)]
+  pub average_bytes: f64,
 }
 
 impl Field {
   pub fn new<T, U>(name: T, path: U) -> Self
   where
-    T: Into<String>,
-    U: Into<String>,
+    T: Into<Arc<str>>,
+    U: Into<Arc<str>>,
   {
     Field {
       name: name.into(),
@@ -23,17 +45,71 @@ impl Field {
       path: path.into(),
       bson_types: Vec::new(),
       probability: 0.0,
+      required: false,
       types: HashMap::new(),
+      spellings: Vec::new(),
+      total_bytes: 0,
+      average_bytes: 0.0,
     }
   }
 
-  pub fn create_type(&mut self, value: &Bson) {
-    let mut field_type = FieldType::new(&self.path, &FieldType::get_type(&value));
-    field_type.add_to_type(&value, self.count);
+  /// Returns an iterator over all types seen for this field.
+  pub fn types(&self) -> impl Iterator<Item = &FieldType> {
+    self.types.values()
+  }
+
+  /// Returns the fraction of documents in which the field was explicitly
+  /// `null`, as opposed to missing.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "owner": null }"#).unwrap();
+  /// schema_parser.write_json(r#"{ "owner": "Lupe" }"#).unwrap();
+  /// schema_parser.write_json(r#"{ "name": "Nori" }"#).unwrap();
+  /// schema_parser.write_json(r#"{ "name": "Rey" }"#).unwrap();
+  /// let schema = schema_parser.flush();
+  /// let owner = schema.get_field("owner").unwrap();
+  /// assert_eq!(owner.null_probability(), 0.25);
+  /// assert_eq!(owner.missing_probability(), 0.5);
+  /// ```
+  pub fn null_probability(&self) -> f32 {
+    self.type_probability(crate::field_type::NULL)
+  }
+
+  /// Returns the fraction of documents the field was missing from, which
+  /// are counted as its `Undefined` type once the schema is finalised.
+  pub fn missing_probability(&self) -> f32 {
+    self.type_probability(crate::field_type::UNDEFINED)
+  }
+
+  /// Returns how many times the field was present but empty: an empty
+  /// String, Array or Document.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "tags": [] }"#).unwrap();
+  /// schema_parser.write_json(r#"{ "tags": "" }"#).unwrap();
+  /// schema_parser.write_json(r#"{ "tags": ["cat"] }"#).unwrap();
+  /// let schema = schema_parser.flush();
+  /// assert_eq!(schema.get_field("tags").unwrap().empty_count(), 2);
+  /// ```
+  pub fn empty_count(&self) -> usize {
+    self.types().map(|field_type| field_type.empty).sum()
+  }
+
+  pub fn create_type(&mut self, value: &Bson, options: &ParserOptions) {
+    let mut field_type =
+      FieldType::new(self.path.clone(), &FieldType::get_type(&value));
+    options.charge_memory(mem::size_of::<FieldType>());
+    field_type.add_to_type(&value, self.count, options);
     self.bson_types.push(field_type.bson_type.to_string());
-    self
-      .types
-      .insert(FieldType::get_type(&value), field_type.to_owned());
+    self.types.insert(FieldType::get_type(&value), field_type);
   }
 
   pub fn does_field_type_exist(&mut self, value: &Bson) -> bool {
@@ -52,41 +128,162 @@ impl Field {
   }
 
   pub fn finalise_field(&mut self, parent_count: usize) {
+    // probability is the fraction of documents this field was present in, so
+    // it's set before missing documents are accounted for
     self.set_probability(parent_count);
+    self.set_average_bytes();
+    let missing = parent_count.saturating_sub(self.count);
+    if missing > 0 {
+      self.update_for_missing(missing);
+    }
     for field_type in self.types.values_mut() {
       field_type.finalise_type(self.count);
     }
   }
 
   pub fn update_for_missing(&mut self, missing: usize) {
-    // create new field_types of "Null" for missing fields.
-    let mut null_field_type = FieldType::new(&self.path, &FieldType::get_type(&Bson::Null));
-    null_field_type.add_to_type(&Bson::Null, self.count);
-    null_field_type.count = missing;
-    self.types.insert(
-      crate::field_type::NULL.to_string(),
-      null_field_type.to_owned(),
-    );
-    self.bson_types.push(null_field_type.bson_type);
-    // need to update internal field count, since otherwise on the next
-    // iteration we will get integer overflow
+    // documents missing this field are counted as an "Undefined" field_type,
+    // so the probabilities of all types add up to 1.
+    match self.types.get_mut(crate::field_type::UNDEFINED) {
+      Some(undefined_field_type) => undefined_field_type.count += missing,
+      None => {
+        let mut undefined_field_type =
+          FieldType::new(self.path.clone(), crate::field_type::UNDEFINED);
+        undefined_field_type.count = missing;
+        self.bson_types.push(undefined_field_type.bson_type.to_string());
+        self.types.insert(
+          crate::field_type::UNDEFINED.to_string(),
+          undefined_field_type,
+        );
+      }
+    }
+    // need to update internal field count, so field types' probabilities are
+    // relative to all documents
     self.update_count_by(missing);
   }
 
+  /// Restores a Field deserialized from `to_json()` output to the state it
+  /// had before it was finalised, so more documents can be added to it.
+  pub(crate) fn restore(&mut self, options: &ParserOptions) {
+    if let Some(undefined) = self.types.remove(crate::field_type::UNDEFINED) {
+      self.count = self.count.saturating_sub(undefined.count);
+      self
+        .bson_types
+        .retain(|bson_type| bson_type != crate::field_type::UNDEFINED);
+    }
+    for field_type in self.types.values_mut() {
+      field_type.restore(options);
+    }
+  }
+
+  pub fn merge(&mut self, other: Field) {
+    self.update_count_by(other.count);
+    self.total_bytes += other.total_bytes;
+    for spelling in other.spellings {
+      self.add_spelling(spelling);
+    }
+    for (key, field_type) in other.types {
+      match self.types.get_mut(&key) {
+        Some(existing) => existing.merge(field_type),
+        None => {
+          self.bson_types.push(key.to_string());
+          self.types.insert(key, field_type);
+        }
+      }
+    }
+  }
+
   pub fn update_count(&mut self) {
     self.count += 1
   }
 
+  // Numeric types are moved into a single Number type, which takes the place
+  // of the first of them in `bson_types`.
+  pub(crate) fn coalesce_numbers(&mut self, decimal128: bool) {
+    crate::field_type::coalesce_numbers(
+      &mut self.types,
+      &self.path,
+      decimal128,
+    );
+    let mut bson_types: Vec<String> = Vec::new();
+    for bson_type in self.bson_types.drain(..) {
+      let bson_type = if self.types.contains_key(&bson_type) {
+        bson_type
+      } else {
+        crate::field_type::NUMBER.to_string()
+      };
+      if !bson_types.contains(&bson_type) {
+        bson_types.push(bson_type);
+      }
+    }
+    self.bson_types = bson_types;
+  }
+
+  pub(crate) fn add_spelling(&mut self, spelling: String) {
+    if *spelling != *self.name && !self.spellings.contains(&spelling) {
+      self.spellings.push(spelling);
+    }
+  }
+
+  fn type_probability(&self, bson_type: &str) -> f32 {
+    self
+      .types
+      .get(bson_type)
+      .map_or(0.0, |field_type| field_type.probability)
+  }
+
   fn update_count_by(&mut self, num: usize) {
     self.count += num
   }
 
+  fn set_average_bytes(&mut self) {
+    self.average_bytes = self.total_bytes as f64 / self.count as f64
+  }
+
   #[inline]
   fn set_probability(&mut self, parent_count: usize) {
     self.probability = self.count as f32 / parent_count as f32
   }
 }
 
+/// Finds the field at a dotted path, i.e. `address.geo.lat`, descending into
+/// subdocuments and into documents in arrays, whose fields are at the array's
+/// path, i.e. `visits.vet`.
+pub(crate) fn find_field<'a>(
+  fields: &'a IndexMap<String, Field>,
+  path: &str,
+) -> Option<&'a Field> {
+  if let Some(field) = fields.get(path) {
+    return Some(field);
+  }
+  fields
+    .values()
+    .filter(|field| is_parent_path(&field.path, path))
+    .flat_map(|field| field.types())
+    .find_map(|field_type| find_nested_field(field_type, path))
+}
+
+fn find_nested_field<'a>(
+  field_type: &'a FieldType,
+  path: &str,
+) -> Option<&'a Field> {
+  if let Some(field) = field_type
+    .schema()
+    .and_then(|schema| find_field(&schema.fields, path))
+  {
+    return Some(field);
+  }
+  field_type
+    .types()
+    .find_map(|element_type| find_nested_field(element_type, path))
+}
+
+pub(crate) fn is_parent_path(parent: &str, path: &str) -> bool {
+  path.len() > parent.len()
+    && path.starts_with(parent)
+    && path.as_bytes()[parent.len()] == b'.'
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -99,11 +296,35 @@ mod tests {
 
     let field = Field::new("Nori", path);
 
-    assert_eq!(field.name, "Nori".to_string());
-    assert_eq!(field.path, path);
+    assert_eq!(&*field.name, "Nori");
+    assert_eq!(&*field.path, path);
     assert_eq!(field.count, count);
   }
 
+  #[allow(clippy::float_cmp)]
+  #[test]
+  fn it_tells_null_from_missing() {
+    let options = ParserOptions::default();
+    let mut field = Field::new("owner", "owner");
+    field.create_type(&Bson::Null, &options);
+    field.finalise_field(4);
+    assert_eq!(field.null_probability(), 0.25);
+    assert_eq!(field.missing_probability(), 0.75);
+    assert_eq!(field.types[crate::field_type::NULL].count, 1);
+    assert_eq!(field.types[crate::field_type::UNDEFINED].count, 3);
+  }
+
+  #[test]
+  fn it_shares_its_path_with_its_types() {
+    let mut field = Field::new("name", "owner.name");
+    field.create_type(&Bson::Null, &ParserOptions::default());
+    field.finalise_field(2);
+    assert_eq!(field.types.len(), 2);
+    for field_type in field.types() {
+      assert!(Arc::ptr_eq(&field.path, &field_type.path));
+    }
+  }
+
   // #[bench]
   // fn bench_it_creates_new(bench: &mut Bencher) {
   //   let path = "Nori.cat";
@@ -149,6 +370,28 @@ mod tests {
   //   bench.iter(|| field.update_count());
   // }
 
+  #[test]
+  fn it_merges() {
+    let mut field = Field::new("name", "name");
+    field.create_type(
+      &Bson::String("Nori".to_string()),
+      &ParserOptions::default(),
+    );
+    let mut other = Field::new("name", "name");
+    other.create_type(&Bson::I32(1234), &ParserOptions::default());
+    field.merge(other);
+    assert_eq!(field.count, 2);
+    assert_eq!(field.bson_types, vec!["String", "Int32"]);
+    assert_eq!(field.types.len(), 2);
+  }
+
+  #[test]
+  fn it_finds_parent_paths() {
+    assert!(is_parent_path("address", "address.geo"));
+    assert!(!is_parent_path("address", "address"));
+    assert!(!is_parent_path("address", "addresses.geo"));
+  }
+
   #[allow(clippy::float_cmp)]
   #[test]
   fn it_sets_probability() {
@@ -1,25 +1,138 @@
 #![allow(clippy::option_map_unit_fn)]
-use super::{Bson, SchemaParser, ValueType, HashMap, console};
+use super::{Bson, SchemaParser, ValueType, HashMap};
+use crate::budget::value_size;
+use crate::builder::ParserOptions;
+use crate::digest::{Percentiles, TDigest};
+use crate::distinct::DistinctValues;
+use crate::enums::EnumValues;
+use crate::frequent::{FrequentValues, ValueCount};
+use crate::histogram::{HistogramBucket, HistogramOptions};
+use crate::lengths::{ArrayLengths, StringLengths};
+use crate::moments::Moments;
+use crate::reservoir::Reservoir;
+use bson::oid::ObjectId;
+use bson::Document;
+use chrono::{DateTime, TimeZone, Utc};
+use serde::Serializer;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::sync::Arc;
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct FieldType {
-  pub path: String,
+  pub path: Arc<str>,
   pub count: usize,
   pub bson_type: String,
   pub name: String,
   pub probability: f32,
-  #[serde(skip_serializing_if = "Vec::is_empty")]
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
   pub values: Vec<ValueType>,
-  #[serde(skip_serializing_if = "Vec::is_empty")]
+  /// The most frequent values and how many times each was seen, most
+  /// frequent first, if `top_values` is set.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub top_values: Option<Vec<ValueCount>>,
+  /// For String, Int32 and Long types with few distinct values that are
+  /// repeated, every value seen, sorted, if `enum_threshold` is set. Types
+  /// that have them are enum candidates.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub enum_values: Option<Vec<ValueType>>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
   pub lengths: Vec<usize>,
+  /// For Arrays, the range of their numbers of elements and how many arrays
+  /// had each of them.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub array_lengths: Option<ArrayLengths>,
   pub has_duplicates: bool,
+  /// How many values were empty: empty Strings, Arrays without elements and
+  /// Documents without fields. Missing and null values aren't counted.
+  #[serde(default, skip_serializing_if = "is_zero")]
+  pub empty: usize,
   #[serde(skip_serializing_if = "Option::is_none")]
-  #[serde(flatten)]
   pub schema: Option<SchemaParser>,
-  #[serde(skip_serializing_if = "HashMap::is_empty")]
+  #[serde(
+    default,
+    skip_serializing_if = "HashMap::is_empty",
+    serialize_with = "sorted_types"
+  )]
   pub types: HashMap<String, FieldType>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub unique: Option<usize>,
+  /// Whether `unique` is estimated by a HyperLogLog sketch rather than
+  /// counted exactly, i.e. with `approximate_distinct` or past the memory
+  /// budget. Estimates are usually within a few percent.
+  #[serde(default, skip_serializing_if = "is_false")]
+  pub unique_approximate: bool,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub min: Option<ValueType>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub max: Option<ValueType>,
+  /// For numeric types, the mean of the numbers seen.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub mean: Option<f64>,
+  /// For numeric types, the population standard deviation of the numbers
+  /// seen.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub stddev: Option<f64>,
+  /// For ObjectIds, the range of creation times embedded in them.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub created: Option<DateRange>,
+  /// For dates, the earliest and latest dates seen.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub date_range: Option<DateRange>,
+  /// For dates, how many fall into each bucket, labelled by the start of the
+  /// bucket, if `date_histogram` is set.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub date_histogram: Option<BTreeMap<String, usize>>,
+  /// For Booleans, how many values were true and how many false.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub booleans: Option<BooleanCounts>,
+  /// For Strings, how many values look like a 24 character ObjectId hex.
+  #[serde(default, skip_serializing_if = "is_zero")]
+  pub object_id_strings: usize,
+  /// For Strings, the lengths of the values seen, in characters and bytes.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub string_lengths: Option<StringLengths>,
+  /// For numeric types, the median, 90th and 99th percentile of the numbers
+  /// seen, along with their min and max.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub percentiles: Option<Percentiles>,
+  /// For numeric types, how many numbers fall into each bucket, if
+  /// `numeric_histogram` is set.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub histogram: Option<Vec<HistogramBucket>>,
+  #[serde(skip)]
+  distinct: DistinctValues,
+  #[serde(skip)]
+  reservoir: Reservoir,
+  #[serde(skip)]
+  frequent: Option<FrequentValues>,
+  #[serde(skip)]
+  enums: Option<EnumValues>,
+  #[serde(skip)]
+  digest: Option<TDigest>,
+  #[serde(skip)]
+  histogram_options: Option<HistogramOptions>,
+  #[serde(skip)]
+  moments: Moments,
+  // range of dates seen, in milliseconds since the epoch
+  #[serde(skip)]
+  dates: Option<(i64, i64)>,
+}
+
+/// Numbers of true and false values of a Boolean type, and the share of
+/// them that were true.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct BooleanCounts {
+  pub true_count: usize,
+  pub false_count: usize,
+  pub true_ratio: f64,
+}
+
+/// Range of dates, formatted as RFC 3339 strings.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DateRange {
+  pub min: String,
+  pub max: String,
 }
 
 pub static JAVASCRIPT_CODE_WITH_SCOPE: &str = "JavaScriptCodeWithScope";
@@ -39,11 +152,15 @@ pub static ARRAY: &str = "Array";
 pub static I32: &str = "Int32";
 pub static I64: &str = "Long";
 pub static NULL: &str = "Null";
+// not a bson type, used for documents a field is missing from
+pub static UNDEFINED: &str = "Undefined";
+// not a bson type, used for numbers of any type when they're coalesced
+pub static NUMBER: &str = "Number";
 
 impl FieldType {
   pub fn new<T, U>(path: T, bson_type: U) -> Self
   where
-    T: Into<String>,
+    T: Into<Arc<str>>,
     U: Into<String> + Copy,
    {
     FieldType {
@@ -55,119 +172,335 @@ impl FieldType {
       // look specifically at name field
       name: bson_type.into(),
       values: Vec::new(),
+      top_values: None,
+      enum_values: None,
       has_duplicates: false,
+      empty: 0,
       lengths: Vec::new(),
+      array_lengths: None,
       schema: None,
       types: HashMap::new(),
       unique: None,
+      unique_approximate: false,
+      min: None,
+      max: None,
+      mean: None,
+      stddev: None,
+      created: None,
+      date_range: None,
+      date_histogram: None,
+      booleans: None,
+      object_id_strings: 0,
+      string_lengths: None,
+      percentiles: None,
+      histogram: None,
+      distinct: DistinctValues::default(),
+      reservoir: Reservoir::default(),
+      frequent: None,
+      enums: None,
+      digest: None,
+      histogram_options: None,
+      moments: Moments::default(),
+      dates: None,
     }
   }
 
-  pub fn add_to_type(&mut self, value: &Bson, parent_count: usize) {
-    let bson_value = value.clone();
+  /// Returns the values seen for this type.
+  pub fn values(&self) -> &[ValueType] {
+    &self.values
+  }
+
+  /// Returns the schema of nested document fields, if this is a Document type.
+  pub fn schema(&self) -> Option<&SchemaParser> {
+    self.schema.as_ref()
+  }
+
+  /// Returns an iterator over the types of array elements, if this is an
+  /// Array type.
+  pub fn types(&self) -> impl Iterator<Item = &FieldType> {
+    self.types.values()
+  }
+
+  pub fn add_to_type(
+    &mut self,
+    value: &Bson,
+    parent_count: usize,
+    options: &ParserOptions,
+  ) {
     self.set_probability(parent_count);
+    self.count_empty(value);
 
     match value {
-      Bson::Array(arr) => {
-        // push items into a types array for nested documents. if current item
-        // type is a Document, create another schema parser;
-        for val in arr.iter() {
-          let current_type = Self::get_type(val);
-
-          if self.types.contains_key(&current_type) {
-            // console::log_2(&"current type contains key".into(), &current_type.clone().into());
-            // console::log_2(&"current self field_type contains key".into(), &self.path.clone().into());
-            self.types.get_mut(&current_type).unwrap().add_to_type(&val, self.count);
-          } else {
-            let mut field_type = FieldType::new(&self.path, &current_type);
-            field_type.add_to_type(&val, self.count); // this is recursive
-            self.types.insert(current_type, field_type.clone());
-          }
-          self.lengths.push(arr.len());
-          Self::get_value(&val).map(|v| self.values.push(v));
-        }
+      Bson::Array(arr) => self.add_elements(arr, options),
+      Bson::Document(subdoc) => self.add_subdocument(subdoc, options),
+      _ => {
+        self.update_stats(value, options);
+        self.push_value(value, options);
       }
-      Bson::Document(subdoc) => {
-        match self.schema.as_mut() {
-          Some(doc) => {
-            doc.generate_field(subdoc.to_owned(), Some(self.path.clone()), Some(self.count));
-          },
-          None => {
-            let mut schema_parser = SchemaParser::new();
-            schema_parser.generate_field(
-              subdoc.to_owned(),
-              Some(self.path.clone()),
-              Some(self.count),
-            );
-            self.set_schema(schema_parser);
-          }
+    }
+  }
+
+  pub fn update_type(&mut self, value: &Bson, options: &ParserOptions) {
+    self.count_empty(value);
+    if let Bson::Document(subdoc) = value {
+      self.add_subdocument(subdoc, options);
+    }
+
+    self.update_count();
+    self.update_value(&value, options);
+  }
+
+  fn update_value(&mut self, value: &Bson, options: &ParserOptions) {
+    match value {
+      Bson::Array(arr) => self.add_elements(arr, options),
+      _ => {
+        self.update_stats(&value, options);
+        self.push_value(&value, options);
+      }
+    }
+  }
+
+  fn is_enum_type(&self) -> bool {
+    self.bson_type == STRING || self.bson_type == I32 || self.bson_type == I64
+  }
+
+  fn count_empty(&mut self, value: &Bson) {
+    let empty = match value {
+      Bson::String(string) => string.is_empty(),
+      Bson::Array(arr) => arr.is_empty(),
+      Bson::Document(doc) => doc.is_empty(),
+      _ => false,
+    };
+    if empty {
+      self.empty += 1;
+    }
+  }
+
+  // Keeps track of the distribution of numbers, of the smallest and largest
+  // number and ObjectId values seen, of the range of dates, of true and false
+  // values, and of the lengths of strings and those that look like ObjectIds.
+  fn update_stats(&mut self, value: &Bson, options: &ParserOptions) {
+    if let Some(number) = to_f64(value) {
+      let digest = self.digest.get_or_insert_with(TDigest::default);
+      digest.insert(number);
+      self.moments.insert(number);
+      self.histogram_options = options.numeric_histogram;
+    }
+    let value = match value {
+      Bson::I32(num) => ValueType::I32(*num),
+      Bson::I64(num) => ValueType::I64(*num),
+      Bson::FloatingPoint(num) => ValueType::FloatingPoint(*num),
+      Bson::Decimal128(d128) => ValueType::Decimal128(d128.to_string()),
+      Bson::ObjectId(id) => ValueType::Str(id.to_hex()),
+      Bson::String(string) => {
+        if is_object_id_hex(string) {
+          self.object_id_strings += 1;
+        }
+        match self.string_lengths.as_mut() {
+          Some(lengths) => lengths.insert(string),
+          None => self.string_lengths = Some(StringLengths::new(string)),
         }
+        return;
       }
-      _ => {
-        Self::get_value(&bson_value).map(|v| self.values.push(v));
+      Bson::UtcDatetime(date) => {
+        self.update_dates(date, options);
+        return;
       }
+      Bson::Boolean(boolean) => {
+        let booleans = self.booleans.get_or_insert_with(Default::default);
+        if *boolean {
+          booleans.true_count += 1;
+        } else {
+          booleans.false_count += 1;
+        }
+        return;
+      }
+      _ => return,
+    };
+    self.merge_range(Some(value.clone()), Some(value));
+  }
+
+  fn update_dates(&mut self, date: &DateTime<Utc>, options: &ParserOptions) {
+    let millis = date.timestamp_millis();
+    self.dates = Some(match self.dates {
+      Some((min, max)) => (min.min(millis), max.max(millis)),
+      None => (millis, millis),
+    });
+    if let Some(bucket) = options.date_histogram {
+      let histogram = self.date_histogram.get_or_insert_with(BTreeMap::new);
+      *histogram.entry(bucket.label(date)).or_insert(0) += 1;
     }
   }
 
-  pub fn update_type(&mut self, value: &Bson) {
-    if self.bson_type == "Document" {
-      match &mut self.schema {
-        Some(schema_parser) => match &value {
-          Bson::Document(subdoc) => schema_parser.generate_field(
-            subdoc.to_owned(),
-            Some(self.path.clone()),
-            Some(self.count),
-          ),
-          _ => {
-            unimplemented!()
-          }
-        },
+  fn merge_range(&mut self, min: Option<ValueType>, max: Option<ValueType>) {
+    if let Some(min) = min.filter(|min| is_less(min, self.min.as_ref())) {
+      self.min = Some(min);
+    }
+    if let Some(max) = max.filter(|max| is_greater(max, self.max.as_ref())) {
+      self.max = Some(max);
+    }
+  }
+
+  // Each array element is added to the type it has, so mixed arrays report
+  // every element type separately, and arrays of documents get the nested
+  // fields of all their elements in a single schema.
+  fn add_elements(&mut self, arr: &[Bson], options: &ParserOptions) {
+    self.lengths.push(arr.len());
+    for val in arr.iter() {
+      let current_type = Self::get_type(val);
+      match self.types.get_mut(&current_type) {
+        Some(field_type) => field_type.update_type(val, options),
         None => {
-          unimplemented!()
+          let mut field_type = FieldType::new(self.path.clone(), &current_type);
+          field_type.add_to_type(val, self.count, options);
+          self.types.insert(current_type, field_type);
         }
       }
+      self.push_value(val, options);
     }
+  }
 
-    self.update_count();
-    self.update_value(&value);
+  // Nested documents get their own schema parser. Past the configured max
+  // depth they are only counted as a Document type, and not descended into.
+  fn add_subdocument(&mut self, subdoc: &Document, options: &ParserOptions) {
+    let options = match options.for_subdocument() {
+      Some(options) => options,
+      None => return,
+    };
+    match self.schema.as_mut() {
+      Some(doc) => {
+        doc.generate_field(subdoc, Some(&self.path), Some(self.count));
+      }
+      None => {
+        let mut schema_parser = SchemaParser::with_options(options);
+        schema_parser.generate_field(
+          subdoc,
+          Some(&self.path),
+          Some(self.count),
+        );
+        self.set_schema(schema_parser);
+      }
+    }
   }
 
-  fn update_value(&mut self, value: &Bson) {
-    match value {
-      Bson::Array(arr) => {
-        for val in arr.iter() {
-          let current_type = Self::get_type(val);
-
-          if self.types.contains_key(&current_type) {
-            self.types.get_mut(&current_type).unwrap().add_to_type(&val, self.count);
-          } else {
-            let mut field_type = FieldType::new(&self.path, &current_type);
-            field_type.add_to_type(&val, self.count);
-            self.types.insert(current_type, field_type.to_owned());
-          }
-          self.lengths.push(arr.len());
-          Self::get_value(&val).map(|v| self.values.push(v));
-        }
+  fn push_value(&mut self, bson_value: &Bson, options: &ParserOptions) {
+    let value = match Self::get_value(bson_value) {
+      Some(value) => value,
+      None => return,
+    };
+    // distinct values are counted even when they aren't stored
+    let over_budget = options.over_memory_budget();
+    let approximate = options.approximate_distinct || over_budget;
+    options.charge_memory(self.distinct.insert(bson_value, approximate));
+    if let Some(top) = options.top_values {
+      let frequent = self
+        .frequent
+        .get_or_insert_with(|| FrequentValues::new(top));
+      options.charge_memory(frequent.insert(&value));
+    }
+    if let (Some(max), true) = (options.enum_threshold, self.is_enum_type()) {
+      let enums = self.enums.get_or_insert_with(|| EnumValues::new(max));
+      options.charge_memory(enums.insert(&value));
+    }
+    if options.store_values {
+      // past the memory budget the sample stops growing, but stored values
+      // are still replaced so it stays representative
+      let cap = if over_budget {
+        Some(self.values.len())
+      } else {
+        options.max_values
+      };
+      let stored = self.values.len();
+      self.reservoir.sample(&mut self.values, value, cap);
+      if self.values.len() > stored {
+        options.charge_memory(self.values.last().map_or(0, value_size));
       }
-      _ => {
-        Self::get_value(&value).map(|v| self.values.push(v));
+    }
+  }
+
+  pub fn merge(&mut self, other: FieldType) {
+    self.count += other.count;
+    self.empty += other.empty;
+    self.reservoir.merge(&mut self.values, other.reservoir, other.values);
+    self.lengths.extend(other.lengths);
+    self.distinct.merge(other.distinct);
+    merge_frequent(&mut self.frequent, other.frequent);
+    match (self.enums.as_mut(), other.enums) {
+      (Some(enums), Some(other)) => enums.merge(other),
+      (None, other) => self.enums = other,
+      (_, None) => (),
+    }
+    self.unique_approximate |= other.unique_approximate;
+    merge_digests(&mut self.digest, other.digest);
+    self.histogram_options = self.histogram_options.or(other.histogram_options);
+    self.moments.merge(&other.moments);
+    if let Some((min, max)) = other.dates {
+      self.dates = Some(match self.dates {
+        Some((own_min, own_max)) => (own_min.min(min), own_max.max(max)),
+        None => (min, max),
+      });
+    }
+    if let Some(other_histogram) = other.date_histogram {
+      let histogram = self.date_histogram.get_or_insert_with(BTreeMap::new);
+      for (bucket, count) in other_histogram {
+        *histogram.entry(bucket).or_insert(0) += count;
+      }
+    }
+    self.merge_range(other.min, other.max);
+    self.object_id_strings += other.object_id_strings;
+    if let Some(other) = other.booleans {
+      let booleans = self.booleans.get_or_insert_with(Default::default);
+      booleans.true_count += other.true_count;
+      booleans.false_count += other.false_count;
+    }
+    match (self.string_lengths.as_mut(), other.string_lengths) {
+      (Some(lengths), Some(other)) => lengths.merge(&other),
+      (None, other) => self.string_lengths = other,
+      (_, None) => (),
+    }
+
+    if let Some(other_schema) = other.schema {
+      match self.schema.as_mut() {
+        Some(schema) => schema.merge(other_schema),
+        None => self.set_schema(other_schema),
+      }
+    }
+
+    for (key, field_type) in other.types {
+      match self.types.get_mut(&key) {
+        Some(existing) => existing.merge(field_type),
+        None => {
+          self.types.insert(key, field_type);
+        }
       }
     }
   }
 
   pub fn get_value(value: &Bson) -> Option<ValueType> {
     match value {
-      Bson::RegExp(val, _)
-      | Bson::JavaScriptCode(val)
+      Bson::JavaScriptCode(val)
       | Bson::JavaScriptCodeWithScope(val, _)
       | Bson::Symbol(val) => Some(ValueType::Str(val.to_string())),
-      Bson::I64(num) | Bson::TimeStamp(num) => Some(ValueType::I64(*num)),
+      Bson::RegExp(pattern, options) => Some(ValueType::RegExp {
+        pattern: pattern.to_string(),
+        options: options.to_string(),
+      }),
+      // timestamps are stored as increment in the low and seconds in the high
+      // 32 bits
+      Bson::TimeStamp(num) => Some(ValueType::Timestamp {
+        t: (*num >> 32) as u32,
+        i: *num as u32,
+      }),
+      Bson::I64(num) => Some(ValueType::I64(*num)),
       Bson::FloatingPoint(num) => Some(ValueType::FloatingPoint(*num)),
       Bson::UtcDatetime(date) => Some(ValueType::Str(date.clone().to_string())),
       Bson::Decimal128(d128) => Some(ValueType::Decimal128(d128.to_string())),
       Bson::Boolean(boolean) => Some(ValueType::Boolean(*boolean)),
       Bson::String(string) => Some(ValueType::Str(string.to_string())),
-      Bson::Binary(_, vec) => Some(ValueType::Binary(vec.clone())),
+      Bson::Binary(sub_type, bytes) => Some(ValueType::Binary {
+        sub_type: u8::from(*sub_type),
+        length: bytes.len(),
+      }),
       Bson::ObjectId(id) => Some(ValueType::Str(id.to_string())),
       Bson::I32(num) => Some(ValueType::I32(*num)),
       Bson::Null => Some(ValueType::Null("Null".to_string())),
@@ -177,21 +510,134 @@ impl FieldType {
   }
 
   pub fn finalise_type(&mut self, parent_count: usize) {
-    if !self.types.is_empty() {
-      for (key, field) in self.types.iter_mut() {
-        if key == "Document" {
-          // TODO: get option without unwrap
-          let schema = &mut field.schema;
-          if let Some(schema) = schema {
-            schema.finalise_schema();
-          }
-        }
-        field.finalise_type(self.count);
-      }
+    // nested documents update their own missing fields and probabilities
+    if let Some(schema) = self.schema.as_mut() {
+      schema.finalise_schema();
+    }
+    // element type probabilities are relative to the number of elements seen
+    // across all arrays, rather than to the number of arrays
+    let element_count = self.types.values().map(|t| t.count).sum();
+    for field in self.types.values_mut() {
+      field.finalise_type(element_count);
     }
     self.set_probability(parent_count);
     self.set_unique();
     self.set_duplicates();
+    self.set_created();
+    self.set_date_range();
+    if let Some(frequent) = self.frequent.as_ref() {
+      self.top_values = Some(frequent.top_values());
+    }
+    if let Some(enums) = self.enums.as_ref() {
+      self.enum_values = enums.candidates(self.count);
+    }
+    self.set_percentiles();
+    self.set_histogram();
+    self.set_true_ratio();
+    self.array_lengths = ArrayLengths::from_lengths(&self.lengths);
+    self.mean = self.moments.mean();
+    self.stddev = self.moments.stddev();
+  }
+
+  /// Restores the state of a FieldType deserialized from `to_json()` output
+  /// that isn't serialized itself, so more values can be added to it.
+  pub(crate) fn restore(&mut self, options: &ParserOptions) {
+    // untagged values can't be told apart from strings and smaller numbers
+    // by their json alone
+    let element_type = match self.types.keys().next() {
+      Some(element_type) if self.types.len() == 1 => element_type.clone(),
+      _ => self.bson_type.clone(),
+    };
+    for value in self.values.iter_mut() {
+      restore_value(value, &element_type);
+    }
+    if let Some(top_values) = self.top_values.as_mut() {
+      for value_count in top_values.iter_mut() {
+        restore_value(&mut value_count.value, &element_type);
+      }
+      if let Some(top) = options.top_values {
+        self.frequent = Some(FrequentValues::restore(top, top_values));
+      }
+    }
+    for value in self.min.iter_mut().chain(self.max.iter_mut()) {
+      restore_value(value, &self.bson_type);
+    }
+    for value in self.enum_values.iter_mut().flatten() {
+      restore_value(value, &self.bson_type);
+    }
+    if let (Some(max), true) = (options.enum_threshold, self.is_enum_type()) {
+      // types that weren't candidates can only be restored from their values
+      // if every one of them was stored
+      let values = match self.enum_values.as_ref() {
+        Some(values) => Some(&values[..]),
+        None if self.values.len() == self.count => Some(&self.values[..]),
+        None => None,
+      };
+      self.enums = Some(EnumValues::restore(values, max));
+    }
+
+    let seen = if self.bson_type == ARRAY {
+      self.lengths.iter().sum()
+    } else {
+      self.count
+    };
+    self.reservoir.restore(&self.values, seen);
+    if let Some(percentiles) = self.percentiles.as_ref() {
+      self.digest = Some(TDigest::restore(percentiles, self.count));
+      self.histogram_options = options.numeric_histogram;
+    }
+    if let Some(lengths) = self.string_lengths.as_mut() {
+      lengths.restore(self.count);
+    }
+    if let Some(range) = self.date_range.as_ref() {
+      let millis = |date: &str| {
+        DateTime::parse_from_rfc3339(date)
+          .ok()
+          .map(|date| date.timestamp_millis())
+      };
+      if let (Some(min), Some(max)) = (millis(&range.min), millis(&range.max)) {
+        self.dates = Some((min, max));
+      }
+    }
+    if let (Some(mean), Some(stddev)) = (self.mean, self.stddev) {
+      self.moments = Moments::restore(mean, stddev, self.count);
+    }
+    self
+      .distinct
+      .restore(self.unique.unwrap_or(0), self.has_duplicates);
+
+    if let Some(schema) = self.schema.as_mut() {
+      let options =
+        options.for_subdocument().unwrap_or_else(|| options.clone());
+      schema.restore(options);
+    }
+    for field_type in self.types.values_mut() {
+      field_type.restore(options);
+    }
+  }
+
+  // Limits the fields of its subdocuments, see SchemaParser::limit_fields.
+  #[cfg(feature = "parallel")]
+  pub(crate) fn limit_fields(&mut self, max_fields: Option<usize>) {
+    if let Some(schema) = self.schema.as_mut() {
+      schema.limit_fields(max_fields);
+    }
+    for field_type in self.types.values_mut() {
+      field_type.limit_fields(max_fields);
+    }
+  }
+
+  /// Whether this String, Int32 or Long type is flagged as an enum
+  /// candidate, i.e. a status or a level with a handful of values, and lists
+  /// its values in `enum_values`.
+  pub fn is_enum_candidate(&self) -> bool {
+    self.enum_values.is_some()
+  }
+
+  /// Whether every value of this String type looks like an ObjectId, i.e.
+  /// ObjectIds were stored as strings.
+  pub fn is_object_id_string(&self) -> bool {
+    self.bson_type == STRING && self.object_id_strings == self.count
   }
 
   pub fn get_type(value: &Bson) -> String {
@@ -218,39 +664,76 @@ impl FieldType {
     }
   }
 
-  fn get_duplicates(&mut self) -> bool {
-    let unique = self.get_unique();
-    let total_values = self.values.len();
-    (total_values - unique) != 0
+  fn get_duplicates(&self) -> bool {
+    self.distinct.has_duplicates()
   }
 
-  fn get_unique(&mut self) -> usize {
-    let mut vec = self.values.clone();
-    vec.sort_by(|a, b| a.partial_cmp(b).unwrap());
-    vec.dedup();
-    vec.len()
+  fn get_unique(&self) -> usize {
+    self.distinct.unique()
   }
 
   pub fn set_duplicates(&mut self) {
     // if any nested types have duplicates, current field_type will then also
     // have duplicates.
-    if !self.types.is_empty() {
-      for (_key, field) in self.types.iter() {
-        if field.has_duplicates {
-          self.has_duplicates = field.has_duplicates
-        }
-      }
-    }
-    let duplicates = self.get_duplicates();
-    self.has_duplicates = duplicates
+    let nested_duplicates = self.types.values().any(|t| t.has_duplicates);
+    self.has_duplicates = nested_duplicates || self.get_duplicates();
   }
 
   fn set_schema(&mut self, schema: SchemaParser) {
     self.schema = Some(schema)
   }
 
+  // The smallest and largest ObjectIds are also the oldest and newest, as
+  // they start with their creation timestamp.
+  fn set_created(&mut self) {
+    let created = |value: &Option<ValueType>| match value {
+      Some(ValueType::Str(hex)) => ObjectId::with_string(hex)
+        .ok()
+        .map(|id| Utc.timestamp(i64::from(id.timestamp()), 0).to_rfc3339()),
+      _ => None,
+    };
+    if self.bson_type != OBJECTID {
+      return;
+    }
+    if let (Some(min), Some(max)) = (created(&self.min), created(&self.max)) {
+      self.created = Some(DateRange { min, max });
+    }
+  }
+
+  fn set_percentiles(&mut self) {
+    if let Some(digest) = self.digest.as_mut() {
+      self.percentiles = digest.percentiles();
+    }
+  }
+
+  fn set_histogram(&mut self) {
+    if let (Some(options), Some(digest)) =
+      (self.histogram_options, self.digest.as_mut())
+    {
+      self.histogram = options.histogram(digest);
+    }
+  }
+
+  fn set_true_ratio(&mut self) {
+    if let Some(booleans) = self.booleans.as_mut() {
+      let total = booleans.true_count + booleans.false_count;
+      booleans.true_ratio = booleans.true_count as f64 / total as f64;
+    }
+  }
+
+  fn set_date_range(&mut self) {
+    if let Some((min, max)) = self.dates {
+      self.date_range = Some(DateRange {
+        min: Utc.timestamp_millis(min).to_rfc3339(),
+        max: Utc.timestamp_millis(max).to_rfc3339(),
+      });
+    }
+  }
+
   fn set_unique(&mut self) {
-    self.unique = Some(self.get_unique())
+    self.unique = Some(self.get_unique());
+    // unique counts restored from an estimate stay estimates
+    self.unique_approximate |= self.distinct.is_approximate();
   }
 
   fn set_probability(&mut self, parent_count: usize) {
@@ -262,16 +745,200 @@ impl FieldType {
   }
 }
 
+/// Moves the Int32, Long and Double types, and Decimal128 if `decimal128` is
+/// set, into a single Number type, the `types` of which break the numbers
+/// down by their type, as with the types of array elements. Numbers in arrays
+/// are coalesced too. Types must not be finalised yet.
+pub(crate) fn coalesce_numbers(
+  types: &mut HashMap<String, FieldType>,
+  path: &Arc<str>,
+  decimal128: bool,
+) {
+  for field_type in types.values_mut() {
+    if field_type.bson_type != NUMBER {
+      coalesce_numbers(&mut field_type.types, &field_type.path, decimal128);
+    }
+  }
+  let numeric: Vec<String> = types
+    .keys()
+    .filter(|bson_type| is_number(bson_type, decimal128))
+    .cloned()
+    .collect();
+  if numeric.is_empty() {
+    return;
+  }
+  // a Number type restored from json takes in the numbers seen since
+  let mut number = types.remove(NUMBER).unwrap_or_else(|| FieldType {
+    count: 0,
+    ..FieldType::new(path.clone(), NUMBER)
+  });
+  for bson_type in numeric {
+    if let Some(field_type) = types.remove(&bson_type) {
+      number.count += field_type.count;
+      number.empty += field_type.empty;
+      number.reservoir.merge(
+        &mut number.values,
+        field_type.reservoir.clone(),
+        field_type.values.clone(),
+      );
+      number.distinct.merge(field_type.distinct.clone());
+      merge_frequent(&mut number.frequent, field_type.frequent.clone());
+      merge_digests(&mut number.digest, field_type.digest.clone());
+      number.histogram_options =
+        number.histogram_options.or(field_type.histogram_options);
+      number.moments.merge(&field_type.moments);
+      number.merge_range(field_type.min.clone(), field_type.max.clone());
+      match number.types.get_mut(&bson_type) {
+        Some(existing) => existing.merge(field_type),
+        None => {
+          number.types.insert(bson_type, field_type);
+        }
+      }
+    }
+  }
+  types.insert(NUMBER.to_string(), number);
+}
+
+/// Returns the type a value of `bson_type` is counted as among finalised
+/// `types`: its own, or Number if it's a number and numbers were coalesced.
+pub(crate) fn counted_type<'a>(
+  types: &'a HashMap<String, FieldType>,
+  bson_type: &str,
+) -> Option<&'a FieldType> {
+  types.get(bson_type).or_else(|| {
+    if is_number(bson_type, true) {
+      types.get(NUMBER)
+    } else {
+      None
+    }
+  })
+}
+
+fn is_number(bson_type: &str, decimal128: bool) -> bool {
+  bson_type == I32
+    || bson_type == I64
+    || bson_type == FLOATING_POINT
+    || (decimal128 && bson_type == DECIMAL_128)
+}
+
+// Numbers are ordered by their f64 approximation, which is precise enough to
+// find a field's range, also across the types of a coalesced Number, and NaN
+// values are never part of the range. ObjectIds are kept as hex strings,
+// which order the same way as the ids.
+fn compare_range(value: &ValueType, than: &ValueType) -> Option<Ordering> {
+  match (value, than) {
+    (ValueType::Str(value), ValueType::Str(than)) => value.partial_cmp(than),
+    _ => range_number(value)?.partial_cmp(&range_number(than)?),
+  }
+}
+
+fn range_number(value: &ValueType) -> Option<f64> {
+  match value {
+    ValueType::I32(num) => Some(f64::from(*num)),
+    ValueType::I64(num) => Some(*num as f64),
+    ValueType::FloatingPoint(num) => Some(*num),
+    ValueType::Decimal128(num) => num.parse().ok(),
+    _ => None,
+  }
+}
+
+fn is_less(value: &ValueType, than: Option<&ValueType>) -> bool {
+  match than {
+    Some(than) => compare_range(value, than) == Some(Ordering::Less),
+    None => compare_range(value, value).is_some(),
+  }
+}
+
+fn is_greater(value: &ValueType, than: Option<&ValueType>) -> bool {
+  match than {
+    Some(than) => compare_range(value, than) == Some(Ordering::Greater),
+    None => compare_range(value, value).is_some(),
+  }
+}
+
+fn restore_value(value: &mut ValueType, bson_type: &str) {
+  let restored = match value {
+    ValueType::Str(string) if bson_type == DECIMAL_128 => {
+      ValueType::Decimal128(string.clone())
+    }
+    ValueType::Str(string) if bson_type == NULL => {
+      ValueType::Null(string.clone())
+    }
+    ValueType::I32(num) if bson_type == I64 => ValueType::I64(i64::from(*num)),
+    _ => return,
+  };
+  *value = restored;
+}
+
+fn is_object_id_hex(string: &str) -> bool {
+  string.len() == 24 && string.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn merge_digests(digest: &mut Option<TDigest>, other: Option<TDigest>) {
+  match (digest.as_mut(), other) {
+    (Some(digest), Some(other)) => digest.merge(other),
+    (None, other) => *digest = other,
+    (_, None) => (),
+  }
+}
+
+fn merge_frequent(
+  frequent: &mut Option<FrequentValues>,
+  other: Option<FrequentValues>,
+) {
+  match (frequent.as_mut(), other) {
+    (Some(frequent), Some(other)) => frequent.merge(other),
+    (None, other) => *frequent = other,
+    (_, None) => (),
+  }
+}
+
+// Numbers of any type, as a float.
+fn to_f64(value: &Bson) -> Option<f64> {
+  match value {
+    Bson::I32(num) => Some(f64::from(*num)),
+    Bson::I64(num) => Some(*num as f64),
+    Bson::FloatingPoint(num) => Some(*num),
+    Bson::Decimal128(d128) => d128.to_string().parse().ok(),
+    _ => None,
+  }
+}
+
+/// Serializes types sorted by their name, so a schema's output is the same
+/// from run to run, whatever order its types' map iterates in.
+pub(crate) fn sorted_types<S>(
+  types: &HashMap<String, FieldType>,
+  serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+  S: Serializer,
+{
+  let sorted: BTreeMap<&String, &FieldType> = types.iter().collect();
+  serde::Serialize::serialize(&sorted, serializer)
+}
+
+fn is_zero(num: &usize) -> bool {
+  *num == 0
+}
+
+fn is_false(boolean: &bool) -> bool {
+  !*boolean
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::histogram::HistogramScale;
+  use crate::DateBucket;
+  use bson::spec::BinarySubtype;
+  use bson::{bson, doc};
   // use crate::test::Bencher;
 
   #[test]
   fn it_creates_new() {
     let address = "address";
     let field_type = FieldType::new(address, "Oranienstr. 123");
-    assert_eq!(field_type.path, address);
+    assert_eq!(&*field_type.path, address);
   }
 
   // #[bench]
@@ -298,6 +965,31 @@ mod tests {
     assert_eq!(value, Some(ValueType::I64(1234)));
   }
 
+  #[test]
+  fn it_gets_value_timestamp() {
+    let bson_value = Bson::TimeStamp((1_565_545_664 << 32) + 1);
+    let value = FieldType::get_value(&bson_value);
+    assert_eq!(value, Some(ValueType::Timestamp { t: 1_565_545_664, i: 1 }));
+  }
+
+  #[test]
+  fn it_gets_value_binary() {
+    let bson_value = Bson::Binary(BinarySubtype::Uuid, vec![0; 16]);
+    let value = FieldType::get_value(&bson_value);
+    assert_eq!(value, Some(ValueType::Binary { sub_type: 4, length: 16 }));
+  }
+
+  #[test]
+  fn it_gets_value_regexp() {
+    let bson_value = Bson::RegExp("^cat".to_string(), "i".to_string());
+    let value = FieldType::get_value(&bson_value);
+    let regexp = ValueType::RegExp {
+      pattern: "^cat".to_string(),
+      options: "i".to_string(),
+    };
+    assert_eq!(value, Some(regexp));
+  }
+
   #[test]
   fn it_gets_value_floating_point() {
     let bson_value = Bson::FloatingPoint(1.2);
@@ -341,10 +1033,9 @@ mod tests {
   fn it_gets_unique() {
     let mut field_type =
       FieldType::new("address", "Oranienstr. 123");
-    field_type.values.push(ValueType::Str("Berlin".to_string()));
-    field_type
-      .values
-      .push(ValueType::Str("Hamburg".to_string()));
+    let options = ParserOptions::default();
+    field_type.push_value(&Bson::String("Berlin".to_string()), &options);
+    field_type.push_value(&Bson::String("Hamburg".to_string()), &options);
     let unique = field_type.get_unique();
     assert_eq!(unique, 2);
   }
@@ -364,10 +1055,9 @@ mod tests {
   fn it_sets_unique() {
     let mut field_type =
       FieldType::new("address", "Oranienstr. 123");
-    field_type.values.push(ValueType::Str("Berlin".to_string()));
-    field_type
-      .values
-      .push(ValueType::Str("Hamburg".to_string()));
+    let options = ParserOptions::default();
+    field_type.push_value(&Bson::String("Berlin".to_string()), &options);
+    field_type.push_value(&Bson::String("Hamburg".to_string()), &options);
     field_type.set_unique();
     assert_eq!(field_type.unique, Some(2));
   }
@@ -387,10 +1077,9 @@ mod tests {
   fn it_gets_duplicates_when_none() {
     let mut field_type =
       FieldType::new("address", "Oranienstr. 123");
-    field_type.values.push(ValueType::Str("Berlin".to_string()));
-    field_type
-      .values
-      .push(ValueType::Str("Hamburg".to_string()));
+    let options = ParserOptions::default();
+    field_type.push_value(&Bson::String("Berlin".to_string()), &options);
+    field_type.push_value(&Bson::String("Hamburg".to_string()), &options);
     let has_duplicates = field_type.get_duplicates();
     assert_eq!(has_duplicates, false)
   }
@@ -399,8 +1088,9 @@ mod tests {
   fn it_gets_duplicates_when_some() {
     let mut field_type =
       FieldType::new("address", "Oranienstr. 123");
-    field_type.values.push(ValueType::Str("Berlin".to_string()));
-    field_type.values.push(ValueType::Str("Berlin".to_string()));
+    let options = ParserOptions::default();
+    field_type.push_value(&Bson::String("Berlin".to_string()), &options);
+    field_type.push_value(&Bson::String("Berlin".to_string()), &options);
     let has_duplicates = field_type.get_duplicates();
     assert_eq!(has_duplicates, true)
   }
@@ -420,8 +1110,9 @@ mod tests {
   fn it_sets_duplicates() {
     let mut field_type =
       FieldType::new("address", "Oranienstr. 123");
-    field_type.values.push(ValueType::Str("Berlin".to_string()));
-    field_type.values.push(ValueType::Str("Berlin".to_string()));
+    let options = ParserOptions::default();
+    field_type.push_value(&Bson::String("Berlin".to_string()), &options);
+    field_type.push_value(&Bson::String("Berlin".to_string()), &options);
     field_type.set_duplicates();
     assert_eq!(field_type.has_duplicates, true)
   }
@@ -448,15 +1139,339 @@ mod tests {
   //   bench.iter(|| field_type.update_count());
   // }
 
+  #[test]
+  fn it_merges() {
+    let mut field_type = FieldType::new("address", "String");
+    field_type.values.push(ValueType::Str("Berlin".to_string()));
+    let mut other = FieldType::new("address", "String");
+    other.values.push(ValueType::Str("Hamburg".to_string()));
+    field_type.merge(other);
+    assert_eq!(field_type.count, 2);
+    assert_eq!(field_type.values.len(), 2);
+  }
+
+  #[test]
+  fn it_merges_distinct_values() {
+    let options = ParserOptions::default();
+    let mut field_type = FieldType::new("address", "String");
+    field_type.push_value(&Bson::String("Berlin".to_string()), &options);
+    let mut other = FieldType::new("address", "String");
+    other.push_value(&Bson::String("Berlin".to_string()), &options);
+    field_type.merge(other);
+    field_type.finalise_type(2);
+    assert_eq!(field_type.unique, Some(1));
+    assert!(field_type.has_duplicates);
+  }
+
+  #[test]
+  fn it_counts_unique_values_that_are_not_stored() {
+    let options = ParserOptions {
+      max_values: Some(1),
+      ..ParserOptions::default()
+    };
+    let mut field_type = FieldType::new("age", "Int32");
+    for num in &[1, 2, 2] {
+      field_type.update_value(&Bson::I32(*num), &options);
+    }
+    field_type.finalise_type(3);
+    assert_eq!(field_type.values.len(), 1);
+    assert_eq!(field_type.unique, Some(2));
+    assert!(field_type.has_duplicates);
+  }
+
   #[test]
   fn it_updates_value_some() {
     let bson_value = Bson::I32(1234);
     let mut field_type =
       FieldType::new("address", "Oranienstr. 123");
-    field_type.update_value(&bson_value);
+    field_type.update_value(&bson_value, &ParserOptions::default());
     assert_eq!(field_type.values[0], ValueType::I32(1234));
   }
 
+  #[test]
+  fn it_caps_values() {
+    let options = ParserOptions {
+      max_values: Some(2),
+      ..ParserOptions::default()
+    };
+    let mut field_type = FieldType::new("age", "Int32");
+    for num in 0..5 {
+      field_type.update_value(&Bson::I32(num), &options);
+    }
+    assert_eq!(field_type.values.len(), 2);
+  }
+
+  #[test]
+  fn it_estimates_unique_values() {
+    let options = ParserOptions {
+      approximate_distinct: true,
+      ..ParserOptions::default()
+    };
+    let mut field_type = FieldType::new("uuid", "String");
+    for num in 0..20_000 {
+      let uuid = Bson::String(format!("{:032x}", num));
+      field_type.update_value(&uuid, &options);
+    }
+    field_type.finalise_type(20_000);
+    let unique = field_type.unique.unwrap() as f64;
+    assert!((unique - 20_000.0).abs() < 20_000.0 * 0.1);
+    assert!(field_type.unique_approximate);
+  }
+
+  #[test]
+  fn it_counts_few_unique_values_exactly_when_approximate() {
+    let options = ParserOptions {
+      approximate_distinct: true,
+      ..ParserOptions::default()
+    };
+    let mut field_type = FieldType::new("active", "Boolean");
+    for boolean in &[true, false, true] {
+      field_type.update_value(&Bson::Boolean(*boolean), &options);
+    }
+    field_type.finalise_type(3);
+    assert_eq!(field_type.unique, Some(2));
+    assert!(!field_type.unique_approximate);
+  }
+
+  #[test]
+  fn it_reports_percentiles_of_numbers() {
+    let mut field_type = FieldType::new("age", "Int32");
+    for num in 1..=1000 {
+      field_type.update_value(&Bson::I32(num), &ParserOptions::default());
+    }
+    field_type.finalise_type(1000);
+    let percentiles = field_type.percentiles.unwrap();
+    assert_eq!(percentiles.min, 1.0);
+    assert_eq!(percentiles.max, 1000.0);
+    assert!((percentiles.p50 - 500.0).abs() < 10.0);
+    assert!((percentiles.p99 - 990.0).abs() < 5.0);
+  }
+
+  #[test]
+  fn it_reports_mean_and_stddev_of_numbers() {
+    let mut field_type = FieldType::new("age", "Int32");
+    for num in &[2, 4, 4, 4, 5, 5, 7, 9] {
+      field_type.update_value(&Bson::I32(*num), &ParserOptions::default());
+    }
+    field_type.finalise_type(8);
+    assert_eq!(field_type.min, Some(ValueType::I32(2)));
+    assert_eq!(field_type.max, Some(ValueType::I32(9)));
+    assert_eq!(field_type.mean, Some(5.0));
+    assert_eq!(field_type.stddev, Some(2.0));
+  }
+
+  #[test]
+  fn it_reports_array_lengths() {
+    let mut field_type = FieldType::new("location", "Array");
+    let pair = Bson::Array(vec![Bson::I32(52), Bson::I32(13)]);
+    field_type.update_value(&pair, &ParserOptions::default());
+    field_type.update_value(&pair, &ParserOptions::default());
+    field_type.finalise_type(2);
+    let lengths = field_type.array_lengths.unwrap();
+    assert_eq!(lengths.fixed_length(), Some(2));
+    assert_eq!(lengths.distribution[&2], 2);
+  }
+
+  #[test]
+  fn it_serializes_types_sorted() {
+    let mut field_type = FieldType::new("tags", "Array");
+    let tags = Bson::Array(vec![
+      Bson::String("new".to_string()),
+      Bson::I32(1),
+      Bson::Null,
+      Bson::Boolean(true),
+    ]);
+    field_type.add_to_type(&tags, 1, &ParserOptions::default());
+    let value = serde_json::to_value(&field_type).unwrap();
+    let types: Vec<&String> =
+      value["types"].as_object().unwrap().keys().collect();
+    assert_eq!(types, vec!["Boolean", "Int32", "Null", "String"]);
+  }
+
+  #[test]
+  fn it_reports_date_ranges() {
+    let options = ParserOptions {
+      date_histogram: Some(DateBucket::Week),
+      ..ParserOptions::default()
+    };
+    let mut field_type = FieldType::new("visited", "UtcDatetime");
+    for secs in &[1_567_493_659, 1_565_546_054, 1_565_600_000] {
+      let date = Bson::UtcDatetime(Utc.timestamp(*secs, 0));
+      field_type.update_value(&date, &options);
+    }
+    field_type.finalise_type(3);
+    let range = field_type.date_range.unwrap();
+    assert_eq!(range.min, "2019-08-11T17:54:14+00:00");
+    assert_eq!(range.max, "2019-09-03T06:54:19+00:00");
+    let histogram = field_type.date_histogram.unwrap();
+    let weeks: Vec<&String> = histogram.keys().collect();
+    assert_eq!(weeks, vec!["2019-08-05", "2019-08-12", "2019-09-02"]);
+    assert!(histogram.values().all(|count| *count == 1));
+  }
+
+  #[test]
+  fn it_builds_numeric_histograms() {
+    let options = ParserOptions {
+      numeric_histogram: Some(HistogramOptions {
+        buckets: 2,
+        scale: HistogramScale::Linear,
+      }),
+      ..ParserOptions::default()
+    };
+    let mut field_type = FieldType::new("age", "Int32");
+    for age in &[20, 25, 30, 60] {
+      field_type.update_value(&Bson::I32(*age), &options);
+    }
+    field_type.finalise_type(4);
+    let histogram = field_type.histogram.unwrap();
+    assert_eq!(histogram.len(), 2);
+    assert_eq!(histogram[0].min, 20.0);
+    assert_eq!(histogram[0].max, 40.0);
+    assert_eq!(histogram[0].count, 3);
+    assert_eq!(histogram[1].count, 1);
+  }
+
+  #[test]
+  fn it_flags_enum_candidates() {
+    let options = ParserOptions {
+      enum_threshold: Some(3),
+      ..ParserOptions::default()
+    };
+    let mut field_type = FieldType {
+      count: 0,
+      ..FieldType::new("status", "String")
+    };
+    for status in &["pending", "active", "active", "deleted", "active"] {
+      let status = Bson::String(status.to_string());
+      field_type.update_type(&status, &options);
+    }
+    // three values seen five times are too few repeats for an enum
+    field_type.finalise_type(5);
+    assert!(!field_type.is_enum_candidate());
+
+    field_type.update_type(&Bson::String("active".to_string()), &options);
+    field_type.finalise_type(6);
+    assert!(field_type.is_enum_candidate());
+    let values = field_type.enum_values.as_ref().unwrap();
+    assert_eq!(values[0], ValueType::Str("active".to_string()));
+    assert_eq!(values.len(), 3);
+
+    field_type.update_type(&Bson::String("lost".to_string()), &options);
+    field_type.finalise_type(7);
+    assert!(!field_type.is_enum_candidate());
+  }
+
+  #[test]
+  fn it_counts_empty_values() {
+    let options = ParserOptions::default();
+    let mut field_type = FieldType::new("nickname", "String");
+    for nickname in &["", "Nori", ""] {
+      let nickname = Bson::String(nickname.to_string());
+      field_type.update_type(&nickname, &options);
+    }
+    assert_eq!(field_type.empty, 2);
+    let mut toys = FieldType::new("toys", "Array");
+    toys.add_to_type(&Bson::Array(vec![]), 1, &options);
+    assert_eq!(toys.empty, 1);
+  }
+
+  #[test]
+  fn it_counts_booleans() {
+    let options = ParserOptions::default();
+    let mut field_type = FieldType::new("verified", "Boolean");
+    for verified in &[true, false, true, true] {
+      field_type.update_value(&Bson::Boolean(*verified), &options);
+    }
+    let mut other = FieldType::new("verified", "Boolean");
+    other.update_value(&Bson::Boolean(false), &options);
+    field_type.merge(other);
+    field_type.finalise_type(5);
+    let booleans = field_type.booleans.unwrap();
+    assert_eq!(booleans.true_count, 3);
+    assert_eq!(booleans.false_count, 2);
+    assert_eq!(booleans.true_ratio, 0.6);
+  }
+
+  #[test]
+  fn it_reports_top_values() {
+    let options = ParserOptions {
+      top_values: Some(2),
+      ..ParserOptions::default()
+    };
+    let mut field_type = FieldType::new("status", "String");
+    for status in &["active", "pending", "active", "deleted", "active"] {
+      let status = Bson::String(status.to_string());
+      field_type.update_value(&status, &options);
+    }
+    field_type.finalise_type(5);
+    let top_values = field_type.top_values.unwrap();
+    assert_eq!(top_values.len(), 2);
+    assert_eq!(top_values[0].value, ValueType::Str("active".to_string()));
+    assert_eq!(top_values[0].count, 3);
+  }
+
+  #[test]
+  fn it_reports_string_lengths() {
+    let mut field_type = FieldType::new("name", "String");
+    for name in &["Nori", "Zoë", "Chashu"] {
+      let name = Bson::String(name.to_string());
+      field_type.update_value(&name, &ParserOptions::default());
+    }
+    let lengths = field_type.string_lengths.unwrap();
+    assert_eq!(lengths.chars.min, 3);
+    assert_eq!(lengths.chars.max, 6);
+    assert_eq!(lengths.bytes.min, 4);
+    assert_eq!(lengths.bytes.max, 6);
+  }
+
+  #[test]
+  fn it_has_no_percentiles_for_strings() {
+    let mut field_type = FieldType::new("name", "String");
+    let name = Bson::String("Nori".to_string());
+    field_type.update_value(&name, &ParserOptions::default());
+    field_type.finalise_type(1);
+    assert!(field_type.percentiles.is_none());
+  }
+
+  #[test]
+  fn it_stops_sampling_past_the_memory_budget() {
+    let options = ParserOptions {
+      memory_budget_bytes: Some(0),
+      ..ParserOptions::default()
+    };
+    let mut field_type = FieldType::new("age", "Int32");
+    for num in 0..5 {
+      field_type.update_value(&Bson::I32(num), &options);
+    }
+    assert_eq!(field_type.values.len(), 1);
+    assert!(options.over_memory_budget());
+  }
+
+  #[test]
+  fn it_skips_values_when_not_stored() {
+    let options = ParserOptions {
+      store_values: false,
+      ..ParserOptions::default()
+    };
+    let mut field_type = FieldType::new("age", "Int32");
+    field_type.add_to_type(&Bson::I32(4), 1, &options);
+    assert!(field_type.values.is_empty());
+  }
+
+  #[test]
+  fn it_stops_at_max_depth() {
+    let options = ParserOptions {
+      max_depth: Some(0),
+      ..ParserOptions::default()
+    };
+    let mut field_type = FieldType::new("owner", "Document");
+    let subdoc = Bson::Document(doc! {"name": "Irina"});
+    field_type.add_to_type(&subdoc, 1, &options);
+    field_type.update_type(&subdoc, &options);
+    assert!(field_type.schema.is_none());
+    assert_eq!(field_type.count, 2);
+  }
+
   // #[bench]
   // fn bench_it_updates_value_some(bench: &mut Bencher) {
   //   let bson_value = Bson::I32(1234);
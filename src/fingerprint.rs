@@ -0,0 +1,110 @@
+use super::{Field, FieldType};
+use crate::field_type;
+
+static FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+static FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Hashes the structure of finalised fields: every path, including the paths
+/// of subdocument fields, and the set of types seen for it. Counts, samples
+/// and whether fields are missing from some documents are left out, and so is
+/// the order fields were seen in, so two analyses of the same structure have
+/// the same fingerprint.
+pub(crate) fn fingerprint<'a>(
+  fields: impl Iterator<Item = &'a Field>,
+) -> String {
+  let mut lines = Vec::new();
+  structure(fields, &mut lines);
+  lines.sort();
+  lines.dedup();
+  format!("{:016x}", fnv1a(lines.join("\n").as_bytes()))
+}
+
+// One `path:Type|Type` line per field, and one `path[]:Type|Type` line for
+// the elements of each array.
+fn structure<'a>(
+  fields: impl Iterator<Item = &'a Field>,
+  lines: &mut Vec<String>,
+) {
+  for field in fields {
+    types_line(&field.path, field.types(), lines);
+  }
+}
+
+fn types_line<'a>(
+  path: &str,
+  types: impl Iterator<Item = &'a FieldType>,
+  lines: &mut Vec<String>,
+) {
+  let mut types: Vec<&FieldType> = types
+    .filter(|t| t.bson_type != field_type::UNDEFINED)
+    .collect();
+  types.sort_by(|a, b| a.bson_type.cmp(&b.bson_type));
+  let names: Vec<&str> = types.iter().map(|t| t.bson_type.as_str()).collect();
+  lines.push(format!("{}:{}", path, names.join("|")));
+
+  for field_type in types {
+    if let Some(schema) = field_type.schema() {
+      structure(schema.fields(), lines);
+    }
+    if field_type.bson_type == field_type::ARRAY {
+      types_line(&format!("{}[]", path), field_type.types(), lines);
+    }
+  }
+}
+
+// 64 bit FNV-1a, which unlike std's hashers is the same across Rust versions
+// and platforms.
+fn fnv1a(bytes: &[u8]) -> u64 {
+  bytes.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+    (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::SchemaParser;
+
+  fn fingerprint_of(documents: &[&str]) -> String {
+    let mut schema_parser = SchemaParser::new();
+    for json in documents {
+      schema_parser.write_json(json).unwrap();
+    }
+    fingerprint(schema_parser.flush().fields())
+  }
+
+  #[test]
+  fn it_ignores_counts_values_and_order() {
+    let fingerprint = fingerprint_of(&[
+      r#"{"name": "Nori", "tags": ["cat"], "owner": {"name": "Ana"}}"#,
+    ]);
+    assert_eq!(fingerprint.len(), 16);
+    assert_eq!(
+      fingerprint,
+      fingerprint_of(&[
+        r#"{"owner": {"name": "Ben"}, "tags": ["dog", "big"], "name": "Rey"}"#,
+        r#"{"name": "Chashu", "tags": []}"#,
+      ])
+    );
+  }
+
+  #[test]
+  fn it_changes_with_structure() {
+    let fingerprint = fingerprint_of(&[r#"{"name": "Nori", "tags": ["cat"]}"#]);
+    assert_ne!(fingerprint, fingerprint_of(&[r#"{"name": "Nori"}"#]));
+    assert_ne!(
+      fingerprint,
+      fingerprint_of(&[r#"{"name": "Nori", "tags": [1]}"#])
+    );
+    assert_ne!(
+      fingerprint,
+      fingerprint_of(&[r#"{"name": null, "tags": ["cat"]}"#])
+    );
+  }
+
+  #[test]
+  fn it_hashes_with_fnv1a() {
+    assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+    assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+  }
+}
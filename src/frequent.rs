@@ -0,0 +1,202 @@
+use crate::ValueType;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::mem;
+
+// Counters kept per most frequent value reported. Counts are exact as long as
+// a type has no more distinct values than counters.
+static COUNTERS_PER_VALUE: usize = 10;
+
+/// A value and how many times it was seen.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ValueCount {
+  pub value: ValueType,
+  pub count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Counter {
+  hash: u64,
+  value: ValueType,
+  count: usize,
+}
+
+/// Counts the most frequent values of a FieldType with the Space-Saving
+/// algorithm: a fixed number of counters, where a value that isn't counted
+/// yet takes over the counter with the smallest count once all are taken.
+/// It inherits that count, so rare values can be overestimated, but frequent
+/// values are never missed.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct FrequentValues {
+  top: usize,
+  counters: Vec<Counter>,
+  // position of each counted value in `counters`, by hash
+  positions: HashMap<u64, usize>,
+}
+
+impl FrequentValues {
+  pub fn new(top: usize) -> Self {
+    FrequentValues {
+      top,
+      counters: Vec::new(),
+      positions: HashMap::new(),
+    }
+  }
+
+  /// Restores the counters of the top values deserialized from `to_json()`
+  /// output.
+  pub fn restore(top: usize, values: &[ValueCount]) -> Self {
+    let mut frequent = FrequentValues::new(top);
+    for value_count in values {
+      frequent.add(&value_count.value, value_count.count);
+    }
+    frequent
+  }
+
+  /// Counts a value, and returns the bytes newly taken up by its counter.
+  pub fn insert(&mut self, value: &ValueType) -> usize {
+    self.add(value, 1)
+  }
+
+  pub fn merge(&mut self, other: FrequentValues) {
+    for counter in other.counters {
+      self.add(&counter.value, counter.count);
+    }
+  }
+
+  /// Returns the most frequent values, most frequent first.
+  pub fn top_values(&self) -> Vec<ValueCount> {
+    let mut counters: Vec<&Counter> = self.counters.iter().collect();
+    counters.sort_by(|a, b| b.count.cmp(&a.count));
+    counters
+      .into_iter()
+      .take(self.top)
+      .map(|counter| ValueCount {
+        value: counter.value.clone(),
+        count: counter.count,
+      })
+      .collect()
+  }
+
+  fn add(&mut self, value: &ValueType, count: usize) -> usize {
+    let hash = hash(value);
+    if let Some(&position) = self.positions.get(&hash) {
+      self.counters[position].count += count;
+      return 0;
+    }
+    let counter = Counter {
+      hash,
+      value: value.clone(),
+      count,
+    };
+    if self.counters.len() < self.top * COUNTERS_PER_VALUE {
+      self.positions.insert(hash, self.counters.len());
+      self.counters.push(counter);
+      return mem::size_of::<Counter>() + mem::size_of::<(u64, usize)>();
+    }
+    let smallest = self
+      .counters
+      .iter()
+      .enumerate()
+      .min_by_key(|(_, counter)| counter.count)
+      .map(|(position, _)| position);
+    if let Some(position) = smallest {
+      let replaced = &mut self.counters[position];
+      self.positions.remove(&replaced.hash);
+      self.positions.insert(hash, position);
+      *replaced = Counter {
+        count: replaced.count + count,
+        ..counter
+      };
+    }
+    0
+  }
+}
+
+// Values are hashed by their json representation's parts, with floats
+// hashed by their bits.
+fn hash(value: &ValueType) -> u64 {
+  let mut hasher = DefaultHasher::new();
+  hash_value(value, &mut hasher);
+  hasher.finish()
+}
+
+fn hash_value<H: Hasher>(value: &ValueType, state: &mut H) {
+  mem::discriminant(value).hash(state);
+  match value {
+    ValueType::Str(string)
+    | ValueType::Decimal128(string)
+    | ValueType::Null(string) => string.hash(state),
+    ValueType::I32(num) => num.hash(state),
+    ValueType::I64(num) => num.hash(state),
+    ValueType::FloatingPoint(num) => num.to_bits().hash(state),
+    ValueType::Array(values) => {
+      values.len().hash(state);
+      for value in values {
+        hash_value(value, state);
+      }
+    }
+    ValueType::Boolean(boolean) => boolean.hash(state),
+    ValueType::Binary { sub_type, length } => {
+      sub_type.hash(state);
+      length.hash(state);
+    }
+    ValueType::Timestamp { t, i } => {
+      t.hash(state);
+      i.hash(state);
+    }
+    ValueType::RegExp { pattern, options } => {
+      pattern.hash(state);
+      options.hash(state);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn status(status: &str) -> ValueType {
+    ValueType::Str(status.to_string())
+  }
+
+  #[test]
+  fn it_counts_top_values() {
+    let mut frequent = FrequentValues::new(2);
+    for value in &["active", "active", "deleted", "active", "pending"] {
+      frequent.insert(&status(value));
+    }
+    let top = frequent.top_values();
+    assert_eq!(top.len(), 2);
+    assert_eq!(top[0].value, status("active"));
+    assert_eq!(top[0].count, 3);
+    assert_eq!(top[1].count, 1);
+  }
+
+  #[test]
+  fn it_keeps_frequent_values_past_its_counters() {
+    let mut frequent = FrequentValues::new(1);
+    for num in 0..1000 {
+      frequent.insert(&ValueType::I32(num));
+      frequent.insert(&ValueType::I32(-1));
+    }
+    assert_eq!(frequent.counters.len(), COUNTERS_PER_VALUE);
+    let top = frequent.top_values();
+    assert_eq!(top[0].value, ValueType::I32(-1));
+    assert_eq!(top[0].count, 1000);
+  }
+
+  #[test]
+  fn it_merges_and_restores() {
+    let mut frequent = FrequentValues::new(2);
+    frequent.insert(&status("active"));
+    let mut other = FrequentValues::new(2);
+    other.insert(&status("active"));
+    other.insert(&status("pending"));
+    frequent.merge(other);
+    let restored = FrequentValues::restore(2, &frequent.top_values());
+    assert_eq!(restored.top_values()[0].count, 2);
+    assert_eq!(restored.top_values()[1].value, status("pending"));
+  }
+}
@@ -0,0 +1,193 @@
+use super::{Field, FieldType};
+use crate::field_type;
+use crate::typescript::pascal_case;
+use std::collections::BTreeSet;
+
+/// Converts finalised fields into GraphQL SDL object type definitions. The
+/// top level type is called `name`, subdocuments get their own types named
+/// after their path, and arrays become list types. Fields are non-null when
+/// they are present in at least `required_threshold` of the documents and
+/// never null.
+pub(crate) fn to_graphql_sdl<'a>(
+  fields: impl Iterator<Item = &'a Field>,
+  name: &str,
+  required_threshold: f32,
+) -> String {
+  let mut writer = Writer {
+    root: pascal_case(name),
+    types: Vec::new(),
+    scalars: BTreeSet::new(),
+    required_threshold,
+  };
+  let root = writer.root.clone();
+  writer.object_type(fields, &root);
+
+  let mut sdl = String::new();
+  for scalar in &writer.scalars {
+    sdl.push_str(&format!("scalar {}\n", scalar));
+  }
+  if !writer.scalars.is_empty() {
+    sdl.push('\n');
+  }
+  sdl.push_str(&writer.types.join("\n"));
+  sdl
+}
+
+struct Writer {
+  root: String,
+  types: Vec<String>,
+  scalars: BTreeSet<&'static str>,
+  required_threshold: f32,
+}
+
+impl Writer {
+  // Nested types are written after the type that uses them.
+  fn object_type<'a>(
+    &mut self,
+    fields: impl Iterator<Item = &'a Field>,
+    name: &str,
+  ) {
+    let index = self.types.len();
+    self.types.push(String::new());
+
+    let mut object_type = format!("type {} {{\n", name);
+    for field in fields {
+      let non_null = field.probability >= self.required_threshold;
+      let graphql_type = self.field_type(field.types(), non_null);
+      object_type.push_str(&format!(
+        "  {}: {}\n",
+        field_name(&field.name),
+        graphql_type
+      ));
+    }
+    object_type.push_str("}\n");
+    self.types[index] = object_type;
+  }
+
+  // Fields with several types can't be expressed as a GraphQL union, as
+  // unions only take object types, so they become the JSON scalar.
+  fn field_type<'a>(
+    &mut self,
+    types: impl Iterator<Item = &'a FieldType>,
+    mut non_null: bool,
+  ) -> String {
+    let mut graphql_types: Vec<String> = Vec::new();
+    for field_type in types {
+      let bson_type = &field_type.bson_type;
+      if bson_type == field_type::NULL || bson_type == field_type::UNDEFINED {
+        non_null = false;
+        continue;
+      }
+      let graphql_type = self.graphql_type(field_type);
+      if !graphql_types.contains(&graphql_type) {
+        graphql_types.push(graphql_type);
+      }
+    }
+
+    let graphql_type = match graphql_types.len() {
+      1 => graphql_types.remove(0),
+      _ => self.scalar("JSON"),
+    };
+    if non_null {
+      format!("{}!", graphql_type)
+    } else {
+      graphql_type
+    }
+  }
+
+  fn graphql_type(&mut self, field_type: &FieldType) -> String {
+    match field_type.bson_type.as_str() {
+      t if t == field_type::DOCUMENT => match field_type.schema() {
+        Some(schema) => {
+          let path = pascal_case(&field_type.path);
+          let name = format!("{}{}", self.root, path);
+          self.object_type(schema.fields(), &name);
+          name
+        }
+        None => self.scalar("JSON"),
+      },
+      t if t == field_type::ARRAY => {
+        let items = self.field_type(field_type.types(), true);
+        format!("[{}]", items)
+      }
+      t if t == field_type::FLOATING_POINT || t == field_type::NUMBER => {
+        "Float".to_string()
+      }
+      t if t == field_type::I32 => "Int".to_string(),
+      t if t == field_type::STRING || t == field_type::SYMBOL => {
+        "String".to_string()
+      }
+      t if t == field_type::BOOLEAN => "Boolean".to_string(),
+      t if t == field_type::OBJECTID => "ID".to_string(),
+      // GraphQL's Int is only 32 bits
+      t if t == field_type::I64 => self.scalar("Long"),
+      t if t == field_type::DECIMAL_128 => self.scalar("Decimal"),
+      t if t == field_type::UTCDATE_TIME => self.scalar("DateTime"),
+      _ => self.scalar("JSON"),
+    }
+  }
+
+  // Custom scalars are declared at the top of the SDL.
+  fn scalar(&mut self, name: &'static str) -> String {
+    self.scalars.insert(name);
+    name.to_string()
+  }
+}
+
+// GraphQL names may only contain letters, digits and underscores, and can't
+// start with a digit.
+fn field_name(name: &str) -> String {
+  let mut field_name: String = name
+    .chars()
+    .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+    .collect();
+  let starts_with_digit = field_name.starts_with(|c: char| c.is_ascii_digit());
+  if field_name.is_empty() || starts_with_digit {
+    field_name.insert(0, '_');
+  }
+  field_name
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::SchemaParser;
+
+  #[test]
+  fn it_converts_to_graphql_sdl() {
+    let mut schema_parser = SchemaParser::new();
+    let json_str1 = r#"{
+      "_id": {"$oid": "5d505646cf6d4fe581014ab2"},
+      "name": "Nori",
+      "age": 5,
+      "tags": ["cat"]
+    }"#;
+    let json_str2 = r#"{
+      "_id": {"$oid": "5d505646cf6d4fe581014ab3"},
+      "name": null,
+      "age": "3",
+      "owner": {"first-name": "Ana"}
+    }"#;
+    schema_parser.write_json(&json_str1).unwrap();
+    schema_parser.write_json(&json_str2).unwrap();
+    let schema = schema_parser.flush();
+    let sdl = to_graphql_sdl(schema.fields(), "pets", 1.0);
+    assert_eq!(
+      sdl,
+      r#"scalar JSON
+
+type Pets {
+  _id: ID!
+  name: String
+  age: JSON!
+  tags: [String!]
+  owner: PetsOwner
+}
+
+type PetsOwner {
+  first_name: String!
+}
+"#
+    );
+  }
+}
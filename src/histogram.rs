@@ -0,0 +1,161 @@
+use crate::digest::TDigest;
+
+/// How the range of a numeric histogram is split into buckets.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum HistogramScale {
+  /// Buckets of equal width between the min and max.
+  Linear,
+  /// Buckets of equal width on a log scale, for numbers spanning orders of
+  /// magnitude, i.e. prices or file sizes. Falls back to `Linear` for types
+  /// with numbers of zero or less.
+  Log,
+}
+
+/// Histogram of the numbers of each numeric type, set with
+/// `SchemaParser::builder().numeric_histogram()`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct HistogramOptions {
+  pub buckets: usize,
+  pub scale: HistogramScale,
+}
+
+/// A bucket of a numeric histogram, with how many numbers fell between its
+/// `min` and `max`. Counts are estimated from the type's t-digest.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HistogramBucket {
+  pub min: f64,
+  pub max: f64,
+  pub count: usize,
+}
+
+impl HistogramOptions {
+  /// Splits the numbers of a digest into buckets, if it has any. A single
+  /// bucket is returned when all numbers are the same.
+  pub(crate) fn histogram(
+    &self,
+    digest: &mut TDigest,
+  ) -> Option<Vec<HistogramBucket>> {
+    let percentiles = digest.percentiles()?;
+    let (min, max) = (percentiles.min, percentiles.max);
+    let total = digest.count();
+    if self.buckets == 0 || !min.is_finite() || !max.is_finite() {
+      return None;
+    }
+    if min >= max {
+      return Some(vec![HistogramBucket {
+        min,
+        max,
+        count: total,
+      }]);
+    }
+    let edges = self.edges(min, max);
+    let mut below = 0;
+    let buckets = edges
+      .windows(2)
+      .enumerate()
+      .map(|(i, edge)| {
+        // the last bucket includes its upper edge
+        let upto = if i == self.buckets - 1 {
+          total
+        } else {
+          digest.rank(edge[1]).round() as usize
+        };
+        let count = upto.saturating_sub(below);
+        below = below.max(upto);
+        HistogramBucket {
+          min: edge[0],
+          max: edge[1],
+          count,
+        }
+      })
+      .collect();
+    Some(buckets)
+  }
+
+  fn edges(&self, min: f64, max: f64) -> Vec<f64> {
+    let buckets = self.buckets as f64;
+    let edge = |i: usize| match self.scale {
+      HistogramScale::Log if min > 0.0 => {
+        (min.ln() + (max.ln() - min.ln()) * i as f64 / buckets).exp()
+      }
+      _ => min + (max - min) * i as f64 / buckets,
+    };
+    let mut edges: Vec<f64> = (0..=self.buckets).map(edge).collect();
+    // exact ends, whatever the rounding of the scale
+    edges[0] = min;
+    edges[self.buckets] = max;
+    edges
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn digest_of(values: impl Iterator<Item = f64>) -> TDigest {
+    let mut digest = TDigest::default();
+    for value in values {
+      digest.insert(value);
+    }
+    digest
+  }
+
+  #[test]
+  fn it_builds_linear_histograms() {
+    let mut digest = digest_of((1..=1000).map(f64::from));
+    let options = HistogramOptions {
+      buckets: 4,
+      scale: HistogramScale::Linear,
+    };
+    let histogram = options.histogram(&mut digest).unwrap();
+    assert_eq!(histogram.len(), 4);
+    assert_eq!(histogram[0].min, 1.0);
+    assert_eq!(histogram[1].min, 250.75);
+    assert_eq!(histogram[3].max, 1000.0);
+    let counts: Vec<usize> = histogram.iter().map(|b| b.count).collect();
+    assert_eq!(counts.iter().sum::<usize>(), 1000);
+    assert!(counts.iter().all(|count| (*count as i64 - 250).abs() <= 5));
+  }
+
+  #[test]
+  fn it_builds_log_histograms() {
+    // spread evenly over the orders of magnitude from 1 to 10,000
+    let values = (0..=1000).map(|i| 10f64.powf(f64::from(i) / 250.0));
+    let mut digest = digest_of(values);
+    let options = HistogramOptions {
+      buckets: 4,
+      scale: HistogramScale::Log,
+    };
+    let histogram = options.histogram(&mut digest).unwrap();
+    assert!((histogram[1].min - 10.0).abs() < 1e-9);
+    assert_eq!(histogram[3].max, 10_000.0);
+    for bucket in &histogram {
+      assert!((bucket.count as i64 - 250).abs() <= 5);
+    }
+  }
+
+  #[test]
+  fn it_falls_back_to_linear_for_non_positive_numbers() {
+    let mut digest = digest_of((-10..10).map(f64::from));
+    let options = HistogramOptions {
+      buckets: 2,
+      scale: HistogramScale::Log,
+    };
+    let histogram = options.histogram(&mut digest).unwrap();
+    assert_eq!(histogram[0].max, -0.5);
+    assert_eq!(histogram[0].count + histogram[1].count, 20);
+  }
+
+  #[test]
+  fn it_has_one_bucket_for_a_single_number() {
+    let mut digest = digest_of(vec![7.0, 7.0].into_iter());
+    let options = HistogramOptions {
+      buckets: 10,
+      scale: HistogramScale::Linear,
+    };
+    let histogram = options.histogram(&mut digest).unwrap();
+    assert_eq!(histogram.len(), 1);
+    assert_eq!(histogram[0].count, 2);
+    assert!(options.histogram(&mut TDigest::default()).is_none());
+  }
+}
@@ -0,0 +1,294 @@
+use super::{Field, IndexMap, ParseError, Schema};
+use crate::{diff, field_type};
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+/// When a SchemaParser takes snapshots of its schema.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SnapshotInterval {
+  /// After every n documents.
+  Documents(usize),
+  /// After the first document written once the duration has passed since the
+  /// last snapshot. Needs a system clock, which isn't available in wasm.
+  Time(Duration),
+}
+
+/// The schema of all documents written up to a point in time.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Snapshot {
+  /// When the snapshot was taken, formatted as an RFC 3339 string.
+  pub taken_at: String,
+  pub schema: Schema,
+}
+
+/// Snapshots of a schema over time, from oldest to newest. Serializes to a
+/// timeline of `{ "snapshots": [{ "taken_at", "schema" }] }`, with each
+/// schema in the same format as `Schema::to_json()`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct SchemaHistory {
+  pub snapshots: Vec<Snapshot>,
+  #[serde(skip)]
+  interval: Option<SnapshotInterval>,
+  #[serde(skip)]
+  since: Option<DateTime<Utc>>,
+}
+
+/// How a field evolved over the snapshots of a SchemaHistory.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FieldTimeline {
+  pub path: String,
+  /// Time of the first snapshot the field is in.
+  pub first_seen: String,
+  /// Time of the last snapshot with documents the field was in since the
+  /// snapshot before it.
+  pub last_seen: String,
+  /// The field's probability in each snapshot, or 0.0 before it was seen.
+  pub probability: Vec<f32>,
+  /// For each type, its share of the field's values written between each
+  /// snapshot and the one before it, or 0.0 if the field had no values then.
+  pub types: IndexMap<String, Vec<f32>>,
+}
+
+impl SchemaHistory {
+  /// Returns an empty history, to record snapshots to by hand.
+  pub fn new() -> Self {
+    SchemaHistory::default()
+  }
+
+  pub(crate) fn with_interval(interval: SnapshotInterval) -> Self {
+    SchemaHistory {
+      interval: Some(interval),
+      ..SchemaHistory::default()
+    }
+  }
+
+  /// Adds a snapshot of `schema`, taken now.
+  pub fn record(&mut self, schema: Schema) {
+    let now = Utc::now();
+    self.snapshots.push(Snapshot {
+      taken_at: now.to_rfc3339(),
+      schema,
+    });
+    self.since = Some(now);
+  }
+
+  // Documents left to write after `count` until a snapshot is due, if
+  // snapshots are taken every so many documents.
+  pub(crate) fn documents_until_due(&self, count: usize) -> Option<usize> {
+    match self.interval {
+      Some(SnapshotInterval::Documents(documents)) if documents > 0 => {
+        Some(documents - count % documents)
+      }
+      _ => None,
+    }
+  }
+
+  // Whether a snapshot should be taken after `count` documents were written.
+  pub(crate) fn is_due(&mut self, count: usize) -> bool {
+    match self.interval {
+      Some(SnapshotInterval::Documents(documents)) => {
+        documents > 0 && count % documents == 0
+      }
+      Some(SnapshotInterval::Time(duration)) => {
+        let now = Utc::now();
+        let since = *self.since.get_or_insert(now);
+        now
+          .signed_duration_since(since)
+          .to_std()
+          .map_or(false, |elapsed| elapsed >= duration)
+      }
+      None => false,
+    }
+  }
+
+  /// Returns how each field evolved over the snapshots, including the fields
+  /// of subdocuments, in the order they were first seen.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu" }"#);
+  /// schema_parser.snapshot();
+  /// schema_parser.write_json(r#"{ "name": "Rey", "age": 4 }"#);
+  /// schema_parser.snapshot();
+  /// let timeline = schema_parser.history().unwrap().timeline();
+  /// assert_eq!(timeline[1].path, "age");
+  /// assert_eq!(timeline[1].probability, vec![0.0, 0.5]);
+  /// ```
+  pub fn timeline(&self) -> Vec<FieldTimeline> {
+    let snapshots: Vec<IndexMap<&str, &Field>> = self
+      .snapshots
+      .iter()
+      .map(|snapshot| diff::fields_by_path(snapshot.schema.fields()))
+      .collect();
+    let mut paths: Vec<&str> = Vec::new();
+    for fields in &snapshots {
+      for path in fields.keys() {
+        if !paths.contains(path) {
+          paths.push(*path);
+        }
+      }
+    }
+
+    paths
+      .into_iter()
+      .map(|path| {
+        let fields: Vec<Option<&Field>> = snapshots
+          .iter()
+          .map(|fields| fields.get(path).cloned())
+          .collect();
+        self.field_timeline(path, &fields)
+      })
+      .collect()
+  }
+
+  // `fields` has the field as it was in each snapshot, if it was there yet.
+  fn field_timeline(
+    &self,
+    path: &str,
+    fields: &[Option<&Field>],
+  ) -> FieldTimeline {
+    let counts = |bson_type: Option<&str>| -> Vec<usize> {
+      fields
+        .iter()
+        .map(|field| field.map_or(0, |field| type_count(field, bson_type)))
+        .collect()
+    };
+    let present = counts(None);
+    let first = fields.iter().position(Option::is_some).unwrap_or(0);
+    let last = (0..fields.len())
+      .rev()
+      .find(|i| growth(&present, *i) > 0)
+      .unwrap_or(first);
+
+    let mut bson_types: Vec<&str> = Vec::new();
+    for field in fields.iter().flatten() {
+      for bson_type in &field.bson_types {
+        if bson_type != field_type::UNDEFINED
+          && !bson_types.contains(&bson_type.as_str())
+        {
+          bson_types.push(bson_type);
+        }
+      }
+    }
+    let types = bson_types
+      .into_iter()
+      .map(|bson_type| {
+        let type_counts = counts(Some(bson_type));
+        let shares = (0..fields.len())
+          .map(|i| match growth(&present, i) {
+            0 => 0.0,
+            values => growth(&type_counts, i) as f32 / values as f32,
+          })
+          .collect();
+        (bson_type.to_string(), shares)
+      })
+      .collect();
+
+    FieldTimeline {
+      path: path.to_string(),
+      first_seen: self.snapshots[first].taken_at.clone(),
+      last_seen: self.snapshots[last].taken_at.clone(),
+      probability: fields
+        .iter()
+        .map(|field| field.map_or(0.0, |field| field.probability))
+        .collect(),
+      types,
+    }
+  }
+
+  /// Returns the history as a json timeline.
+  pub fn to_json(&self) -> Result<String, ParseError> {
+    serde_json::to_string(&self).map_err(ParseError::Serialization)
+  }
+
+  /// Loads a history from a json timeline returned by `to_json()`.
+  ///
+  /// # Arguments
+  /// * `json` - A history serialized with `to_json()`.
+  pub fn from_json(json: &str) -> Result<Self, ParseError> {
+    serde_json::from_str(json).map_err(ParseError::InvalidJson)
+  }
+}
+
+// How much a count grew since the snapshot before.
+fn growth(counts: &[usize], i: usize) -> usize {
+  match i {
+    0 => counts[0],
+    _ => counts[i].saturating_sub(counts[i - 1]),
+  }
+}
+
+// How many values a field has of a type, or of any type but Undefined.
+fn type_count(field: &Field, bson_type: Option<&str>) -> usize {
+  match bson_type {
+    Some(bson_type) => field.types.get(bson_type).map_or(0, |t| t.count),
+    None => field
+      .types()
+      .filter(|t| t.bson_type != field_type::UNDEFINED)
+      .map(|t| t.count)
+      .sum(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::SchemaParser;
+
+  fn schema_parser() -> SchemaParser {
+    let mut schema_parser = SchemaParser::builder()
+      .snapshot_every(SnapshotInterval::Documents(2))
+      .build();
+    let documents = [
+      r#"{"name": "Nori", "age": 5}"#,
+      r#"{"name": "Rey", "age": 3}"#,
+      r#"{"name": "Chashu", "age": "2", "owner": {"name": "Ana"}}"#,
+      r#"{"name": "Lox", "age": "4"}"#,
+      r#"{"name": "Tom"}"#,
+      r#"{"name": "Pip", "age": 1}"#,
+    ];
+    for json in documents.iter() {
+      schema_parser.write_json(json).unwrap();
+    }
+    schema_parser
+  }
+
+  #[test]
+  fn it_takes_snapshots_every_n_documents() {
+    let history = schema_parser().history().unwrap().clone();
+    let counts: Vec<usize> =
+      history.snapshots.iter().map(|s| s.schema.count).collect();
+    assert_eq!(counts, vec![2, 4, 6]);
+  }
+
+  #[test]
+  #[allow(clippy::float_cmp)]
+  fn it_builds_a_timeline() {
+    let history = schema_parser().history().unwrap().clone();
+    let timeline = history.timeline();
+    let paths: Vec<&str> = timeline.iter().map(|t| t.path.as_str()).collect();
+    assert_eq!(paths, vec!["name", "age", "owner", "owner.name"]);
+
+    let age = &timeline[1];
+    assert_eq!(age.types["Int32"], vec![1.0, 0.0, 1.0]);
+    assert_eq!(age.types["String"], vec![0.0, 1.0, 0.0]);
+    assert_eq!(age.probability, vec![1.0, 1.0, 5.0 / 6.0]);
+
+    let owner = &timeline[2];
+    assert_eq!(owner.probability, vec![0.0, 0.25, 1.0 / 6.0]);
+    assert_eq!(owner.first_seen, history.snapshots[1].taken_at);
+    assert_eq!(owner.last_seen, history.snapshots[1].taken_at);
+  }
+
+  #[test]
+  fn it_serializes_to_json() {
+    let history = schema_parser().history().unwrap().clone();
+    let json = history.to_json().unwrap();
+    let restored = SchemaHistory::from_json(&json).unwrap();
+    assert_eq!(restored.snapshots.len(), 3);
+    assert_eq!(restored.timeline().len(), 4);
+  }
+}
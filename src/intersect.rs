@@ -0,0 +1,102 @@
+use super::{FieldType, HashMap, Schema, SchemaParser};
+
+/// Combines the fields two finalised Schemas have in common. Fields only one
+/// of them has are left out, and so are types of common fields that only one
+/// of them has. Documents whose field had such a type count as missing it.
+pub(crate) fn intersect(schema: &Schema, other: &Schema) -> Schema {
+  let mut schema_parser = SchemaParser::from_schema(schema.clone());
+  let mut other_parser = SchemaParser::from_schema(other.clone());
+  retain_common(&mut schema_parser, &other_parser);
+  retain_common(&mut other_parser, &schema_parser);
+  schema_parser.merge(other_parser);
+  schema_parser.into_schema()
+}
+
+// Keeps the fields `other` has too, with only the types both of them have.
+fn retain_common(schema_parser: &mut SchemaParser, other: &SchemaParser) {
+  schema_parser
+    .fields
+    .retain(|path, field| match other.fields.get(path) {
+      Some(other_field) => {
+        retain_common_types(&mut field.types, &other_field.types);
+        let types = &field.types;
+        field
+          .bson_types
+          .retain(|bson_type| types.contains_key(bson_type));
+        // every document a field is in has one of its types
+        field.count = types.values().map(|t| t.count).sum();
+        !types.is_empty()
+      }
+      None => false,
+    });
+}
+
+// Subdocuments and array elements only keep what they have in common too.
+fn retain_common_types(
+  types: &mut HashMap<String, FieldType>,
+  other: &HashMap<String, FieldType>,
+) {
+  types.retain(|bson_type, field_type| match other.get(bson_type) {
+    Some(other_type) => {
+      if let (Some(schema), Some(other_schema)) =
+        (field_type.schema.as_mut(), other_type.schema.as_ref())
+      {
+        retain_common(schema, other_schema);
+      }
+      retain_common_types(&mut field_type.types, &other_type.types);
+      true
+    }
+    None => false,
+  });
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn schema(documents: &[&str]) -> Schema {
+    let mut schema_parser = SchemaParser::new();
+    for json in documents {
+      schema_parser.write_json(json).unwrap();
+    }
+    schema_parser.flush()
+  }
+
+  #[test]
+  #[allow(clippy::float_cmp)]
+  fn it_intersects_schemas() {
+    let orders = schema(&[
+      r#"{"_id": 1, "customer": {"name": "Ana", "vip": true}, "total": 5}"#,
+      r#"{"_id": 2, "customer": {"name": "Ben"}, "total": 7.5}"#,
+    ]);
+    let returns = schema(&[
+      r#"{"_id": 3, "customer": {"name": "Ana"}, "total": 5, "note": "x"}"#,
+    ]);
+    let schema = intersect(&orders, &returns);
+    assert_eq!(schema.count, 3);
+
+    let paths: Vec<&str> = schema.fields().map(|f| &*f.path).collect();
+    assert_eq!(paths, vec!["_id", "customer", "total"]);
+
+    let customer = schema.fields.get("customer").unwrap();
+    let customer = customer.types.get("Document").unwrap();
+    let customer = customer.schema.as_ref().unwrap();
+    let names: Vec<&str> = customer.fields().map(|f| &*f.name).collect();
+    assert_eq!(names, vec!["name"]);
+
+    // the order with a Double total counts as missing it
+    let total = schema.fields.get("total").unwrap();
+    assert_eq!(total.bson_types, vec!["Int32", "Undefined"]);
+    assert_eq!(total.probability, 2.0 / 3.0);
+  }
+
+  #[test]
+  fn it_leaves_out_fields_without_common_types() {
+    let schema = intersect(
+      &schema(&[r#"{"age": 5, "name": "Nori"}"#]),
+      &schema(&[r#"{"age": "5", "name": "Rey"}"#]),
+    );
+    let paths: Vec<&str> = schema.fields().map(|f| &*f.path).collect();
+    assert_eq!(paths, vec!["name"]);
+  }
+}
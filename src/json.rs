@@ -0,0 +1,88 @@
+use crate::ParseError;
+
+use serde_json::Value;
+#[cfg(feature = "simd")]
+use std::cell::RefCell;
+
+// simd-json is only built for targets with AVX2 or SSE 4.2, so anywhere else
+// the `simd` feature would quietly parse with serde_json.
+#[cfg(all(
+  feature = "simd",
+  not(any(target_feature = "avx2", target_feature = "sse4.2"))
+))]
+compile_error!(
+  "the `simd` feature needs a target with AVX2 or SSE 4.2, i.e. build with \
+   RUSTFLAGS=\"-C target-cpu=native\""
+);
+
+#[cfg(feature = "simd")]
+thread_local! {
+  // simd-json parses in place, so documents are copied into a buffer that's
+  // reused for every document parsed on the thread.
+  static BUFFER: RefCell<Vec<u8>> = RefCell::new(Vec::new());
+}
+
+/// Parses a json document. With the `simd` feature documents are parsed with
+/// `simd-json`, and with `serde_json` otherwise.
+#[cfg(feature = "simd")]
+pub(crate) fn from_str(json: &str) -> Result<Value, ParseError> {
+  use serde::de::Error as _;
+
+  BUFFER.with(|buffer| {
+    let mut buffer = buffer.borrow_mut();
+    buffer.clear();
+    buffer.extend_from_slice(json.as_bytes());
+    match simd_json::serde::from_slice(&mut buffer[..]) {
+      Ok(value) => Ok(value),
+      // integers that don't fit in 64 bits are floats to serde_json, but are
+      // rejected by simd-json
+      Err(e) if is_overflow(&e) => {
+        serde_json::from_str(json).map_err(ParseError::InvalidJson)
+      }
+      Err(e) => Err(ParseError::InvalidJson(serde_json::Error::custom(e))),
+    }
+  })
+}
+
+// simd-json's errors only tell what went wrong in their message.
+#[cfg(feature = "simd")]
+fn is_overflow(error: &simd_json::Error) -> bool {
+  error.to_string().starts_with("Overflow")
+}
+
+#[cfg(not(feature = "simd"))]
+pub(crate) fn from_str(json: &str) -> Result<Value, ParseError> {
+  serde_json::from_str(json).map_err(ParseError::InvalidJson)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_parses_documents_in_order() {
+    let value = from_str(r#"{"name": "Nori", "age": 5, "a": [1.5]}"#);
+    let keys: Vec<String> = value
+      .unwrap()
+      .as_object()
+      .unwrap()
+      .keys()
+      .cloned()
+      .collect();
+    assert_eq!(keys, vec!["name", "age", "a"]);
+  }
+
+  #[test]
+  fn it_parses_integers_past_64_bits_as_floats() {
+    let value = from_str(r#"{"id": 18446744073709551616}"#).unwrap();
+    assert!(value["id"].is_f64());
+  }
+
+  #[test]
+  fn it_errors_on_invalid_json() {
+    match from_str(r#"{"name": "Nori""#) {
+      Err(ParseError::InvalidJson(_)) => (),
+      other => panic!("expected InvalidJson, got {:?}", other),
+    }
+  }
+}
@@ -0,0 +1,56 @@
+use crate::{extended_json, ParseError, SchemaParser};
+
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde_json::Value;
+use std::fmt;
+
+/// Writes each element of a top level json array, i.e. the output of
+/// `mongoexport --jsonArray`, to `schema_parser`. Elements are deserialized
+/// and analysed one at a time, so the array is never held in memory as a
+/// whole.
+pub(crate) fn write_elements(
+  schema_parser: &mut SchemaParser,
+  json: &str,
+) -> Result<(), ParseError> {
+  let mut deserializer = serde_json::Deserializer::from_str(json);
+  let mut elements = Elements {
+    schema_parser,
+    error: None,
+  };
+  let result = (&mut deserializer)
+    .deserialize_seq(&mut elements)
+    .and_then(|()| deserializer.end());
+  // an element that isn't a valid document aborts deserialization, with the
+  // reason kept aside
+  match (elements.error, result) {
+    (Some(e), _) => Err(e),
+    (None, Err(e)) => Err(ParseError::InvalidJson(e)),
+    (None, Ok(())) => Ok(()),
+  }
+}
+
+struct Elements<'a> {
+  schema_parser: &'a mut SchemaParser,
+  error: Option<ParseError>,
+}
+
+impl<'de, 'a> Visitor<'de> for &mut Elements<'a> {
+  type Value = ();
+
+  fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "a json array of documents")
+  }
+
+  fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+    while let Some(value) = seq.next_element::<Value>()? {
+      let written = extended_json::to_bson(value)
+        .and_then(|bson| self.schema_parser.write_root(bson));
+      if let Err(e) = written {
+        let message = e.to_string();
+        self.error = Some(e);
+        return Err(de::Error::custom(message));
+      }
+    }
+    Ok(())
+  }
+}
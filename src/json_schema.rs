@@ -0,0 +1,437 @@
+use super::{Field, FieldType, ParserOptions, ValueType};
+use crate::field_type;
+use serde_json::{Map, Value};
+
+pub static DRAFT_07: &str = "http://json-schema.org/draft-07/schema#";
+
+// JSON Schema flavour to write. MongoDB's `$jsonSchema` uses `bsonType`
+// instead of `type`, and doesn't support the `$schema` keyword.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Dialect {
+  Draft07,
+  MongoDB { additional_properties: bool },
+}
+
+/// Ranges of values seen during analysis to constrain values to: the
+/// `minimum` and `maximum` of numbers, and the `minLength` and `maxLength` of
+/// strings.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct Bounds {
+  pub numeric: bool,
+  pub length: bool,
+}
+
+impl Bounds {
+  pub fn from_options(options: &ParserOptions) -> Self {
+    Bounds {
+      numeric: options.numeric_bounds,
+      length: options.length_bounds,
+    }
+  }
+}
+
+/// Converts finalised fields into a JSON Schema draft-07 document. Fields
+/// present in at least `required_threshold` of the documents are required.
+/// Numbers and strings are bounded by the ranges seen as set in `bounds`.
+pub(crate) fn to_json_schema<'a>(
+  fields: impl Iterator<Item = &'a Field>,
+  required_threshold: f32,
+  bounds: Bounds,
+) -> Value {
+  let mut json_schema = Map::new();
+  json_schema.insert("$schema".to_string(), Value::from(DRAFT_07));
+  let schema =
+    object_schema(fields, required_threshold, Dialect::Draft07, bounds);
+  json_schema.extend(schema);
+  Value::Object(json_schema)
+}
+
+/// Converts finalised fields into the schema of a MongoDB `$jsonSchema`
+/// validator, using `bsonType` keywords.
+pub(crate) fn to_bson_schema<'a>(
+  fields: impl Iterator<Item = &'a Field>,
+  required_threshold: f32,
+  additional_properties: bool,
+  bounds: Bounds,
+) -> Value {
+  let dialect = Dialect::MongoDB {
+    additional_properties,
+  };
+  Value::Object(object_schema(fields, required_threshold, dialect, bounds))
+}
+
+fn object_schema<'a>(
+  fields: impl Iterator<Item = &'a Field>,
+  required_threshold: f32,
+  dialect: Dialect,
+  bounds: Bounds,
+) -> Map<String, Value> {
+  let mut properties = Map::new();
+  let mut required = Vec::new();
+  for field in fields {
+    if field.probability >= required_threshold {
+      required.push(Value::from(&*field.name));
+    }
+    let field_schema =
+      types_schema(field.types(), required_threshold, dialect, bounds);
+    properties.insert(field.name.to_string(), Value::Object(field_schema));
+  }
+
+  let mut schema = Map::new();
+  schema.insert(type_keyword(dialect).to_string(), Value::from("object"));
+  schema.insert("properties".to_string(), Value::Object(properties));
+  if !required.is_empty() {
+    schema.insert("required".to_string(), Value::Array(required));
+  }
+  if let Dialect::MongoDB {
+    additional_properties: false,
+  } = dialect
+  {
+    schema.insert("additionalProperties".to_string(), Value::from(false));
+  }
+  schema
+}
+
+// A field with several types gets a list of types, with the properties of its
+// Document type and the items of its Array type alongside. Numeric bounds
+// span the ranges of all its numeric types. Lengths are bounded in
+// characters. Values are listed in an `enum` if all of its types but Null are
+// enum candidates.
+fn types_schema<'a>(
+  types: impl Iterator<Item = &'a FieldType>,
+  required_threshold: f32,
+  dialect: Dialect,
+  bounds: Bounds,
+) -> Map<String, Value> {
+  let mut types: Vec<&FieldType> = types.collect();
+  types.sort_by(|a, b| a.bson_type.cmp(&b.bson_type));
+
+  let mut schema = Map::new();
+  let mut schema_types = Vec::new();
+  let mut range: Option<(Value, Value)> = None;
+  let mut lengths = None;
+  let mut enum_values = Some(Vec::new());
+  for field_type in types {
+    let schema_type = match dialect {
+      Dialect::Draft07 => json_type(&field_type.bson_type),
+      Dialect::MongoDB { .. } => bson_type_alias(&field_type.bson_type),
+    };
+    let schema_type = match schema_type {
+      Some(schema_type) => schema_type,
+      None => continue,
+    };
+    if !schema_types.contains(&schema_type) {
+      schema_types.push(schema_type);
+    }
+    enum_values = add_enum_values(enum_values, field_type);
+    if let Some(nested) = field_type.schema() {
+      let nested =
+        object_schema(nested.fields(), required_threshold, dialect, bounds);
+      schema.extend(nested);
+    }
+    if field_type.bson_type == field_type::ARRAY {
+      let items =
+        types_schema(field_type.types(), required_threshold, dialect, bounds);
+      schema.insert("items".to_string(), Value::Object(items));
+    }
+    let min = field_type.min.as_ref().and_then(bound);
+    let max = field_type.max.as_ref().and_then(bound);
+    if let (true, Some(min), Some(max)) = (bounds.numeric, min, max) {
+      range = Some(match range.take() {
+        Some((low, high)) => (lower(low, min), higher(high, max)),
+        None => (min, max),
+      });
+    }
+    if let Some(string_lengths) = field_type.string_lengths.as_ref() {
+      lengths = Some(&string_lengths.chars);
+    }
+  }
+  if let Some((min, max)) = range {
+    schema.insert("minimum".to_string(), min);
+    schema.insert("maximum".to_string(), max);
+  }
+  if let (true, Some(lengths)) = (bounds.length, lengths) {
+    schema.insert("minLength".to_string(), Value::from(lengths.min));
+    schema.insert("maxLength".to_string(), Value::from(lengths.max));
+  }
+  // a field that's only ever null isn't an enum
+  let enum_values =
+    enum_values.filter(|values| values.iter().any(|value| !value.is_null()));
+  if let Some(values) = enum_values {
+    schema.insert("enum".to_string(), Value::Array(values));
+  }
+
+  let keyword = type_keyword(dialect).to_string();
+  match schema_types.len() {
+    0 => schema.remove(&keyword),
+    1 => schema.insert(keyword, Value::from(schema_types[0])),
+    _ => schema.insert(keyword, Value::from(schema_types)),
+  };
+  schema
+}
+
+// Adds the values of an enum candidate to those of the other types of a
+// field, or null for a Null type. Any other type rules out an enum.
+fn add_enum_values(
+  enum_values: Option<Vec<Value>>,
+  field_type: &FieldType,
+) -> Option<Vec<Value>> {
+  let mut enum_values = enum_values?;
+  let values: Vec<Value> = match field_type.enum_values.as_ref() {
+    Some(values) => values.iter().filter_map(enum_value).collect(),
+    None if field_type.bson_type == field_type::NULL => vec![Value::Null],
+    None => return None,
+  };
+  for value in values {
+    if !enum_values.contains(&value) {
+      enum_values.push(value);
+    }
+  }
+  Some(enum_values)
+}
+
+fn enum_value(value: &ValueType) -> Option<Value> {
+  match value {
+    ValueType::Str(string) => Some(Value::from(string.clone())),
+    ValueType::I32(num) => Some(Value::from(*num)),
+    ValueType::I64(num) => Some(Value::from(*num)),
+    _ => None,
+  }
+}
+
+// Bound of a numeric type's range as a json number. Decimal128 bounds are
+// approximated by a double, and ObjectIds have no numeric bounds.
+fn bound(value: &ValueType) -> Option<Value> {
+  match value {
+    ValueType::I32(num) => Some(Value::from(*num)),
+    ValueType::I64(num) => Some(Value::from(*num)),
+    ValueType::FloatingPoint(num) => Some(Value::from(*num)),
+    ValueType::Decimal128(num) => num.parse::<f64>().ok().map(Value::from),
+    _ => None,
+  }
+}
+
+fn lower(a: Value, b: Value) -> Value {
+  if b.as_f64() < a.as_f64() {
+    b
+  } else {
+    a
+  }
+}
+
+fn higher(a: Value, b: Value) -> Value {
+  if b.as_f64() > a.as_f64() {
+    b
+  } else {
+    a
+  }
+}
+
+fn type_keyword(dialect: Dialect) -> &'static str {
+  match dialect {
+    Dialect::Draft07 => "type",
+    Dialect::MongoDB { .. } => "bsonType",
+  }
+}
+
+// Json type a bson type is represented as. Undefined isn't a type a value
+// can have, so it has none.
+fn json_type(bson_type: &str) -> Option<&'static str> {
+  match bson_type {
+    t if t == field_type::DOCUMENT
+      || t == field_type::JAVASCRIPT_CODE_WITH_SCOPE =>
+    {
+      Some("object")
+    }
+    t if t == field_type::ARRAY => Some("array"),
+    t if t == field_type::BOOLEAN => Some("boolean"),
+    t if t == field_type::NULL => Some("null"),
+    t if t == field_type::I32
+      || t == field_type::I64
+      || t == field_type::TIMESTAMP =>
+    {
+      Some("integer")
+    }
+    t if t == field_type::FLOATING_POINT
+      || t == field_type::DECIMAL_128
+      || t == field_type::NUMBER =>
+    {
+      Some("number")
+    }
+    t if t == field_type::UNDEFINED => None,
+    // ObjectIds, dates, binaries, regular expressions, code and symbols are
+    // all represented as strings in json.
+    _ => Some("string"),
+  }
+}
+
+// Alias MongoDB uses for a bson type in `bsonType` and `$type`.
+fn bson_type_alias(bson_type: &str) -> Option<&'static str> {
+  let aliases = [
+    (field_type::FLOATING_POINT, "double"),
+    (field_type::STRING, "string"),
+    (field_type::DOCUMENT, "object"),
+    (field_type::ARRAY, "array"),
+    (field_type::BINARY, "binData"),
+    (field_type::OBJECTID, "objectId"),
+    (field_type::BOOLEAN, "bool"),
+    (field_type::UTCDATE_TIME, "date"),
+    (field_type::NULL, "null"),
+    (field_type::REGEXP, "regex"),
+    (field_type::JAVASCRIPT_CODE, "javascript"),
+    (field_type::SYMBOL, "symbol"),
+    (field_type::JAVASCRIPT_CODE_WITH_SCOPE, "javascriptWithScope"),
+    (field_type::I32, "int"),
+    (field_type::TIMESTAMP, "timestamp"),
+    (field_type::I64, "long"),
+    (field_type::DECIMAL_128, "decimal"),
+    (field_type::NUMBER, "number"),
+  ];
+  aliases
+    .iter()
+    .find(|(name, _)| *name == bson_type)
+    .map(|(_, alias)| *alias)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::SchemaParser;
+  use serde_json::json;
+
+  #[test]
+  fn it_converts_to_json_schema() {
+    let mut schema_parser = SchemaParser::new();
+    let json_str1 = r#"{"name": "Nori", "age": 5, "tags": ["cat"]}"#;
+    let json_str2 = r#"{"name": "Rey", "age": 3.5, "owner": {"name": "Ana"}}"#;
+    schema_parser.write_json(&json_str1).unwrap();
+    schema_parser.write_json(&json_str2).unwrap();
+    let schema = schema_parser.flush();
+    let json_schema = to_json_schema(schema.fields(), 1.0, Bounds::default());
+    assert_eq!(
+      json_schema,
+      json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "type": "object",
+        "properties": {
+          "name": { "type": "string" },
+          "age": { "type": ["number", "integer"] },
+          "tags": { "type": "array", "items": { "type": "string" } },
+          "owner": {
+            "type": "object",
+            "properties": { "name": { "type": "string" } },
+            "required": ["name"]
+          }
+        },
+        "required": ["name", "age"]
+      })
+    );
+  }
+
+  #[test]
+  fn it_requires_fields_above_threshold() {
+    let mut schema_parser = SchemaParser::new();
+    schema_parser.write_json(r#"{"name": "Nori", "age": 5}"#).unwrap();
+    schema_parser.write_json(r#"{"name": "Rey"}"#).unwrap();
+    let schema = schema_parser.flush();
+    let json_schema = to_json_schema(schema.fields(), 0.5, Bounds::default());
+    assert_eq!(json_schema["required"], json!(["name", "age"]));
+  }
+
+  #[test]
+  fn it_converts_to_bson_schema() {
+    let mut schema_parser = SchemaParser::new();
+    let json_str1 = r#"{"_id": {"$oid": "5d505646cf6d4fe581014ab2"}, "n": 5}"#;
+    let json_str2 = r#"{"_id": {"$oid": "5d505646cf6d4fe581014ab3"}}"#;
+    schema_parser.write_json(&json_str1).unwrap();
+    schema_parser.write_json(&json_str2).unwrap();
+    let schema = schema_parser.flush();
+    let bson_schema =
+      to_bson_schema(schema.fields(), 1.0, false, Bounds::default());
+    assert_eq!(
+      bson_schema,
+      json!({
+        "bsonType": "object",
+        "properties": {
+          "_id": { "bsonType": "objectId" },
+          "n": { "bsonType": "int" }
+        },
+        "required": ["_id"],
+        "additionalProperties": false
+      })
+    );
+  }
+
+  #[test]
+  fn it_allows_null() {
+    let mut schema_parser = SchemaParser::new();
+    schema_parser.write_json(r#"{"name": "Nori"}"#).unwrap();
+    schema_parser.write_json(r#"{"name": null}"#).unwrap();
+    let schema = schema_parser.flush();
+    let json_schema = to_json_schema(schema.fields(), 1.0, Bounds::default());
+    assert_eq!(
+      json_schema["properties"]["name"],
+      json!({ "type": ["null", "string"] })
+    );
+  }
+
+  #[test]
+  fn it_bounds_numbers() {
+    let mut schema_parser = SchemaParser::new();
+    let json_str1 = r#"{"age": 5, "tags": [1]}"#;
+    let json_str2 = r#"{"age": 3.5, "tags": [7]}"#;
+    schema_parser.write_json(&json_str1).unwrap();
+    schema_parser.write_json(&json_str2).unwrap();
+    let schema = schema_parser.flush();
+    let bounds = Bounds {
+      numeric: true,
+      length: false,
+    };
+    let json_schema = to_json_schema(schema.fields(), 1.0, bounds);
+    let age = &json_schema["properties"]["age"];
+    assert_eq!(age["minimum"], json!(3.5));
+    assert_eq!(age["maximum"], json!(5));
+    let items = &json_schema["properties"]["tags"]["items"];
+    assert_eq!(items["minimum"], json!(1));
+    assert_eq!(items["maximum"], json!(7));
+    let bson_schema =
+      to_bson_schema(schema.fields(), 1.0, true, Bounds::default());
+    assert!(bson_schema["properties"]["age"].get("minimum").is_none());
+  }
+
+  #[test]
+  fn it_bounds_string_lengths() {
+    let mut schema_parser = SchemaParser::new();
+    schema_parser.write_json(r#"{"name": "Zoë"}"#).unwrap();
+    schema_parser.write_json(r#"{"name": "Chashu"}"#).unwrap();
+    let schema = schema_parser.flush();
+    let bounds = Bounds {
+      numeric: false,
+      length: true,
+    };
+    let json_schema = to_json_schema(schema.fields(), 1.0, bounds);
+    assert_eq!(
+      json_schema["properties"]["name"],
+      json!({ "type": "string", "minLength": 3, "maxLength": 6 })
+    );
+  }
+
+  #[test]
+  fn it_lists_enum_values() {
+    let mut schema_parser = SchemaParser::builder().enum_threshold(3).build();
+    for status in &["active", "deleted", "active", "deleted", "active"] {
+      let json = format!(r#"{{"status": "{}", "code": {{}}}}"#, status);
+      schema_parser.write_json(&json).unwrap();
+    }
+    let json = r#"{"status": null, "code": "x"}"#;
+    schema_parser.write_json(json).unwrap();
+    let schema = schema_parser.flush();
+    let json_schema = to_json_schema(schema.fields(), 1.0, Bounds::default());
+    assert_eq!(
+      json_schema["properties"]["status"],
+      json!({ "type": ["null", "string"], "enum": [null, "active", "deleted"] })
+    );
+    // documents can't be listed in an enum
+    assert!(json_schema["properties"]["code"].get("enum").is_none());
+  }
+}
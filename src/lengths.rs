@@ -0,0 +1,188 @@
+use std::collections::BTreeMap;
+
+/// Lengths of the strings seen for a String type, both in characters and in
+/// UTF-8 encoded bytes.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StringLengths {
+  pub chars: LengthRange,
+  pub bytes: LengthRange,
+}
+
+/// Shortest, longest and average length of the strings seen.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LengthRange {
+  pub min: usize,
+  pub max: usize,
+  pub average: f64,
+  #[serde(skip)]
+  total: usize,
+  #[serde(skip)]
+  count: usize,
+}
+
+/// Numbers of elements of the arrays seen for an Array type, with how many
+/// arrays had each number of elements.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ArrayLengths {
+  pub min: usize,
+  pub max: usize,
+  pub average: f64,
+  pub distribution: BTreeMap<usize, usize>,
+}
+
+impl ArrayLengths {
+  /// Summarises the lengths of arrays, if there are any.
+  pub(crate) fn from_lengths(lengths: &[usize]) -> Option<Self> {
+    let mut distribution = BTreeMap::new();
+    for length in lengths {
+      *distribution.entry(*length).or_insert(0) += 1;
+    }
+    let total: usize = lengths.iter().sum();
+    Some(ArrayLengths {
+      min: *lengths.iter().min()?,
+      max: *lengths.iter().max()?,
+      average: total as f64 / lengths.len() as f64,
+      distribution,
+    })
+  }
+
+  /// Returns the number of elements every array had, if they all had the
+  /// same, i.e. 2 for coordinate pairs.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "location": [52.5, 13.4] }"#).unwrap();
+  /// let schema = schema_parser.flush();
+  /// let location = &schema.get_field("location").unwrap().types["Array"];
+  /// let lengths = location.array_lengths.as_ref().unwrap();
+  /// assert_eq!(lengths.fixed_length(), Some(2));
+  /// ```
+  pub fn fixed_length(&self) -> Option<usize> {
+    if self.min == self.max {
+      Some(self.min)
+    } else {
+      None
+    }
+  }
+}
+
+impl StringLengths {
+  pub(crate) fn new(string: &str) -> Self {
+    StringLengths {
+      chars: LengthRange::new(string.chars().count()),
+      bytes: LengthRange::new(string.len()),
+    }
+  }
+
+  pub(crate) fn insert(&mut self, string: &str) {
+    self.chars.insert(string.chars().count());
+    self.bytes.insert(string.len());
+  }
+
+  pub(crate) fn merge(&mut self, other: &StringLengths) {
+    self.chars.merge(&other.chars);
+    self.bytes.merge(&other.bytes);
+  }
+
+  /// Restores the running totals of lengths deserialized from `to_json()`
+  /// output, which were the average of `count` strings.
+  pub(crate) fn restore(&mut self, count: usize) {
+    self.chars.restore(count);
+    self.bytes.restore(count);
+  }
+}
+
+impl LengthRange {
+  fn new(length: usize) -> Self {
+    LengthRange {
+      min: length,
+      max: length,
+      average: length as f64,
+      total: length,
+      count: 1,
+    }
+  }
+
+  fn insert(&mut self, length: usize) {
+    self.min = self.min.min(length);
+    self.max = self.max.max(length);
+    self.total += length;
+    self.count += 1;
+    self.average = self.total as f64 / self.count as f64;
+  }
+
+  fn merge(&mut self, other: &LengthRange) {
+    self.min = self.min.min(other.min);
+    self.max = self.max.max(other.max);
+    self.total += other.total;
+    self.count += other.count;
+    self.average = self.total as f64 / self.count as f64;
+  }
+
+  fn restore(&mut self, count: usize) {
+    self.total = (self.average * count as f64).round() as usize;
+    self.count = count;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_counts_chars_and_bytes() {
+    let mut lengths = StringLengths::new("Nori");
+    lengths.insert("Zoë");
+    assert_eq!(lengths.chars.min, 3);
+    assert_eq!(lengths.chars.max, 4);
+    assert_eq!(lengths.chars.average, 3.5);
+    assert_eq!(lengths.bytes.min, 4);
+    assert_eq!(lengths.bytes.max, 4);
+  }
+
+  #[test]
+  fn it_summarises_array_lengths() {
+    let lengths = ArrayLengths::from_lengths(&[2, 2, 5]).unwrap();
+    assert_eq!(lengths.min, 2);
+    assert_eq!(lengths.max, 5);
+    assert_eq!(lengths.average, 3.0);
+    assert_eq!(lengths.distribution[&2], 2);
+    assert_eq!(lengths.distribution[&5], 1);
+    assert_eq!(lengths.fixed_length(), None);
+    assert!(ArrayLengths::from_lengths(&[]).is_none());
+  }
+
+  #[test]
+  fn it_finds_fixed_lengths() {
+    let lengths = ArrayLengths::from_lengths(&[2, 2]).unwrap();
+    assert_eq!(lengths.fixed_length(), Some(2));
+    let json = serde_json::to_value(&lengths).unwrap();
+    assert_eq!(json["max"], 2);
+    assert_eq!(json["distribution"]["2"], 2);
+    let restored: ArrayLengths = serde_json::from_value(json).unwrap();
+    assert_eq!(restored, lengths);
+  }
+
+  #[test]
+  fn it_merges() {
+    let mut lengths = StringLengths::new("Rey");
+    lengths.merge(&StringLengths::new("Chashu"));
+    assert_eq!(lengths.chars.min, 3);
+    assert_eq!(lengths.chars.max, 6);
+    assert_eq!(lengths.chars.average, 4.5);
+  }
+
+  #[test]
+  fn it_restores_averages() {
+    let mut lengths = StringLengths::new("Nori");
+    lengths.insert("Chashu");
+    let json = serde_json::to_string(&lengths).unwrap();
+    let mut restored: StringLengths = serde_json::from_str(&json).unwrap();
+    restored.restore(2);
+    restored.insert("Rey");
+    assert_eq!(restored.chars.average, 13.0 / 3.0);
+  }
+}
@@ -52,7 +52,6 @@
 #![allow(clippy::new_without_default)]
 // #![feature(test)]
 
-use failure::format_err;
 // extern crate test;
 
 use bson::{bson, decode_document, doc, Bson, Document};
@@ -62,152 +61,1394 @@ extern crate serde_derive;
 extern crate serde;
 use serde_json::Value;
 
+#[cfg(feature = "wasm")]
 use js_sys::{Object, Uint8Array};
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 // add to use console.log to send debugs to js land
+#[cfg(feature = "wasm")]
 use web_sys::console;
 
 // using custom allocator which is built specifically for wasm; makes it smaller
-// + faster
+// + faster. Native builds keep the system allocator even with the `wasm`
+// feature, as it copes far better with threads.
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
 use wee_alloc;
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
-use std::collections::HashMap;
+use indexmap::IndexMap;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::iter::FromIterator;
+use std::mem;
+use std::path::Path;
 use std::string::String;
 
+#[cfg(feature = "async")]
+use futures::stream::{Stream, StreamExt};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+#[cfg(feature = "async")]
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+
 mod field;
-use crate::field::Field;
+pub use crate::field::Field;
 
 mod field_type;
-use crate::field_type::FieldType;
+pub use crate::field_type::{BooleanCounts, DateRange, FieldType};
+
+mod value_type;
+pub use crate::value_type::ValueType;
+
+mod budget;
+mod distinct;
+mod reservoir;
+
+mod digest;
+pub use crate::digest::Percentiles;
+mod moments;
+
+mod lengths;
+pub use crate::lengths::{ArrayLengths, LengthRange, StringLengths};
+
+mod dates;
+pub use crate::dates::DateBucket;
+
+mod frequent;
+pub use crate::frequent::ValueCount;
+
+mod size;
+use crate::size::element_size;
+
+mod enums;
+
+mod histogram;
+pub use crate::histogram::{HistogramBucket, HistogramOptions, HistogramScale};
+
+mod schema;
+pub use crate::schema::Schema;
+
+mod diff;
+pub use crate::diff::{FieldDiff, SchemaDiff, TypeDiff};
+
+mod intersect;
+
+mod validator;
+pub use crate::validator::Violation;
+
+mod drift;
+pub use crate::drift::{DriftDetector, DriftEvent, DEFAULT_DRIFT_THRESHOLD};
+
+mod anomaly;
+
+mod history;
+pub use crate::history::{
+  FieldTimeline, SchemaHistory, Snapshot, SnapshotInterval,
+};
+
+mod fingerprint;
+
+mod subschema;
+
+mod prune;
+
+mod order;
+pub use crate::order::FieldOrder;
+
+mod transform;
+pub use crate::transform::SchemaTransform;
+
+mod visitor;
+pub use crate::visitor::SchemaVisitor;
+
+mod wildcard;
+pub use crate::wildcard::WILDCARD;
+
+// Reading of `mongodump` output.
+mod archive;
+mod dump;
+
+mod builder;
+pub use crate::builder::{
+  ParserOptions, SchemaParserBuilder, DEFAULT_MAX_VALUES,
+};
+
+mod error;
+pub use crate::error::{LineError, ParseError};
+
+// Analysis of live collections with the MongoDB driver.
+#[cfg(feature = "driver")]
+mod driver;
+#[cfg(feature = "driver")]
+pub use crate::driver::{change_stream, AnalyzeOptions, DEFAULT_SAMPLE_SIZE};
+#[cfg(feature = "driver")]
+mod database;
+#[cfg(feature = "driver")]
+pub use crate::database::{DatabaseAnalyzer, DEFAULT_CONCURRENCY};
+
+// Decompression of gzip and zstd input.
+#[cfg(feature = "compression")]
+mod compression;
+#[cfg(feature = "compression")]
+pub use crate::compression::decompress;
+
+// Extended JSON conversion for json input.
+mod extended_json;
+// Parsing of json input, with simd-json with the `simd` feature.
+mod json;
+mod json_array;
+
+mod json_schema;
+use crate::json_schema::Bounds;
+
+mod avro;
+
+mod typescript;
+
+mod codegen;
+
+mod graphql;
+
+mod proto;
+
+mod mongoose;
+
+mod openapi;
+
+mod markdown;
+
+mod report;
+
+mod csv;
+
+mod arrow;
+
+mod sql;
+pub use crate::sql::SqlDialect;
+
+mod compat;
+
+// WASM Api of the Schema Parser.
+#[cfg(feature = "wasm")]
+mod lib_wasm;
+#[cfg(feature = "wasm")]
+use crate::lib_wasm::*;
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SchemaParser {
+  pub count: usize,
+  fields: IndexMap<String, Field>,
+  #[serde(skip)]
+  options: ParserOptions,
+  #[serde(skip)]
+  history: Option<SchemaHistory>,
+  #[cfg(feature = "wasm")]
+  #[serde(skip)]
+  progress: Option<Progress>,
+}
+
+impl SchemaParser {
+  /// Returns a new instance of Schema Parser populated with zero `count` and an
+  /// empty `fields` vector.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  /// let schema_parser = SchemaParser::new();
+  /// ```
+  #[inline]
+  pub fn new() -> Self {
+    Self::with_options(ParserOptions::default())
+  }
+
+  /// Returns a builder to create a Schema Parser with custom analysis options,
+  /// like max nesting depth or how many values to store per type.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  /// let schema_parser = SchemaParser::builder()
+  ///   .max_depth(5)
+  ///   .store_values(false)
+  ///   .build();
+  /// ```
+  pub fn builder() -> SchemaParserBuilder {
+    SchemaParserBuilder::new()
+  }
+
+  #[inline]
+  pub(crate) fn with_options(options: ParserOptions) -> Self {
+    SchemaParser {
+      count: 0,
+      fields: IndexMap::new(),
+      options,
+      history: None,
+      #[cfg(feature = "wasm")]
+      progress: None,
+    }
+  }
+
+  /// Returns a Schema Parser that continues from a previously produced schema,
+  /// i.e. the output of `to_json()` or `into_json()`. Counts, types, sampled
+  /// values and ranges are restored, so more documents can be written to it
+  /// and flushed again. Values written afterwards can't be compared with the
+  /// values seen before, so they are counted as distinct from them. Default
+  /// analysis options are used.
+  ///
+  /// # Arguments
+  /// * `json` - A schema serialized with `to_json()`.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let json = schema_parser.into_json().unwrap();
+  ///
+  /// let mut schema_parser = SchemaParser::from_json(&json).unwrap();
+  /// schema_parser.write_json(r#"{ "name": "Nori", "type": "Cat" }"#);
+  /// assert_eq!(schema_parser.count, 2);
+  /// ```
+  pub fn from_json(json: &str) -> Result<Self, ParseError> {
+    let schema: Schema =
+      serde_json::from_str(json).map_err(ParseError::InvalidJson)?;
+    Ok(Self::from_schema(schema))
+  }
+
+  // Undoes the finalisation of a Schema, so more documents or other parsers
+  // can be added to it.
+  pub(crate) fn from_schema(schema: Schema) -> Self {
+    let options = ParserOptions::default();
+    let mut schema_parser = Self::with_options(options.clone());
+    schema_parser.count = schema.count;
+    schema_parser.fields = schema.fields;
+    schema_parser.restore(options);
+    schema_parser
+  }
+
+  // Options aren't serialized, so nested parsers get theirs from the parent.
+  fn restore(&mut self, options: ParserOptions) {
+    self.options = options;
+    for field in self.fields.values_mut() {
+      field.restore(&self.options);
+    }
+  }
+
+  /// Returns the analysis options this Schema Parser was built with.
+  pub fn options(&self) -> &ParserOptions {
+    &self.options
+  }
+
+  /// Writes json-like string slices SchemaParser's fields vector.
+  ///
+  /// # Arguments
+  /// * `json` - A json-like string slice. i.e `{ "name": "Nori", "type": "Cat"}`
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  /// let mut schema_parser = SchemaParser::new();
+  /// let json = r#"{ "name": "Chashu", "type": "Cat" }"#;
+  /// schema_parser.write_json(&json);
+  /// ```
+  #[inline]
+  pub fn write_json(&mut self, json: &str) -> Result<(), ParseError> {
+    let val = json::from_str(json)?;
+    self.write_value(val)
+  }
+
+  /// Writes an already parsed json value to SchemaParser's fields, without
+  /// serializing it to a string first. Extended JSON wrappers are recognised
+  /// as in `write_json`, and values other than objects are rejected the same
+  /// way.
+  ///
+  /// # Arguments
+  /// * `value` - A `serde_json::Value` object. i.e `json!({ "name": "Nori" })`
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  /// use serde_json::json;
+  /// let mut schema_parser = SchemaParser::new();
+  /// let value = json!({ "name": "Chashu", "type": "Cat" });
+  /// schema_parser.write_value(value).unwrap();
+  /// ```
+  pub fn write_value(&mut self, value: Value) -> Result<(), ParseError> {
+    let bson = extended_json::to_bson(value)?;
+    self.write_root(bson)
+  }
+
+  /// Writes json-like string slices in legacy Extended JSON v1 format to
+  /// SchemaParser's fields. On top of what `write_json` understands, shell-mode
+  /// constructors written out as strings, i.e. `"NumberLong(\"5\")"`, are
+  /// parsed into their Bson types.
+  ///
+  /// # Arguments
+  /// * `json` - A json-like string slice. i.e `{ "age": "NumberInt(4)" }`
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  /// let mut schema_parser = SchemaParser::new();
+  /// let json = r#"{ "name": "Chashu", "born": {"$date": 1542321000000} }"#;
+  /// schema_parser.write_extjson_v1(&json);
+  /// ```
+  #[inline]
+  pub fn write_extjson_v1(
+    &mut self,
+    json: &str,
+  ) -> Result<(), ParseError> {
+    let val = json::from_str(json)?;
+    let bson = extended_json::to_bson_legacy(val)?;
+    self.write_root(bson)
+  }
+
+  /// Writes a whole block of newline delimited json to SchemaParser's fields,
+  /// one document per line, skipping empty lines. Unlike `read_from`, a line
+  /// that can't be written doesn't stop the rest: every failed line is
+  /// returned with its line number instead. Useful to cross the wasm boundary
+  /// once per block rather than once per document.
+  ///
+  /// # Arguments
+  /// * `json` - Newline delimited json documents.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  /// let mut schema_parser = SchemaParser::new();
+  /// let json = "{\"name\": \"Chashu\"}\nnot json\n{\"name\": \"Nori\"}\n";
+  /// let errors = schema_parser.write_many(json);
+  /// assert_eq!(errors[0].line, 2);
+  /// ```
+  pub fn write_many(&mut self, json: &str) -> Vec<LineError> {
+    json
+      .lines()
+      .enumerate()
+      .filter(|(_, line)| !line.trim().is_empty())
+      .filter_map(|(index, line)| {
+        let error = self.write_json(line).err()?;
+        Some(LineError {
+          line: index + 1,
+          error,
+        })
+      })
+      .collect()
+  }
+
+  /// Writes each document of a top level json array, i.e.
+  /// `[{ "name": "Nori" }, { "name": "Rey" }]` as written by
+  /// `mongoexport --jsonArray`, to SchemaParser's fields. The array is
+  /// streamed through, so only one document is deserialized at a time.
+  /// Documents before an invalid one are kept.
+  ///
+  /// # Arguments
+  /// * `json` - A json array string slice.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  /// let mut schema_parser = SchemaParser::new();
+  /// let json = r#"[{ "name": "Chashu" }, { "name": "Nori", "age": 5 }]"#;
+  /// schema_parser.write_json_array(&json).unwrap();
+  /// ```
+  pub fn write_json_array(&mut self, json: &str) -> Result<(), ParseError> {
+    json_array::write_elements(self, json)
+  }
+
+  /// Decodes a raw Bson document from a byte slice and writes it to
+  /// SchemaParser's fields. Useful for reading `.bson` dumps without
+  /// converting them to json first.
+  ///
+  /// # Arguments
+  /// * `bytes` - A byte slice containing a single encoded Bson document.
+  ///
+  /// # Examples
+  /// ```ignore
+  /// use mongodb_schema_parser::SchemaParser;
+  /// use bson::{doc, bson, encode_document};
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// let mut bytes = Vec::new();
+  /// encode_document(&mut bytes, &doc! {"name": "Chashu", "type": "Cat"});
+  /// schema_parser.write_raw(&bytes);
+  /// ```
+  #[inline]
+  pub fn write_raw(&mut self, bytes: &[u8]) -> Result<(), ParseError> {
+    // decode_document needs a byte stream that implements a reader and u8
+    // slice does this.
+    let mut slice = bytes;
+    let doc = decode_document(&mut slice)?;
+    self.write_document(doc)
+  }
+
+  /// Writes a Bson Document directly to SchemaParser's fields, without going
+  /// through a json string first. Useful when documents already come from the
+  /// MongoDB Rust driver.
+  ///
+  /// # Arguments
+  /// * `doc` - A Bson Document.
+  ///
+  /// # Examples
+  /// ```ignore
+  /// use mongodb_schema_parser::SchemaParser;
+  /// use bson::{doc, bson};
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// let doc = doc! {"name": "Chashu", "type": "Cat"};
+  /// schema_parser.write_document(doc);
+  /// ```
+  #[inline]
+  pub fn write_document(
+    &mut self,
+    doc: Document,
+  ) -> Result<(), ParseError> {
+    self.add_document(&doc);
+    Ok(())
+  }
+
+  // Every document written ends up here, so snapshots are taken and progress
+  // is reported however documents are written.
+  fn add_document(&mut self, doc: &Document) {
+    self.update_count();
+    self.generate_field(doc, None, None);
+    self.documents_written();
+  }
+
+  // Takes a snapshot and reports progress if either is due after the
+  // documents written so far.
+  fn documents_written(&mut self) {
+    let count = self.count;
+    if let Some(history) = self.history.as_mut() {
+      if history.is_due(count) {
+        self.snapshot();
+      }
+    }
+    #[cfg(feature = "wasm")]
+    {
+      if let Some(progress) = self.progress.as_ref() {
+        progress.report(count);
+      }
+    }
+  }
+
+  // Documents left to write until a snapshot or progress report is due, if
+  // either is due every so many documents.
+  #[cfg(feature = "parallel")]
+  fn documents_until_due(&self) -> Option<usize> {
+    let count = self.count;
+    let snapshot = self
+      .history
+      .as_ref()
+      .and_then(|history| history.documents_until_due(count));
+    #[cfg(feature = "wasm")]
+    let progress = self
+      .progress
+      .as_ref()
+      .map(|progress| progress.documents_until_due(count));
+    #[cfg(not(feature = "wasm"))]
+    let progress = None;
+    snapshot.into_iter().chain(progress).min()
+  }
+
+  /// Analyses a live collection with the MongoDB driver: a sample of its
+  /// documents is picked with a `$sample` aggregation and written straight
+  /// to a SchemaParser, and the finalised schema is returned. Only available
+  /// with the `driver` feature.
+  ///
+  /// # Arguments
+  /// * `collection` - Collection to analyse.
+  /// * `options` - Sample size, filter and analysis options.
+  ///
+  /// # Examples
+  /// ```no_run
+  /// use mongodb::Client;
+  /// use mongodb_schema_parser::{AnalyzeOptions, SchemaParser};
+  ///
+  /// let client = Client::with_uri_str("mongodb://localhost:27017").unwrap();
+  /// let pets = client.database("shelter").collection("pets");
+  /// let options = AnalyzeOptions {
+  ///   sample_size: Some(500),
+  ///   ..AnalyzeOptions::default()
+  /// };
+  /// let schema = SchemaParser::analyze_collection(&pets, options).unwrap();
+  /// println!("{}", schema.to_json().unwrap());
+  /// ```
+  #[cfg(feature = "driver")]
+  pub fn analyze_collection(
+    collection: &mongodb::Collection,
+    options: AnalyzeOptions,
+  ) -> Result<Schema, ParseError> {
+    Ok(driver::sample(collection, &options)?.into_schema())
+  }
+
+  /// Analyses a sharded collection with a SchemaParser per partition, i.e.
+  /// per shard, in parallel, and merges them into one schema. Samples are
+  /// taken from each partition in proportion to its share of the documents,
+  /// so probabilities are weighted by document counts rather than skewed
+  /// towards small partitions. Only available with the `driver` feature.
+  ///
+  /// # Arguments
+  /// * `partitions` - The collection on each shard, i.e. through a direct
+  /// connection to the shard, or any other disjoint parts of a collection.
+  /// * `options` - Total sample size, filter and analysis options.
+  ///
+  /// # Examples
+  /// ```no_run
+  /// use mongodb::Client;
+  /// use mongodb_schema_parser::{AnalyzeOptions, SchemaParser};
+  ///
+  /// let shards = ["mongodb://shard0:27018", "mongodb://shard1:27018"];
+  /// let partitions: Vec<_> = shards
+  ///   .iter()
+  ///   .map(|uri| {
+  ///     let client = Client::with_uri_str(uri).unwrap();
+  ///     client.database("shelter").collection("pets")
+  ///   })
+  ///   .collect();
+  /// let options = AnalyzeOptions::default();
+  /// let schema = SchemaParser::analyze_sharded(&partitions, options).unwrap();
+  /// ```
+  #[cfg(feature = "driver")]
+  pub fn analyze_sharded(
+    partitions: &[mongodb::Collection],
+    options: AnalyzeOptions,
+  ) -> Result<Schema, ParseError> {
+    Ok(driver::sample_partitions(partitions, &options)?.into_schema())
+  }
+
+  /// Keeps the schema current with a collection's change stream, i.e. one
+  /// opened with `change_stream()`. Inserted, replaced and updated documents
+  /// are written as they come in, and `on_update` is called with a flushed
+  /// Schema after every `every` of them, i.e. to refresh a data catalog or
+  /// to send the Schema down a channel. Returns once the stream ends, or
+  /// with the first error it yields. Only available with the `driver`
+  /// feature.
+  ///
+  /// # Arguments
+  /// * `stream` - Change stream events, i.e. a change stream cursor.
+  /// * `every` - Number of documents to write between updates.
+  /// * `on_update` - Called with each updated Schema.
+  ///
+  /// # Examples
+  /// ```no_run
+  /// use mongodb::Client;
+  /// use mongodb_schema_parser::{change_stream, SchemaParser};
+  /// use std::sync::mpsc;
+  ///
+  /// let client = Client::with_uri_str("mongodb://localhost:27017").unwrap();
+  /// let pets = client.database("shelter").collection("pets");
+  /// let (sender, receiver) = mpsc::channel();
+  /// let mut schema_parser = SchemaParser::new();
+  /// let stream = change_stream(&pets).unwrap();
+  /// schema_parser
+  ///   .tail_change_stream(stream, 100, |schema| sender.send(schema).unwrap())
+  ///   .unwrap();
+  /// ```
+  #[cfg(feature = "driver")]
+  pub fn tail_change_stream<I, E, F>(
+    &mut self,
+    stream: I,
+    every: usize,
+    mut on_update: F,
+  ) -> Result<(), ParseError>
+  where
+    I: IntoIterator<Item = Result<Document, E>>,
+    ParseError: From<E>,
+    F: FnMut(Schema),
+  {
+    let mut written = 0;
+    for event in stream {
+      if let Some(doc) = driver::changed_document(event?) {
+        self.write_document(doc)?;
+        written += 1;
+        if written % every.max(1) == 0 {
+          on_update(self.flush());
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// Writes Bson documents to SchemaParser's fields vector. This is an alias
+  /// for `write_document`.
+  ///
+  /// # Arguments
+  /// * `doc` - A Bson Document.
+  ///
+  /// # Examples
+  /// ```ignore
+  /// use mongodb_schema_parser::SchemaParser;
+  /// use bson::{doc, bson};
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// let doc = doc! {"name": "Chashu", "type": "Cat"};
+  /// schema_parser.write_bson(doc);
+  /// ```
+  #[inline]
+  pub fn write_bson(&mut self, doc: Document) -> Result<(), ParseError> {
+    self.write_document(doc)
+  }
+
+  /// Reads newline delimited json from any reader, i.e. a file, and writes
+  /// each line to SchemaParser's fields. Lines are read one at a time, so the
+  /// whole input never has to be held in memory. Empty lines are skipped.
+  /// With the `compression` feature, gzip and zstd compressed input is
+  /// decompressed on the fly.
+  ///
+  /// # Arguments
+  /// * `reader` - Anything implementing `io::Read` with one json document per
+  /// line.
+  ///
+  /// # Examples
+  /// ```no_run
+  /// use mongodb_schema_parser::SchemaParser;
+  /// use std::fs::File;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// let file = File::open("examples/fanclub.json").unwrap();
+  /// schema_parser.read_from(file).unwrap();
+  /// ```
+  pub fn read_from<R: io::Read>(
+    &mut self,
+    reader: R,
+  ) -> Result<(), ParseError> {
+    #[cfg(feature = "compression")]
+    let reader = compression::decompress(reader)?;
+    let mut reader = BufReader::new(reader);
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 {
+      if !line.trim().is_empty() {
+        self.write_json(&line)?;
+      }
+      line.clear();
+    }
+    Ok(())
+  }
+
+  /// Same as `read_from`, for async readers: streams newline delimited json
+  /// one line at a time without blocking the executor's threads on IO. Only
+  /// available with the `async` feature.
+  ///
+  /// # Arguments
+  /// * `reader` - Anything implementing `tokio::io::AsyncBufRead` with one
+  /// json document per line, i.e. a `tokio::io::BufReader` of a file.
+  ///
+  /// # Examples
+  /// ```no_run
+  /// use mongodb_schema_parser::SchemaParser;
+  /// use tokio::fs::File;
+  /// use tokio::io::BufReader;
+  ///
+  /// # async fn run() {
+  /// let mut schema_parser = SchemaParser::new();
+  /// let file = File::open("examples/fanclub.json").await.unwrap();
+  /// schema_parser
+  ///   .read_from_async(BufReader::new(file))
+  ///   .await
+  ///   .unwrap();
+  /// # }
+  /// ```
+  #[cfg(feature = "async")]
+  pub async fn read_from_async<R: AsyncBufRead + Unpin>(
+    &mut self,
+    mut reader: R,
+  ) -> Result<(), ParseError> {
+    let mut line = String::new();
+    while reader.read_line(&mut line).await? > 0 {
+      if !line.trim().is_empty() {
+        self.write_json(&line)?;
+      }
+      line.clear();
+    }
+    Ok(())
+  }
+
+  /// Writes every document of a stream, i.e. a driver cursor or a channel
+  /// receiver, as it comes in. Only available with the `async` feature.
+  ///
+  /// # Arguments
+  /// * `stream` - Stream of Bson Documents.
+  ///
+  /// # Examples
+  /// ```
+  /// use bson::{bson, doc};
+  /// use futures::{executor, stream};
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// let docs = stream::iter(vec![doc! { "name": "Chashu" }]);
+  /// executor::block_on(schema_parser.write_stream(docs)).unwrap();
+  /// assert_eq!(schema_parser.count, 1);
+  /// ```
+  #[cfg(feature = "async")]
+  pub async fn write_stream<S: Stream<Item = Document> + Unpin>(
+    &mut self,
+    mut stream: S,
+  ) -> Result<(), ParseError> {
+    while let Some(doc) = stream.next().await {
+      self.write_document(doc)?;
+    }
+    Ok(())
+  }
+
+  /// Reads Bson documents stored one after another from any reader, i.e. a
+  /// `.bson` file written by `mongodump`, and writes each to SchemaParser's
+  /// fields, without a `bsondump` conversion to json first. Documents are
+  /// read one at a time. With the `compression` feature, gzip and zstd
+  /// compressed input, i.e. from `mongodump --gzip`, is decompressed on the
+  /// fly.
+  ///
+  /// # Arguments
+  /// * `reader` - Anything implementing `io::Read` with consecutive Bson
+  /// documents.
+  ///
+  /// # Examples
+  /// ```no_run
+  /// use mongodb_schema_parser::SchemaParser;
+  /// use std::fs::File;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// let file = File::open("dump/shelter/pets.bson").unwrap();
+  /// schema_parser.read_bson_from(file).unwrap();
+  /// ```
+  pub fn read_bson_from<R: io::Read>(
+    &mut self,
+    reader: R,
+  ) -> Result<(), ParseError> {
+    #[cfg(feature = "compression")]
+    let reader = compression::decompress(reader)?;
+    let mut reader = BufReader::new(reader);
+    while let Some(bytes) = dump::read_document(&mut reader)? {
+      self.write_raw(&bytes)?;
+    }
+    Ok(())
+  }
+
+  /// Analyses every collection of a `mongodump` output directory, i.e.
+  /// `dump/` or `dump/shelter/`, and returns their schemas by namespace,
+  /// i.e. `shelter.pets`, sorted by namespace. Each `<collection>.bson` file
+  /// is read with its own SchemaParser built with `options`, as are
+  /// `<collection>.bson.gz` files with the `compression` feature.
+  ///
+  /// # Arguments
+  /// * `path` - Directory `mongodump` wrote to.
+  /// * `options` - Analysis options for each collection.
+  ///
+  /// # Examples
+  /// ```no_run
+  /// use mongodb_schema_parser::{ParserOptions, SchemaParser};
+  ///
+  /// let schemas =
+  ///   SchemaParser::read_dump("dump", ParserOptions::default()).unwrap();
+  /// for (namespace, schema) in schemas {
+  ///   println!("{}: {} documents", namespace, schema.count);
+  /// }
+  /// ```
+  pub fn read_dump<P: AsRef<Path>>(
+    path: P,
+    options: ParserOptions,
+  ) -> Result<IndexMap<String, Schema>, ParseError> {
+    let mut schemas = IndexMap::new();
+    for (namespace, file) in dump::collection_files(path.as_ref())? {
+      let mut schema_parser = Self::with_options(options.clone());
+      schema_parser.read_bson_from(File::open(&file)?)?;
+      schemas.insert(namespace, schema_parser.into_schema());
+    }
+    Ok(schemas)
+  }
+
+  /// Analyses every namespace of a `mongodump --archive`, which keeps all
+  /// dumped collections in one file, and returns their schemas by namespace,
+  /// i.e. `shelter.pets`, sorted by namespace. Collections listed in the
+  /// archive without any documents get an empty schema. With the
+  /// `compression` feature, archives written with `--gzip` are decompressed
+  /// on the fly.
+  ///
+  /// # Arguments
+  /// * `reader` - Anything implementing `io::Read` with an archive, i.e. a
+  /// file or the standard output of `mongodump --archive`.
+  /// * `options` - Analysis options for each namespace.
+  ///
+  /// # Examples
+  /// ```no_run
+  /// use mongodb_schema_parser::{ParserOptions, SchemaParser};
+  /// use std::fs::File;
+  ///
+  /// let file = File::open("shelter.archive").unwrap();
+  /// let schemas =
+  ///   SchemaParser::read_archive(file, ParserOptions::default()).unwrap();
+  /// println!("{} documents", schemas["shelter.pets"].count);
+  /// ```
+  pub fn read_archive<R: io::Read>(
+    reader: R,
+    options: ParserOptions,
+  ) -> Result<IndexMap<String, Schema>, ParseError> {
+    #[cfg(feature = "compression")]
+    let reader = compression::decompress(reader)?;
+    let parsers = archive::read(BufReader::new(reader), &options)?;
+    Ok(
+      parsers
+        .into_iter()
+        .map(|(namespace, schema_parser)| {
+          (namespace, schema_parser.into_schema())
+        })
+        .collect(),
+    )
+  }
+
+  // Top level values have to be documents, unless a root field is configured
+  // to wrap scalars and arrays under.
+  fn write_root(&mut self, bson: Bson) -> Result<(), ParseError> {
+    match bson {
+      Bson::Document(doc) => self.write_document(doc),
+      value => match self.options.root_field.clone() {
+        Some(root_field) => {
+          let mut doc = Document::new();
+          doc.insert(root_field, value);
+          self.write_document(doc)
+        }
+        None => Err(ParseError::NotADocument),
+      },
+    }
+  }
+
+  /// Returns an iterator over the fields parsed so far, in the order they were
+  /// first seen.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let names: Vec<&str> =
+  ///   schema_parser.fields().map(|field| &*field.name).collect();
+  /// assert_eq!(names, vec!["name", "type"]);
+  /// ```
+  pub fn fields(&self) -> impl Iterator<Item = &Field> {
+    self.fields.values()
+  }
+
+  /// Returns the field at a dotted path, i.e. `address.geo.lat`, as written so
+  /// far. Fields of documents in arrays are at the path of the array, i.e.
+  /// `visits.vet`. Probabilities are only set once the SchemaParser is
+  /// flushed; use `Schema::get_field()` to look a field up with them.
+  ///
+  /// # Arguments
+  /// * `path` - Dotted path of the field.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "address": { "geo": { "lat": 52.5 } } }"#);
+  /// let lat = schema_parser.get_field("address.geo.lat").unwrap();
+  /// assert_eq!(lat.bson_types, vec!["Double"]);
+  /// assert!(schema_parser.get_field("address.geo.lng").is_none());
+  /// ```
+  pub fn get_field(&self, path: &str) -> Option<&Field> {
+    field::find_field(&self.fields, path)
+  }
+
+  /// Analyses a batch of documents on all cores: the batch is split into one
+  /// chunk per thread of rayon's pool, each chunk is written to a
+  /// SchemaParser of its own, and the results are merged into this one in
+  /// order. Only available with the `parallel` feature.
+  ///
+  /// # Arguments
+  /// * `docs` - Documents to analyse.
+  ///
+  /// # Examples
+  /// ```
+  /// use bson::{bson, doc};
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let docs = vec![doc! { "name": "Chashu" }, doc! { "name": "Nori" }];
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_batch_parallel(&docs).unwrap();
+  /// assert_eq!(schema_parser.count, 2);
+  /// ```
+  #[cfg(feature = "parallel")]
+  pub fn write_batch_parallel(
+    &mut self,
+    mut docs: &[Document],
+  ) -> Result<(), ParseError> {
+    // documents are analysed in batches that end where a snapshot or progress
+    // report is due, so they're taken after the same documents as when
+    // documents are written one by one
+    while !docs.is_empty() {
+      let batch_size = self
+        .documents_until_due()
+        .map_or(docs.len(), |due| due.min(docs.len()));
+      let (batch, rest) = docs.split_at(batch_size);
+      let chunk_size = (batch.len() / rayon::current_num_threads()).max(1);
+      // a chunk can't tell which fields the ones before it have, so fields
+      // are only limited once chunks are merged, in the order they're seen
+      let options = ParserOptions {
+        max_fields: None,
+        ..self.options.clone()
+      };
+      let parsers: Vec<SchemaParser> = batch
+        .par_chunks(chunk_size)
+        .map(|chunk| {
+          let mut schema_parser = Self::with_options(options.clone());
+          for doc in chunk {
+            schema_parser.update_count();
+            schema_parser.generate_field(doc, None, None);
+          }
+          schema_parser
+        })
+        .collect();
+      for schema_parser in parsers {
+        self.merge(schema_parser);
+      }
+      self.limit_fields(self.options.max_fields);
+      self.documents_written();
+      docs = rest;
+    }
+    Ok(())
+  }
+
+  /// Merges another SchemaParser into this one, combining document counts,
+  /// fields, field types and their values. Useful when documents are analysed
+  /// in parallel, i.e. one SchemaParser per shard or file. Both parsers should
+  /// be merged before they are flushed. Keys are matched case-insensitively
+  /// and fields are limited as they are when documents are written, following
+  /// this parser's options.
+  ///
+  /// # Arguments
+  /// * `other` - SchemaParser to fold into this one.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let mut other = SchemaParser::new();
+  /// other.write_json(r#"{ "name": "Rey", "age": 4 }"#);
+  /// schema_parser.merge(other);
+  /// assert_eq!(schema_parser.count, 2);
+  /// ```
+  pub fn merge(&mut self, other: SchemaParser) {
+    self.count += other.count;
+    for (key, mut field) in other.fields {
+      if let Some(existing) = self.fields.get_mut(&key) {
+        existing.merge(field);
+      } else if let Some(existing) = self.case_variant(&key) {
+        // the first spelling names the field, as when documents are written
+        transform::repath_field(&mut field, &key, &existing);
+        if let Some(existing) = self.fields.get_mut(&existing) {
+          existing.add_spelling(field.name.to_string());
+          existing.merge(field);
+        }
+      } else if !self.is_full() {
+        self.fields.insert(key, field);
+      }
+    }
+  }
+
+  /// Finalizes and returns the Schema -- result of all parsed documents.
+  /// SchemaParser itself is left as is, so more documents can be written to it
+  /// and flushed again later.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// let json = r#"{ "name": "Chashu", "type": "Cat" }"#;
+  /// schema_parser.write_json(&json);
+  /// let schema = schema_parser.flush();
+  /// println!("{:?}", schema);
+  /// ```
+  pub fn flush(&self) -> Schema {
+    // snapshots aren't part of the schema, and aren't worth cloning
+    let schema_parser = SchemaParser {
+      count: self.count,
+      fields: self.fields.clone(),
+      options: self.options.clone(),
+      history: None,
+      #[cfg(feature = "wasm")]
+      progress: None,
+    };
+    schema_parser.into_schema()
+  }
+
+  /// Records a snapshot of the schema of all documents written so far to the
+  /// SchemaParser's history. Snapshots are also taken automatically when an
+  /// interval is set with `SchemaParser::builder().snapshot_every()`.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// schema_parser.snapshot();
+  /// assert_eq!(schema_parser.history().unwrap().snapshots.len(), 1);
+  /// ```
+  pub fn snapshot(&mut self) {
+    let schema = self.flush();
+    self
+      .history
+      .get_or_insert_with(SchemaHistory::new)
+      .record(schema);
+  }
+
+  /// Returns the snapshots taken so far, if any.
+  pub fn history(&self) -> Option<&SchemaHistory> {
+    self.history.as_ref()
+  }
+
+  /// Consumes SchemaParser and returns the finalized Schema. Same as `flush`,
+  /// without having to clone the parsed fields.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// let json = r#"{ "name": "Chashu", "type": "Cat" }"#;
+  /// schema_parser.write_json(&json);
+  /// let schema = schema_parser.into_schema();
+  /// println!("{:?}", schema);
+  /// ```
+  pub fn into_schema(mut self) -> Schema {
+    self.finalise_schema();
+    if let Some(min_probability) = self.options.min_probability {
+      prune::prune(&mut self.fields, min_probability);
+    }
+    order::sort(&mut self.fields, self.options.field_order);
+    Schema::new(self.count, self.fields)
+  }
+
+  /// Returns the schema of all documents written so far as a JSON Schema
+  /// draft-07 document, e.g. to use with generic JSON validators. Fields
+  /// present in at least `required_threshold` of all documents, as set with
+  /// `SchemaParser::builder()`, are listed as required. Numbers and string
+  /// lengths are bounded by the ranges seen with `numeric_bounds` and
+  /// `length_bounds`.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::builder()
+  ///   .required_threshold(0.9)
+  ///   .build();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let json_schema = schema_parser.to_json_schema();
+  /// println!("{}", json_schema);
+  /// ```
+  pub fn to_json_schema(&self) -> Value {
+    json_schema::to_json_schema(
+      self.flush().fields(),
+      self.options.required_threshold,
+      Bounds::from_options(&self.options),
+    )
+  }
+
+  /// Returns a MongoDB collection validator, a `$jsonSchema` document using
+  /// `bsonType` keywords, for all documents written so far. Which fields are
+  /// required, whether unknown fields are allowed and whether numbers are
+  /// bounded is set with `SchemaParser::builder()`.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::builder()
+  ///   .required_threshold(0.9)
+  ///   .additional_properties(false)
+  ///   .build();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let validator = schema_parser.to_mongodb_validator();
+  /// println!("{}", validator);
+  /// ```
+  pub fn to_mongodb_validator(&self) -> Document {
+    self.flush().validator(
+      self.options.required_threshold,
+      self.options.additional_properties,
+      Bounds::from_options(&self.options),
+    )
+  }
+
+  /// Returns an Avro record schema for all documents written so far, e.g. to
+  /// land collection data in Kafka.
+  ///
+  /// # Arguments
+  /// * `name` - Name of the Avro record, i.e. the collection name.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let avro = schema_parser.to_avro("pets");
+  /// println!("{}", avro);
+  /// ```
+  pub fn to_avro(&self, name: &str) -> Value {
+    self.flush().to_avro(name)
+  }
 
-mod value_type;
-use crate::value_type::ValueType;
+  /// Returns TypeScript interface declarations for all documents written so
+  /// far. Fields present in less than `required_threshold` of the documents,
+  /// as set with `SchemaParser::builder()`, are optional.
+  ///
+  /// # Arguments
+  /// * `name` - Name of the top level interface, i.e. the collection name.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let typescript = schema_parser.to_typescript("pets");
+  /// println!("{}", typescript);
+  /// ```
+  pub fn to_typescript(&self, name: &str) -> String {
+    self
+      .flush()
+      .to_typescript(name, self.options.required_threshold)
+  }
 
-// WASM Api of the Schema Parser.
-mod lib_wasm;
-use crate::lib_wasm::*;
+  /// Returns Rust struct definitions with serde attributes for all documents
+  /// written so far, to use as typed models for the collection. Fields
+  /// present in less than `required_threshold` of the documents, as set with
+  /// `SchemaParser::builder()`, are `Option`s.
+  ///
+  /// # Arguments
+  /// * `name` - Name of the top level struct, i.e. the collection name.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let rust = schema_parser.to_rust_structs("pets");
+  /// println!("{}", rust);
+  /// ```
+  pub fn to_rust_structs(&self, name: &str) -> String {
+    self
+      .flush()
+      .to_rust_structs(name, self.options.required_threshold)
+  }
 
-#[wasm_bindgen]
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
-pub struct SchemaParser {
-  pub count: usize,
-  fields: HashMap<String, Field>,
-}
+  /// Returns GraphQL SDL type definitions for all documents written so far,
+  /// to bootstrap a GraphQL API over the collection. Fields present in less
+  /// than `required_threshold` of the documents, as set with
+  /// `SchemaParser::builder()`, or that are sometimes null, are nullable.
+  ///
+  /// # Arguments
+  /// * `name` - Name of the top level type, i.e. the collection name.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let sdl = schema_parser.to_graphql_sdl("pets");
+  /// println!("{}", sdl);
+  /// ```
+  pub fn to_graphql_sdl(&self, name: &str) -> String {
+    self
+      .flush()
+      .to_graphql_sdl(name, self.options.required_threshold)
+  }
 
-impl SchemaParser {
-  /// Returns a new instance of Schema Parser populated with zero `count` and an
-  /// empty `fields` vector.
+  /// Returns proto3 message definitions for all documents written so far, to
+  /// describe the collection's documents in gRPC services. Scalar fields
+  /// present in less than `required_threshold` of the documents, as set with
+  /// `SchemaParser::builder()`, or that are sometimes null, are `optional`.
+  ///
+  /// # Arguments
+  /// * `name` - Name of the top level message, i.e. the collection name.
   ///
   /// # Examples
   /// ```
   /// use mongodb_schema_parser::SchemaParser;
-  /// let schema_parser = SchemaParser::new();
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let proto = schema_parser.to_proto("pets");
+  /// println!("{}", proto);
   /// ```
-  #[inline]
-  pub fn new() -> Self {
-    SchemaParser {
-      count: 0,
-      fields: HashMap::new(),
-    }
+  pub fn to_proto(&self, name: &str) -> String {
+    self.flush().to_proto(name, self.options.required_threshold)
   }
 
-  /// Writes json-like string slices SchemaParser's fields vector.
+  /// Returns the source of a Mongoose model for all documents written so
+  /// far, to scaffold Node.js models from existing data. Fields present in at
+  /// least `required_threshold` of the documents, as set with
+  /// `SchemaParser::builder()`, and never null are `required`.
   ///
   /// # Arguments
-  /// * `json` - A json-like string slice. i.e `{ "name": "Nori", "type": "Cat"}`
+  /// * `name` - Name of the model, i.e. the collection name.
   ///
   /// # Examples
   /// ```
   /// use mongodb_schema_parser::SchemaParser;
+  ///
   /// let mut schema_parser = SchemaParser::new();
-  /// let json = r#"{ "name": "Chashu", "type": "Cat" }"#;
-  /// schema_parser.write_json(&json);
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let mongoose = schema_parser.to_mongoose_schema("pets");
+  /// println!("{}", mongoose);
   /// ```
-  #[inline]
-  pub fn write_json(&mut self, json: &str) -> Result<(), failure::Error> {
-    let val: Value = serde_json::from_str(json)?;
-    let bson = Bson::from(val);
-    // should do a match for NoneError
-    let doc = bson
-      .as_document()
-      .ok_or_else(|| format_err!("Failed to parse bson"))?
-      .to_owned();
-    self.update_count();
-    self.generate_field(doc, None, None);
-    Ok(())
+  pub fn to_mongoose_schema(&self, name: &str) -> String {
+    self
+      .flush()
+      .to_mongoose_schema(name, self.options.required_threshold)
   }
 
-  /// Writes Bson documents to SchemaParser's fields vector.
+  /// Returns an OpenAPI 3.0 `components.schemas` entry for all documents
+  /// written so far, to document REST APIs backed by the collection. Fields
+  /// present in at least `required_threshold` of the documents, as set with
+  /// `SchemaParser::builder()`, are required.
   ///
   /// # Arguments
-  /// * `doc` - A Bson Document.
+  /// * `name` - Name of the component schema, i.e. the collection name.
   ///
   /// # Examples
-  /// ```ignore
+  /// ```
   /// use mongodb_schema_parser::SchemaParser;
-  /// use js_sys::Uint8Array;
-  /// use bson::{doc, bson};
   ///
   /// let mut schema_parser = SchemaParser::new();
-  /// let uint8 = Uint8Array::new(&JsValue::from_str(r#"{ "name": "Chashu", "type": "Cat" }"#));
-  /// schema_parser.write_raw(uint8);
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let openapi = schema_parser.to_openapi("pets");
+  /// println!("{}", openapi);
   /// ```
-  #[inline]
-  pub fn write_raw(&mut self, uint8: Uint8Array) -> Result<(), failure::Error> {
-    let mut decoded_vec = vec![0u8; uint8.length() as usize];
-    // fill up a new u8 vec with bytes we get from js; decode_document needs a
-    // byte stream that implements a reader and u8 slice does this.
-    uint8.copy_to(&mut decoded_vec);
-    let mut slice: &[u8] = &decoded_vec;
-    let doc = decode_document(&mut slice)?.to_owned();
-    // write bson internally
-    self.update_count();
-    self.generate_field(doc, None, None);
+  pub fn to_openapi(&self, name: &str) -> Value {
+    self.flush().to_openapi(name, self.options.required_threshold)
+  }
 
-    Ok(())
+  /// Returns a human-readable Markdown report for all documents written so
+  /// far, with a table of fields for the documents and each subdocument.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let markdown = schema_parser.to_markdown();
+  /// println!("{}", markdown);
+  /// ```
+  pub fn to_markdown(&self) -> String {
+    self.flush().to_markdown()
+  }
+
+  /// Returns a self-contained HTML report for all documents written so far,
+  /// to review a collection's shape from a single file.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let html = schema_parser.to_html_report();
+  /// println!("{}", html);
+  /// ```
+  pub fn to_html_report(&self) -> String {
+    self.flush().to_html_report()
+  }
+
+  /// Returns CSV field statistics for all documents written so far, with one
+  /// row per field path, for spreadsheets and data catalogs.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let csv = schema_parser.to_csv();
+  /// println!("{}", csv);
+  /// ```
+  pub fn to_csv(&self) -> String {
+    self.flush().to_csv()
+  }
+
+  /// Returns an Apache Arrow schema for all documents written so far, in
+  /// Arrow's JSON format, to load the collection into columnar storage.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let arrow = schema_parser.to_arrow_schema();
+  /// println!("{}", arrow);
+  /// ```
+  pub fn to_arrow_schema(&self) -> Value {
+    self.flush().to_arrow_schema()
   }
 
-  /// Writes Bson documents to SchemaParser's fields vector.
+  /// Returns a Parquet schema message for all documents written so far.
   ///
   /// # Arguments
-  /// * `doc` - A Bson Document.
+  /// * `name` - Name of the message, i.e. the collection name.
   ///
   /// # Examples
-  /// ```ignore
+  /// ```
   /// use mongodb_schema_parser::SchemaParser;
-  /// use bson::{doc, bson};
   ///
   /// let mut schema_parser = SchemaParser::new();
-  /// let doc = doc! {"name": "Chashu", "type": "Cat"};
-  /// schema_parser.write_bson(doc);
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let parquet = schema_parser.to_parquet_schema("pets");
+  /// println!("{}", parquet);
   /// ```
-  #[inline]
-  pub fn write_bson(&mut self, doc: Document) -> Result<(), failure::Error> {
-    // write bson internally
-    self.update_count();
-    self.generate_field(doc, None, None);
+  pub fn to_parquet_schema(&self, name: &str) -> String {
+    self.flush().to_parquet_schema(name)
+  }
 
-    Ok(())
+  /// Returns `CREATE TABLE` statements for all documents written so far, to
+  /// migrate the collection into a relational database.
+  ///
+  /// # Arguments
+  /// * `name` - Name of the top level table, i.e. the collection name.
+  /// * `dialect` - SQL dialect to write the statements in.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::{SchemaParser, SqlDialect};
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let sql = schema_parser.to_sql_ddl("pets", SqlDialect::MySql);
+  /// println!("{}", sql);
+  /// ```
+  pub fn to_sql_ddl(&self, name: &str, dialect: SqlDialect) -> String {
+    self.flush().to_sql_ddl(name, dialect)
   }
 
-  /// Finalizes and returns SchemaParser struct -- result of all parsed
-  /// documents.
+  /// Returns all documents written so far in the structure the npm
+  /// `mongodb-schema` package produces, to use this crate as a drop-in
+  /// replacement for it.
   ///
   /// # Examples
   /// ```
   /// use mongodb_schema_parser::SchemaParser;
   ///
   /// let mut schema_parser = SchemaParser::new();
-  /// let json = r#"{ "name": "Chashu", "type": "Cat" }"#;
-  /// schema_parser.write_json(&json);
-  /// let schema = schema_parser.flush();
-  /// println!("{:?}", schema);
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let compat = schema_parser.to_json_compat();
+  /// println!("{}", compat);
   /// ```
-  pub fn flush(&mut self) -> SchemaParser {
-    self.finalise_schema();
-    self.to_owned()
+  pub fn to_json_compat(&self) -> Value {
+    self.flush().to_json_compat()
   }
 
   /// Returns a serde_json string. This should be called after all values were
@@ -220,58 +1461,141 @@ impl SchemaParser {
   /// let mut schema_parser = SchemaParser::new();
   /// let json = r#"{ "name": "Chashu", "type": "Cat" }"#;
   /// schema_parser.write_json(&json);
-  /// schema_parser.flush();
   /// let schema = schema_parser.into_json().unwrap();
   /// println!("{}", schema);
   /// ```
   #[inline]
-  pub fn into_json(mut self) -> Result<String, failure::Error> {
-    let schema = self.flush();
-    Ok(serde_json::to_string(&schema)?)
+  pub fn into_json(self) -> Result<String, ParseError> {
+    self.into_schema().to_json()
   }
 
   #[inline]
   fn generate_field(
     &mut self,
-    doc: Document,
-    path: Option<String>,
+    doc: &Document,
+    path: Option<&str>,
     count: Option<usize>,
   ) {
     if let Some(_count) = count {
       self.update_count();
     }
+    // Paths are built in one buffer that's reused for every key, and
+    // documents are only borrowed all the way down, so fields that already
+    // exist are found and updated without allocating. A field's name and path
+    // are only copied once, when it's created.
+    let mut current_path = String::new();
+    // With case-insensitive keys, a document can spell the same field more
+    // than once, i.e. `Name` and `name`. Only the first spelling's value is
+    // counted, so a field is counted at most once per document.
+    let mut seen = HashSet::new();
     for (key, value) in doc {
-      let current_path = Field::get_path(key.to_owned(), path.to_owned());
-      self.update_or_create_field(key.to_owned(), &value, &current_path)
+      current_path.clear();
+      if let Some(path) = path {
+        current_path.push_str(path);
+        current_path.push('.');
+      }
+      current_path.push_str(key);
+      if self.options.case_insensitive_keys
+        && !seen.insert(current_path.to_ascii_lowercase())
+      {
+        self.add_spelling(key, &current_path);
+        continue;
+      }
+      self.update_or_create_field(key, value, &current_path)
     }
   }
 
   #[inline]
-  fn update_or_create_field(&mut self, key: String, value: &Bson, path: &str) {
-    // check if we already have a field for this key;
-    // if name exist, call self.update_field, otherwise create new
-    if self.fields.contains_key(&key) {
-      self.update_field(&key, value);
+  fn update_or_create_field(&mut self, key: &str, value: &Bson, path: &str) {
+    // fields are keyed by their full path, so the same key at different
+    // nesting levels doesn't collide. If path exists, call self.update_field,
+    // otherwise create new
+    if self.fields.contains_key(path) {
+      self.update_field(key, path, value);
+    } else if let Some(existing) = self.case_variant(path) {
+      if let Some(field) = self.fields.get_mut(&existing) {
+        field.add_spelling(key.to_string());
+      }
+      self.update_field(key, &existing, value);
     } else {
+      if self.is_full() {
+        return;
+      }
       let mut field = Field::new(key, path);
-      field.create_type(value);
-      self.fields.insert(field.name.to_string(), field);
+      // the path is kept both as the key and in the field
+      let size = mem::size_of::<Field>() + key.len() + 2 * path.len();
+      self.options.charge_memory(size);
+      field.create_type(value, &self.options);
+      field.total_bytes = element_size(key, value);
+      self.fields.insert(path.to_string(), field);
+    }
+  }
+
+  // Records `key` as a spelling of the field at `path`, or of the field whose
+  // path only differs from it in case.
+  fn add_spelling(&mut self, key: &str, path: &str) {
+    let existing = if self.fields.contains_key(path) {
+      Some(path.to_string())
+    } else {
+      self.case_variant(path)
+    };
+    let field = existing.and_then(|existing| self.fields.get_mut(&existing));
+    if let Some(field) = field {
+      field.add_spelling(key.to_string());
+    }
+  }
+
+  // Whether no more fields can be tracked at this document level.
+  fn is_full(&self) -> bool {
+    self
+      .options
+      .max_fields
+      .map_or(false, |max_fields| self.fields.len() >= max_fields)
+  }
+
+  // Drops the fields past `max_fields` at this document level and in
+  // subdocuments, keeping the first ones seen, and limits the fields tracked
+  // from then on.
+  #[cfg(feature = "parallel")]
+  pub(crate) fn limit_fields(&mut self, max_fields: Option<usize>) {
+    self.options.max_fields = max_fields;
+    if let Some(max_fields) = max_fields {
+      self.fields.truncate(max_fields);
+    }
+    for field in self.fields.values_mut() {
+      for field_type in field.types.values_mut() {
+        field_type.limit_fields(max_fields);
+      }
+    }
+  }
+
+  // Path of a field whose path only differs from `path` in case, if keys are
+  // matched case-insensitively.
+  fn case_variant(&self, path: &str) -> Option<String> {
+    if !self.options.case_insensitive_keys {
+      return None;
     }
+    self
+      .fields
+      .keys()
+      .find(|existing| existing.eq_ignore_ascii_case(path))
+      .cloned()
   }
 
   #[inline]
-  fn update_field(&mut self, key: &str, value: &Bson) {
-    let field = self.fields.get_mut(key);
+  fn update_field(&mut self, key: &str, path: &str, value: &Bson) {
+    let field = self.fields.get_mut(path);
     if let Some(field) = field {
       field.update_count();
+      field.total_bytes += element_size(key, value);
       if !field.does_field_type_exist(&value) {
         // field type doesn't exist in field.types, create a new field_type
-        field.create_type(&value);
+        field.create_type(&value, &self.options);
       } else {
         let type_val = FieldType::get_type(&value);
         let field_type = field.types.get_mut(&type_val);
         if let Some(field_type) = field_type {
-          field_type.update_type(&value);
+          field_type.update_type(&value, &self.options);
         }
       }
     }
@@ -279,25 +1603,22 @@ impl SchemaParser {
 
   #[inline]
   pub fn finalise_schema(&mut self) {
-    for field in self.fields.values_mut() {
-      // If bson_types includes a Document, find that document and let its schema
-      // field update its own missing fields.
-      let field_type = field.types.get_mut(crate::field_type::DOCUMENT);
-      if let Some(field_type) = field_type {
-        let schema = &mut field_type.schema;
-        if let Some(schema) = schema {
-          return schema.finalise_schema();
-        }
+    if self.options.coalesce_numbers {
+      for field in self.fields.values_mut() {
+        field.coalesce_numbers(self.options.coalesce_decimal128);
       }
-
-      // create new field_types as Null for missing fields
-      let missing = self.count - field.count;
-      if missing > 0 {
-        field.update_for_missing(missing);
-      }
-
-      // check for duplicates, unique values, set probability
+    }
+    let wildcard =
+      wildcard::coalesce(&mut self.fields, self.options.wildcard_threshold);
+    for field in self.fields.values_mut() {
+      // account for missing fields, check for duplicates, unique values, set
+      // probability
       field.finalise_field(self.count);
+      field.required = field.probability >= self.options.required_threshold;
+    }
+    if let Some(mut field) = wildcard {
+      wildcard::finalise(&mut field);
+      self.fields.insert(field.path.to_string(), field);
     }
   }
 
@@ -307,6 +1628,24 @@ impl SchemaParser {
   }
 }
 
+// Json lines don't implement Extend and FromIterator, since lines that fail to
+// parse couldn't be reported. `write_many` returns them instead.
+impl Extend<Document> for SchemaParser {
+  fn extend<T: IntoIterator<Item = Document>>(&mut self, iter: T) {
+    for doc in iter {
+      self.add_document(&doc);
+    }
+  }
+}
+
+impl FromIterator<Document> for SchemaParser {
+  fn from_iter<T: IntoIterator<Item = Document>>(iter: T) -> Self {
+    let mut schema_parser = SchemaParser::new();
+    schema_parser.extend(iter);
+    schema_parser
+  }
+}
+
 #[cfg(test)]
 mod tests {
   // use self::test::Bencher;
@@ -340,15 +1679,24 @@ mod tests {
   //   bench.iter(|| schema_parser.write_json(&json_str));
   // }
 
-  // #[test]
-  // fn it_writes_raw() {
-  //   let mut schema_parser = SchemaParser::new();
-  //   // let bson_str = bson!({"name": "Nori", "type": "Cat"});
-  //   schema_parser.write_raw(&bson_str).unwrap();
-  //   println!("{:?}", schema_parser.flush());
-  //   assert_eq!(schema_parser.count, 1);
-  //   assert_eq!(schema_parser.fields.len(), 2);
-  // }
+  #[test]
+  fn it_writes_raw() {
+    let mut schema_parser = SchemaParser::new();
+    let mut bytes = Vec::new();
+    bson::encode_document(&mut bytes, &doc! {"name": "Nori", "type": "Cat"})
+      .unwrap();
+    schema_parser.write_raw(&bytes).unwrap();
+    assert_eq!(schema_parser.count, 1);
+    assert_eq!(schema_parser.fields.len(), 2);
+  }
+
+  #[test]
+  fn it_errors_on_invalid_raw_bytes() {
+    let mut schema_parser = SchemaParser::new();
+    let bytes = vec![1, 2, 3];
+    assert!(schema_parser.write_raw(&bytes).is_err());
+    assert_eq!(schema_parser.count, 0);
+  }
 
   #[test]
   fn it_writes_bson() {
@@ -363,6 +1711,18 @@ mod tests {
     assert_eq!(schema_parser.fields.len(), 2);
   }
 
+  #[test]
+  fn it_writes_document() {
+    let mut schema_parser = SchemaParser::new();
+    let bson_doc = doc! {
+      "name": "Rey",
+      "type": "Dog"
+    };
+    schema_parser.write_document(bson_doc).unwrap();
+    assert_eq!(schema_parser.count, 1);
+    assert_eq!(schema_parser.fields.len(), 2);
+  }
+
   // #[bench]
   // fn bench_it_creates_write_json(bench: &mut Bencher) {
   //   let mut schema_parser = SchemaParser::new();
@@ -380,6 +1740,56 @@ mod tests {
     assert_eq!(output.fields.len(), 2);
   }
 
+  #[test]
+  fn it_flushes_without_finalising_parser() {
+    let mut schema_parser = SchemaParser::new();
+    schema_parser
+      .write_json(r#"{"name": "Nori", "type": "Cat"}"#)
+      .unwrap();
+    schema_parser.write_json(r#"{"name": "Rey"}"#).unwrap();
+    let first = schema_parser.flush();
+    let second = schema_parser.flush();
+    assert_eq!(first, second);
+    // missing values are only accounted for in the flushed Schema
+    let type_field = schema_parser.fields.get("type").unwrap();
+    assert_eq!(type_field.count, 1);
+    assert_eq!(type_field.types.len(), 1);
+  }
+
+  #[test]
+  fn it_converts_into_schema() {
+    let mut schema_parser = SchemaParser::new();
+    schema_parser
+      .write_json(r#"{"name": "Nori", "type": "Cat"}"#)
+      .unwrap();
+    let flushed = schema_parser.flush();
+    let schema = schema_parser.into_schema();
+    assert_eq!(flushed, schema);
+    assert_eq!(schema.count, 1);
+  }
+
+  #[test]
+  fn it_traverses_schema() {
+    let mut schema_parser = SchemaParser::new();
+    let json_str = r#"{"name": "Nori", "owner": {"name": "Irina"}, "tags": ["cat", 1]}"#;
+    schema_parser.write_json(&json_str).unwrap();
+    let schema = schema_parser.flush();
+
+    let fields: Vec<&Field> = schema.fields().collect();
+    assert_eq!(fields.len(), 3);
+
+    let name_type = fields[0].types().next().unwrap();
+    assert_eq!(name_type.values(), &[ValueType::Str("Nori".to_string())]);
+
+    let owner_type = fields[1].types().next().unwrap();
+    let owner_schema = owner_type.schema().unwrap();
+    let owner_fields: Vec<&Field> = owner_schema.fields().collect();
+    assert_eq!(&*owner_fields[0].path, "owner.name");
+
+    let tags_type = fields[2].types().next().unwrap();
+    assert_eq!(tags_type.types().count(), 2);
+  }
+
   #[test]
   fn it_adjusts_missing() {
     let mut schema_parser = SchemaParser::new();
@@ -393,46 +1803,131 @@ mod tests {
     let type_field = output.fields.get_mut("type");
     if let Some(type_field) = type_field {
       assert_eq!(type_field.count, 3);
-      assert!(type_field.bson_types.contains(&"Null".to_string()));
+      assert!(type_field.bson_types.contains(&"Undefined".to_string()));
 
-      let null_field_type = type_field.types.get_mut("Null");
-      if let Some(null_field_type) = null_field_type {
-        assert_eq!(null_field_type.count, 2)
+      let undefined_field_type = type_field.types.get_mut("Undefined");
+      if let Some(undefined_field_type) = undefined_field_type {
+        assert_eq!(undefined_field_type.count, 2)
       }
     }
   }
 
+  #[allow(clippy::float_cmp)]
+  #[test]
+  fn it_sets_probabilities() {
+    let mut schema_parser = SchemaParser::new();
+    let json_str1 = r#"{"name": "Nori", "age": 5}"#;
+    let json_str2 = r#"{"name": "Rey", "age": "three"}"#;
+    let json_str3 = r#"{"name": "Chashu", "age": null}"#;
+    let json_str4 = r#"{"name": "Marble"}"#;
+    schema_parser.write_json(&json_str1).unwrap();
+    schema_parser.write_json(&json_str2).unwrap();
+    schema_parser.write_json(&json_str3).unwrap();
+    schema_parser.write_json(&json_str4).unwrap();
+    let output = schema_parser.flush();
+
+    assert_eq!(output.fields.get("name").unwrap().probability, 1.0);
+
+    // the field was present in three out of four documents
+    let age = output.fields.get("age").unwrap();
+    assert_eq!(age.probability, 0.75);
+    assert_eq!(age.types.get("Int32").unwrap().probability, 0.25);
+    assert_eq!(age.types.get("String").unwrap().probability, 0.25);
+    assert_eq!(age.types.get("Null").unwrap().probability, 0.25);
+    assert_eq!(age.types.get("Undefined").unwrap().probability, 0.25);
+    let total: f32 = age.types().map(|t| t.probability).sum();
+    assert_eq!(total, 1.0);
+  }
+
   #[test]
   fn it_adjusts_missing_with_nested_document() {
     let mut schema_parser = SchemaParser::new();
-    let json_str1 = r#"{"name": "Nori", "type": {"breed": "Norwegian Forest", "type": "cat"}}"#;
-    let json_str2 = r#"{"name": "Rey", "type": {"breed": "Viszla"}}"#;
+    let json_str1 = r#"{"name": "Nori", "type": {"breed": "Norwegian Forest", "type": "cat"}}"#;
+    let json_str2 = r#"{"name": "Rey", "type": {"breed": "Viszla"}}"#;
+    schema_parser.write_json(&json_str1).unwrap();
+    schema_parser.write_json(&json_str2).unwrap();
+    let output = schema_parser.flush();
+    let type_field = output.fields.get("type");
+    if let Some(type_field) = type_field {
+      let doc = type_field.types.get(crate::field_type::DOCUMENT);
+      if let Some(doc) = doc {
+        assert_eq!(doc.count, 2);
+
+        let schema = &doc.schema;
+        if let Some(schema) = schema {
+          assert_eq!(schema.count, 2);
+
+          let type_schema_field = schema.fields.get("type.type");
+          if let Some(type_schema_field) = type_schema_field {
+            assert_eq!(type_schema_field.count, 2);
+            assert!(type_schema_field.bson_types.contains(&"Undefined".to_string()));
+
+            let undefined_type_schema_field = type_schema_field.types.get("Undefined");
+            if let Some(undefined_type_schema_field) = undefined_type_schema_field {
+              assert_eq!(undefined_type_schema_field.count, 1)
+            }
+          }
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn it_keys_fields_by_path() {
+    let mut schema_parser = SchemaParser::new();
+    let json_str = r#"{"name": "Nori", "owner": {"name": "Irina"}}"#;
+    schema_parser.write_json(&json_str).unwrap();
+    let owner = schema_parser.fields.get("owner").unwrap();
+    let doc = owner.types.get(crate::field_type::DOCUMENT).unwrap();
+    let schema = doc.schema.as_ref().unwrap();
+    let name = schema.fields.get("owner.name").unwrap();
+    assert_eq!(&*name.name, "name");
+    assert_eq!(&*name.path, "owner.name");
+  }
+
+  #[test]
+  fn it_preserves_field_order() {
+    let mut schema_parser = SchemaParser::new();
+    let json_str = r#"{"name": "Nori", "type": "Cat", "age": 4}"#;
+    schema_parser.write_json(&json_str).unwrap();
+    schema_parser.write_json(r#"{"owner": "Irina"}"#).unwrap();
+    let keys: Vec<&String> = schema_parser.fields.keys().collect();
+    assert_eq!(keys, vec!["name", "type", "age", "owner"]);
+  }
+
+  #[test]
+  fn it_finalises_fields_after_nested_document() {
+    let mut schema_parser = SchemaParser::new();
+    let json_str1 = r#"{"owner": {"name": "Irina", "city": "Berlin"}, "type": "Cat"}"#;
+    let json_str2 = r#"{"owner": {"name": "Lena"}}"#;
     schema_parser.write_json(&json_str1).unwrap();
     schema_parser.write_json(&json_str2).unwrap();
     let output = schema_parser.flush();
-    let type_field = output.fields.get("type");
-    if let Some(type_field) = type_field {
-      let doc = type_field.types.get(crate::field_type::DOCUMENT);
-      if let Some(doc) = doc {
-        assert_eq!(doc.count, 2);
 
-        let schema = &doc.schema;
-        if let Some(schema) = schema {
-          assert_eq!(schema.count, 2);
+    // fields following a nested document still get finalised
+    let type_field = output.fields.get("type").unwrap();
+    assert_eq!(type_field.count, 2);
+    assert_eq!(type_field.types.get("Undefined").unwrap().count, 1);
 
-          let type_schema_field = schema.fields.get("type");
-          if let Some(type_schema_field) = type_schema_field {
-            assert_eq!(type_schema_field.count, 2);
-            assert!(type_schema_field.bson_types.contains(&"Null".to_string()));
+    // and so do the nested document's own fields
+    let owner = output.fields.get("owner").unwrap();
+    let doc = owner.types.get(crate::field_type::DOCUMENT).unwrap();
+    let schema = doc.schema.as_ref().unwrap();
+    let city = schema.fields.get("owner.city").unwrap();
+    assert_eq!(city.count, 2);
+    assert_eq!(city.types.get("Undefined").unwrap().count, 1);
+  }
 
-            let null_type_schema_field = type_schema_field.types.get("Null");
-            if let Some(null_type_schema_field) = null_type_schema_field {
-              assert_eq!(null_type_schema_field.count, 1)
-            }
-          }
-        }
-      }
-    }
+  #[test]
+  fn it_serializes_nested_documents_under_their_type() {
+    let mut schema_parser = SchemaParser::new();
+    let json_str = r#"{"owner": {"name": "Irina"}}"#;
+    schema_parser.write_json(&json_str).unwrap();
+    let json = schema_parser.into_json().unwrap();
+    let value: Value = serde_json::from_str(&json).unwrap();
+    let doc = &value["fields"]["owner"]["types"]["Document"];
+    assert_eq!(doc["schema"]["count"], 1);
+    assert!(doc["schema"]["fields"]["owner.name"].is_object());
   }
 
   #[test]
@@ -450,7 +1945,7 @@ mod tests {
     let json_str = r#"{"name": "Chashu", "type": "Cat"}"#;
     schema_parser.write_json(&json_str).unwrap();
     let name = Bson::String("Nori".to_owned());
-    schema_parser.update_field("name", &name);
+    schema_parser.update_field("name", "name", &name);
     let vec = vec![
       ValueType::Str("Chashu".to_owned()),
       ValueType::Str("Nori".to_owned()),
@@ -464,6 +1959,22 @@ mod tests {
     }
   }
 
+  #[test]
+  fn it_sums_field_sizes() {
+    let mut schema_parser = SchemaParser::new();
+    let json = r#"{"name": "Chashu", "age": 5}"#;
+    schema_parser.write_json(json).unwrap();
+    schema_parser.write_json(r#"{"name": "Nori"}"#).unwrap();
+    let schema = schema_parser.flush();
+    let name = schema.get_field("name").unwrap();
+    // type byte, "name\0", and the length prefixed, null terminated string
+    assert_eq!(name.total_bytes, (6 + 4 + 7) + (6 + 4 + 5));
+    assert_eq!(name.average_bytes, 16.0);
+    let age = schema.get_field("age").unwrap();
+    assert_eq!(age.total_bytes, 9);
+    assert_eq!(age.average_bytes, 9.0);
+  }
+
   // #[bench]
   // fn bench_it_updates_fields(bench: &mut Bencher) {
   //   let mut schema_parser = SchemaParser::new();
@@ -471,7 +1982,7 @@ mod tests {
   //   schema_parser.write_json(&json_str).unwrap();
   //   let name = Bson::String("Chashu".to_owned());
 
-  //   bench.iter(|| schema_parser.update_field("name", &name));
+  //   bench.iter(|| schema_parser.update_field("name", "name", &name));
   // }
 
   #[test]
@@ -481,7 +1992,7 @@ mod tests {
       "name": "Rey",
       "type": "Dog"
     };
-    schema_parser.generate_field(doc, None, None);
+    schema_parser.generate_field(&doc, None, None);
     assert_eq!(schema_parser.fields.len(), 2);
     if let Some(f) = schema_parser.fields.get("name") {
       if let Some(t) = f.types.get("String") {
@@ -505,7 +2016,7 @@ mod tests {
   //       "type": "Dog"
   //     };
   //     let n = test::black_box(doc);
-  //     schema_parser.generate_field(n, None, None)
+  //     schema_parser.generate_field(&n, None, None)
   //   });
   // }
 
@@ -519,7 +2030,7 @@ mod tests {
   //       "type": "Dog"
   //     };
   //     let n = test::black_box(doc);
-  //     schema_parser.generate_field(n, Some("treats".to_owned()), None)
+  //     schema_parser.generate_field(&n, Some("treats"), None)
   //   });
   // }
 
@@ -548,16 +2059,34 @@ mod tests {
     let vec_json2 = r#"{"animals": [{"name": "Rey"}, {"name": "Emma"}]}"#;
     schema_parser.write_json(vec_json1).unwrap();
     schema_parser.write_json(vec_json2).unwrap();
-    println!("{:?}", schema_parser);
-    // assert_eq!(schema_parser.fields.len(), 1);
-    // let field = schema_parser.fields.get("animals");
-    // if let Some(field) = field {
-    //   assert_eq!(field.types.len(), 1);
-    //   let field_type = field.types.get("Array");
-    //   if let Some(field_type) = field_type {
-    //     assert_eq!(field_type.values.len(), 4);
-    //   }
-    // }
+    let output = schema_parser.flush();
+    let field = output.fields.get("animals").unwrap();
+    let array = field.types.get("Array").unwrap();
+    let docs = array.types.get("Document").unwrap();
+    assert_eq!(docs.count, 4);
+    let schema = docs.schema.as_ref().unwrap();
+    assert_eq!(schema.count, 4);
+    assert_eq!(schema.fields.get("animals.name").unwrap().count, 4);
+  }
+
+  #[allow(clippy::float_cmp)]
+  #[test]
+  fn it_infers_array_element_types() {
+    let mut schema_parser = SchemaParser::new();
+    let vec_json1 = r#"{"tags": ["cat", "dog", 1]}"#;
+    let vec_json2 = r#"{"tags": ["bird"]}"#;
+    schema_parser.write_json(vec_json1).unwrap();
+    schema_parser.write_json(vec_json2).unwrap();
+    let output = schema_parser.flush();
+    let array = output.fields.get("tags").unwrap().types.get("Array").unwrap();
+    assert_eq!(array.count, 2);
+    assert_eq!(array.lengths, vec![3, 1]);
+    let strings = array.types.get("String").unwrap();
+    assert_eq!(strings.count, 3);
+    assert_eq!(strings.probability, 0.75);
+    let ints = array.types.get("Int32").unwrap();
+    assert_eq!(ints.count, 1);
+    assert_eq!(ints.probability, 0.25);
   }
 
   #[test]
@@ -738,6 +2267,737 @@ mod tests {
     }
   }
 
+  #[test]
+  fn it_reads_from_reader() {
+    let mut schema_parser = SchemaParser::new();
+    let input = "{\"name\": \"Nori\"}\n\n{\"name\": \"Rey\"}\n";
+    schema_parser.read_from(input.as_bytes()).unwrap();
+    assert_eq!(schema_parser.count, 2);
+    assert_eq!(schema_parser.fields.len(), 1);
+  }
+
+  #[test]
+  #[cfg(feature = "async")]
+  fn it_reads_from_async_reader() {
+    let mut schema_parser = SchemaParser::new();
+    let input = "{\"name\": \"Nori\"}\n\n{\"name\": \"Rey\"}";
+    futures::executor::block_on(
+      schema_parser.read_from_async(input.as_bytes()),
+    )
+    .unwrap();
+    assert_eq!(schema_parser.count, 2);
+    assert_eq!(schema_parser.fields.len(), 1);
+  }
+
+  #[test]
+  #[cfg(feature = "async")]
+  fn it_writes_streams() {
+    let docs = futures::stream::iter(vec![
+      doc! {"name": "Nori", "type": "Cat"},
+      doc! {"name": "Rey", "type": "Dog"},
+    ]);
+    let mut schema_parser = SchemaParser::new();
+    futures::executor::block_on(schema_parser.write_stream(docs)).unwrap();
+    assert_eq!(schema_parser.count, 2);
+    assert_eq!(schema_parser.fields.len(), 2);
+  }
+
+  #[test]
+  #[cfg(feature = "parallel")]
+  fn it_writes_batches_in_parallel() {
+    let docs: Vec<Document> = (0..1000)
+      .map(|i| match i % 3 {
+        0 => doc! {"name": "Nori", "age": i},
+        1 => doc! {"name": "Rey"},
+        _ => doc! {"name": "Chashu", "type": "Cat"},
+      })
+      .collect();
+    let mut parallel = SchemaParser::new();
+    parallel.write_batch_parallel(&docs).unwrap();
+    let mut sequential = SchemaParser::new();
+    for doc in docs {
+      sequential.write_document(doc).unwrap();
+    }
+    assert_eq!(parallel.count, 1000);
+    let names: Vec<&String> = parallel.fields.keys().collect();
+    assert_eq!(names, vec!["name", "age", "type"]);
+    let (parallel, sequential) = (parallel.flush(), sequential.flush());
+    for (field, expected) in parallel.fields().zip(sequential.fields()) {
+      assert_eq!(field.count, expected.count);
+      assert_eq!(field.bson_types, expected.bson_types);
+    }
+  }
+
+  #[test]
+  #[cfg(feature = "parallel")]
+  fn it_writes_batches_in_parallel_with_options() {
+    let docs: Vec<Document> = (0..1000)
+      .map(|i| {
+        let mut doc = Document::new();
+        match i % 3 {
+          0 => {
+            let mut owner = doc! {"name": "Ana"};
+            owner.insert(format!("k{}", i), i);
+            doc.insert("userId", i);
+            doc.insert("owner", owner);
+          }
+          1 => {
+            doc.insert("UserID", "Rey");
+            doc.insert("Owner", doc! {"Name": "Ben"});
+          }
+          _ => {
+            doc.insert(format!("k{}", i), true);
+          }
+        }
+        doc
+      })
+      .collect();
+    let builder = || {
+      SchemaParser::builder()
+        .case_insensitive_keys(true)
+        .max_fields(3)
+        .build()
+    };
+    let mut parallel = builder();
+    parallel.write_batch_parallel(&docs).unwrap();
+    let mut sequential = builder();
+    for doc in docs {
+      sequential.write_document(doc).unwrap();
+    }
+    let fields = |schema_parser: &SchemaParser| {
+      let schema = schema_parser.flush();
+      let fields: Vec<_> = schema
+        .iter_fields()
+        .map(|(path, field)| {
+          let types = field.bson_types.clone();
+          let spellings = field.spellings.clone();
+          (path.to_string(), field.count, types, spellings)
+        })
+        .collect();
+      fields
+    };
+    let expected = fields(&sequential);
+    assert_eq!(expected[0].0, "userId");
+    assert_eq!(expected[0].3, vec!["UserID"]);
+    assert_eq!(expected.len(), 6);
+    assert_eq!(fields(&parallel), expected);
+  }
+
+  #[test]
+  #[cfg(feature = "parallel")]
+  fn it_takes_snapshots_of_parallel_batches() {
+    let docs: Vec<Document> = (0..10).map(|age| doc! {"age": age}).collect();
+    let mut schema_parser = SchemaParser::builder()
+      .snapshot_every(SnapshotInterval::Documents(4))
+      .build();
+    schema_parser.write_document(doc! {"age": 1}).unwrap();
+    schema_parser.write_batch_parallel(&docs).unwrap();
+    let snapshots = &schema_parser.history().unwrap().snapshots;
+    let counts: Vec<usize> = snapshots.iter().map(|s| s.schema.count).collect();
+    assert_eq!(counts, vec![4, 8]);
+    assert_eq!(schema_parser.count, 11);
+  }
+
+  #[test]
+  fn it_errors_reading_invalid_line() {
+    let mut schema_parser = SchemaParser::new();
+    let input = "{\"name\": \"Nori\"}\nnot json\n";
+    assert!(schema_parser.read_from(input.as_bytes()).is_err());
+    assert_eq!(schema_parser.count, 1);
+  }
+
+  #[test]
+  fn it_writes_many_lines() {
+    let mut schema_parser = SchemaParser::new();
+    let json = "{\"name\": \"Nori\"}\n\nnot json\n  \n42\r\n{\"age\": 5}";
+    let errors = schema_parser.write_many(json);
+    assert_eq!(schema_parser.count, 2);
+    assert_eq!(schema_parser.fields.len(), 2);
+    let lines: Vec<usize> = errors.iter().map(|e| e.line).collect();
+    assert_eq!(lines, vec![3, 5]);
+    match errors[1].error {
+      ParseError::NotADocument => (),
+      ref other => panic!("expected NotADocument, got {:?}", other),
+    }
+    assert!(schema_parser.write_many("").is_empty());
+  }
+
+  #[test]
+  fn it_collects_documents() {
+    let docs = vec![
+      doc! {"name": "Nori", "type": "Cat"},
+      doc! {"name": "Rey", "type": "Dog"},
+    ];
+    let mut schema_parser: SchemaParser = docs.into_iter().collect();
+    assert_eq!(schema_parser.count, 2);
+    schema_parser.extend(vec![doc! {"name": "Chashu", "age": 4}]);
+    assert_eq!(schema_parser.count, 3);
+    assert_eq!(schema_parser.fields.len(), 3);
+  }
+
+  #[test]
+  fn it_takes_snapshots_of_collected_documents() {
+    let mut schema_parser = SchemaParser::builder()
+      .snapshot_every(SnapshotInterval::Documents(2))
+      .build();
+    schema_parser.extend((0..5).map(|age| doc! {"age": age}));
+    let snapshots = &schema_parser.history().unwrap().snapshots;
+    let counts: Vec<usize> = snapshots.iter().map(|s| s.schema.count).collect();
+    assert_eq!(counts, vec![2, 4]);
+  }
+
+  #[test]
+  fn it_limits_tracked_fields() {
+    let mut schema_parser = SchemaParser::builder().max_fields(2).build();
+    let json_str = r#"{"name": "Nori", "type": "Cat", "age": 4}"#;
+    schema_parser.write_json(&json_str).unwrap();
+    schema_parser.write_json(r#"{"name": "Rey", "age": 2}"#).unwrap();
+    assert_eq!(schema_parser.fields.len(), 2);
+    assert_eq!(schema_parser.fields.get("name").unwrap().count, 2);
+    assert!(schema_parser.fields.get("age").is_none());
+  }
+
+  #[test]
+  fn it_limits_nesting_depth() {
+    let mut schema_parser = SchemaParser::builder().max_depth(1).build();
+    let json_str = r#"{"owner": {"address": {"city": "Berlin"}}}"#;
+    schema_parser.write_json(&json_str).unwrap();
+    schema_parser.write_json(&json_str).unwrap();
+    let owner = schema_parser.fields.get("owner").unwrap();
+    let owner_doc = owner.types.get(crate::field_type::DOCUMENT).unwrap();
+    let owner_schema = owner_doc.schema.as_ref().unwrap();
+    let address = owner_schema.fields.get("owner.address").unwrap();
+    let address_doc = address.types.get(crate::field_type::DOCUMENT).unwrap();
+    assert_eq!(address_doc.count, 2);
+    assert!(address_doc.schema.is_none());
+  }
+
+  #[test]
+  fn it_returns_typed_errors() {
+    let mut schema_parser = SchemaParser::new();
+    match schema_parser.write_json("{ not json") {
+      Err(ParseError::InvalidJson(_)) => (),
+      other => panic!("expected InvalidJson, got {:?}", other),
+    }
+    match schema_parser.write_json(r#"{"_id": {"$oid": 1234}}"#) {
+      Err(ParseError::InvalidExtendedJson(_)) => (),
+      other => panic!("expected InvalidExtendedJson, got {:?}", other),
+    }
+    match schema_parser.write_raw(&[1, 2, 3]) {
+      Err(ParseError::InvalidBson(_)) => (),
+      other => panic!("expected InvalidBson, got {:?}", other),
+    }
+    assert_eq!(schema_parser.count, 0);
+  }
+
+  #[test]
+  fn it_errors_on_non_document_json() {
+    let mut schema_parser = SchemaParser::new();
+    for json in &["42", "[1, 2]", "null", r#""Nori""#] {
+      match schema_parser.write_json(json) {
+        Err(ParseError::NotADocument) => (),
+        other => panic!("expected NotADocument, got {:?}", other),
+      }
+    }
+    assert_eq!(schema_parser.count, 0);
+  }
+
+  #[test]
+  fn it_writes_json_values() {
+    let mut schema_parser = SchemaParser::new();
+    let value = serde_json::json!({
+      "name": "Nori",
+      "born": {"$date": 1542321000000i64}
+    });
+    schema_parser.write_value(value).unwrap();
+    let born = schema_parser.fields.get("born").unwrap();
+    assert_eq!(
+      born.bson_types,
+      vec![crate::field_type::UTCDATE_TIME.to_string()]
+    );
+    match schema_parser.write_value(serde_json::json!([1, 2])) {
+      Err(ParseError::NotADocument) => (),
+      other => panic!("expected NotADocument, got {:?}", other),
+    }
+    assert_eq!(schema_parser.count, 1);
+  }
+
+  #[test]
+  fn it_writes_json_array() {
+    let mut schema_parser = SchemaParser::new();
+    let json = r#"[
+      {"name": "Nori", "age": 5},
+      {"name": "Rey", "born": {"$date": 1542321000000}}
+    ]"#;
+    schema_parser.write_json_array(json).unwrap();
+    assert_eq!(schema_parser.count, 2);
+    assert_eq!(schema_parser.fields.len(), 3);
+    schema_parser.write_json_array("[]").unwrap();
+    assert_eq!(schema_parser.count, 2);
+  }
+
+  #[test]
+  fn it_errors_on_invalid_json_array() {
+    let mut schema_parser = SchemaParser::new();
+    match schema_parser.write_json_array(r#"{"name": "Nori"}"#) {
+      Err(ParseError::InvalidJson(_)) => (),
+      other => panic!("expected InvalidJson, got {:?}", other),
+    }
+    match schema_parser.write_json_array(r#"[{"name": "Nori"}, 42]"#) {
+      Err(ParseError::NotADocument) => (),
+      other => panic!("expected NotADocument, got {:?}", other),
+    }
+    assert_eq!(schema_parser.count, 1);
+    let truncated = schema_parser.write_json_array(r#"[{"name": "Rey"}"#);
+    assert!(truncated.is_err());
+  }
+
+  #[test]
+  fn it_wraps_non_documents_under_root_field() {
+    let mut schema_parser = SchemaParser::builder().root_field("value").build();
+    schema_parser.write_json("42").unwrap();
+    schema_parser.write_json("[1, 2]").unwrap();
+    schema_parser.write_json(r#"{"value": "Nori"}"#).unwrap();
+    assert_eq!(schema_parser.count, 3);
+    assert_eq!(schema_parser.fields.len(), 1);
+    let field = schema_parser.fields.get("value").unwrap();
+    assert_eq!(field.count, 3);
+    assert_eq!(field.types.len(), 3);
+  }
+
+  #[test]
+  fn it_writes_extended_json() {
+    let mut schema_parser = SchemaParser::new();
+    let json_str = r#"{"_id": {"$oid": "5bd761dcae323e45a93ccff1"}, "saleDate": {"$date": {"$numberLong": "1427144809506"}}}"#;
+    schema_parser.write_json(&json_str).unwrap();
+    let id = schema_parser.fields.get("_id").unwrap();
+    assert_eq!(id.bson_types, vec![crate::field_type::OBJECTID.to_string()]);
+    let date = schema_parser.fields.get("saleDate").unwrap();
+    assert_eq!(
+      date.bson_types,
+      vec![crate::field_type::UTCDATE_TIME.to_string()]
+    );
+  }
+
+  #[test]
+  fn it_writes_extjson_v1() {
+    let mut schema_parser = SchemaParser::new();
+    let json_str = r#"{"born": {"$date": 1542321000000}, "age": "NumberInt(4)"}"#;
+    schema_parser.write_extjson_v1(&json_str).unwrap();
+    let born = schema_parser.fields.get("born").unwrap();
+    assert_eq!(
+      born.bson_types,
+      vec![crate::field_type::UTCDATE_TIME.to_string()]
+    );
+    let age = schema_parser.fields.get("age").unwrap();
+    assert_eq!(age.bson_types, vec![crate::field_type::I32.to_string()]);
+  }
+
+  #[test]
+  fn it_merges() {
+    let mut schema_parser = SchemaParser::new();
+    schema_parser
+      .write_json(r#"{"name": "Nori", "type": "Cat"}"#)
+      .unwrap();
+    let mut other = SchemaParser::new();
+    other.write_json(r#"{"name": "Rey", "age": 4}"#).unwrap();
+    other.write_json(r#"{"name": 1234}"#).unwrap();
+    schema_parser.merge(other);
+
+    assert_eq!(schema_parser.count, 3);
+    assert_eq!(schema_parser.fields.len(), 3);
+    let name = schema_parser.fields.get("name").unwrap();
+    assert_eq!(name.count, 3);
+    assert_eq!(name.types.len(), 2);
+    let string_type = name.types.get("String").unwrap();
+    assert_eq!(string_type.count, 2);
+    assert_eq!(string_type.values.len(), 2);
+  }
+
+  #[test]
+  fn it_merges_nested_documents() {
+    let mut schema_parser = SchemaParser::new();
+    schema_parser
+      .write_json(r#"{"type": {"breed": "Norwegian Forest"}}"#)
+      .unwrap();
+    let mut other = SchemaParser::new();
+    other
+      .write_json(r#"{"type": {"breed": "Viszla", "size": "medium"}}"#)
+      .unwrap();
+    schema_parser.merge(other);
+
+    let type_field = schema_parser.fields.get("type").unwrap();
+    let doc = type_field.types.get(crate::field_type::DOCUMENT).unwrap();
+    assert_eq!(doc.count, 2);
+    let schema = doc.schema.as_ref().unwrap();
+    assert_eq!(schema.count, 2);
+    assert_eq!(schema.fields.len(), 2);
+  }
+
+  #[test]
+  fn it_writes_json_to_writers() {
+    let mut schema_parser = SchemaParser::new();
+    schema_parser
+      .write_json(r#"{"name": "Nori", "address": {"city": "Berlin"}}"#)
+      .unwrap();
+    let schema = schema_parser.flush();
+    let mut bytes = Vec::new();
+    schema.write_json_to(&mut bytes).unwrap();
+    assert_eq!(String::from_utf8(bytes).unwrap(), schema.to_json().unwrap());
+  }
+
+  #[test]
+  fn it_restores_from_json() {
+    let mut schema_parser = SchemaParser::new();
+    let json_str1 = r#"{
+      "name": "Nori",
+      "owner": {"name": "Ana"},
+      "price": {"$numberDecimal": "9.99"},
+      "visits": {"$numberLong": "3"}
+    }"#;
+    schema_parser.write_json(&json_str1).unwrap();
+    schema_parser.write_json(r#"{"name": null}"#).unwrap();
+    let json = schema_parser.flush().to_json().unwrap();
+
+    let restored = SchemaParser::from_json(&json).unwrap();
+    let restored_json = restored.flush().to_json().unwrap();
+    let value = |json: &str| serde_json::from_str::<Value>(json).unwrap();
+    assert_eq!(value(&restored_json), value(&json));
+
+    let types = |path: &str| &restored.fields.get(path).unwrap().types;
+    let price = types("price").get(crate::field_type::DECIMAL_128).unwrap();
+    assert_eq!(
+      price.values,
+      vec![ValueType::Decimal128("9.99".to_string())]
+    );
+    assert_eq!(price.min, Some(ValueType::Decimal128("9.99".to_string())));
+    let visits = types("visits").get(crate::field_type::I64).unwrap();
+    assert_eq!(visits.values, vec![ValueType::I64(3)]);
+    let null = types("name").get(crate::field_type::NULL).unwrap();
+    assert_eq!(null.values, vec![ValueType::Null("Null".to_string())]);
+    assert!(types("owner").get(crate::field_type::UNDEFINED).is_none());
+  }
+
+  #[test]
+  fn it_restores_types_with_skipped_fields() {
+    let mut schema_parser = SchemaParser::new();
+    let json = r#"{"name": "Nori", "toys": ["ball"], "owner": {"age": 5}}"#;
+    schema_parser.write_json(json).unwrap();
+    let json = schema_parser.flush().to_json().unwrap();
+
+    let restored = SchemaParser::from_json(&json).unwrap();
+    let restored_json = restored.flush().to_json().unwrap();
+    let value = |json: &str| serde_json::from_str::<Value>(json).unwrap();
+    assert_eq!(value(&restored_json), value(&json));
+    let types = |path: &str| &restored.fields.get(path).unwrap().types;
+    assert!(types("name")[crate::field_type::STRING].lengths.is_empty());
+    let toys = &types("toys")[crate::field_type::ARRAY];
+    assert_eq!(toys.lengths, vec![1]);
+    assert_eq!(toys.types.len(), 1);
+    let owner = &types("owner")[crate::field_type::DOCUMENT];
+    assert!(owner.types.is_empty());
+    let owner = owner.schema.as_ref().unwrap();
+    assert!(owner.fields.contains_key("owner.age"));
+  }
+
+  #[test]
+  fn it_continues_from_json() {
+    let mut schema_parser = SchemaParser::new();
+    schema_parser
+      .write_json(r#"{"name": "Nori", "owner": {"name": "Ana"}}"#)
+      .unwrap();
+    schema_parser.write_json(r#"{"name": "Rey"}"#).unwrap();
+    let json = schema_parser.into_json().unwrap();
+
+    let mut schema_parser = SchemaParser::from_json(&json).unwrap();
+    schema_parser
+      .write_json(r#"{"name": "Chashu", "owner": {"name": "Ana"}}"#)
+      .unwrap();
+    let output = schema_parser.flush();
+    assert_eq!(output.count, 3);
+
+    let name = output.fields.get("name").unwrap();
+    assert_eq!(name.bson_types, vec!["String"]);
+    let name = name.types.get("String").unwrap();
+    assert_eq!(name.count, 3);
+    assert_eq!(name.values.len(), 3);
+    assert_eq!(name.unique, Some(3));
+
+    let owner = output.fields.get("owner").unwrap();
+    assert_eq!(owner.count, 3);
+    let undefined = owner.types.get(crate::field_type::UNDEFINED).unwrap();
+    assert_eq!(undefined.count, 1);
+    let doc = owner.types.get(crate::field_type::DOCUMENT).unwrap();
+    assert_eq!(doc.count, 2);
+    assert_eq!(doc.schema.as_ref().unwrap().count, 2);
+  }
+
+  #[test]
+  fn it_errors_restoring_invalid_json() {
+    match SchemaParser::from_json(r#"{"count": "Nori"}"#) {
+      Err(ParseError::InvalidJson(_)) => (),
+      result => panic!("expected invalid json, got {:?}", result),
+    }
+  }
+
+  #[test]
+  #[allow(clippy::float_cmp)]
+  fn it_unions_schemas() {
+    let mut schema_parser = SchemaParser::new();
+    schema_parser
+      .write_json(r#"{"name": "Nori", "owner": {"name": "Ana"}}"#)
+      .unwrap();
+    let mut other = SchemaParser::new();
+    other.write_json(r#"{"name": "Rey", "age": 4}"#).unwrap();
+    other.write_json(r#"{"name": 1234}"#).unwrap();
+    other.write_json(r#"{"name": "Nori"}"#).unwrap();
+
+    let other_json = other.flush().to_json().unwrap();
+    let other_schema: Schema = serde_json::from_str(&other_json).unwrap();
+    let schema = schema_parser.flush().union(&other_schema);
+    assert_eq!(schema.count, 4);
+
+    let name = schema.fields.get("name").unwrap();
+    assert_eq!(name.count, 4);
+    assert_eq!(name.probability, 1.0);
+    let string_type = name.types.get("String").unwrap();
+    assert_eq!(string_type.count, 3);
+    assert_eq!(string_type.probability, 0.75);
+    assert_eq!(string_type.values.len(), 3);
+
+    let owner = schema.fields.get("owner").unwrap();
+    assert_eq!(owner.probability, 0.25);
+    let undefined = owner.types.get(crate::field_type::UNDEFINED).unwrap();
+    assert_eq!(undefined.count, 3);
+    let age = schema.fields.get("age").unwrap();
+    assert_eq!(age.probability, 0.25);
+  }
+
+  #[test]
+  fn it_unions_schema_with_itself() {
+    let mut schema_parser = SchemaParser::new();
+    schema_parser.write_json(r#"{"name": "Nori"}"#).unwrap();
+    schema_parser.write_json(r#"{"name": "Rey"}"#).unwrap();
+    let schema = schema_parser.flush();
+    let union = schema.union(&schema);
+    assert_eq!(union.count, 4);
+    let name = union.fields.get("name").unwrap().types.get("String").unwrap();
+    assert_eq!(name.count, 4);
+    assert_eq!(name.unique, Some(2));
+    assert!(name.has_duplicates);
+  }
+
+  #[test]
+  fn it_gets_fields_by_path() {
+    let mut schema_parser = SchemaParser::new();
+    schema_parser
+      .write_json(
+        r#"{"address": {"geo": {"lat": 52.5}}, "visits": [[{"vet": "Ana"}]]}"#,
+      )
+      .unwrap();
+    let lat = schema_parser.get_field("address.geo.lat").unwrap();
+    assert_eq!(&*lat.path, "address.geo.lat");
+    assert_eq!(lat.count, 1);
+    let vet = schema_parser.get_field("visits.vet").unwrap();
+    assert_eq!(vet.bson_types, vec!["String"]);
+    let address = schema_parser.get_field("address").unwrap();
+    assert_eq!(&*address.name, "address");
+    assert!(schema_parser.get_field("address.geo.lng").is_none());
+    assert!(schema_parser.get_field("visits.vet.name").is_none());
+    assert!(schema_parser.flush().get_field("address.geo").is_some());
+  }
+
+  #[test]
+  fn it_iterates_fields_depth_first() {
+    let mut schema_parser = SchemaParser::new();
+    schema_parser
+      .write_json(r#"{"owner": {"geo": {"lat": 1.5}}, "visits": [{"vet": 1}]}"#)
+      .unwrap();
+    schema_parser
+      .write_json(r#"{"owner": [{"geo": {"lat": 2.5}}], "name": "Rey"}"#)
+      .unwrap();
+    let schema = schema_parser.flush();
+    let paths: Vec<&str> = schema.iter_fields().map(|(path, _)| path).collect();
+    assert_eq!(
+      paths,
+      vec![
+        "owner",
+        "owner.geo",
+        "owner.geo.lat",
+        "visits",
+        "visits.vet",
+        "name"
+      ]
+    );
+    let (_, lat) = schema.iter_fields().nth(2).unwrap();
+    assert_eq!(&*lat.name, "lat");
+  }
+
+  #[test]
+  fn it_prunes_when_finalising() {
+    let mut schema_parser =
+      SchemaParser::builder().min_probability(0.5).build();
+    let documents = [
+      r#"{"name": "Nori"}"#,
+      r#"{"name": "Rey", "x1f3": 1}"#,
+      r#"{"name": "Lox", "x9a2": 2}"#,
+    ];
+    for json in documents.iter() {
+      schema_parser.write_json(json).unwrap();
+    }
+    let schema = schema_parser.flush();
+    let keys: Vec<&String> = schema.fields.keys().collect();
+    assert_eq!(keys, vec!["name"]);
+    // the parser itself still has every field
+    assert_eq!(schema_parser.fields().count(), 3);
+  }
+
+  #[test]
+  fn it_merges_case_variant_keys() {
+    let mut schema_parser =
+      SchemaParser::builder().case_insensitive_keys(true).build();
+    let documents = [
+      r#"{"userId": 1, "owner": {"Name": "Ana"}}"#,
+      r#"{"userID": 2, "Owner": {"name": "Ben"}}"#,
+      r#"{"UserId": 3}"#,
+    ];
+    for json in documents.iter() {
+      schema_parser.write_json(json).unwrap();
+    }
+    let keys: Vec<&String> = schema_parser.fields.keys().collect();
+    assert_eq!(keys, vec!["userId", "owner"]);
+    let user_id = schema_parser.get_field("userId").unwrap();
+    assert_eq!(user_id.count, 3);
+    assert_eq!(user_id.spellings, vec!["userID", "UserId"]);
+    let name = schema_parser.get_field("owner.Name").unwrap();
+    assert_eq!(name.count, 2);
+    assert_eq!(name.spellings, vec!["name"]);
+
+    let mut schema_parser = SchemaParser::new();
+    schema_parser.write_json(documents[1]).unwrap();
+    schema_parser.write_json(documents[2]).unwrap();
+    assert_eq!(schema_parser.fields().count(), 3);
+  }
+
+  #[test]
+  #[allow(clippy::float_cmp)]
+  fn it_counts_case_variant_keys_once_per_document() {
+    let mut schema_parser =
+      SchemaParser::builder().case_insensitive_keys(true).build();
+    let json = r#"{"Name": 1, "name": 2}"#;
+    schema_parser.write_json(json).unwrap();
+    schema_parser.write_json(r#"{"age": 3}"#).unwrap();
+    let name = schema_parser.get_field("Name").unwrap();
+    assert_eq!(name.count, 1);
+    assert_eq!(name.spellings, vec!["name"]);
+    let schema = schema_parser.flush();
+    let name = schema.get_field("Name").unwrap();
+    assert_eq!(name.probability, 0.5);
+    assert_eq!(name.types["Int32"].count, 1);
+    assert_eq!(name.types["Undefined"].count, 1);
+  }
+
+  #[test]
+  #[allow(clippy::float_cmp)]
+  fn it_coalesces_numbers() {
+    let documents = [
+      r#"{"age": 4, "scores": [1, 2.5], "price": {"$numberDecimal": "9.99"}}"#,
+      r#"{"age": 5.5, "scores": [3], "price": 10}"#,
+      r#"{"age": "six"}"#,
+    ];
+    let mut schema_parser =
+      SchemaParser::builder().coalesce_numbers(true).build();
+    for json in documents.iter() {
+      schema_parser.write_json(json).unwrap();
+    }
+    let schema = schema_parser.flush();
+    let age = schema.get_field("age").unwrap();
+    assert_eq!(age.bson_types, vec!["Number", "String"]);
+    let number = &age.types["Number"];
+    assert_eq!(number.count, 2);
+    assert_eq!(number.probability, 2.0 / 3.0);
+    assert_eq!(number.types["Int32"].probability, 0.5);
+    assert_eq!(number.types["Double"].probability, 0.5);
+    assert_eq!(number.values.len(), 2);
+    assert!(number.values.contains(&ValueType::I32(4)));
+    assert!(number.values.contains(&ValueType::FloatingPoint(5.5)));
+
+    let scores = &schema.get_field("scores").unwrap().types["Array"];
+    let elements: Vec<&String> = scores.types.keys().collect();
+    assert_eq!(elements, vec!["Number"]);
+    assert_eq!(scores.types["Number"].count, 3);
+    assert_eq!(scores.types["Number"].values.len(), 3);
+
+    let price = schema.get_field("price").unwrap();
+    assert_eq!(price.bson_types, vec!["Decimal128", "Number", "Undefined"]);
+
+    let mut schema_parser = SchemaParser::builder()
+      .coalesce_numbers(true)
+      .coalesce_decimal128(true)
+      .build();
+    for json in documents.iter() {
+      schema_parser.write_json(json).unwrap();
+    }
+    let schema = schema_parser.flush();
+    let price = schema.get_field("price").unwrap();
+    assert_eq!(price.bson_types, vec!["Number", "Undefined"]);
+    assert_eq!(price.types["Number"].types.len(), 2);
+  }
+
+  #[test]
+  fn it_infers_decimal128() {
+    let mut schema_parser = SchemaParser::new();
+    for price in &["9.99", "12.50", "1.5"] {
+      let json = format!(r#"{{"price": {{"$numberDecimal": "{}"}}}}"#, price);
+      schema_parser.write_json(&json).unwrap();
+    }
+    let output = schema_parser.flush();
+    let price = output.fields.get("price").unwrap();
+    assert_eq!(price.bson_types, vec!["Decimal128"]);
+    let decimal = price.types.get("Decimal128").unwrap();
+    assert_eq!(decimal.count, 3);
+    assert_eq!(decimal.values.len(), 3);
+    assert_eq!(decimal.min, Some(ValueType::Decimal128("1.5".to_string())));
+    assert_eq!(decimal.max, Some(ValueType::Decimal128("12.50".to_string())));
+  }
+
+  #[test]
+  fn it_extracts_object_id_creation_range() {
+    let mut schema_parser = SchemaParser::new();
+    let ids = [
+      "5d6e0e1bcf6d4fe581014ab3",
+      "5d505646cf6d4fe581014ab2",
+      "5d5a1e2ecf6d4fe581014ab4",
+    ];
+    for id in &ids {
+      let json = format!(r#"{{"_id": {{"$oid": "{}"}}}}"#, id);
+      schema_parser.write_json(&json).unwrap();
+    }
+    let output = schema_parser.flush();
+    let id = output.fields.get("_id").unwrap().types.get("ObjectId").unwrap();
+    assert_eq!(id.min, Some(ValueType::Str(ids[1].to_string())));
+    assert_eq!(id.max, Some(ValueType::Str(ids[0].to_string())));
+    let created = id.created.as_ref().unwrap();
+    assert_eq!(created.min, "2019-08-11T17:54:14+00:00");
+    assert_eq!(created.max, "2019-09-03T06:54:19+00:00");
+  }
+
+  #[test]
+  fn it_flags_object_ids_stored_as_strings() {
+    let mut schema_parser = SchemaParser::new();
+    let json_str1 = r#"{"owner_id": "5d505646cf6d4fe581014ab2", "name": "Nori"}"#;
+    let json_str2 = r#"{"owner_id": "5D6E0E1BCF6D4FE581014AB3", "name": "Rey"}"#;
+    schema_parser.write_json(&json_str1).unwrap();
+    schema_parser.write_json(&json_str2).unwrap();
+    let output = schema_parser.flush();
+    let owner_id = output.fields.get("owner_id").unwrap();
+    let owner_id = owner_id.types.get("String").unwrap();
+    assert_eq!(owner_id.object_id_strings, 2);
+    assert!(owner_id.is_object_id_string());
+    let name = output.fields.get("name").unwrap().types.get("String").unwrap();
+    assert!(!name.is_object_id_string());
+  }
+
   #[test]
   fn it_creates_field_type_for_null() {
     let mut schema_parser = SchemaParser::new();
@@ -748,4 +3008,21 @@ mod tests {
       assert_eq!(field.bson_types[0], "Null");
     }
   }
+
+  #[test]
+  fn it_bounds_numbers_in_validator() {
+    let mut schema_parser =
+      SchemaParser::builder().numeric_bounds(true).build();
+    schema_parser.write_json(r#"{"age": 3}"#).unwrap();
+    schema_parser.write_json(r#"{"age": 12}"#).unwrap();
+    let validator = schema_parser.to_mongodb_validator();
+    let age = validator
+      .get_document("$jsonSchema")
+      .and_then(|schema| schema.get_document("properties"))
+      .and_then(|properties| properties.get_document("age"))
+      .unwrap();
+    // json numbers are converted to Bson as longs
+    assert_eq!(age.get("minimum"), Some(&Bson::I64(3)));
+    assert_eq!(age.get("maximum"), Some(&Bson::I64(12)));
+  }
 }
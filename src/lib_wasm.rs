@@ -1,10 +1,143 @@
-use super::SchemaParser;
+use super::{ParseError, SchemaParser};
 use failure::format_err;
-use js_sys::{Object, Uint8Array};
+use js_sys::{Function, Object, Promise, Reflect, Uint8Array};
+use std::io::{self, Write};
 use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::{future_to_promise, JsFuture};
+use web_sys::{ReadableStream, ReadableStreamDefaultReader};
 
 use console_error_panic_hook;
 
+// TypeScript definitions of the objects returned to JavaScript, which
+// wasm-bindgen can only describe as `any` on its own.
+#[wasm_bindgen(typescript_custom_section)]
+const SCHEMA_TYPES: &str = r#"
+/** Name of a type a field was seen with. */
+export type BsonType =
+  | "Double"
+  | "String"
+  | "Document"
+  | "Array"
+  | "BinData"
+  | "Undefined"
+  | "ObjectId"
+  | "Boolean"
+  | "UtcDatetime"
+  | "Null"
+  | "Regex"
+  | "JavaScriptCode"
+  | "Symbol"
+  | "JavaScriptCodeWithScope"
+  | "Int32"
+  | "Timestamp"
+  | "Long"
+  | "Decimal128"
+  | "Number";
+
+/** A sampled value of a type, also used for its `min` and `max`. */
+export type SchemaValue =
+  | string
+  | number
+  | boolean
+  | SchemaValue[]
+  | { sub_type: number; length: number }
+  | { t: number; i: number }
+  | { pattern: string; options: string };
+
+/** One of the types a field was seen with. */
+export interface SchemaType {
+  path: string;
+  count: number;
+  bson_type: BsonType;
+  name: string;
+  probability: number;
+  values?: SchemaValue[];
+  /** Most frequent values, most frequent first, with their counts. */
+  top_values?: { value: SchemaValue; count: number }[];
+  /** Every value of an enum candidate, sorted. */
+  enum_values?: SchemaValue[];
+  lengths?: number[];
+  /** For Arrays, their numbers of elements, and how many had each. */
+  array_lengths?: {
+    min: number;
+    max: number;
+    average: number;
+    distribution: { [length: string]: number };
+  };
+  has_duplicates: boolean;
+  /** How many values were empty Strings, Arrays or Documents. */
+  empty?: number;
+  /** Fields of subdocuments, and of documents in arrays. */
+  schema?: Schema;
+  /** Types of the elements of arrays. */
+  types?: { [bsonType: string]: SchemaType };
+  unique?: number;
+  /** Whether unique is estimated rather than counted exactly. */
+  unique_approximate?: boolean;
+  min?: SchemaValue;
+  max?: SchemaValue;
+  /** For numeric types, the mean of the numbers seen. */
+  mean?: number;
+  /** For numeric types, the standard deviation of the numbers seen. */
+  stddev?: number;
+  /** For ObjectIds, the range of creation times embedded in them. */
+  created?: { min: string; max: string };
+  /** For dates, the earliest and latest dates seen. */
+  date_range?: { min: string; max: string };
+  /** For dates, how many fall into each bucket, by the start of the bucket. */
+  date_histogram?: { [bucket: string]: number };
+  /** For Booleans, how many values were true and how many false. */
+  booleans?: { true_count: number; false_count: number; true_ratio: number };
+  /** For Strings, how many values look like an ObjectId hex. */
+  object_id_strings?: number;
+  /** For Strings, their lengths in characters and in bytes. */
+  string_lengths?: {
+    chars: { min: number; max: number; average: number };
+    bytes: { min: number; max: number; average: number };
+  };
+  /** For numeric types, percentiles of the numbers seen. */
+  percentiles?: {
+    min: number;
+    p50: number;
+    p90: number;
+    p99: number;
+    max: number;
+  };
+  /** For numeric types, how many numbers fall into each bucket. */
+  histogram?: { min: number; max: number; count: number }[];
+}
+
+/** A field, and the types it was seen with. */
+export interface SchemaField {
+  name: string;
+  path: string;
+  count: number;
+  bson_types: BsonType[];
+  probability: number;
+  required: boolean;
+  types: { [bsonType: string]: SchemaType };
+  spellings?: string[];
+  total_bytes: number;
+  average_bytes: number;
+}
+
+/** The schema of all documents written to a SchemaParser. */
+export interface Schema {
+  count: number;
+  fields: { [name: string]: SchemaField };
+}
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+  #[wasm_bindgen(typescript_type = "Schema")]
+  pub type JsSchema;
+
+  #[wasm_bindgen(typescript_type = "SchemaField")]
+  pub type JsSchemaField;
+}
+
 // Need to wrap schema parser impl for wasm suppport.
 // Here we are wrapping the exported to JS land methods and mathing on Result to
 // turn the error message to JsValue.
@@ -25,6 +158,25 @@ impl SchemaParser {
     Self::new()
   }
 
+  /// Wrapper method for `SchemaParser::from_json()` to be used in JavaScript.
+  /// `wasm_bindgen(js_name = "fromJson")`
+  ///
+  /// ```js, ignore
+  /// import { SchemaParser } from "mongodb-schema-parser"
+  ///
+  /// // continue from a schema saved with toJson()
+  /// var schemaParser = SchemaParser.fromJson(savedSchema)
+  /// schemaParser.writeJson("{"name": "Nori", "type": "Cat"}")
+  /// ````
+  #[wasm_bindgen(js_name = "fromJson")]
+  pub fn wasm_from_json(json: &str) -> Result<SchemaParser, JsValue> {
+    console_error_panic_hook::set_once();
+    match Self::from_json(json) {
+      Err(e) => Err(JsValue::from_str(&format!("{}", e))),
+      Ok(schema_parser) => Ok(schema_parser),
+    }
+  }
+
   /// Wrapper method for `schema_parser.write_json()` to be used in JavaScript.
   /// `wasm_bindgen(js_name = "writeJson")`
   ///
@@ -44,9 +196,145 @@ impl SchemaParser {
     }
   }
 
+  /// Wrapper method for `schema_parser.write_value()` to be used in
+  /// JavaScript, taking a plain JavaScript object instead of a json string.
+  /// `wasm_bindgen(js_name = "writeObject")`
+  ///
+  /// ```js, ignore
+  /// import { SchemaParser } from "mongodb-schema-parser"
+  ///
+  /// var schemaParser = new SchemaParser()
+  /// var docs = await fetch("/pets").then(res => res.json())
+  /// docs.forEach(doc => schemaParser.writeObject(doc))
+  /// ````
+  #[wasm_bindgen(js_name = "writeObject")]
+  pub fn wasm_write_object(&mut self, value: JsValue) -> Result<(), JsValue> {
+    let value: serde_json::Value = serde_wasm_bindgen::from_value(value)
+      .map_err(|e| JsValue::from_str(&format!("{}", e)))?;
+    match self.write_value(value) {
+      Err(e) => Err(JsValue::from_str(&format!("{}", e))),
+      _ => Ok(()),
+    }
+  }
+
+  /// Wrapper method for `schema_parser.write_many()` to be used in
+  /// JavaScript. Returns an array of `{ line, error }` objects for the lines
+  /// that could not be written.
+  /// `wasm_bindgen(js_name = "writeMany")`
+  ///
+  /// ```js, ignore
+  /// import { SchemaParser } from "mongodb-schema-parser"
+  ///
+  /// var schemaParser = new SchemaParser()
+  /// var json = "{"name": "Nori"}\n{"name": "Rey"}\n"
+  /// var errors = schemaParser.writeMany(json)
+  /// ````
+  #[wasm_bindgen(js_name = "writeMany")]
+  pub fn wasm_write_many(&mut self, json: &str) -> Result<JsValue, JsValue> {
+    let errors: Vec<serde_json::Value> = self
+      .write_many(json)
+      .iter()
+      .map(|e| {
+        serde_json::json!({ "line": e.line, "error": format!("{}", e.error) })
+      })
+      .collect();
+    match JsValue::from_serde(&errors) {
+      Err(e) => Err(JsValue::from_str(&format!("{}", e))),
+      Ok(val) => Ok(val),
+    }
+  }
+
+  /// Reads newline delimited json from a web `ReadableStream`, i.e. the body
+  /// of a `fetch()` response, and writes each line to SchemaParser. Chunks
+  /// are split into lines as they arrive, joining up lines cut off between
+  /// chunks. Returns a Promise of the schema, as with `toObject()`. Like
+  /// `toJson()`, this consumes the SchemaParser.
+  /// `wasm_bindgen(js_name = "analyzeStream")`
+  ///
+  /// ```js, ignore
+  /// import { SchemaParser } from "mongodb-schema-parser"
+  ///
+  /// var schemaParser = new SchemaParser()
+  /// var response = await fetch("/pets.json")
+  /// var result = await schemaParser.analyzeStream(response.body)
+  /// console.log(result) //
+  /// ````
+  #[wasm_bindgen(js_name = "analyzeStream")]
+  pub fn wasm_analyze_stream(self, stream: ReadableStream) -> Promise {
+    let mut schema_parser = self;
+    future_to_promise(async move {
+      let reader: ReadableStreamDefaultReader =
+        stream.get_reader().unchecked_into();
+      let mut lines = LineBuffer::default();
+      loop {
+        let chunk = JsFuture::from(reader.read()).await?;
+        let done = Reflect::get(&chunk, &JsValue::from_str("done"))?;
+        if done.as_bool() == Some(true) {
+          break;
+        }
+        let value = Reflect::get(&chunk, &JsValue::from_str("value"))?;
+        // streams piped through a TextDecoderStream give strings
+        let bytes = match value.as_string() {
+          Some(text) => text.into_bytes(),
+          None => {
+            let uint8 = Uint8Array::new(&value);
+            let mut bytes = vec![0u8; uint8.length() as usize];
+            uint8.copy_to(&mut bytes);
+            bytes
+          }
+        };
+        for line in lines.push(&bytes) {
+          schema_parser.write_line(&line)?;
+        }
+      }
+      schema_parser.write_line(&lines.finish())?;
+      match schema_parser.to_js_object() {
+        Err(e) => Err(JsValue::from_str(&format!("{}", e))),
+        Ok(val) => Ok(val.into()),
+      }
+    })
+  }
+
+  /// Calls `callback` with the number of documents written so far every
+  /// `every` documents, i.e. to show progress while `writeMany` works
+  /// through a large export. Passing no callback stops the calls.
+  /// `wasm_bindgen(js_name = "setProgress")`
+  ///
+  /// ```js, ignore
+  /// import { SchemaParser } from "mongodb-schema-parser"
+  ///
+  /// var schemaParser = new SchemaParser()
+  /// schemaParser.setProgress(10000, count => progressBar.update(count))
+  /// schemaParser.writeMany(json)
+  /// ````
+  #[wasm_bindgen(js_name = "setProgress")]
+  pub fn wasm_set_progress(
+    &mut self,
+    every: usize,
+    callback: Option<Function>,
+  ) {
+    self.progress = callback.map(|callback| Progress {
+      every: every.max(1),
+      callback,
+    });
+  }
+
+  /// Wrapper method for `schema_parser.write_raw()` to be used in JavaScript.
+  /// `wasm_bindgen(js_name = "writeRaw")`
+  ///
+  /// ```js, ignore
+  /// import { SchemaParser } from "mongodb-schema-parser"
+  ///
+  /// var schemaParser = new SchemaParser()
+  /// var bytes = BSON.serialize({ name: "Nori", type: "Cat" })
+  /// schemaParser.writeRaw(new Uint8Array(bytes))
+  /// ````
   #[wasm_bindgen(js_name = "writeRaw")]
   pub fn wasm_write_raw(&mut self, uint8: Uint8Array) -> Result<(), JsValue> {
-    match self.write_raw(uint8) {
+    // fill up a new u8 vec with bytes we get from js
+    let mut bytes = vec![0u8; uint8.length() as usize];
+    uint8.copy_to(&mut bytes);
+    match self.write_raw(&bytes) {
       Err(e) => Err(JsValue::from_str(&format!("{}", e))),
       _ => Ok(()),
     }
@@ -87,21 +375,331 @@ impl SchemaParser {
   /// console.log(result) //
   /// ````
   #[wasm_bindgen(js_name = "toObject")]
-  pub fn wasm_to_js_object(&mut self) -> Result<Object, JsValue> {
-    self.flush();
+  pub fn wasm_to_js_object(&self) -> Result<JsSchema, JsValue> {
     match self.to_js_object() {
+      Err(e) => Err(JsValue::from_str(&format!("{}", e))),
+      Ok(val) => Ok(val.unchecked_into()),
+    }
+  }
+
+  /// Wrapper method for `schema_parser.get_field()` to be used in JavaScript.
+  /// `wasm_bindgen(js_name = "getField")`
+  ///
+  /// ```js, ignore
+  /// import { SchemaParser } from "mongodb-schema-parser"
+  ///
+  /// var schemaParser = new SchemaParser()
+  /// var json = "{"address": {"geo": {"lat": 52.5}}}"
+  /// schemaParser.writeJson(json)
+  /// // get a single field, or undefined if there's none at the path
+  /// var lat = schemaParser.getField("address.geo.lat")
+  /// console.log(lat) //
+  /// ````
+  #[wasm_bindgen(js_name = "getField")]
+  pub fn wasm_get_field(
+    &self,
+    path: &str,
+  ) -> Result<Option<JsSchemaField>, JsValue> {
+    let field = match self.get_field(path) {
+      Some(field) => field,
+      None => return Ok(None),
+    };
+    let value = serde_json::to_value(field)
+      .map_err(|e| JsValue::from_str(&format!("{}", e)))?;
+    match js_object(&value) {
+      Err(e) => Err(JsValue::from_str(&format!("{}", e))),
+      Ok(val) => Ok(Some(val.unchecked_into())),
+    }
+  }
+
+  /// Wrapper method for `schema_parser.to_json_schema()` to be used in
+  /// JavaScript.
+  /// `wasm_bindgen(js_name = "toJsonSchema")`
+  ///
+  /// ```js, ignore
+  /// import { SchemaParser } from "mongodb-schema-parser"
+  ///
+  /// var schemaParser = new SchemaParser()
+  /// var json = "{"name": "Nori", "type": "Cat"}"
+  /// schemaParser.writeJson(json)
+  /// // get the result as a JSON Schema draft-07 object
+  /// var result = schemaParser.toJsonSchema()
+  /// console.log(result) //
+  /// ````
+  #[wasm_bindgen(js_name = "toJsonSchema")]
+  pub fn wasm_to_json_schema(&self) -> Result<Object, JsValue> {
+    match js_object(&self.to_json_schema()) {
       Err(e) => Err(JsValue::from_str(&format!("{}", e))),
       Ok(val) => Ok(val),
     }
   }
 
+  /// Wrapper method for `schema_parser.to_json_compat()` to be used in
+  /// JavaScript.
+  /// `wasm_bindgen(js_name = "toJsonCompat")`
+  ///
+  /// ```js, ignore
+  /// import { SchemaParser } from "mongodb-schema-parser"
+  ///
+  /// var schemaParser = new SchemaParser()
+  /// var json = "{"name": "Nori", "type": "Cat"}"
+  /// schemaParser.writeJson(json)
+  /// // get the result in the shape of the mongodb-schema package
+  /// var result = schemaParser.toJsonCompat()
+  /// console.log(result) //
+  /// ````
+  #[wasm_bindgen(js_name = "toJsonCompat")]
+  pub fn wasm_to_json_compat(&self) -> Result<Object, JsValue> {
+    match js_object(&self.to_json_compat()) {
+      Err(e) => Err(JsValue::from_str(&format!("{}", e))),
+      Ok(val) => Ok(val),
+    }
+  }
+
+  /// Wrapper method for `schema_parser.to_typescript()` to be used in
+  /// JavaScript.
+  /// `wasm_bindgen(js_name = "toTypeScript")`
+  ///
+  /// ```js, ignore
+  /// import { SchemaParser } from "mongodb-schema-parser"
+  ///
+  /// var schemaParser = new SchemaParser()
+  /// var json = "{"name": "Nori", "type": "Cat"}"
+  /// schemaParser.writeJson(json)
+  /// // get TypeScript interfaces for the collection
+  /// var result = schemaParser.toTypeScript("pets")
+  /// console.log(result) //
+  /// ````
+  #[wasm_bindgen(js_name = "toTypeScript")]
+  pub fn wasm_to_typescript(&self, name: &str) -> String {
+    self.to_typescript(name)
+  }
+
+  /// Wrapper method for `schema_parser.to_mongoose_schema()` to be used in
+  /// JavaScript.
+  /// `wasm_bindgen(js_name = "toMongooseSchema")`
+  ///
+  /// ```js, ignore
+  /// import { SchemaParser } from "mongodb-schema-parser"
+  ///
+  /// var schemaParser = new SchemaParser()
+  /// var json = "{"name": "Nori", "type": "Cat"}"
+  /// schemaParser.writeJson(json)
+  /// // get the source of a Mongoose model for the collection
+  /// var result = schemaParser.toMongooseSchema("pets")
+  /// console.log(result) //
+  /// ````
+  #[wasm_bindgen(js_name = "toMongooseSchema")]
+  pub fn wasm_to_mongoose_schema(&self, name: &str) -> String {
+    self.to_mongoose_schema(name)
+  }
+
+  /// Serializes the schema as json a chunk at a time, calling `callback`
+  /// with each chunk as a Uint8Array of utf-8, so large schemas never need
+  /// to be held in memory as a whole string. The chunks joined up are the
+  /// same as `toJson()`'s output.
+  /// `wasm_bindgen(js_name = "writeJsonChunks")`
+  ///
+  /// ```js, ignore
+  /// import { SchemaParser } from "mongodb-schema-parser"
+  ///
+  /// var schemaParser = new SchemaParser()
+  /// schemaParser.writeMany(json)
+  /// var file = fs.createWriteStream("schema.json")
+  /// schemaParser.writeJsonChunks(chunk => file.write(chunk))
+  /// file.end()
+  /// ````
+  #[wasm_bindgen(js_name = "writeJsonChunks")]
+  pub fn wasm_write_json_chunks(
+    &self,
+    callback: Function,
+  ) -> Result<(), JsValue> {
+    let mut chunks = ChunkWriter {
+      callback,
+      buffer: Vec::with_capacity(CHUNK_SIZE),
+    };
+    let written = self
+      .flush()
+      .write_json_to(&mut chunks)
+      .and_then(|()| chunks.flush().map_err(ParseError::Io));
+    match written {
+      Err(e) => Err(JsValue::from_str(&format!("{}", e))),
+      _ => Ok(()),
+    }
+  }
+
+  fn write_line(&mut self, line: &[u8]) -> Result<(), JsValue> {
+    let line = std::str::from_utf8(line)
+      .map_err(|e| JsValue::from_str(&format!("{}", e)))?;
+    if line.trim().is_empty() {
+      return Ok(());
+    }
+    match self.write_json(line) {
+      Err(e) => Err(JsValue::from_str(&format!("{}", e))),
+      _ => Ok(()),
+    }
+  }
+
   fn to_js_object(&self) -> Result<Object, failure::Error> {
-    let js_val = JsValue::from_serde(&serde_json::to_value(&self)?)?;
-    let js_obj = Object::try_from(&js_val);
-    if let Some(js_obj) = js_obj {
-      Ok(js_obj.clone())
-    } else {
-      Err(format_err!("Cannot create JavaScript Object from Schema."))
+    let schema = self.flush();
+    js_object(&serde_json::to_value(&schema)?)
+  }
+}
+
+/// A JavaScript callback told how many documents have been written, every
+/// `every` documents.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Progress {
+  every: usize,
+  callback: Function,
+}
+
+impl Progress {
+  // Documents left to write after `count` until progress is next reported.
+  pub(crate) fn documents_until_due(&self, count: usize) -> usize {
+    self.every - count % self.every
+  }
+
+  pub(crate) fn report(&self, count: usize) {
+    if count % self.every == 0 {
+      // an exception thrown by the callback shouldn't stop the analysis
+      let _ = self
+        .callback
+        .call1(&JsValue::NULL, &JsValue::from_f64(count as f64));
     }
   }
 }
+
+// Size of the chunks `writeJsonChunks` hands to JavaScript.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Collects serialized json into chunks of `CHUNK_SIZE` bytes, and hands each
+/// to a JavaScript callback.
+struct ChunkWriter {
+  callback: Function,
+  buffer: Vec<u8>,
+}
+
+impl io::Write for ChunkWriter {
+  fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+    self.buffer.extend_from_slice(bytes);
+    if self.buffer.len() >= CHUNK_SIZE {
+      self.flush()?;
+    }
+    Ok(bytes.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    if self.buffer.is_empty() {
+      return Ok(());
+    }
+    let chunk = Uint8Array::from(self.buffer.as_slice());
+    self.buffer.clear();
+    match self.callback.call1(&JsValue::NULL, &chunk) {
+      Err(e) => Err(io::Error::new(io::ErrorKind::Other, format!("{:?}", e))),
+      _ => Ok(()),
+    }
+  }
+}
+
+/// Splits chunks of a stream into lines. A line cut off at the end of a
+/// chunk is kept until the rest of it arrives.
+#[derive(Default)]
+struct LineBuffer {
+  pending: Vec<u8>,
+}
+
+impl LineBuffer {
+  /// Returns the lines completed by `chunk`.
+  fn push(&mut self, chunk: &[u8]) -> Vec<Vec<u8>> {
+    self.pending.extend_from_slice(chunk);
+    let end = match self.pending.iter().rposition(|&b| b == b'\n') {
+      Some(newline) => newline + 1,
+      None => return Vec::new(),
+    };
+    let rest = self.pending.split_off(end);
+    let complete = std::mem::replace(&mut self.pending, rest);
+    complete[..end - 1]
+      .split(|&b| b == b'\n')
+      .map(|line| line.to_vec())
+      .collect()
+  }
+
+  /// Returns the last line, which doesn't need to end with a newline.
+  fn finish(self) -> Vec<u8> {
+    self.pending
+  }
+}
+
+fn js_object(value: &serde_json::Value) -> Result<Object, failure::Error> {
+  let js_val = JsValue::from_serde(value)?;
+  let js_obj = Object::try_from(&js_val);
+  if let Some(js_obj) = js_obj {
+    Ok(js_obj.clone())
+  } else {
+    Err(format_err!("Cannot create JavaScript Object from Schema."))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_declares_every_bson_type() {
+    use crate::field_type::*;
+    let types = &[
+      FLOATING_POINT,
+      STRING,
+      DOCUMENT,
+      ARRAY,
+      BINARY,
+      UNDEFINED,
+      OBJECTID,
+      BOOLEAN,
+      UTCDATE_TIME,
+      NULL,
+      REGEXP,
+      JAVASCRIPT_CODE,
+      SYMBOL,
+      JAVASCRIPT_CODE_WITH_SCOPE,
+      I32,
+      TIMESTAMP,
+      I64,
+      DECIMAL_128,
+      NUMBER,
+    ];
+    for bson_type in types {
+      let member = format!("| \"{}\"", bson_type);
+      assert!(SCHEMA_TYPES.contains(&member), "missing {}", bson_type);
+    }
+  }
+
+  #[test]
+  fn it_joins_lines_cut_off_between_chunks() {
+    let mut lines = LineBuffer::default();
+    assert!(lines.push(b"{\"name\": \"No").is_empty());
+    assert_eq!(
+      lines.push(b"ri\"}\n{\"name\": \"Rey\"}\n\n{\"age\""),
+      vec![
+        b"{\"name\": \"Nori\"}".to_vec(),
+        b"{\"name\": \"Rey\"}".to_vec(),
+        b"".to_vec()
+      ]
+    );
+    assert!(lines.push(b": 4}").is_empty());
+    assert_eq!(lines.finish(), b"{\"age\": 4}".to_vec());
+  }
+
+  #[test]
+  fn it_splits_multibyte_characters_between_chunks() {
+    let line = "{\"name\": \"Nöri\"}\n".as_bytes();
+    let mut lines = LineBuffer::default();
+    assert!(lines.push(&line[..12]).is_empty());
+    let complete = lines.push(&line[12..]);
+    assert_eq!(
+      std::str::from_utf8(&complete[0]),
+      Ok("{\"name\": \"Nöri\"}")
+    );
+  }
+}
@@ -0,0 +1,181 @@
+use super::{Field, FieldType, SchemaParser};
+use crate::field_type;
+
+// How many sampled values are shown per field.
+static SAMPLES: usize = 3;
+
+/// Renders finalised fields as a Markdown report, with a table of fields for
+/// the top level document followed by a table for each subdocument.
+pub(crate) fn to_markdown<'a>(
+  fields: impl Iterator<Item = &'a Field>,
+  count: usize,
+) -> String {
+  let mut markdown = String::from("# Schema\n\n");
+  markdown.push_str(&documents(count));
+  let mut sections = Vec::new();
+  markdown.push_str(&table(fields, &mut sections));
+  for (path, schema) in sections {
+    markdown.push_str(&subdocument(&path, schema));
+  }
+  markdown
+}
+
+// Nested subdocuments are listed after the subdocument they're in.
+fn subdocument(path: &str, schema: &SchemaParser) -> String {
+  let mut markdown = format!("\n## `{}`\n\n", path);
+  markdown.push_str(&documents(schema.count));
+  let mut sections = Vec::new();
+  markdown.push_str(&table(schema.fields(), &mut sections));
+  for (path, schema) in sections {
+    markdown.push_str(&subdocument(&path, schema));
+  }
+  markdown
+}
+
+fn documents(count: usize) -> String {
+  match count {
+    1 => "1 document.\n\n".to_string(),
+    _ => format!("{} documents.\n\n", count),
+  }
+}
+
+// Writes a row for each field, and collects the subdocuments of its Document
+// types, and those of its arrays' elements, into `sections`.
+fn table<'a>(
+  fields: impl Iterator<Item = &'a Field>,
+  sections: &mut Vec<(String, &'a SchemaParser)>,
+) -> String {
+  let mut table = String::from(
+    "| Field | Types | Probability | Samples | Cardinality |\n\
+     | --- | --- | --- | --- | --- |\n",
+  );
+  for field in fields {
+    let types = sorted_types(field.types());
+    let type_names: Vec<String> = types
+      .iter()
+      .map(|t| format!("{} ({})", t.bson_type, percent(t.probability)))
+      .collect();
+    // subdocuments get their own table, so only scalar values are sampled
+    let scalars: Vec<&FieldType> =
+      types.iter().cloned().filter(|t| is_scalar(t)).collect();
+    let samples: Vec<String> = scalars
+      .iter()
+      .flat_map(|t| t.values().iter())
+      .take(SAMPLES)
+      .map(|value| {
+        let value = serde_json::to_string(value).unwrap_or_default();
+        format!("`{}`", value)
+      })
+      .collect();
+    let unique: Vec<usize> = scalars.iter().filter_map(|t| t.unique).collect();
+    let approximate = scalars.iter().any(|t| t.unique_approximate);
+    let cardinality = if unique.is_empty() {
+      String::new()
+    } else if approximate {
+      format!("~{}", unique.iter().sum::<usize>())
+    } else {
+      unique.iter().sum::<usize>().to_string()
+    };
+    table.push_str(&format!(
+      "| `{}` | {} | {} | {} | {} |\n",
+      escape(&field.path),
+      type_names.join(", "),
+      percent(field.probability),
+      escape(&samples.join(", ")),
+      cardinality
+    ));
+    for field_type in types {
+      subdocuments(field_type, sections);
+    }
+  }
+  table
+}
+
+fn subdocuments<'a>(
+  field_type: &'a FieldType,
+  sections: &mut Vec<(String, &'a SchemaParser)>,
+) {
+  if let Some(schema) = field_type.schema() {
+    sections.push((field_type.path.to_string(), schema));
+  }
+  for element_type in sorted_types(field_type.types()) {
+    subdocuments(element_type, sections);
+  }
+}
+
+// Most common types first. Undefined is left out, as the field's probability
+// already shows how often it's missing.
+fn sorted_types<'a>(
+  types: impl Iterator<Item = &'a FieldType>,
+) -> Vec<&'a FieldType> {
+  let mut types: Vec<&FieldType> = types
+    .filter(|t| t.bson_type != field_type::UNDEFINED)
+    .collect();
+  types.sort_by(|a, b| {
+    b.count
+      .cmp(&a.count)
+      .then_with(|| a.bson_type.cmp(&b.bson_type))
+  });
+  types
+}
+
+fn is_scalar(field_type: &FieldType) -> bool {
+  field_type.bson_type != field_type::DOCUMENT
+    && field_type.bson_type != field_type::ARRAY
+}
+
+// Percentage with at most one decimal, i.e. `50%` or `33.3%`.
+pub(crate) fn percent(probability: f32) -> String {
+  let percent = format!("{:.1}", probability * 100.0);
+  format!("{}%", percent.trim_end_matches(".0"))
+}
+
+// Pipes would end the table cell.
+fn escape(cell: &str) -> String {
+  cell.replace('|', "\\|")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::SchemaParser;
+
+  #[test]
+  fn it_renders_markdown() {
+    let mut schema_parser = SchemaParser::new();
+    let json_str1 = r#"{"name": "Nori", "age": 5}"#;
+    let json_str2 = r#"{"name": "Rey", "age": "3", "owner": {"name": "Ana"}}"#;
+    schema_parser.write_json(&json_str1).unwrap();
+    schema_parser.write_json(&json_str2).unwrap();
+    let schema = schema_parser.flush();
+    let markdown = to_markdown(schema.fields(), schema.count);
+    assert_eq!(
+      markdown,
+      r#"# Schema
+
+2 documents.
+
+| Field | Types | Probability | Samples | Cardinality |
+| --- | --- | --- | --- | --- |
+| `name` | String (100%) | 100% | `"Nori"`, `"Rey"` | 2 |
+| `age` | Int32 (50%), String (50%) | 100% | `5`, `"3"` | 2 |
+| `owner` | Document (50%) | 50% |  |  |
+
+## `owner`
+
+1 document.
+
+| Field | Types | Probability | Samples | Cardinality |
+| --- | --- | --- | --- | --- |
+| `owner.name` | String (100%) | 100% | `"Ana"` | 1 |
+"#
+    );
+  }
+
+  #[test]
+  fn it_formats_percentages() {
+    assert_eq!(percent(1.0), "100%");
+    assert_eq!(percent(0.5), "50%");
+    assert_eq!(percent(1.0 / 3.0), "33.3%");
+  }
+}
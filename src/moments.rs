@@ -0,0 +1,105 @@
+/// Running mean and variance of a type's numbers, updated one number at a
+/// time with Welford's algorithm, which stays accurate where summing squares
+/// wouldn't. Infinite and NaN values are left out.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Moments {
+  count: f64,
+  mean: f64,
+  // sum of squared differences from the mean
+  m2: f64,
+}
+
+impl Moments {
+  /// Restores the moments of `count` numbers from their mean and standard
+  /// deviation.
+  pub fn restore(mean: f64, stddev: f64, count: usize) -> Self {
+    let count = count as f64;
+    Moments {
+      count,
+      mean,
+      m2: stddev * stddev * count,
+    }
+  }
+
+  pub fn insert(&mut self, value: f64) {
+    if !value.is_finite() {
+      return;
+    }
+    self.count += 1.0;
+    let delta = value - self.mean;
+    self.mean += delta / self.count;
+    self.m2 += delta * (value - self.mean);
+  }
+
+  // Combines both sides' moments as if all their numbers had been inserted
+  // into one, after Chan et al.
+  pub fn merge(&mut self, other: &Moments) {
+    if other.count == 0.0 {
+      return;
+    }
+    let count = self.count + other.count;
+    let delta = other.mean - self.mean;
+    self.mean += delta * other.count / count;
+    self.m2 += other.m2 + delta * delta * self.count * other.count / count;
+    self.count = count;
+  }
+
+  pub fn mean(&self) -> Option<f64> {
+    if self.count == 0.0 {
+      return None;
+    }
+    Some(self.mean)
+  }
+
+  /// Population standard deviation of the numbers inserted.
+  pub fn stddev(&self) -> Option<f64> {
+    if self.count == 0.0 {
+      return None;
+    }
+    Some((self.m2 / self.count).sqrt())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn moments_of(values: &[f64]) -> Moments {
+    let mut moments = Moments::default();
+    for value in values {
+      moments.insert(*value);
+    }
+    moments
+  }
+
+  #[test]
+  fn it_computes_mean_and_stddev() {
+    let moments = moments_of(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]);
+    assert_eq!(moments.mean(), Some(5.0));
+    assert_eq!(moments.stddev(), Some(2.0));
+  }
+
+  #[test]
+  fn it_leaves_out_nan_and_infinity() {
+    let moments = moments_of(&[1.0, f64::NAN, f64::INFINITY, 3.0]);
+    assert_eq!(moments.mean(), Some(2.0));
+    assert_eq!(Moments::default().mean(), None);
+  }
+
+  #[test]
+  fn it_merges() {
+    let mut moments = moments_of(&[2.0, 4.0, 4.0, 4.0]);
+    moments.merge(&moments_of(&[5.0, 5.0, 7.0, 9.0]));
+    assert_eq!(moments.mean(), Some(5.0));
+    assert_eq!(moments.stddev(), Some(2.0));
+  }
+
+  #[test]
+  fn it_restores() {
+    let moments = moments_of(&[2.0, 4.0, 4.0, 4.0]);
+    let mut restored =
+      Moments::restore(moments.mean().unwrap(), moments.stddev().unwrap(), 4);
+    restored.merge(&moments_of(&[5.0, 5.0, 7.0, 9.0]));
+    assert!((restored.stddev().unwrap() - 2.0).abs() < 1e-9);
+  }
+}
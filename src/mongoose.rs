@@ -0,0 +1,211 @@
+use super::{Field, FieldType};
+use crate::field_type;
+use crate::typescript::{pascal_case, property_name};
+
+/// Converts finalised fields into the source of a Mongoose model. The model
+/// is called `name`, and each subdocument gets its own schema named after its
+/// path, declared before the schema that uses it. Fields present in at least
+/// `required_threshold` of the documents and never null are `required`.
+pub(crate) fn to_mongoose_schema<'a>(
+  fields: impl Iterator<Item = &'a Field>,
+  name: &str,
+  required_threshold: f32,
+) -> String {
+  let mut writer = Writer {
+    root: pascal_case(name),
+    schemas: Vec::new(),
+    required_threshold,
+  };
+  let root = writer.root.clone();
+  let model = writer.schema(fields, &root, true);
+
+  let mut mongoose = String::from("const mongoose = require(\"mongoose\");\n");
+  mongoose.push_str("const { Schema } = mongoose;\n\n");
+  for schema in writer.schemas {
+    mongoose.push_str(&schema);
+    mongoose.push('\n');
+  }
+  mongoose.push_str(&format!(
+    "module.exports = mongoose.model({:?}, {});\n",
+    root, model
+  ));
+  mongoose
+}
+
+struct Writer {
+  root: String,
+  schemas: Vec<String>,
+  required_threshold: f32,
+}
+
+impl Writer {
+  // Writes the schema for `fields` after the schemas of its subdocuments, and
+  // returns the name of its variable, i.e. `petsOwnerSchema` for `owner`.
+  fn schema<'a>(
+    &mut self,
+    fields: impl Iterator<Item = &'a Field>,
+    name: &str,
+    is_root: bool,
+  ) -> String {
+    let variable = format!("{}Schema", camel_case(name));
+    let mut has_id = false;
+    let mut paths = String::new();
+    for field in fields {
+      has_id |= &*field.name == "_id";
+      // Mongoose adds an ObjectId `_id` to models itself
+      if is_root && &*field.name == "_id" && is_object_id(field) {
+        continue;
+      }
+      let nullable = field.types().any(|t| is_nullable(&t.bson_type));
+      let required = !nullable && field.probability >= self.required_threshold;
+      let schema_type = self.schema_type(field.types());
+      let required = if required { ", required: true" } else { "" };
+      paths.push_str(&format!(
+        "  {}: {{ type: {}{} }},\n",
+        property_name(&field.name),
+        schema_type,
+        required
+      ));
+    }
+
+    // subdocuments only get an `_id` if the documents have one
+    let options = if is_root || has_id {
+      ""
+    } else {
+      ", { _id: false }"
+    };
+    self.schemas.push(format!(
+      "const {} = new Schema({{\n{}}}{});\n",
+      variable, paths, options
+    ));
+    variable
+  }
+
+  // Fields with several types are Mixed.
+  fn schema_type<'a>(
+    &mut self,
+    types: impl Iterator<Item = &'a FieldType>,
+  ) -> String {
+    let mut schema_types: Vec<String> = Vec::new();
+    for field_type in types {
+      if is_nullable(&field_type.bson_type) {
+        continue;
+      }
+      let schema_type = self.mongoose_type(field_type);
+      if !schema_types.contains(&schema_type) {
+        schema_types.push(schema_type);
+      }
+    }
+    match schema_types.len() {
+      1 => schema_types.remove(0),
+      _ => "Schema.Types.Mixed".to_string(),
+    }
+  }
+
+  fn mongoose_type(&mut self, field_type: &FieldType) -> String {
+    let mongoose_type = match field_type.bson_type.as_str() {
+      t if t == field_type::DOCUMENT => match field_type.schema() {
+        Some(schema) => {
+          let path = pascal_case(&field_type.path);
+          let name = format!("{}{}", self.root, path);
+          return self.schema(schema.fields(), &name, false);
+        }
+        None => "Schema.Types.Mixed",
+      },
+      t if t == field_type::ARRAY => {
+        let items = self.schema_type(field_type.types());
+        return format!("[{}]", items);
+      }
+      t if t == field_type::FLOATING_POINT
+        || t == field_type::I32
+        || t == field_type::I64
+        || t == field_type::NUMBER =>
+      {
+        "Number"
+      }
+      t if t == field_type::STRING || t == field_type::SYMBOL => "String",
+      t if t == field_type::BOOLEAN => "Boolean",
+      t if t == field_type::UTCDATE_TIME => "Date",
+      t if t == field_type::BINARY => "Buffer",
+      t if t == field_type::OBJECTID => "Schema.Types.ObjectId",
+      t if t == field_type::DECIMAL_128 => "Schema.Types.Decimal128",
+      // timestamps, regular expressions and code
+      _ => "Schema.Types.Mixed",
+    };
+    mongoose_type.to_string()
+  }
+}
+
+fn is_nullable(bson_type: &str) -> bool {
+  bson_type == field_type::NULL || bson_type == field_type::UNDEFINED
+}
+
+fn is_object_id(field: &Field) -> bool {
+  field.types().all(|t| t.bson_type == field_type::OBJECTID)
+}
+
+fn camel_case(name: &str) -> String {
+  let mut chars = name.chars();
+  match chars.next() {
+    Some(first) => format!("{}{}", first.to_ascii_lowercase(), chars.as_str()),
+    None => String::new(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::SchemaParser;
+
+  #[test]
+  fn it_converts_to_mongoose_schema() {
+    let mut schema_parser = SchemaParser::new();
+    let json_str1 = r#"{
+      "_id": {"$oid": "5d505646cf6d4fe581014ab2"},
+      "name": "Nori",
+      "age": 5,
+      "tags": ["cat"]
+    }"#;
+    let json_str2 = r#"{
+      "_id": {"$oid": "5d505646cf6d4fe581014ab3"},
+      "name": null,
+      "age": "3",
+      "owner": {"first-name": "Ana"}
+    }"#;
+    schema_parser.write_json(&json_str1).unwrap();
+    schema_parser.write_json(&json_str2).unwrap();
+    let schema = schema_parser.flush();
+    let mongoose = to_mongoose_schema(schema.fields(), "pets", 1.0);
+    assert_eq!(
+      mongoose,
+      r#"const mongoose = require("mongoose");
+const { Schema } = mongoose;
+
+const petsOwnerSchema = new Schema({
+  "first-name": { type: String, required: true },
+}, { _id: false });
+
+const petsSchema = new Schema({
+  name: { type: String },
+  age: { type: Schema.Types.Mixed, required: true },
+  tags: { type: [String] },
+  owner: { type: petsOwnerSchema },
+});
+
+module.exports = mongoose.model("Pets", petsSchema);
+"#
+    );
+  }
+
+  #[test]
+  fn it_keeps_ids_that_are_not_object_ids() {
+    let mut schema_parser = SchemaParser::new();
+    schema_parser
+      .write_json(r#"{"_id": "nori", "n": [{"a": 1}]}"#)
+      .unwrap();
+    let schema = schema_parser.flush();
+    let mongoose = to_mongoose_schema(schema.fields(), "pets", 1.0);
+    assert!(mongoose.contains("  _id: { type: String, required: true },\n"));
+    assert!(mongoose.contains("  n: { type: [petsNSchema], required: true },"));
+  }
+}
@@ -0,0 +1,266 @@
+use super::{Field, FieldType, ValueType};
+use crate::field_type;
+use crate::typescript::pascal_case;
+use chrono::DateTime;
+use serde_json::{json, Map, Value};
+
+// UUID binary subtypes, the legacy one and the standard one.
+static UUID_SUB_TYPES: &[u8] = &[3, 4];
+
+/// Converts finalised fields into an OpenAPI 3.0 document with a single
+/// `components.schemas` entry named `name`. Fields present in at least
+/// `required_threshold` of the documents are required, and fields that are
+/// sometimes null are `nullable`.
+pub(crate) fn to_openapi<'a>(
+  fields: impl Iterator<Item = &'a Field>,
+  name: &str,
+  required_threshold: f32,
+) -> Value {
+  let mut schemas = Map::new();
+  let schema = object_schema(fields, required_threshold);
+  schemas.insert(pascal_case(name), Value::Object(schema));
+  json!({ "components": { "schemas": schemas } })
+}
+
+fn object_schema<'a>(
+  fields: impl Iterator<Item = &'a Field>,
+  required_threshold: f32,
+) -> Map<String, Value> {
+  let mut properties = Map::new();
+  let mut required = Vec::new();
+  for field in fields {
+    if field.probability >= required_threshold {
+      required.push(Value::from(&*field.name));
+    }
+    let field_schema = types_schema(field.types(), required_threshold);
+    properties.insert(field.name.to_string(), Value::Object(field_schema));
+  }
+
+  let mut schema = Map::new();
+  schema.insert("type".to_string(), Value::from("object"));
+  schema.insert("properties".to_string(), Value::Object(properties));
+  if !required.is_empty() {
+    schema.insert("required".to_string(), Value::Array(required));
+  }
+  schema
+}
+
+// OpenAPI 3.0 has no list of types or null type, so a field with several
+// types gets a `oneOf`, and nulls make it `nullable`.
+fn types_schema<'a>(
+  types: impl Iterator<Item = &'a FieldType>,
+  required_threshold: f32,
+) -> Map<String, Value> {
+  let mut types: Vec<&FieldType> = types.collect();
+  types.sort_by(|a, b| a.bson_type.cmp(&b.bson_type));
+
+  let mut nullable = false;
+  let mut schemas: Vec<Value> = Vec::new();
+  for field_type in types {
+    if field_type.bson_type == field_type::NULL {
+      nullable = true;
+      continue;
+    }
+    if field_type.bson_type == field_type::UNDEFINED {
+      continue;
+    }
+    let schema = Value::Object(type_schema(field_type, required_threshold));
+    if !schemas.contains(&schema) {
+      schemas.push(schema);
+    }
+  }
+
+  let mut schema = match schemas.len() {
+    0 => Map::new(),
+    1 => match schemas.remove(0) {
+      Value::Object(schema) => schema,
+      _ => Map::new(),
+    },
+    _ => {
+      let mut schema = Map::new();
+      schema.insert("oneOf".to_string(), Value::Array(schemas));
+      schema
+    }
+  };
+  if nullable {
+    schema.insert("nullable".to_string(), Value::from(true));
+  }
+  schema
+}
+
+fn type_schema(
+  field_type: &FieldType,
+  required_threshold: f32,
+) -> Map<String, Value> {
+  let schema = match field_type.bson_type.as_str() {
+    t if t == field_type::DOCUMENT => match field_type.schema() {
+      Some(schema) => {
+        return object_schema(schema.fields(), required_threshold)
+      }
+      None => json!({ "type": "object" }),
+    },
+    t if t == field_type::ARRAY => {
+      let items = types_schema(field_type.types(), required_threshold);
+      json!({ "type": "array", "items": items })
+    }
+    t if t == field_type::FLOATING_POINT => {
+      json!({ "type": "number", "format": "double" })
+    }
+    t if t == field_type::NUMBER => json!({ "type": "number" }),
+    t if t == field_type::I32 => {
+      json!({ "type": "integer", "format": "int32" })
+    }
+    t if t == field_type::I64 || t == field_type::TIMESTAMP => {
+      json!({ "type": "integer", "format": "int64" })
+    }
+    t if t == field_type::DECIMAL_128 => {
+      json!({ "type": "string", "format": "decimal" })
+    }
+    t if t == field_type::BOOLEAN => json!({ "type": "boolean" }),
+    t if t == field_type::UTCDATE_TIME => {
+      json!({ "type": "string", "format": "date-time" })
+    }
+    t if t == field_type::OBJECTID => {
+      json!({ "type": "string", "pattern": "^[0-9a-fA-F]{24}$" })
+    }
+    t if t == field_type::BINARY => {
+      let format = if all_values(field_type, is_uuid_binary) {
+        "uuid"
+      } else {
+        "byte"
+      };
+      json!({ "type": "string", "format": format })
+    }
+    t if t == field_type::STRING => match string_format(field_type) {
+      Some(format) => json!({ "type": "string", "format": format }),
+      None => json!({ "type": "string" }),
+    },
+    // regular expressions, code and symbols
+    _ => json!({ "type": "string" }),
+  };
+  match schema {
+    Value::Object(schema) => schema,
+    _ => Map::new(),
+  }
+}
+
+// Format of a String type, if all of its sampled values have one.
+fn string_format(field_type: &FieldType) -> Option<&'static str> {
+  let is_date_time = |value: &ValueType| match value {
+    ValueType::Str(value) => DateTime::parse_from_rfc3339(value).is_ok(),
+    _ => false,
+  };
+  let is_uuid = |value: &ValueType| match value {
+    ValueType::Str(value) => is_uuid(value),
+    _ => false,
+  };
+  if all_values(field_type, is_date_time) {
+    Some("date-time")
+  } else if all_values(field_type, is_uuid) {
+    Some("uuid")
+  } else {
+    None
+  }
+}
+
+// Without sampled values there's nothing to detect a format from.
+fn all_values(field_type: &FieldType, f: impl Fn(&ValueType) -> bool) -> bool {
+  !field_type.values().is_empty() && field_type.values().iter().all(f)
+}
+
+fn is_uuid_binary(value: &ValueType) -> bool {
+  match value {
+    ValueType::Binary { sub_type, length } => {
+      UUID_SUB_TYPES.contains(sub_type) && *length == 16
+    }
+    _ => false,
+  }
+}
+
+// Hyphenated hex UUID, i.e. `123e4567-e89b-12d3-a456-426614174000`.
+fn is_uuid(value: &str) -> bool {
+  value.len() == 36
+    && value.char_indices().all(|(i, c)| match i {
+      8 | 13 | 18 | 23 => c == '-',
+      _ => c.is_ascii_hexdigit(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::SchemaParser;
+
+  #[test]
+  fn it_converts_to_openapi() {
+    let mut schema_parser = SchemaParser::new();
+    let json_str1 = r#"{
+      "name": "Nori",
+      "born": {"$date": "2015-04-01T00:00:00Z"},
+      "chip": "123e4567-e89b-12d3-a456-426614174000",
+      "tags": ["cat"]
+    }"#;
+    let json_str2 = r#"{
+      "name": null,
+      "born": {"$date": "2017-01-05T00:00:00Z"},
+      "chip": "00000000-0000-4000-8000-000000000000",
+      "owner": {"name": "Ana", "age": 5}
+    }"#;
+    schema_parser.write_json(&json_str1).unwrap();
+    schema_parser.write_json(&json_str2).unwrap();
+    let schema = schema_parser.flush();
+    let openapi = to_openapi(schema.fields(), "pets", 1.0);
+    assert_eq!(
+      openapi,
+      json!({
+        "components": {
+          "schemas": {
+            "Pets": {
+              "type": "object",
+              "properties": {
+                "name": { "type": "string", "nullable": true },
+                "born": { "type": "string", "format": "date-time" },
+                "chip": { "type": "string", "format": "uuid" },
+                "tags": { "type": "array", "items": { "type": "string" } },
+                "owner": {
+                  "type": "object",
+                  "properties": {
+                    "name": { "type": "string" },
+                    "age": { "type": "integer", "format": "int32" }
+                  },
+                  "required": ["name", "age"]
+                }
+              },
+              "required": ["name", "born", "chip"]
+            }
+          }
+        }
+      })
+    );
+  }
+
+  #[test]
+  fn it_uses_one_of_for_several_types() {
+    let mut schema_parser = SchemaParser::new();
+    schema_parser.write_json(r#"{"age": 5}"#).unwrap();
+    schema_parser.write_json(r#"{"age": "5"}"#).unwrap();
+    let schema = schema_parser.flush();
+    let openapi = to_openapi(schema.fields(), "pets", 1.0);
+    assert_eq!(
+      openapi["components"]["schemas"]["Pets"]["properties"]["age"],
+      json!({
+        "oneOf": [
+          { "type": "integer", "format": "int32" },
+          { "type": "string" }
+        ]
+      })
+    );
+  }
+
+  #[test]
+  fn it_detects_uuids() {
+    assert!(is_uuid("123e4567-e89b-12d3-a456-426614174000"));
+    assert!(!is_uuid("123e4567e89b12d3a456426614174000"));
+    assert!(!is_uuid("nori"));
+  }
+}
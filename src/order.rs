@@ -0,0 +1,108 @@
+use super::{Field, FieldType, IndexMap};
+use std::cmp::Ordering;
+
+/// Order fields are listed in by `to_json()` and the other outputs of a
+/// Schema.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum FieldOrder {
+  /// In the order they were first seen, which depends on the order documents
+  /// were written in.
+  FirstSeen,
+  /// By name.
+  Name,
+  /// By probability, most common first.
+  Probability,
+  /// By count, most common first.
+  Count,
+}
+
+impl Default for FieldOrder {
+  fn default() -> Self {
+    FieldOrder::FirstSeen
+  }
+}
+
+/// Sorts finalised fields, and the fields of subdocuments and of documents in
+/// arrays. Fields that are equally common are sorted by name, so the order is
+/// the same however documents were written.
+pub(crate) fn sort(fields: &mut IndexMap<String, Field>, order: FieldOrder) {
+  if order == FieldOrder::FirstSeen {
+    return;
+  }
+  fields.sort_by(|_, a, _, b| compare(a, b, order));
+  for field in fields.values_mut() {
+    for field_type in field.types.values_mut() {
+      sort_type(field_type, order);
+    }
+  }
+}
+
+fn sort_type(field_type: &mut FieldType, order: FieldOrder) {
+  if let Some(schema) = field_type.schema.as_mut() {
+    sort(&mut schema.fields, order);
+  }
+  for element_type in field_type.types.values_mut() {
+    sort_type(element_type, order);
+  }
+}
+
+fn compare(a: &Field, b: &Field, order: FieldOrder) -> Ordering {
+  let by_name = a.name.cmp(&b.name);
+  match order {
+    FieldOrder::FirstSeen => Ordering::Equal,
+    FieldOrder::Name => by_name,
+    FieldOrder::Probability => b
+      .probability
+      .partial_cmp(&a.probability)
+      .unwrap_or(Ordering::Equal)
+      .then(by_name),
+    FieldOrder::Count => b.count.cmp(&a.count).then(by_name),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::SchemaParser;
+
+  fn sorted_paths(order: FieldOrder) -> Vec<String> {
+    let mut schema_parser = SchemaParser::new();
+    schema_parser
+      .write_json(
+        r#"{"name": "Nori", "owner": {"phone": "555", "age": 3}, "tags": []}"#,
+      )
+      .unwrap();
+    schema_parser
+      .write_json(r#"{"tags": ["cat"], "owner": {"age": 4}}"#)
+      .unwrap();
+    let mut fields = schema_parser.flush().fields;
+    sort(&mut fields, order);
+    crate::diff::fields_by_path(fields.values())
+      .into_iter()
+      .map(|(path, _)| path.to_string())
+      .collect()
+  }
+
+  #[test]
+  fn it_keeps_first_seen_order() {
+    assert_eq!(
+      sorted_paths(FieldOrder::FirstSeen),
+      vec!["name", "owner", "owner.phone", "owner.age", "tags"]
+    );
+  }
+
+  #[test]
+  fn it_sorts_by_name() {
+    assert_eq!(
+      sorted_paths(FieldOrder::Name),
+      vec!["name", "owner", "owner.age", "owner.phone", "tags"]
+    );
+  }
+
+  #[test]
+  fn it_sorts_most_common_first_then_by_name() {
+    let expected = vec!["owner", "owner.age", "owner.phone", "tags", "name"];
+    assert_eq!(sorted_paths(FieldOrder::Probability), expected);
+    assert_eq!(sorted_paths(FieldOrder::Count), expected);
+  }
+}
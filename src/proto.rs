@@ -0,0 +1,245 @@
+use super::{Field, FieldType};
+use crate::codegen::snake_case;
+use crate::field_type;
+use crate::typescript::pascal_case;
+use std::collections::BTreeSet;
+
+static STRUCT_PROTO: &str = "google/protobuf/struct.proto";
+static TIMESTAMP_PROTO: &str = "google/protobuf/timestamp.proto";
+
+/// Converts finalised fields into proto3 message definitions. The top level
+/// message is called `name`, subdocuments get their own messages named after
+/// their path, and arrays become `repeated` fields. Scalar fields present in
+/// less than `required_threshold` of the documents, or that are sometimes
+/// null, are `optional`.
+pub(crate) fn to_proto<'a>(
+  fields: impl Iterator<Item = &'a Field>,
+  name: &str,
+  required_threshold: f32,
+) -> String {
+  let mut writer = Writer {
+    root: pascal_case(name),
+    messages: Vec::new(),
+    imports: BTreeSet::new(),
+    required_threshold,
+  };
+  let root = writer.root.clone();
+  writer.message(fields, &root);
+
+  let mut proto = String::from("syntax = \"proto3\";\n\n");
+  for import in &writer.imports {
+    proto.push_str(&format!("import \"{}\";\n", import));
+  }
+  if !writer.imports.is_empty() {
+    proto.push('\n');
+  }
+  proto.push_str(&writer.messages.join("\n"));
+  proto
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct ProtoType {
+  name: String,
+  repeated: bool,
+  // messages already have field presence, so are never `optional`
+  message: bool,
+}
+
+impl ProtoType {
+  fn scalar(name: &str) -> Self {
+    ProtoType {
+      name: name.to_string(),
+      repeated: false,
+      message: false,
+    }
+  }
+
+  fn message(name: &str) -> Self {
+    ProtoType {
+      name: name.to_string(),
+      repeated: false,
+      message: true,
+    }
+  }
+}
+
+struct Writer {
+  root: String,
+  messages: Vec<String>,
+  imports: BTreeSet<&'static str>,
+  required_threshold: f32,
+}
+
+impl Writer {
+  // Nested messages are written after the message that uses them. Field
+  // numbers follow the order fields were first seen in.
+  fn message<'a>(
+    &mut self,
+    fields: impl Iterator<Item = &'a Field>,
+    name: &str,
+  ) {
+    let index = self.messages.len();
+    self.messages.push(String::new());
+
+    let mut message = format!("message {} {{\n", name);
+    for (number, field) in fields.enumerate() {
+      let nullable = field.types().any(|t| is_nullable(&t.bson_type));
+      let optional = nullable || field.probability < self.required_threshold;
+      let proto_type = self.field_type(field.types());
+      let label = if proto_type.repeated {
+        "repeated "
+      } else if optional && !proto_type.message {
+        "optional "
+      } else {
+        ""
+      };
+      message.push_str(&format!(
+        "  {}{} {} = {};\n",
+        label,
+        proto_type.name,
+        field_name(&field.name),
+        number + 1
+      ));
+    }
+    message.push_str("}\n");
+    self.messages[index] = message;
+  }
+
+  // Fields with several types use google.protobuf.Value, as a `oneof` can't
+  // be repeated.
+  fn field_type<'a>(
+    &mut self,
+    types: impl Iterator<Item = &'a FieldType>,
+  ) -> ProtoType {
+    let mut proto_types: Vec<ProtoType> = Vec::new();
+    for field_type in types {
+      if is_nullable(&field_type.bson_type) {
+        continue;
+      }
+      let proto_type = self.proto_type(field_type);
+      if !proto_types.contains(&proto_type) {
+        proto_types.push(proto_type);
+      }
+    }
+    match proto_types.len() {
+      1 => proto_types.remove(0),
+      _ => self.well_known("google.protobuf.Value", STRUCT_PROTO),
+    }
+  }
+
+  fn proto_type(&mut self, field_type: &FieldType) -> ProtoType {
+    match field_type.bson_type.as_str() {
+      t if t == field_type::DOCUMENT => match field_type.schema() {
+        Some(schema) => {
+          let path = pascal_case(&field_type.path);
+          let name = format!("{}{}", self.root, path);
+          self.message(schema.fields(), &name);
+          ProtoType::message(&name)
+        }
+        None => self.well_known("google.protobuf.Struct", STRUCT_PROTO),
+      },
+      t if t == field_type::ARRAY => {
+        let mut items = self.field_type(field_type.types());
+        // repeated fields can't be nested, so arrays of arrays are lists of
+        // ListValues
+        if items.repeated {
+          items = self.well_known("google.protobuf.ListValue", STRUCT_PROTO);
+        }
+        items.repeated = true;
+        items
+      }
+      t if t == field_type::UTCDATE_TIME => {
+        self.well_known("google.protobuf.Timestamp", TIMESTAMP_PROTO)
+      }
+      t if t == field_type::FLOATING_POINT || t == field_type::NUMBER => {
+        ProtoType::scalar("double")
+      }
+      t if t == field_type::I32 => ProtoType::scalar("int32"),
+      t if t == field_type::I64 => ProtoType::scalar("int64"),
+      t if t == field_type::TIMESTAMP => ProtoType::scalar("uint64"),
+      t if t == field_type::BOOLEAN => ProtoType::scalar("bool"),
+      t if t == field_type::BINARY => ProtoType::scalar("bytes"),
+      // ObjectIds, Decimal128s, regular expressions, code and symbols are
+      // kept as strings.
+      _ => ProtoType::scalar("string"),
+    }
+  }
+
+  fn well_known(&mut self, name: &str, import: &'static str) -> ProtoType {
+    self.imports.insert(import);
+    ProtoType::message(name)
+  }
+}
+
+fn is_nullable(bson_type: &str) -> bool {
+  bson_type == field_type::NULL || bson_type == field_type::UNDEFINED
+}
+
+// Proto field names are snake_case, and can't start with a digit.
+fn field_name(name: &str) -> String {
+  let field_name = snake_case(name);
+  let starts_with_digit = field_name.starts_with(|c: char| c.is_ascii_digit());
+  if field_name.is_empty() || starts_with_digit {
+    format!("_{}", field_name)
+  } else {
+    field_name
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::SchemaParser;
+
+  #[test]
+  fn it_converts_to_proto() {
+    let mut schema_parser = SchemaParser::new();
+    let json_str1 = r#"{
+      "_id": {"$oid": "5d505646cf6d4fe581014ab2"},
+      "name": "Nori",
+      "age": 5,
+      "tags": ["cat"]
+    }"#;
+    let json_str2 = r#"{
+      "_id": {"$oid": "5d505646cf6d4fe581014ab3"},
+      "name": null,
+      "age": "3",
+      "owner": {"firstName": "Ana"}
+    }"#;
+    schema_parser.write_json(&json_str1).unwrap();
+    schema_parser.write_json(&json_str2).unwrap();
+    let schema = schema_parser.flush();
+    let proto = to_proto(schema.fields(), "pets", 1.0);
+    assert_eq!(
+      proto,
+      r#"syntax = "proto3";
+
+import "google/protobuf/struct.proto";
+
+message Pets {
+  string id = 1;
+  optional string name = 2;
+  google.protobuf.Value age = 3;
+  repeated string tags = 4;
+  PetsOwner owner = 5;
+}
+
+message PetsOwner {
+  string first_name = 1;
+}
+"#
+    );
+  }
+
+  #[test]
+  fn it_converts_nested_arrays_to_list_values() {
+    let mut schema_parser = SchemaParser::new();
+    schema_parser
+      .write_json(r#"{"grid": [[1, 2], [3]]}"#)
+      .unwrap();
+    let schema = schema_parser.flush();
+    let proto = to_proto(schema.fields(), "boards", 1.0);
+    let grid = "  repeated google.protobuf.ListValue grid = 1;\n";
+    assert!(proto.contains(grid));
+  }
+}
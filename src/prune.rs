@@ -0,0 +1,82 @@
+use super::{Field, FieldType, IndexMap, WILDCARD};
+
+/// Drops finalised fields with a probability below `min_probability`,
+/// including fields of subdocuments and of documents in arrays, whose
+/// probabilities are relative to the subdocuments rather than to all
+/// documents. Wildcard fields don't have a probability, so they're kept.
+pub(crate) fn prune(
+  fields: &mut IndexMap<String, Field>,
+  min_probability: f32,
+) {
+  fields.retain(|_, field| {
+    field.probability >= min_probability || &*field.name == WILDCARD
+  });
+  for field in fields.values_mut() {
+    for field_type in field.types.values_mut() {
+      prune_type(field_type, min_probability);
+    }
+  }
+}
+
+fn prune_type(field_type: &mut FieldType, min_probability: f32) {
+  if let Some(schema) = field_type.schema.as_mut() {
+    prune(&mut schema.fields, min_probability);
+  }
+  for element_type in field_type.types.values_mut() {
+    prune_type(element_type, min_probability);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::SchemaParser;
+
+  #[test]
+  fn it_prunes_rare_fields() {
+    let mut schema_parser = SchemaParser::new();
+    for _ in 0..9 {
+      schema_parser
+        .write_json(r#"{"name": "Nori", "owner": {"name": "Ana"}}"#)
+        .unwrap();
+    }
+    schema_parser
+      .write_json(r#"{"name": "Rey", "x1f3": 1, "owner": {"x9a2": true}}"#)
+      .unwrap();
+    let mut fields = schema_parser.flush().fields;
+    prune(&mut fields, 0.5);
+    let keys: Vec<&String> = fields.keys().collect();
+    assert_eq!(keys, vec!["name", "owner"]);
+    let owner = fields["owner"].types["Document"].schema().unwrap();
+    let keys: Vec<&String> = owner.fields.keys().collect();
+    assert_eq!(keys, vec!["owner.name"]);
+  }
+
+  #[test]
+  fn it_keeps_wildcards() {
+    let mut schema_parser =
+      SchemaParser::builder().wildcard_threshold(3).build();
+    schema_parser
+      .write_json(r#"{"votes": {"u1": 1, "u2": -1, "u3": 1, "u4": 1}}"#)
+      .unwrap();
+    let mut fields = schema_parser.flush().fields;
+    prune(&mut fields, 0.5);
+    let votes = fields["votes"].types["Document"].schema().unwrap();
+    let keys: Vec<&String> = votes.fields.keys().collect();
+    assert_eq!(keys, vec!["votes.$*"]);
+  }
+
+  #[test]
+  fn it_prunes_documents_in_arrays() {
+    let mut schema_parser = SchemaParser::new();
+    schema_parser
+      .write_json(r#"{"visits": [{"vet": "Ana"}, {"vet": "Ben", "x": 1}]}"#)
+      .unwrap();
+    let mut fields = schema_parser.flush().fields;
+    prune(&mut fields, 0.6);
+    let visits = &fields["visits"].types["Array"];
+    let visit = visits.types["Document"].schema().unwrap();
+    let keys: Vec<&String> = visit.fields.keys().collect();
+    assert_eq!(keys, vec!["visits.vet"]);
+  }
+}
@@ -0,0 +1,176 @@
+use super::{Field, FieldType};
+use crate::field_type;
+use crate::markdown::percent;
+use std::collections::HashMap;
+
+// How many of the most common sampled values a histogram shows.
+static HISTOGRAM_BARS: usize = 10;
+
+static STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; color: #21313c; }
+ul.fields { list-style: none; padding-left: 1.5em; }
+summary { cursor: pointer; padding: 0.25em 0; }
+.probability { color: #5d6c74; margin-left: 0.5em; }
+.types { display: flex; width: 30em; height: 1.5em; margin: 0.5em 0; }
+.type { overflow: hidden; font-size: 0.75em; line-height: 2em;
+  text-align: center; color: #fff; background: #13aa52; }
+.type:nth-child(2n) { background: #0f7dc2; }
+.type.Undefined { background: #b8c4c2; }
+table.histogram { font-size: 0.85em; margin-bottom: 0.5em; }
+.bar { display: inline-block; height: 0.8em; background: #13aa52; }
+";
+
+/// Renders finalised fields as a self-contained HTML report, with a
+/// collapsible tree of fields, a bar of each field's type distribution and a
+/// histogram of each field's most common sampled values.
+pub(crate) fn to_html_report<'a>(
+  fields: impl Iterator<Item = &'a Field>,
+  count: usize,
+) -> String {
+  let mut html = String::from("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n");
+  html.push_str("<meta charset=\"utf-8\">\n<title>Schema report</title>\n");
+  html.push_str(&format!("<style>{}</style>\n", STYLE));
+  html.push_str("</head>\n<body>\n<h1>Schema report</h1>\n");
+  html.push_str(&format!("<p>{} documents.</p>\n", count));
+  html.push_str(&field_tree(fields));
+  html.push_str("</body>\n</html>\n");
+  html
+}
+
+fn field_tree<'a>(fields: impl Iterator<Item = &'a Field>) -> String {
+  let mut html = String::from("<ul class=\"fields\">\n");
+  for field in fields {
+    let mut types: Vec<&FieldType> = field.types().collect();
+    types.sort_by(|a, b| {
+      b.count
+        .cmp(&a.count)
+        .then_with(|| a.bson_type.cmp(&b.bson_type))
+    });
+
+    html.push_str("<li><details>\n");
+    html.push_str(&format!(
+      "<summary><code>{}</code><span class=\"probability\">{}</span>\
+       </summary>\n",
+      escape(&field.name),
+      percent(field.probability)
+    ));
+    html.push_str(&type_bars(&types));
+    html.push_str(&histogram(&types));
+    for field_type in types {
+      html.push_str(&subdocuments(field_type));
+    }
+    html.push_str("</details></li>\n");
+  }
+  html.push_str("</ul>\n");
+  html
+}
+
+// Subdocuments, including those in arrays, are nested in the tree.
+fn subdocuments(field_type: &FieldType) -> String {
+  let mut html = String::new();
+  if let Some(schema) = field_type.schema() {
+    html.push_str(&field_tree(schema.fields()));
+  }
+  for element_type in field_type.types() {
+    html.push_str(&subdocuments(element_type));
+  }
+  html
+}
+
+// Each type's share of the field's documents, including the documents the
+// field is missing from.
+fn type_bars(types: &[&FieldType]) -> String {
+  let total: usize = types.iter().map(|t| t.count).sum();
+  let mut html = String::from("<div class=\"types\">");
+  for field_type in types {
+    let share = field_type.count as f32 / total.max(1) as f32;
+    html.push_str(&format!(
+      "<span class=\"type {0}\" style=\"width: {1}\" title=\"{0} {1}\">\
+       {0}</span>",
+      escape(&field_type.bson_type),
+      percent(share)
+    ));
+  }
+  html.push_str("</div>\n");
+  html
+}
+
+// Histogram of the most common sampled values of the field's scalar types.
+fn histogram(types: &[&FieldType]) -> String {
+  let mut counts: HashMap<String, usize> = HashMap::new();
+  for field_type in types {
+    if field_type.bson_type == field_type::DOCUMENT
+      || field_type.bson_type == field_type::ARRAY
+    {
+      continue;
+    }
+    for value in field_type.values() {
+      let value = serde_json::to_string(value).unwrap_or_default();
+      *counts.entry(value).or_insert(0) += 1;
+    }
+  }
+  if counts.is_empty() {
+    return String::new();
+  }
+
+  let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+  counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+  counts.truncate(HISTOGRAM_BARS);
+  let max = counts[0].1;
+
+  let mut html = String::from("<table class=\"histogram\">\n");
+  for (value, count) in counts {
+    let width = count as f32 / max as f32 * 10.0;
+    html.push_str(&format!(
+      "<tr><td><code>{}</code></td><td><span class=\"bar\" \
+       style=\"width: {:.1}em\"></span> {}</td></tr>\n",
+      escape(&value),
+      width,
+      count
+    ));
+  }
+  html.push_str("</table>\n");
+  html
+}
+
+fn escape(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::SchemaParser;
+
+  #[test]
+  fn it_renders_an_html_report() {
+    let mut schema_parser = SchemaParser::new();
+    let json_str1 = r#"{"name": "Nori", "owner": {"name": "<Ana>"}}"#;
+    let json_str2 = r#"{"name": "Nori"}"#;
+    schema_parser.write_json(&json_str1).unwrap();
+    schema_parser.write_json(&json_str2).unwrap();
+    let schema = schema_parser.flush();
+    let html = to_html_report(schema.fields(), schema.count);
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<p>2 documents.</p>"));
+    assert!(html.contains(
+      "<summary><code>owner</code><span class=\"probability\">50%</span>"
+    ));
+    assert!(html.contains("title=\"Undefined 50%\""));
+    assert!(html.contains("<code>&quot;Nori&quot;</code>"));
+    assert!(html.contains("style=\"width: 10.0em\"></span> 2</td>"));
+    assert!(html.contains("<code>&quot;&lt;Ana&gt;&quot;</code>"));
+  }
+
+  #[test]
+  fn it_escapes_html() {
+    assert_eq!(
+      escape("<a href=\"x\">&</a>"),
+      "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;"
+    );
+  }
+}
@@ -0,0 +1,163 @@
+// Seed for the sampling rng. Sampling is deterministic, so analysing the same
+// documents twice gives the same sample.
+static SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// Reservoir sampling (Algorithm R) for the values stored per FieldType. Once
+/// the sample is full, the n-th value seen replaces a random stored value with
+/// probability `cap / n`, so every value has the same chance of being kept.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Reservoir {
+  seen: usize,
+  rng: u64,
+}
+
+impl Default for Reservoir {
+  fn default() -> Self {
+    Reservoir { seen: 0, rng: SEED }
+  }
+}
+
+impl Reservoir {
+  /// Adds a value to a sample of at most `cap` values.
+  pub fn sample<T>(
+    &mut self,
+    values: &mut Vec<T>,
+    value: T,
+    cap: Option<usize>,
+  ) {
+    self.seen += 1;
+    match cap {
+      Some(cap) if values.len() >= cap => {
+        let slot = self.below(self.seen);
+        if slot < cap {
+          values[slot] = value;
+        }
+      }
+      _ => values.push(value),
+    }
+  }
+
+  /// Merges another sample into `values`. If either side had values dropped,
+  /// the merged sample keeps the size of the larger one, and draws from both
+  /// in proportion to how many values each of them has seen.
+  pub fn merge<T>(
+    &mut self,
+    values: &mut Vec<T>,
+    other: Reservoir,
+    mut other_values: Vec<T>,
+  ) {
+    let sampled = self.seen > values.len() || other.seen > other_values.len();
+    if sampled {
+      let cap = values.len().max(other_values.len());
+      let mut own_values: Vec<T> = values.drain(..).collect();
+      let total = self.seen + other.seen;
+      while values.len() < cap
+        && (!own_values.is_empty() || !other_values.is_empty())
+      {
+        let from_own = other_values.is_empty()
+          || (!own_values.is_empty() && self.below(total) < self.seen);
+        let source = if from_own {
+          &mut own_values
+        } else {
+          &mut other_values
+        };
+        let index = self.below(source.len());
+        values.push(source.swap_remove(index));
+      }
+    } else {
+      values.extend(other_values);
+    }
+    self.seen += other.seen;
+  }
+
+  /// Restores the number of values seen for a deserialized sample of
+  /// `values`, unless it is still known.
+  pub fn restore<T>(&mut self, values: &[T], seen: usize) {
+    if self.seen == 0 {
+      self.seen = seen.max(values.len());
+    }
+  }
+
+  // Uniformly distributed number in 0..n, using xorshift64*.
+  fn below(&mut self, n: usize) -> usize {
+    self.rng ^= self.rng >> 12;
+    self.rng ^= self.rng << 25;
+    self.rng ^= self.rng >> 27;
+    let random = self.rng.wrapping_mul(0x2545_f491_4f6c_dd1d);
+    (random % n as u64) as usize
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_keeps_all_values_below_cap() {
+    let mut reservoir = Reservoir::default();
+    let mut values = Vec::new();
+    for num in 0..5 {
+      reservoir.sample(&mut values, num, Some(10));
+    }
+    assert_eq!(values, vec![0, 1, 2, 3, 4]);
+  }
+
+  #[test]
+  fn it_caps_values() {
+    let mut reservoir = Reservoir::default();
+    let mut values = Vec::new();
+    for num in 0..1000 {
+      reservoir.sample(&mut values, num, Some(10));
+    }
+    assert_eq!(values.len(), 10);
+    // values from later on get sampled too
+    assert!(values.iter().any(|num| *num >= 10));
+  }
+
+  #[test]
+  fn it_samples_without_bias() {
+    // every value should end up in the sample about cap / n of the time
+    let mut reservoir = Reservoir::default();
+    let mut kept = vec![0; 100];
+    for _ in 0..1000 {
+      let mut values = Vec::new();
+      reservoir.seen = 0;
+      for num in 0..100 {
+        reservoir.sample(&mut values, num, Some(10));
+      }
+      for num in values {
+        kept[num] += 1;
+      }
+    }
+    assert!(kept.iter().all(|count| *count > 50 && *count < 150));
+  }
+
+  #[test]
+  fn it_merges_unsampled_values() {
+    let mut reservoir = Reservoir::default();
+    let mut values = Vec::new();
+    reservoir.sample(&mut values, 1, Some(10));
+    let mut other = Reservoir::default();
+    let mut other_values = Vec::new();
+    other.sample(&mut other_values, 2, Some(10));
+    reservoir.merge(&mut values, other, other_values);
+    assert_eq!(values, vec![1, 2]);
+  }
+
+  #[test]
+  fn it_merges_sampled_values() {
+    let mut reservoir = Reservoir::default();
+    let mut values = Vec::new();
+    for num in 0..100 {
+      reservoir.sample(&mut values, num, Some(10));
+    }
+    let mut other = Reservoir::default();
+    let mut other_values = Vec::new();
+    for num in 100..200 {
+      other.sample(&mut other_values, num, Some(10));
+    }
+    reservoir.merge(&mut values, other, other_values);
+    assert_eq!(values.len(), 10);
+    assert_eq!(reservoir.seen, 200);
+  }
+}
@@ -0,0 +1,738 @@
+use super::{Bson, Field, IndexMap, ParseError, SchemaParser};
+use crate::json_schema::Bounds;
+use crate::{
+  anomaly, arrow, avro, codegen, compat, csv, diff, field, fingerprint,
+  graphql, intersect, json_schema, markdown, mongoose, openapi, order, proto,
+  prune, report, sql, subschema, typescript, validator, visitor, FieldOrder,
+  SchemaDiff, SchemaVisitor, SqlDialect, Violation,
+};
+use bson::Document;
+use serde_json::Value;
+use std::io;
+
+/// Finalised result of all documents written to a SchemaParser. Field
+/// probabilities, unique values and duplicates are computed once when the
+/// Schema is created, and are not touched afterwards.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Schema {
+  pub count: usize,
+  pub(crate) fields: IndexMap<String, Field>,
+}
+
+impl Schema {
+  pub(crate) fn new(count: usize, fields: IndexMap<String, Field>) -> Self {
+    Schema { count, fields }
+  }
+
+  /// Returns an iterator over the top level fields of the Schema, in the order
+  /// they were first seen.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// for field in schema_parser.flush().fields() {
+  ///   println!("{}: {:?}", field.path, field.bson_types);
+  /// }
+  /// ```
+  pub fn fields(&self) -> impl Iterator<Item = &Field> {
+    self.fields.values()
+  }
+
+  /// Returns the field at a dotted path, i.e. `address.geo.lat`. Fields of
+  /// documents in arrays are at the path of the array, i.e. `visits.vet`.
+  ///
+  /// # Arguments
+  /// * `path` - Dotted path of the field.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "visits": [{ "vet": "Dr. Ana" }] }"#);
+  /// schema_parser.write_json(r#"{ "visits": [] }"#);
+  /// let schema = schema_parser.flush();
+  /// let vet = schema.get_field("visits.vet").unwrap();
+  /// assert_eq!(vet.probability, 1.0);
+  /// ```
+  pub fn get_field(&self, path: &str) -> Option<&Field> {
+    field::find_field(&self.fields, path)
+  }
+
+  /// Returns an iterator over every field of the Schema with its path,
+  /// depth-first: the fields of a subdocument, or of documents in an array,
+  /// come right after their parent. Fields that are at the same path in
+  /// several types, i.e. a subdocument in some documents and an array of
+  /// documents in others, are only returned once.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "owner": { "name": "Ana" }, "age": 4 }"#);
+  /// let paths: Vec<&str> = schema_parser
+  ///   .flush()
+  ///   .iter_fields()
+  ///   .map(|(path, _)| path)
+  ///   .collect();
+  /// assert_eq!(paths, vec!["owner", "owner.name", "age"]);
+  /// ```
+  pub fn iter_fields(&self) -> impl Iterator<Item = (&str, &Field)> {
+    diff::fields_by_path(self.fields()).into_iter()
+  }
+
+  /// Returns a standalone Schema of the subdocuments at a dotted path, i.e.
+  /// when only an embedded structure of large documents matters. Paths are
+  /// relative to the subdocuments, the count is how many subdocuments there
+  /// are, and probabilities are relative to that count. Documents in arrays at
+  /// the path are included. Returns `None` if the path was never a document.
+  ///
+  /// # Arguments
+  /// * `path` - Dotted path of the subdocuments.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "payment": { "card": { "cvc": 123 } } }"#);
+  /// schema_parser.write_json(r#"{ "payment": { "iban": "DE89" } }"#);
+  /// let card = schema_parser.flush().subschema("payment.card").unwrap();
+  /// assert_eq!(card.count, 1);
+  /// assert_eq!(card.get_field("cvc").unwrap().probability, 1.0);
+  /// ```
+  pub fn subschema(&self, path: &str) -> Option<Schema> {
+    subschema::subschema(self, path)
+  }
+
+  /// Returns a copy of the Schema without the fields whose probability is
+  /// below `min_probability`, i.e. thousands of one-off keys written by a
+  /// buggy client. Fields of subdocuments and of documents in arrays are
+  /// pruned by their probability within the subdocuments. The counts and
+  /// probabilities of the remaining fields are left as they are.
+  ///
+  /// # Arguments
+  /// * `min_probability` - Probability from 0.0 to 1.0 a field needs to be
+  /// kept.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu" }"#);
+  /// schema_parser.write_json(r#"{ "name": "Rey", "x1f3": 1 }"#);
+  /// let schema = schema_parser.flush().prune(0.6);
+  /// assert_eq!(schema.fields().count(), 1);
+  /// ```
+  pub fn prune(&self, min_probability: f32) -> Schema {
+    let mut fields = self.fields.clone();
+    prune::prune(&mut fields, min_probability);
+    Schema::new(self.count, fields)
+  }
+
+  /// Returns a copy of the Schema with its fields, and the fields of its
+  /// subdocuments, sorted, i.e. so `to_json()` output of two runs diffs
+  /// cleanly. Equally common fields are sorted by name.
+  ///
+  /// # Arguments
+  /// * `order` - Order to list fields in.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::{FieldOrder, SchemaParser};
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "type": "Cat", "name": "Chashu" }"#);
+  /// let schema = schema_parser.flush().sort_fields(FieldOrder::Name);
+  /// let names: Vec<&str> =
+  ///   schema.fields().map(|field| &*field.name).collect();
+  /// assert_eq!(names, vec!["name", "type"]);
+  /// ```
+  pub fn sort_fields(&self, order: FieldOrder) -> Schema {
+    let mut fields = self.fields.clone();
+    order::sort(&mut fields, order);
+    Schema::new(self.count, fields)
+  }
+
+  /// Walks the Schema with a SchemaVisitor, calling it when entering and
+  /// leaving each field, type, subdocument and array, i.e. to render the
+  /// Schema in an output format of your own.
+  ///
+  /// # Arguments
+  /// * `visitor` - SchemaVisitor to call.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::{Field, SchemaParser, SchemaVisitor};
+  ///
+  /// struct Paths(Vec<String>);
+  ///
+  /// impl SchemaVisitor for Paths {
+  ///   fn enter_field(&mut self, field: &Field) {
+  ///     self.0.push(field.path.to_string());
+  ///   }
+  /// }
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Rey", "owner": { "age": 4 } }"#);
+  /// let mut paths = Paths(Vec::new());
+  /// schema_parser.flush().accept(&mut paths);
+  /// assert_eq!(paths.0, vec!["name", "owner", "owner.age"]);
+  /// ```
+  pub fn accept<V: SchemaVisitor + ?Sized>(&self, visitor: &mut V) {
+    visitor::accept(self.fields(), visitor)
+  }
+
+  /// Compares the Schema with a newer one, i.e. of the same collection
+  /// analysed a week later. Reports fields that were added or removed, and
+  /// fields whose probability or type distribution changed. Fields are
+  /// compared by path, including the fields of subdocuments.
+  ///
+  /// # Arguments
+  /// * `other` - Newer Schema to compare with.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let before = schema_parser.flush();
+  /// schema_parser.write_json(r#"{ "name": "Rey", "age": 4 }"#);
+  /// let diff = before.diff(&schema_parser.flush());
+  /// assert_eq!(diff.added, vec!["age"]);
+  /// assert_eq!(diff.changed[0].path, "type");
+  /// ```
+  pub fn diff(&self, other: &Schema) -> SchemaDiff {
+    diff::diff(self, other)
+  }
+
+  /// Combines the Schema with another finalised Schema, i.e. of the same
+  /// collection analysed on different machines or at different times.
+  /// Document counts, field counts and type counts are added up, and
+  /// probabilities are recomputed relative to the combined number of
+  /// documents. To combine SchemaParsers that are still being written to, use
+  /// `SchemaParser::merge()` instead.
+  ///
+  /// # Arguments
+  /// * `other` - Schema to combine with this one.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let mut other = SchemaParser::new();
+  /// other.write_json(r#"{ "name": "Rey", "age": 4 }"#);
+  /// let schema = schema_parser.flush().union(&other.flush());
+  /// assert_eq!(schema.count, 2);
+  /// ```
+  pub fn union(&self, other: &Schema) -> Schema {
+    let mut schema_parser = SchemaParser::from_schema(self.clone());
+    schema_parser.merge(SchemaParser::from_schema(other.clone()));
+    schema_parser.into_schema()
+  }
+
+  /// Returns the fields the Schema has in common with another finalised
+  /// Schema, i.e. the stable contract of two services writing to sibling
+  /// collections. Only fields present in both, with the types they have in
+  /// both, are kept. Counts and probabilities are combined as with `union()`.
+  ///
+  /// # Arguments
+  /// * `other` - Schema to intersect with this one.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let mut other = SchemaParser::new();
+  /// other.write_json(r#"{ "name": "Rey", "age": 4 }"#);
+  /// let schema = schema_parser.flush().intersect(&other.flush());
+  /// assert_eq!(schema.fields().count(), 1);
+  /// ```
+  pub fn intersect(&self, other: &Schema) -> Schema {
+    intersect::intersect(self, other)
+  }
+
+  /// Checks a document against the Schema, i.e. as a lightweight contract in
+  /// an ETL pipeline. Reports fields the Schema has never seen, values with a
+  /// type the Schema has never seen for their field, and missing fields that
+  /// were present in every document. Subdocuments and documents in arrays are
+  /// checked too. Returns no violations for a matching document.
+  ///
+  /// # Arguments
+  /// * `doc` - Document to check.
+  ///
+  /// # Examples
+  /// ```
+  /// use bson::{bson, doc};
+  /// use mongodb_schema_parser::{SchemaParser, Violation};
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let schema = schema_parser.flush();
+  /// let violations = schema.validate(&doc! {"name": 4, "type": "Cat"});
+  /// assert_eq!(
+  ///   violations,
+  ///   vec![Violation::UnexpectedType {
+  ///     path: "name".to_string(),
+  ///     bson_type: "Int32".to_string(),
+  ///   }]
+  /// );
+  /// ```
+  pub fn validate(&self, doc: &Document) -> Vec<Violation> {
+    validator::validate(&self.fields, doc)
+  }
+
+  /// Scores how unusual a document is compared to the Schema, from 0.0 for a
+  /// document like most others to 1.0 for one with nothing in common with
+  /// them, i.e. to find outlier documents when auditing a collection. Rare
+  /// fields, rare types, numbers outside the range of sampled values, unknown
+  /// fields and missing common fields all add to the score.
+  ///
+  /// # Arguments
+  /// * `doc` - Document to score.
+  ///
+  /// # Examples
+  /// ```
+  /// use bson::{bson, doc};
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// schema_parser.write_json(r#"{ "name": "Nori", "type": "Cat" }"#);
+  /// let schema = schema_parser.flush();
+  /// let typical = schema.anomaly_score(&doc! {"name": "Rey", "type": "Dog"});
+  /// let outlier = schema.anomaly_score(&doc! {"name": 4, "color": "red"});
+  /// assert!(outlier > typical);
+  /// ```
+  pub fn anomaly_score(&self, doc: &Document) -> f64 {
+    anomaly::anomaly_score(&self.fields, doc)
+  }
+
+  /// Returns a hash of the Schema's structure, i.e. to cheaply tell whether a
+  /// collection's schema changed between two CI runs. Only the paths of
+  /// fields and the types seen for them are hashed, so the fingerprint
+  /// doesn't change with counts, sampled values or field order. It is the
+  /// same across platforms and versions of Rust.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let fingerprint = schema_parser.flush().fingerprint();
+  /// schema_parser.write_json(r#"{ "type": "Dog", "name": "Rey" }"#);
+  /// assert_eq!(schema_parser.flush().fingerprint(), fingerprint);
+  /// ```
+  pub fn fingerprint(&self) -> String {
+    fingerprint::fingerprint(self.fields())
+  }
+
+  /// Returns a serde_json string of the Schema.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// let json = r#"{ "name": "Chashu", "type": "Cat" }"#;
+  /// schema_parser.write_json(&json);
+  /// let json = schema_parser.flush().to_json().unwrap();
+  /// println!("{}", json);
+  /// ```
+  #[inline]
+  pub fn to_json(&self) -> Result<String, ParseError> {
+    serde_json::to_string(&self).map_err(ParseError::Serialization)
+  }
+
+  /// Serializes the Schema as json straight into a writer, i.e. a file or a
+  /// socket, a bit at a time, so the whole output is never held in memory.
+  /// The output is the same as `to_json()`'s.
+  ///
+  /// # Arguments
+  /// * `writer` - Anything implementing `io::Write`. Writes are small, so
+  /// unbuffered writers are best wrapped in an `io::BufWriter`.
+  ///
+  /// # Examples
+  /// ```no_run
+  /// use mongodb_schema_parser::SchemaParser;
+  /// use std::fs::File;
+  /// use std::io::BufWriter;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let file = BufWriter::new(File::create("schema.json").unwrap());
+  /// schema_parser.flush().write_json_to(file).unwrap();
+  /// ```
+  pub fn write_json_to<W: io::Write>(
+    &self,
+    writer: W,
+  ) -> Result<(), ParseError> {
+    serde_json::to_writer(writer, &self).map_err(|e| {
+      if e.is_io() {
+        ParseError::Io(e.into())
+      } else {
+        ParseError::Serialization(e)
+      }
+    })
+  }
+
+  /// Returns the Schema as a JSON Schema draft-07 document. Fields present in
+  /// at least `required_threshold` of all documents are listed as required.
+  ///
+  /// # Arguments
+  /// * `required_threshold` - Probability from 0.0 to 1.0 a field needs to be
+  /// required.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let json_schema = schema_parser.flush().to_json_schema(1.0);
+  /// assert_eq!(json_schema["required"][0], "name");
+  /// ```
+  pub fn to_json_schema(&self, required_threshold: f32) -> Value {
+    let bounds = Bounds::default();
+    json_schema::to_json_schema(self.fields(), required_threshold, bounds)
+  }
+
+  /// Returns a MongoDB collection validator for the Schema, i.e.
+  /// `{ "$jsonSchema": { "bsonType": "object", ... } }`.
+  ///
+  /// # Arguments
+  /// * `required_threshold` - Probability from 0.0 to 1.0 a field needs to be
+  /// required.
+  /// * `additional_properties` - Whether documents may have fields that
+  /// weren't seen during analysis.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let validator = schema_parser.flush().to_mongodb_validator(1.0, true);
+  /// assert!(validator.contains_key("$jsonSchema"));
+  /// ```
+  pub fn to_mongodb_validator(
+    &self,
+    required_threshold: f32,
+    additional_properties: bool,
+  ) -> Document {
+    let bounds = Bounds::default();
+    self.validator(required_threshold, additional_properties, bounds)
+  }
+
+  // MongoDB collection validator, with numbers and strings bounded by the
+  // ranges seen as set in `bounds`.
+  pub(crate) fn validator(
+    &self,
+    required_threshold: f32,
+    additional_properties: bool,
+    bounds: Bounds,
+  ) -> Document {
+    let bson_schema = json_schema::to_bson_schema(
+      self.fields(),
+      required_threshold,
+      additional_properties,
+      bounds,
+    );
+    let mut validator = Document::new();
+    validator.insert("$jsonSchema", Bson::from(bson_schema));
+    validator
+  }
+
+  /// Returns an Avro record schema for the Schema. Fields with several types
+  /// become unions, and fields that aren't present or are null in some
+  /// documents become optional unions with "null".
+  ///
+  /// # Arguments
+  /// * `name` - Name of the Avro record, i.e. the collection name.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let avro = schema_parser.flush().to_avro("pets");
+  /// assert_eq!(avro["type"], "record");
+  /// ```
+  pub fn to_avro(&self, name: &str) -> Value {
+    avro::to_avro(self.fields(), name)
+  }
+
+  /// Returns TypeScript interface declarations for the Schema. Subdocuments
+  /// get their own interfaces, fields with several types become union types.
+  ///
+  /// # Arguments
+  /// * `name` - Name of the top level interface, i.e. the collection name.
+  /// * `required_threshold` - Probability from 0.0 to 1.0 a field needs to not
+  /// be optional.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let typescript = schema_parser.flush().to_typescript("pets", 1.0);
+  /// assert!(typescript.starts_with("export interface Pets {"));
+  /// ```
+  pub fn to_typescript(&self, name: &str, required_threshold: f32) -> String {
+    typescript::to_typescript(self.fields(), name, required_threshold)
+  }
+
+  /// Returns Rust struct definitions with serde attributes for the Schema.
+  /// Subdocuments get their own structs, and fields with several types get an
+  /// untagged enum, or `bson::Bson` when a type has no Rust equivalent.
+  ///
+  /// # Arguments
+  /// * `name` - Name of the top level struct, i.e. the collection name.
+  /// * `required_threshold` - Probability from 0.0 to 1.0 a field needs to not
+  /// be an `Option`.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let rust = schema_parser.flush().to_rust_structs("pets", 1.0);
+  /// assert!(rust.contains("pub struct Pets {"));
+  /// ```
+  pub fn to_rust_structs(&self, name: &str, required_threshold: f32) -> String {
+    codegen::to_rust_structs(self.fields(), name, required_threshold)
+  }
+
+  /// Returns GraphQL SDL object type definitions for the Schema. Subdocuments
+  /// get their own types, arrays become list types, and types GraphQL has no
+  /// scalar for are declared as custom scalars.
+  ///
+  /// # Arguments
+  /// * `name` - Name of the top level type, i.e. the collection name.
+  /// * `required_threshold` - Probability from 0.0 to 1.0 a field needs to be
+  /// non-null.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let sdl = schema_parser.flush().to_graphql_sdl("pets", 1.0);
+  /// assert!(sdl.starts_with("type Pets {"));
+  /// ```
+  pub fn to_graphql_sdl(&self, name: &str, required_threshold: f32) -> String {
+    graphql::to_graphql_sdl(self.fields(), name, required_threshold)
+  }
+
+  /// Returns proto3 message definitions for the Schema. Subdocuments get their
+  /// own messages, arrays become `repeated` fields, and fields with several
+  /// types use `google.protobuf.Value`.
+  ///
+  /// # Arguments
+  /// * `name` - Name of the top level message, i.e. the collection name.
+  /// * `required_threshold` - Probability from 0.0 to 1.0 a scalar field needs
+  /// to not be `optional`.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let proto = schema_parser.flush().to_proto("pets", 1.0);
+  /// assert!(proto.contains("message Pets {"));
+  /// ```
+  pub fn to_proto(&self, name: &str, required_threshold: f32) -> String {
+    proto::to_proto(self.fields(), name, required_threshold)
+  }
+
+  /// Returns the source of a Mongoose model for the Schema. Subdocuments get
+  /// their own schemas, arrays are declared with their element type, and
+  /// fields with several types are `Schema.Types.Mixed`.
+  ///
+  /// # Arguments
+  /// * `name` - Name of the model, i.e. the collection name.
+  /// * `required_threshold` - Probability from 0.0 to 1.0 a field needs to be
+  /// `required`.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let mongoose = schema_parser.flush().to_mongoose_schema("pets", 1.0);
+  /// assert!(mongoose.contains("const petsSchema = new Schema({"));
+  /// ```
+  pub fn to_mongoose_schema(
+    &self,
+    name: &str,
+    required_threshold: f32,
+  ) -> String {
+    mongoose::to_mongoose_schema(self.fields(), name, required_threshold)
+  }
+
+  /// Returns an OpenAPI 3.0 document with a `components.schemas` entry for
+  /// the Schema. Nulls make fields `nullable`, fields with several types get
+  /// a `oneOf`, and dates, UUIDs and numbers get a `format`.
+  ///
+  /// # Arguments
+  /// * `name` - Name of the component schema, i.e. the collection name.
+  /// * `required_threshold` - Probability from 0.0 to 1.0 a field needs to be
+  /// required.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let openapi = schema_parser.flush().to_openapi("pets", 1.0);
+  /// let pets = &openapi["components"]["schemas"]["Pets"];
+  /// assert_eq!(pets["type"], "object");
+  /// ```
+  pub fn to_openapi(&self, name: &str, required_threshold: f32) -> Value {
+    openapi::to_openapi(self.fields(), name, required_threshold)
+  }
+
+  /// Returns a Markdown report of the Schema, with a table listing each
+  /// field's path, types with percentages, probability, sample values and
+  /// cardinality, followed by a table for each subdocument.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let markdown = schema_parser.flush().to_markdown();
+  /// assert!(markdown.starts_with("# Schema"));
+  /// ```
+  pub fn to_markdown(&self) -> String {
+    markdown::to_markdown(self.fields(), self.count)
+  }
+
+  /// Returns a self-contained HTML report of the Schema, with a collapsible
+  /// tree of fields, each field's type distribution, and a histogram of its
+  /// most common sampled values.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let html = schema_parser.flush().to_html_report();
+  /// assert!(html.starts_with("<!DOCTYPE html>"));
+  /// ```
+  pub fn to_html_report(&self) -> String {
+    report::to_html_report(self.fields(), self.count)
+  }
+
+  /// Returns CSV with a row of statistics per field path: its probability,
+  /// dominant type, type breakdown, distinct value count, and min and max.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let csv = schema_parser.flush().to_csv();
+  /// assert!(csv.starts_with("path,probability,dominant_type"));
+  /// ```
+  pub fn to_csv(&self) -> String {
+    csv::to_csv(self.fields())
+  }
+
+  /// Returns the Schema as an Apache Arrow schema, in Arrow's JSON format.
+  /// Subdocuments become structs, arrays become lists, and fields with
+  /// several types become dense unions.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let arrow = schema_parser.flush().to_arrow_schema();
+  /// assert_eq!(arrow["fields"][0]["type"]["name"], "utf8");
+  /// ```
+  pub fn to_arrow_schema(&self) -> Value {
+    arrow::to_arrow_schema(self.fields())
+  }
+
+  /// Returns the Schema as a Parquet schema message. Fields with several
+  /// types are stored as strings, as Parquet has no unions.
+  ///
+  /// # Arguments
+  /// * `name` - Name of the message, i.e. the collection name.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let parquet = schema_parser.flush().to_parquet_schema("pets");
+  /// assert!(parquet.contains("required binary name (STRING);"));
+  /// ```
+  pub fn to_parquet_schema(&self, name: &str) -> String {
+    arrow::to_parquet_schema(self.fields(), name)
+  }
+
+  /// Returns `CREATE TABLE` statements for a flattened relational projection
+  /// of the Schema. Subdocument fields become dot notation columns, arrays
+  /// become child tables, and column types are chosen from each field's most
+  /// common type.
+  ///
+  /// # Arguments
+  /// * `name` - Name of the top level table, i.e. the collection name.
+  /// * `dialect` - SQL dialect to write the statements in.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::{SchemaParser, SqlDialect};
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let sql = schema_parser.flush().to_sql_ddl("pets", SqlDialect::Postgres);
+  /// assert!(sql.starts_with("CREATE TABLE \"pets\" ("));
+  /// ```
+  pub fn to_sql_ddl(&self, name: &str, dialect: SqlDialect) -> String {
+    sql::to_sql_ddl(self.fields(), name, dialect)
+  }
+
+  /// Returns the Schema in the structure the npm `mongodb-schema` package
+  /// produces, so it can be consumed by tools like Compass. Fields are sorted
+  /// by name, types by probability, and type names match the js bson
+  /// library's, i.e. `ObjectID` or `Date`.
+  ///
+  /// # Examples
+  /// ```
+  /// use mongodb_schema_parser::SchemaParser;
+  ///
+  /// let mut schema_parser = SchemaParser::new();
+  /// schema_parser.write_json(r#"{ "name": "Chashu", "type": "Cat" }"#);
+  /// let compat = schema_parser.flush().to_json_compat();
+  /// assert_eq!(compat["fields"][0]["type"], "String");
+  /// ```
+  pub fn to_json_compat(&self) -> Value {
+    compat::to_json_compat(self.fields(), self.count)
+  }
+}
@@ -0,0 +1,84 @@
+use bson::{Bson, Document};
+
+/// Size of a key and its value encoded as an element of a BSON document: a
+/// type byte, the key as a C string, and the value.
+pub(crate) fn element_size(key: &str, value: &Bson) -> usize {
+  1 + key.len() + 1 + value_size(value)
+}
+
+/// Size of an encoded document: its length, its elements and a terminating
+/// null byte.
+pub(crate) fn document_size(doc: &Document) -> usize {
+  let elements: usize = doc
+    .iter()
+    .map(|(key, value)| element_size(key, value))
+    .sum();
+  4 + elements + 1
+}
+
+fn value_size(value: &Bson) -> usize {
+  match value {
+    Bson::FloatingPoint(_) | Bson::I64(_) | Bson::TimeStamp(_) => 8,
+    Bson::UtcDatetime(_) => 8,
+    Bson::I32(_) => 4,
+    Bson::Boolean(_) => 1,
+    Bson::Null => 0,
+    Bson::ObjectId(_) => 12,
+    Bson::Decimal128(_) => 16,
+    Bson::String(string)
+    | Bson::JavaScriptCode(string)
+    | Bson::Symbol(string) => string_size(string),
+    Bson::Binary(_, bytes) => 4 + 1 + bytes.len(),
+    Bson::RegExp(pattern, options) => pattern.len() + 1 + options.len() + 1,
+    Bson::JavaScriptCodeWithScope(code, scope) => {
+      4 + string_size(code) + document_size(scope)
+    }
+    Bson::Document(doc) => document_size(doc),
+    // arrays are documents keyed by their indexes
+    Bson::Array(values) => {
+      let elements: usize = values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| element_size(&i.to_string(), value))
+        .sum();
+      4 + elements + 1
+    }
+  }
+}
+
+// length, UTF-8 bytes and a terminating null byte
+fn string_size(string: &str) -> usize {
+  4 + string.len() + 1
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use bson::{bson, doc, encode_document};
+
+  fn encoded_size(doc: &Document) -> usize {
+    let mut bytes = Vec::new();
+    encode_document(&mut bytes, doc).unwrap();
+    bytes.len()
+  }
+
+  #[test]
+  fn it_sizes_elements() {
+    assert_eq!(element_size("age", &Bson::I32(9)), 1 + 4 + 4);
+    assert_eq!(element_size("name", &Bson::String("Nori".into())), 15);
+  }
+
+  #[test]
+  fn it_sizes_documents_as_encoded() {
+    let doc = doc! {
+      "name": "Chashu",
+      "age": 5.5,
+      "born": Bson::I64(1_565_545_664),
+      "toys": ["ball", "mouse"],
+      "vet": { "name": "Dr. Koch", "visits": [1, 2, 3] },
+      "neutered": true,
+      "chip": Bson::Null,
+    };
+    assert_eq!(document_size(&doc), encoded_size(&doc));
+  }
+}
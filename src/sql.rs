@@ -0,0 +1,397 @@
+use super::{Field, FieldType};
+use crate::field_type;
+
+static TEXT: &str = "TEXT";
+
+/// SQL dialect to write `CREATE TABLE` statements for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SqlDialect {
+  Postgres,
+  MySql,
+}
+
+#[derive(Debug, Clone)]
+struct Column {
+  name: String,
+  sql_type: &'static str,
+  not_null: bool,
+}
+
+/// Converts finalised fields into `CREATE TABLE` statements for a flattened
+/// relational projection. Subdocument fields become columns named with dot
+/// notation, i.e. `owner.name`, and arrays become child tables keyed by the
+/// document's `_id` and the element's index. Each column's type is chosen
+/// from its field's most common type.
+pub(crate) fn to_sql_ddl<'a>(
+  fields: impl Iterator<Item = &'a Field>,
+  name: &str,
+  dialect: SqlDialect,
+) -> String {
+  let fields: Vec<&Field> = fields.collect();
+  let mut writer = Writer {
+    root: table_name(name),
+    dialect,
+    tables: Vec::new(),
+  };
+
+  // the root document's `_id` is the primary key, and is referenced by the
+  // tables of its arrays
+  let id = fields
+    .iter()
+    .find(|field| &*field.name == "_id")
+    .and_then(|field| {
+      let dominant = value_types(field.types()).into_iter().next()?;
+      let not_null = field.probability >= 1.0 && !is_nullable(field.types());
+      Some(Column {
+        name: "_id".to_string(),
+        sql_type: writer.key_type(dominant),
+        not_null,
+      })
+    })
+    .filter(|id| id.not_null);
+
+  let mut columns = Vec::new();
+  let mut keys = Vec::new();
+  if let Some(id) = &id {
+    keys.push(Column {
+      name: format!("{}._id", writer.root),
+      ..id.clone()
+    });
+  }
+  let index = writer.reserve();
+  writer.columns(fields.into_iter(), "", true, &keys, &mut columns);
+  if let Some(id) = &id {
+    for column in columns.iter_mut().filter(|column| column.name == "_id") {
+      column.sql_type = id.sql_type;
+    }
+  }
+  let primary_key: Vec<Column> = id.into_iter().collect();
+  let root = writer.root.clone();
+  writer.tables[index] = writer.create_table(&root, &columns, &primary_key);
+  writer.tables.join("\n")
+}
+
+struct Writer {
+  root: String,
+  dialect: SqlDialect,
+  tables: Vec<String>,
+}
+
+impl Writer {
+  // Child tables are written after the table that uses them.
+  fn reserve(&mut self) -> usize {
+    self.tables.push(String::new());
+    self.tables.len() - 1
+  }
+
+  // Adds a column for each field, flattening subdocuments, and writes a child
+  // table for each array. `prefix` is the path of the array the fields are
+  // in, which is left out of column names.
+  fn columns<'a>(
+    &mut self,
+    fields: impl Iterator<Item = &'a Field>,
+    prefix: &str,
+    required: bool,
+    keys: &[Column],
+    columns: &mut Vec<Column>,
+  ) {
+    for field in fields {
+      let name = if field.path.starts_with(prefix) {
+        field.path[prefix.len()..].to_string()
+      } else {
+        field.path.to_string()
+      };
+      let not_null =
+        required && field.probability >= 1.0 && !is_nullable(field.types());
+      let dominant = value_types(field.types()).into_iter().next();
+      match dominant {
+        Some(field_type) if field_type.schema().is_some() => {
+          if let Some(schema) = field_type.schema() {
+            self.columns(schema.fields(), prefix, not_null, keys, columns);
+          }
+        }
+        Some(field_type) if field_type.bson_type == field_type::ARRAY => {
+          self.child_table(field_type, keys);
+        }
+        Some(field_type) => columns.push(Column {
+          name,
+          sql_type: self.sql_type(field_type),
+          not_null,
+        }),
+        None => columns.push(Column {
+          name,
+          sql_type: TEXT,
+          not_null: false,
+        }),
+      }
+    }
+  }
+
+  // Tables for arrays have the keys of their parent table, and the index of
+  // the element. Documents in arrays are flattened into columns, and other
+  // elements get a `value` column.
+  fn child_table(&mut self, array: &FieldType, keys: &[Column]) {
+    let mut keys = keys.to_vec();
+    keys.push(Column {
+      name: format!("{}._index", array.path),
+      sql_type: "INTEGER",
+      not_null: true,
+    });
+    let table = format!("{}_{}", self.root, table_name(&array.path));
+    let index = self.reserve();
+
+    let mut columns = keys.clone();
+    let dominant = value_types(array.types()).into_iter().next();
+    let not_null = !is_nullable(array.types());
+    match dominant {
+      Some(element) if element.schema().is_some() => {
+        if let Some(schema) = element.schema() {
+          let prefix = format!("{}.", array.path);
+          self.columns(schema.fields(), &prefix, not_null, &keys, &mut columns);
+        }
+      }
+      // arrays of arrays are kept as json
+      Some(element) if element.bson_type == field_type::ARRAY => {
+        columns.push(Column {
+          name: "value".to_string(),
+          sql_type: self.json(),
+          not_null,
+        })
+      }
+      Some(element) => columns.push(Column {
+        name: "value".to_string(),
+        sql_type: self.sql_type(element),
+        not_null,
+      }),
+      None => columns.push(Column {
+        name: "value".to_string(),
+        sql_type: TEXT,
+        not_null: false,
+      }),
+    }
+
+    // without an `_id` there's nothing to identify the parent document by
+    let root_key = format!("{}._id", self.root);
+    let primary_key = if keys.iter().any(|key| key.name == root_key) {
+      keys
+    } else {
+      Vec::new()
+    };
+    self.tables[index] = self.create_table(&table, &columns, &primary_key);
+  }
+
+  fn create_table(
+    &self,
+    table: &str,
+    columns: &[Column],
+    primary_key: &[Column],
+  ) -> String {
+    let mut lines: Vec<String> = columns
+      .iter()
+      .map(|column| {
+        let not_null = if column.not_null { " NOT NULL" } else { "" };
+        format!(
+          "  {} {}{}",
+          self.quote(&column.name),
+          column.sql_type,
+          not_null
+        )
+      })
+      .collect();
+    if !primary_key.is_empty() {
+      let keys: Vec<String> = primary_key
+        .iter()
+        .map(|key| self.quote(&key.name))
+        .collect();
+      lines.push(format!("  PRIMARY KEY ({})", keys.join(", ")));
+    }
+    // child tables reference the root document
+    let root_key = format!("{}._id", self.root);
+    if primary_key.iter().any(|key| key.name == root_key) {
+      lines.push(format!(
+        "  FOREIGN KEY ({}) REFERENCES {} ({})",
+        self.quote(&root_key),
+        self.quote(&self.root),
+        self.quote("_id")
+      ));
+    }
+    format!(
+      "CREATE TABLE {} (\n{}\n);\n",
+      self.quote(table),
+      lines.join(",\n")
+    )
+  }
+
+  fn sql_type(&self, field_type: &FieldType) -> &'static str {
+    let postgres = self.dialect == SqlDialect::Postgres;
+    match field_type.bson_type.as_str() {
+      t if t == field_type::FLOATING_POINT || t == field_type::NUMBER => {
+        if postgres {
+          "DOUBLE PRECISION"
+        } else {
+          "DOUBLE"
+        }
+      }
+      t if t == field_type::I32 => "INTEGER",
+      t if t == field_type::I64 => "BIGINT",
+      t if t == field_type::TIMESTAMP => {
+        if postgres {
+          "BIGINT"
+        } else {
+          "BIGINT UNSIGNED"
+        }
+      }
+      t if t == field_type::DECIMAL_128 => {
+        if postgres {
+          "NUMERIC"
+        } else {
+          "DECIMAL(65, 30)"
+        }
+      }
+      t if t == field_type::BOOLEAN => "BOOLEAN",
+      t if t == field_type::UTCDATE_TIME => {
+        if postgres {
+          "TIMESTAMPTZ"
+        } else {
+          "DATETIME(3)"
+        }
+      }
+      t if t == field_type::OBJECTID => "CHAR(24)",
+      t if t == field_type::BINARY => {
+        if postgres {
+          "BYTEA"
+        } else {
+          "LONGBLOB"
+        }
+      }
+      // documents past the max depth
+      t if t == field_type::DOCUMENT => self.json(),
+      _ => TEXT,
+    }
+  }
+
+  // MySQL can't index TEXT columns without a prefix length.
+  fn key_type(&self, field_type: &FieldType) -> &'static str {
+    match self.sql_type(field_type) {
+      "TEXT" if self.dialect == SqlDialect::MySql => "VARCHAR(255)",
+      sql_type => sql_type,
+    }
+  }
+
+  fn json(&self) -> &'static str {
+    match self.dialect {
+      SqlDialect::Postgres => "JSONB",
+      SqlDialect::MySql => "JSON",
+    }
+  }
+
+  fn quote(&self, identifier: &str) -> String {
+    match self.dialect {
+      SqlDialect::Postgres => {
+        format!("\"{}\"", identifier.replace('"', "\"\""))
+      }
+      SqlDialect::MySql => format!("`{}`", identifier.replace('`', "``")),
+    }
+  }
+}
+
+// Types of the values a field has, most common first.
+fn value_types<'a>(
+  types: impl Iterator<Item = &'a FieldType>,
+) -> Vec<&'a FieldType> {
+  let mut types: Vec<&FieldType> = types
+    .filter(|t| {
+      t.bson_type != field_type::NULL && t.bson_type != field_type::UNDEFINED
+    })
+    .collect();
+  types.sort_by(|a, b| {
+    b.count
+      .cmp(&a.count)
+      .then_with(|| a.bson_type.cmp(&b.bson_type))
+  });
+  types
+}
+
+fn is_nullable<'a>(mut types: impl Iterator<Item = &'a FieldType>) -> bool {
+  types.any(|t| {
+    t.bson_type == field_type::NULL || t.bson_type == field_type::UNDEFINED
+  })
+}
+
+fn table_name(name: &str) -> String {
+  name
+    .chars()
+    .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::SchemaParser;
+
+  #[test]
+  fn it_converts_to_postgres_ddl() {
+    let mut schema_parser = SchemaParser::new();
+    let json_str1 = r#"{
+      "_id": {"$oid": "5d505646cf6d4fe581014ab2"},
+      "name": "Nori",
+      "owner": {"name": "Ana", "born": {"$date": "1990-04-01T00:00:00Z"}},
+      "tags": ["cat"],
+      "visits": [{"vet": "Rey", "cost": 20.5}]
+    }"#;
+    let json_str2 = r#"{
+      "_id": {"$oid": "5d505646cf6d4fe581014ab3"},
+      "name": null,
+      "owner": {"name": "Ana"},
+      "tags": []
+    }"#;
+    schema_parser.write_json(&json_str1).unwrap();
+    schema_parser.write_json(&json_str2).unwrap();
+    let schema = schema_parser.flush();
+    let sql = to_sql_ddl(schema.fields(), "pets", SqlDialect::Postgres);
+    assert_eq!(
+      sql,
+      r#"CREATE TABLE "pets" (
+  "_id" CHAR(24) NOT NULL,
+  "name" TEXT,
+  "owner.name" TEXT NOT NULL,
+  "owner.born" TIMESTAMPTZ,
+  PRIMARY KEY ("_id")
+);
+
+CREATE TABLE "pets_tags" (
+  "pets._id" CHAR(24) NOT NULL,
+  "tags._index" INTEGER NOT NULL,
+  "value" TEXT NOT NULL,
+  PRIMARY KEY ("pets._id", "tags._index"),
+  FOREIGN KEY ("pets._id") REFERENCES "pets" ("_id")
+);
+
+CREATE TABLE "pets_visits" (
+  "pets._id" CHAR(24) NOT NULL,
+  "visits._index" INTEGER NOT NULL,
+  "vet" TEXT NOT NULL,
+  "cost" DOUBLE PRECISION NOT NULL,
+  PRIMARY KEY ("pets._id", "visits._index"),
+  FOREIGN KEY ("pets._id") REFERENCES "pets" ("_id")
+);
+"#
+    );
+  }
+
+  #[test]
+  fn it_converts_to_mysql_ddl() {
+    let mut schema_parser = SchemaParser::new();
+    schema_parser
+      .write_json(r#"{"_id": "nori", "age": 5}"#)
+      .unwrap();
+    let schema = schema_parser.flush();
+    let sql = to_sql_ddl(schema.fields(), "pets", SqlDialect::MySql);
+    assert_eq!(
+      sql,
+      "CREATE TABLE `pets` (\n  `_id` VARCHAR(255) NOT NULL,\n  \
+       `age` INTEGER NOT NULL,\n  PRIMARY KEY (`_id`)\n);\n"
+    );
+  }
+}
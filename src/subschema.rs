@@ -0,0 +1,122 @@
+use super::{Field, FieldType, IndexMap, Schema, SchemaParser};
+use crate::field;
+use std::sync::Arc;
+
+/// Returns the schema of the subdocuments at `path` as a standalone Schema,
+/// with paths relative to the subdocuments and a count of how many there
+/// are, which the probabilities of their fields are already relative to.
+/// Documents in arrays at `path` are subdocuments too, and are combined with
+/// the others as with `Schema::union()`.
+pub(crate) fn subschema(schema: &Schema, path: &str) -> Option<Schema> {
+  let field = field::find_field(&schema.fields, path)?;
+  let mut subdocuments = Vec::new();
+  for field_type in field.types() {
+    collect_subdocuments(field_type, &mut subdocuments);
+  }
+  // paths of the subdocuments' fields start with `path.`
+  let prefix_len = field.path.len() + 1;
+  subdocuments
+    .into_iter()
+    .map(|schema_parser| rebase(schema_parser, prefix_len))
+    .fold(None, |subschema: Option<Schema>, schema| match subschema {
+      Some(subschema) => Some(subschema.union(&schema)),
+      None => Some(schema),
+    })
+}
+
+fn collect_subdocuments<'a>(
+  field_type: &'a FieldType,
+  subdocuments: &mut Vec<&'a SchemaParser>,
+) {
+  if let Some(schema) = field_type.schema() {
+    subdocuments.push(schema);
+  }
+  for element_type in field_type.types() {
+    collect_subdocuments(element_type, subdocuments);
+  }
+}
+
+fn rebase(schema_parser: &SchemaParser, prefix_len: usize) -> Schema {
+  let fields = rebase_fields(&schema_parser.fields, prefix_len);
+  Schema::new(schema_parser.count, fields)
+}
+
+fn rebase_fields(
+  fields: &IndexMap<String, Field>,
+  prefix_len: usize,
+) -> IndexMap<String, Field> {
+  fields
+    .values()
+    .map(|field| {
+      let mut field = field.clone();
+      field.path = field.path[prefix_len..].into();
+      for field_type in field.types.values_mut() {
+        rebase_type(field_type, &field.path, prefix_len);
+      }
+      (field.path.to_string(), field)
+    })
+    .collect()
+}
+
+fn rebase_type(field_type: &mut FieldType, path: &Arc<str>, prefix_len: usize) {
+  field_type.path = path.clone();
+  if let Some(schema) = field_type.schema.as_mut() {
+    schema.fields = rebase_fields(&schema.fields, prefix_len);
+  }
+  for element_type in field_type.types.values_mut() {
+    rebase_type(element_type, path, prefix_len);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn schema(documents: &[&str]) -> Schema {
+    let mut schema_parser = SchemaParser::new();
+    for json in documents {
+      schema_parser.write_json(json).unwrap();
+    }
+    schema_parser.flush()
+  }
+
+  #[test]
+  #[allow(clippy::float_cmp)]
+  fn it_rebases_the_subtree() {
+    let schema = schema(&[
+      r#"{"payment": {"card": {"number": "4242", "cvc": {"code": 123}}}}"#,
+      r#"{"payment": {"card": {"number": "4000"}}}"#,
+      r#"{"payment": {"iban": "DE89"}}"#,
+    ]);
+    let card = schema.subschema("payment.card").unwrap();
+    assert_eq!(card.count, 2);
+    let paths: Vec<&str> = card.iter_fields().map(|(path, _)| path).collect();
+    assert_eq!(paths, vec!["number", "cvc", "cvc.code"]);
+
+    let cvc = card.get_field("cvc").unwrap();
+    assert_eq!(cvc.probability, 0.5);
+    let code = card.get_field("cvc.code").unwrap();
+    assert_eq!(&*code.types["Int32"].path, "cvc.code");
+  }
+
+  #[test]
+  #[allow(clippy::float_cmp)]
+  fn it_combines_documents_in_arrays() {
+    let schema = schema(&[
+      r#"{"card": {"number": "4242"}}"#,
+      r#"{"card": [{"number": "4000"}, {"cvc": 123}]}"#,
+    ]);
+    let card = schema.subschema("card").unwrap();
+    assert_eq!(card.count, 3);
+    assert_eq!(card.get_field("number").unwrap().probability, 2.0 / 3.0);
+    assert_eq!(card.get_field("cvc").unwrap().probability, 1.0 / 3.0);
+  }
+
+  #[test]
+  fn it_returns_none_without_subdocuments() {
+    let schema = schema(&[r#"{"name": "Nori", "tags": ["cat"]}"#]);
+    assert!(schema.subschema("name").is_none());
+    assert!(schema.subschema("tags").is_none());
+    assert!(schema.subschema("owner").is_none());
+  }
+}
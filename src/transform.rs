@@ -0,0 +1,315 @@
+use super::{Field, FieldType, IndexMap, Schema, SchemaParser};
+use crate::field;
+use std::sync::Arc;
+
+/// Curates a finalised Schema before it's exported, i.e. to give fields the
+/// names an API uses, leave out internal fields, or pin the type of a field
+/// that's sometimes written wrong. Changes are applied in the order they were
+/// added, each to the result of the ones before, and counts and
+/// probabilities are recomputed afterwards.
+///
+/// # Examples
+/// ```
+/// use mongodb_schema_parser::{SchemaParser, SchemaTransform};
+///
+/// let mut schema_parser = SchemaParser::new();
+/// schema_parser.write_json(r#"{ "nm": "Chashu", "_v": 1, "age": "4" }"#);
+/// let transform = SchemaTransform::new()
+///   .rename("nm", "name")
+///   .remove("_v")
+///   .override_type("age", "Int32");
+/// let schema = transform.apply(&schema_parser.flush());
+/// let paths: Vec<&str> =
+///   schema.fields().map(|field| &*field.path).collect();
+/// assert_eq!(paths, vec!["name", "age"]);
+/// assert_eq!(schema.get_field("age").unwrap().bson_types, vec!["Int32"]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SchemaTransform {
+  changes: Vec<Change>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Change {
+  Rename { path: String, name: String },
+  Remove { path: String },
+  OverrideType { path: String, bson_type: String },
+}
+
+impl SchemaTransform {
+  /// Returns a transform that doesn't change anything yet.
+  pub fn new() -> Self {
+    SchemaTransform::default()
+  }
+
+  /// Renames the field at a dotted path, keeping it in the same document.
+  /// The paths of its subdocument fields change with it. Renaming it to the
+  /// name of another field combines the two, assuming no document had both.
+  ///
+  /// # Arguments
+  /// * `path` - Dotted path of the field, i.e. `owner.nm`.
+  /// * `name` - New name of the field, i.e. `name`.
+  pub fn rename<T, U>(mut self, path: T, name: U) -> Self
+  where
+    T: Into<String>,
+    U: Into<String>,
+  {
+    self.changes.push(Change::Rename {
+      path: path.into(),
+      name: name.into(),
+    });
+    self
+  }
+
+  /// Removes the field at a dotted path, along with its subdocument fields.
+  ///
+  /// # Arguments
+  /// * `path` - Dotted path of the field, i.e. `owner._v`.
+  pub fn remove<T: Into<String>>(mut self, path: T) -> Self {
+    self.changes.push(Change::Remove { path: path.into() });
+    self
+  }
+
+  /// Treats every value of the field at a dotted path as `bson_type`. The
+  /// stats of the field's values of that type are kept, and values of other
+  /// types are only counted.
+  ///
+  /// # Arguments
+  /// * `path` - Dotted path of the field, i.e. `price`.
+  /// * `bson_type` - Type name as used in the Schema, i.e. `Decimal128`.
+  pub fn override_type<T, U>(mut self, path: T, bson_type: U) -> Self
+  where
+    T: Into<String>,
+    U: Into<String>,
+  {
+    self.changes.push(Change::OverrideType {
+      path: path.into(),
+      bson_type: bson_type.into(),
+    });
+    self
+  }
+
+  /// Returns a copy of `schema` with the changes applied. Paths that aren't
+  /// in the Schema are ignored.
+  ///
+  /// # Arguments
+  /// * `schema` - Finalised Schema to transform.
+  pub fn apply(&self, schema: &Schema) -> Schema {
+    let mut schema_parser = SchemaParser::from_schema(schema.clone());
+    for change in &self.changes {
+      match change {
+        Change::Rename { path, name } => {
+          for_each_parent(&mut schema_parser, path, &mut |parent| {
+            rename(parent, path, name)
+          })
+        }
+        Change::Remove { path } => {
+          for_each_parent(&mut schema_parser, path, &mut |parent| {
+            parent.fields.retain(|key, _| key != path)
+          })
+        }
+        Change::OverrideType { path, bson_type } => {
+          for_each_parent(&mut schema_parser, path, &mut |parent| {
+            if let Some(field) = parent.fields.get_mut(path) {
+              override_type(field, bson_type);
+            }
+          })
+        }
+      }
+    }
+    schema_parser.into_schema()
+  }
+}
+
+// Calls `change` with each SchemaParser, at any depth, that has a field at
+// `path`. There can be several, i.e. when the field's parent is a subdocument
+// in some documents and an array of documents in others.
+fn for_each_parent<F>(
+  schema_parser: &mut SchemaParser,
+  path: &str,
+  change: &mut F,
+) where
+  F: FnMut(&mut SchemaParser),
+{
+  if schema_parser.fields.contains_key(path) {
+    change(schema_parser);
+    return;
+  }
+  for field in schema_parser.fields.values_mut() {
+    if field::is_parent_path(&field.path, path) {
+      for field_type in field.types.values_mut() {
+        for_each_parent_in_type(field_type, path, change);
+      }
+    }
+  }
+}
+
+fn for_each_parent_in_type<F>(
+  field_type: &mut FieldType,
+  path: &str,
+  change: &mut F,
+) where
+  F: FnMut(&mut SchemaParser),
+{
+  if let Some(schema) = field_type.schema.as_mut() {
+    for_each_parent(schema, path, change);
+  }
+  for element_type in field_type.types.values_mut() {
+    for_each_parent_in_type(element_type, path, change);
+  }
+}
+
+// Renames a field in place, so it keeps its position among its siblings.
+fn rename(parent: &mut SchemaParser, path: &str, name: &str) {
+  let fields = std::mem::replace(&mut parent.fields, IndexMap::new());
+  for (key, mut field) in fields {
+    if key == path {
+      let prefix_len = field.path.len() - field.name.len();
+      let new_path = format!("{}{}", &field.path[..prefix_len], name);
+      repath_field(&mut field, path, &new_path);
+      field.name = name.into();
+    }
+    match parent.fields.get_mut(&*field.path) {
+      Some(existing) => {
+        existing.merge(field);
+        // a field can't be in more documents than its parent
+        existing.count = existing.count.min(parent.count);
+      }
+      None => {
+        parent.fields.insert(field.path.to_string(), field);
+      }
+    }
+  }
+}
+
+// Moves a field, and the fields of its subdocuments, from one path to another.
+pub(crate) fn repath_field(field: &mut Field, from: &str, to: &str) {
+  field.path = replace_prefix(&field.path, from, to).into();
+  for field_type in field.types.values_mut() {
+    repath_type(field_type, &field.path, from, to);
+  }
+}
+
+// A type's path, and the paths of its element types, are the same as its
+// field's, and keep being shared with it.
+fn repath_type(
+  field_type: &mut FieldType,
+  path: &Arc<str>,
+  from: &str,
+  to: &str,
+) {
+  field_type.path = path.clone();
+  if let Some(schema) = field_type.schema.as_mut() {
+    let fields = std::mem::replace(&mut schema.fields, IndexMap::new());
+    schema.fields = fields
+      .into_iter()
+      .map(|(_, mut field)| {
+        repath_field(&mut field, from, to);
+        (field.path.to_string(), field)
+      })
+      .collect();
+  }
+  for element_type in field_type.types.values_mut() {
+    repath_type(element_type, path, from, to);
+  }
+}
+
+fn replace_prefix(path: &str, from: &str, to: &str) -> String {
+  if path == from {
+    to.to_string()
+  } else if field::is_parent_path(from, path) {
+    format!("{}{}", to, &path[from.len()..])
+  } else {
+    path.to_string()
+  }
+}
+
+// Fields aren't finalised while they're transformed, so there's no Undefined
+// type to keep.
+fn override_type(field: &mut Field, bson_type: &str) {
+  let count = field.types.values().map(|t| t.count).sum();
+  let mut field_type = field
+    .types
+    .remove(bson_type)
+    .unwrap_or_else(|| FieldType::new(field.path.clone(), bson_type));
+  field_type.count = count;
+  field.types.clear();
+  field.types.insert(bson_type.to_string(), field_type);
+  field.bson_types = vec![bson_type.to_string()];
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn schema(documents: &[&str]) -> Schema {
+    let mut schema_parser = SchemaParser::new();
+    for json in documents {
+      schema_parser.write_json(json).unwrap();
+    }
+    schema_parser.flush()
+  }
+
+  fn paths(schema: &Schema) -> Vec<&str> {
+    schema.iter_fields().map(|(path, _)| path).collect()
+  }
+
+  #[test]
+  fn it_renames_fields_and_their_subdocuments() {
+    let schema = schema(&[
+      r#"{"name": "Nori", "owner": {"nm": "Ana"}, "age": 5}"#,
+      r#"{"name": "Rey", "owner": [{"nm": "Ben"}]}"#,
+    ]);
+    let schema = SchemaTransform::new()
+      .rename("owner", "person")
+      .rename("person.nm", "name")
+      .apply(&schema);
+    assert_eq!(paths(&schema), vec!["name", "person", "person.name", "age"]);
+    let name = schema.get_field("person.name").unwrap();
+    assert_eq!(&*name.name, "name");
+    assert_eq!(&*name.types["String"].path, "person.name");
+  }
+
+  #[test]
+  #[allow(clippy::float_cmp)]
+  fn it_combines_renamed_fields() {
+    let schema = schema(&[
+      r#"{"addr": "Main St 1"}"#,
+      r#"{"address": "Main St 2"}"#,
+      r#"{"address": "Main St 3"}"#,
+    ]);
+    let schema = SchemaTransform::new()
+      .rename("addr", "address")
+      .apply(&schema);
+    assert_eq!(paths(&schema), vec!["address"]);
+    let address = schema.get_field("address").unwrap();
+    assert_eq!(address.probability, 1.0);
+    assert_eq!(address.types["String"].count, 3);
+  }
+
+  #[test]
+  fn it_removes_fields() {
+    let schema = schema(&[r#"{"name": "Nori", "owner": {"_v": 1, "a": 2}}"#]);
+    let schema = SchemaTransform::new()
+      .remove("owner._v")
+      .remove("missing")
+      .apply(&schema);
+    assert_eq!(paths(&schema), vec!["name", "owner", "owner.a"]);
+  }
+
+  #[test]
+  #[allow(clippy::float_cmp)]
+  fn it_overrides_types() {
+    let schema =
+      schema(&[r#"{"age": 5}"#, r#"{"age": "4"}"#, r#"{"name": "Rey"}"#]);
+    let schema = SchemaTransform::new()
+      .override_type("age", "Int32")
+      .apply(&schema);
+    let age = schema.get_field("age").unwrap();
+    assert_eq!(age.bson_types, vec!["Int32", "Undefined"]);
+    let int = &age.types["Int32"];
+    assert_eq!(int.count, 2);
+    assert_eq!(int.probability, 2.0 / 3.0);
+    assert_eq!(int.values().len(), 1);
+  }
+}
@@ -0,0 +1,253 @@
+use super::{Field, FieldType, ValueType};
+use crate::field_type;
+use std::collections::BTreeSet;
+
+/// Converts finalised fields into TypeScript interface declarations. The
+/// top level interface is called `name`, and each subdocument gets its own
+/// interface named after its path. Fields present in less than
+/// `required_threshold` of the documents are optional.
+pub(crate) fn to_typescript<'a>(
+  fields: impl Iterator<Item = &'a Field>,
+  name: &str,
+  required_threshold: f32,
+) -> String {
+  let mut writer = Writer {
+    root: pascal_case(name),
+    interfaces: Vec::new(),
+    imports: BTreeSet::new(),
+    required_threshold,
+  };
+  let root = writer.root.clone();
+  writer.interface(fields, &root);
+
+  let mut typescript = String::new();
+  if !writer.imports.is_empty() {
+    let imports: Vec<&str> = writer.imports.into_iter().collect();
+    typescript.push_str(&format!(
+      "import {{ {} }} from \"bson\";\n\n",
+      imports.join(", ")
+    ));
+  }
+  typescript.push_str(&writer.interfaces.join("\n"));
+  typescript
+}
+
+struct Writer {
+  root: String,
+  interfaces: Vec<String>,
+  imports: BTreeSet<&'static str>,
+  required_threshold: f32,
+}
+
+impl Writer {
+  // Nested interfaces are written after the interface that uses them, and
+  // are named after the top level interface and their path, i.e.
+  // `PetsOwnerAddress` for `owner.address`.
+  fn interface<'a>(
+    &mut self,
+    fields: impl Iterator<Item = &'a Field>,
+    name: &str,
+  ) {
+    let index = self.interfaces.len();
+    self.interfaces.push(String::new());
+
+    let mut interface = format!("export interface {} {{\n", name);
+    for field in fields {
+      let optional = if field.probability < self.required_threshold {
+        "?"
+      } else {
+        ""
+      };
+      let types = self.union(field.types());
+      interface.push_str(&format!(
+        "  {}{}: {};\n",
+        property_name(&field.name),
+        optional,
+        types
+      ));
+    }
+    interface.push_str("}\n");
+    self.interfaces[index] = interface;
+  }
+
+  fn union<'a>(
+    &mut self,
+    types: impl Iterator<Item = &'a FieldType>,
+  ) -> String {
+    let mut types: Vec<&FieldType> = types.collect();
+    types.sort_by(|a, b| a.bson_type.cmp(&b.bson_type));
+
+    // enum candidates are unions of their values' literal types
+    let mut union: Vec<String> = Vec::new();
+    for field_type in types {
+      let ts_types = match field_type.enum_values.as_ref() {
+        Some(values) => values.iter().filter_map(literal_type).collect(),
+        None => self.ts_type(field_type).into_iter().collect(),
+      };
+      for ts_type in ts_types {
+        if !union.contains(&ts_type) {
+          union.push(ts_type);
+        }
+      }
+    }
+    if union.is_empty() {
+      return "unknown".to_string();
+    }
+    union.join(" | ")
+  }
+
+  fn ts_type(&mut self, field_type: &FieldType) -> Option<String> {
+    let bson_class = |name: &'static str, imports: &mut BTreeSet<_>| {
+      imports.insert(name);
+      Some(name.to_string())
+    };
+    match field_type.bson_type.as_str() {
+      t if t == field_type::DOCUMENT => match field_type.schema() {
+        Some(schema) => {
+          let path = pascal_case(&field_type.path);
+          let name = format!("{}{}", self.root, path);
+          self.interface(schema.fields(), &name);
+          Some(name)
+        }
+        None => Some("Record<string, unknown>".to_string()),
+      },
+      t if t == field_type::ARRAY => {
+        let items = self.union(field_type.types());
+        Some(format!("Array<{}>", items))
+      }
+      t if t == field_type::FLOATING_POINT
+        || t == field_type::I32
+        || t == field_type::I64
+        || t == field_type::NUMBER =>
+      {
+        Some("number".to_string())
+      }
+      t if t == field_type::STRING || t == field_type::SYMBOL => {
+        Some("string".to_string())
+      }
+      t if t == field_type::BOOLEAN => Some("boolean".to_string()),
+      t if t == field_type::NULL => Some("null".to_string()),
+      t if t == field_type::UTCDATE_TIME => Some("Date".to_string()),
+      t if t == field_type::REGEXP => Some("RegExp".to_string()),
+      t if t == field_type::OBJECTID => {
+        bson_class("ObjectId", &mut self.imports)
+      }
+      t if t == field_type::DECIMAL_128 => {
+        bson_class("Decimal128", &mut self.imports)
+      }
+      t if t == field_type::BINARY => bson_class("Binary", &mut self.imports),
+      t if t == field_type::TIMESTAMP => {
+        bson_class("Timestamp", &mut self.imports)
+      }
+      t if t == field_type::JAVASCRIPT_CODE
+        || t == field_type::JAVASCRIPT_CODE_WITH_SCOPE =>
+      {
+        bson_class("Code", &mut self.imports)
+      }
+      // Undefined only makes a field optional
+      _ => None,
+    }
+  }
+}
+
+fn literal_type(value: &ValueType) -> Option<String> {
+  match value {
+    ValueType::Str(string) => serde_json::to_string(string).ok(),
+    ValueType::I32(num) => Some(num.to_string()),
+    ValueType::I64(num) => Some(num.to_string()),
+    _ => None,
+  }
+}
+
+pub(crate) fn pascal_case(name: &str) -> String {
+  let mut pascal_case = String::new();
+  for part in name.split(|c: char| !c.is_ascii_alphanumeric()) {
+    let mut chars = part.chars();
+    if let Some(first) = chars.next() {
+      pascal_case.push(first.to_ascii_uppercase());
+      pascal_case.push_str(chars.as_str());
+    }
+  }
+  if pascal_case.starts_with(|c: char| c.is_ascii_digit()) {
+    pascal_case.insert(0, '_');
+  }
+  pascal_case
+}
+
+// Field names that aren't valid identifiers are quoted.
+pub(crate) fn property_name(name: &str) -> String {
+  let is_identifier = !name.is_empty()
+    && !name.starts_with(|c: char| c.is_ascii_digit())
+    && name
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+  if is_identifier {
+    name.to_string()
+  } else {
+    serde_json::to_string(name).unwrap_or_else(|_| format!("\"{}\"", name))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::SchemaParser;
+
+  #[test]
+  fn it_converts_to_typescript() {
+    let mut schema_parser = SchemaParser::new();
+    let json_str1 = r#"{
+      "_id": {"$oid": "5d505646cf6d4fe581014ab2"},
+      "name": "Nori",
+      "age": 5,
+      "tags": ["cat"]
+    }"#;
+    let json_str2 = r#"{
+      "_id": {"$oid": "5d505646cf6d4fe581014ab3"},
+      "name": "Rey",
+      "age": "3",
+      "owner": {"first-name": "Ana"}
+    }"#;
+    schema_parser.write_json(&json_str1).unwrap();
+    schema_parser.write_json(&json_str2).unwrap();
+    let schema = schema_parser.flush();
+    let typescript = to_typescript(schema.fields(), "pets", 1.0);
+    assert_eq!(
+      typescript,
+      r#"import { ObjectId } from "bson";
+
+export interface Pets {
+  _id: ObjectId;
+  name: string;
+  age: number | string;
+  tags?: Array<string>;
+  owner?: PetsOwner;
+}
+
+export interface PetsOwner {
+  "first-name": string;
+}
+"#
+    );
+  }
+
+  #[test]
+  fn it_converts_enums_to_literal_types() {
+    let mut schema_parser = SchemaParser::builder().enum_threshold(3).build();
+    for status in &["active", "deleted", "active", "deleted", "active"] {
+      let json = format!(r#"{{"status": "{}", "level": 1}}"#, status);
+      schema_parser.write_json(&json).unwrap();
+    }
+    schema_parser.write_json(r#"{"status": null}"#).unwrap();
+    let schema = schema_parser.flush();
+    let typescript = to_typescript(schema.fields(), "user", 0.5);
+    assert!(typescript.contains("  status: null | \"active\" | \"deleted\";"));
+    assert!(typescript.contains("  level: 1;"));
+  }
+
+  #[test]
+  fn it_converts_names_to_pascal_case() {
+    assert_eq!(pascal_case("owner.address"), "OwnerAddress");
+    assert_eq!(pascal_case("first_name"), "FirstName");
+  }
+}
@@ -0,0 +1,251 @@
+use super::{Bson, Field, FieldType, HashMap, IndexMap};
+use crate::{field_type, wildcard};
+use bson::Document;
+use std::fmt::{self, Display};
+
+/// A way a document doesn't match an inferred Schema.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Violation {
+  /// The document has a field the Schema has never seen.
+  UnknownField { path: String },
+  /// The document has a field with a type the Schema has never seen for it.
+  UnexpectedType { path: String, bson_type: String },
+  /// The document is missing a field that's required by the Schema.
+  MissingField { path: String },
+}
+
+impl Display for Violation {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Violation::UnknownField { path } => write!(f, "Unknown field {}", path),
+      Violation::UnexpectedType { path, bson_type } => {
+        write!(f, "Unexpected type {} for field {}", bson_type, path)
+      }
+      Violation::MissingField { path } => write!(f, "Missing field {}", path),
+    }
+  }
+}
+
+/// Checks a document against finalised fields, including its subdocuments
+/// and the documents in its arrays.
+pub(crate) fn validate(
+  fields: &IndexMap<String, Field>,
+  doc: &Document,
+) -> Vec<Violation> {
+  let mut violations = Vec::new();
+  validate_document(fields, doc, None, &mut violations);
+  violations
+}
+
+fn validate_document(
+  fields: &IndexMap<String, Field>,
+  doc: &Document,
+  path: Option<String>,
+  violations: &mut Vec<Violation>,
+) {
+  for (key, value) in doc {
+    let current_path = Field::get_path(key.to_owned(), path.to_owned());
+    // keys that were coalesced are checked against their wildcard field
+    let field = find_field(fields, &current_path)
+      .or_else(|| fields.get(&wildcard::sibling_path(path.to_owned())));
+    match field {
+      Some(field) => {
+        validate_value(&field.path, &field.types, value, violations)
+      }
+      None => violations.push(Violation::UnknownField { path: current_path }),
+    }
+  }
+  for field in fields.values() {
+    if field.required && !has_field(doc, field) {
+      violations.push(Violation::MissingField {
+        path: field.path.to_string(),
+      });
+    }
+  }
+}
+
+// Fields that were spelled differently, i.e. with `case_insensitive_keys`,
+// match their path in any case.
+fn find_field<'a>(
+  fields: &'a IndexMap<String, Field>,
+  path: &str,
+) -> Option<&'a Field> {
+  fields.get(path).or_else(|| {
+    fields.values().find(|field| {
+      !field.spellings.is_empty() && field.path.eq_ignore_ascii_case(path)
+    })
+  })
+}
+
+fn has_field(doc: &Document, field: &Field) -> bool {
+  if field.spellings.is_empty() {
+    doc.contains_key(&field.name)
+  } else {
+    doc.keys().any(|key| key.eq_ignore_ascii_case(&field.name))
+  }
+}
+
+fn validate_value(
+  path: &str,
+  types: &HashMap<String, FieldType>,
+  value: &Bson,
+  violations: &mut Vec<Violation>,
+) {
+  let bson_type = FieldType::get_type(value);
+  let field_type = match field_type::counted_type(types, &bson_type) {
+    Some(field_type) => field_type,
+    None => {
+      violations.push(Violation::UnexpectedType {
+        path: path.to_string(),
+        bson_type,
+      });
+      return;
+    }
+  };
+  match value {
+    // documents past the max depth weren't analysed
+    Bson::Document(subdoc) => {
+      if let Some(schema) = field_type.schema() {
+        let path = Some(path.to_string());
+        validate_document(&schema.fields, subdoc, path, violations);
+      }
+    }
+    // array elements share the path of their array
+    Bson::Array(elements) => {
+      for element in elements {
+        validate_value(path, &field_type.types, element, violations);
+      }
+    }
+    _ => (),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::SchemaParser;
+  use bson::{bson, doc};
+
+  fn fields() -> IndexMap<String, Field> {
+    let mut schema_parser = SchemaParser::new();
+    let json_str1 = r#"{
+      "name": "Nori",
+      "age": 5,
+      "owner": {"name": "Ana"},
+      "visits": [{"vet": "Rey"}]
+    }"#;
+    let json_str2 = r#"{"name": "Chashu", "age": null, "visits": []}"#;
+    schema_parser.write_json(&json_str1).unwrap();
+    schema_parser.write_json(&json_str2).unwrap();
+    schema_parser.flush().fields
+  }
+
+  #[test]
+  fn it_validates_matching_documents() {
+    let doc = doc! {
+      "name": "Rey",
+      "age": Bson::Null,
+      "owner": { "name": "Ben" },
+      "visits": [{ "vet": "Ana" }]
+    };
+    assert!(validate(&fields(), &doc).is_empty());
+  }
+
+  #[test]
+  fn it_reports_violations() {
+    let doc = doc! {
+      "age": "5",
+      "owner": { "name": "Ben", "phone": "555" },
+      "visits": [{ "vet": 1 }],
+      "color": "black"
+    };
+    assert_eq!(
+      validate(&fields(), &doc),
+      vec![
+        Violation::UnexpectedType {
+          path: "age".to_string(),
+          bson_type: "String".to_string(),
+        },
+        Violation::UnknownField {
+          path: "owner.phone".to_string(),
+        },
+        Violation::UnexpectedType {
+          path: "visits.vet".to_string(),
+          bson_type: "Int32".to_string(),
+        },
+        Violation::UnknownField {
+          path: "color".to_string(),
+        },
+        Violation::MissingField {
+          path: "name".to_string(),
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn it_reports_fields_required_by_the_threshold() {
+    let mut schema_parser =
+      SchemaParser::builder().required_threshold(0.5).build();
+    schema_parser
+      .write_json(r#"{"name": "Nori", "owner": {"name": "Ana"}}"#)
+      .unwrap();
+    schema_parser.write_json(r#"{"name": "Chashu"}"#).unwrap();
+    let fields = schema_parser.flush().fields;
+    assert!(fields["owner"].required);
+    assert_eq!(
+      validate(&fields, &doc! { "owner": {} }),
+      vec![
+        Violation::MissingField {
+          path: "owner.name".to_string(),
+        },
+        Violation::MissingField {
+          path: "name".to_string(),
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn it_validates_dynamic_keys_against_their_wildcard() {
+    let mut schema_parser =
+      SchemaParser::builder().wildcard_threshold(3).build();
+    schema_parser
+      .write_json(r#"{"votes": {"u1": 1, "u2": -1, "u3": 1, "u4": 1}}"#)
+      .unwrap();
+    let fields = schema_parser.flush().fields;
+    let doc = doc! { "votes": { "u5": 1, "u6": "up" } };
+    assert_eq!(
+      validate(&fields, &doc),
+      vec![Violation::UnexpectedType {
+        path: "votes.$*".to_string(),
+        bson_type: "String".to_string(),
+      }]
+    );
+  }
+
+  #[test]
+  fn it_matches_fields_spelled_differently() {
+    let mut schema_parser =
+      SchemaParser::builder().case_insensitive_keys(true).build();
+    schema_parser.write_json(r#"{"userId": 1}"#).unwrap();
+    schema_parser.write_json(r#"{"UserId": 2}"#).unwrap();
+    let fields = schema_parser.flush().fields;
+    assert!(validate(&fields, &doc! { "userID": 3 }).is_empty());
+    assert_eq!(
+      validate(&fields, &doc! { "USERID": "3" }),
+      vec![Violation::UnexpectedType {
+        path: "userId".to_string(),
+        bson_type: "String".to_string(),
+      }]
+    );
+  }
+
+  #[test]
+  fn it_displays_violations() {
+    let violation = Violation::MissingField {
+      path: "owner.name".to_string(),
+    };
+    assert_eq!(violation.to_string(), "Missing field owner.name");
+  }
+}
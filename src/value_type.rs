@@ -7,7 +7,10 @@ pub enum ValueType {
   Decimal128(String),
   FloatingPoint(f64),
   Array(Vec<ValueType>),
-  Binary(Vec<u8>),
   Boolean(bool),
   Null(String),
+  /// Binary data is summarised by its subtype and length, rather than stored.
+  Binary { sub_type: u8, length: usize },
+  Timestamp { t: u32, i: u32 },
+  RegExp { pattern: String, options: String },
 }
@@ -0,0 +1,178 @@
+use super::{Field, FieldType, SchemaParser};
+use crate::field_type;
+
+/// Callbacks for walking a finalised Schema with `Schema::accept()`, i.e. to
+/// render it in an output format of your own. Fields are visited in the order
+/// they were first seen, and the types of a field, or of array elements, in
+/// order of their name. Every callback does nothing unless implemented.
+pub trait SchemaVisitor {
+  /// Called for each field, before its types.
+  fn enter_field(&mut self, _field: &Field) {}
+
+  /// Called for each field, after its types.
+  fn leave_field(&mut self, _field: &Field) {}
+
+  /// Called for each type of a field, including `Undefined` for documents the
+  /// field is missing from, and for each type of array elements.
+  fn enter_type(&mut self, _field_type: &FieldType) {}
+
+  /// Called for each type, after its subdocument or array elements.
+  fn leave_type(&mut self, _field_type: &FieldType) {}
+
+  /// Called for a Document type, before the fields of its subdocuments.
+  fn enter_document(&mut self, _schema: &SchemaParser) {}
+
+  /// Called for a Document type, after the fields of its subdocuments.
+  fn leave_document(&mut self, _schema: &SchemaParser) {}
+
+  /// Called for an Array type, before the types of its elements.
+  fn enter_array(&mut self, _field_type: &FieldType) {}
+
+  /// Called for an Array type, after the types of its elements.
+  fn leave_array(&mut self, _field_type: &FieldType) {}
+}
+
+pub(crate) fn accept<'a, V: SchemaVisitor + ?Sized>(
+  fields: impl Iterator<Item = &'a Field>,
+  visitor: &mut V,
+) {
+  for field in fields {
+    visit_field(field, visitor);
+  }
+}
+
+fn visit_field<V: SchemaVisitor + ?Sized>(field: &Field, visitor: &mut V) {
+  visitor.enter_field(field);
+  for field_type in sorted_types(field.types()) {
+    visit_type(field_type, visitor);
+  }
+  visitor.leave_field(field);
+}
+
+fn visit_type<V: SchemaVisitor + ?Sized>(
+  field_type: &FieldType,
+  visitor: &mut V,
+) {
+  visitor.enter_type(field_type);
+  if let Some(schema) = field_type.schema() {
+    visitor.enter_document(schema);
+    accept(schema.fields(), visitor);
+    visitor.leave_document(schema);
+  }
+  if field_type.bson_type == field_type::ARRAY {
+    visitor.enter_array(field_type);
+    for element_type in sorted_types(field_type.types()) {
+      visit_type(element_type, visitor);
+    }
+    visitor.leave_array(field_type);
+  }
+  visitor.leave_type(field_type);
+}
+
+// Types are kept in a HashMap, so they're sorted to be visited in the same
+// order every time.
+fn sorted_types<'a>(
+  types: impl Iterator<Item = &'a FieldType>,
+) -> Vec<&'a FieldType> {
+  let mut types: Vec<&FieldType> = types.collect();
+  types.sort_by(|a, b| a.bson_type.cmp(&b.bson_type));
+  types
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Writes an indented line per field and type.
+  #[derive(Default)]
+  struct Outline {
+    depth: usize,
+    lines: Vec<String>,
+  }
+
+  impl Outline {
+    fn push(&mut self, line: String) {
+      let indent = "  ".repeat(self.depth);
+      self.lines.push(format!("{}{}", indent, line));
+    }
+  }
+
+  impl SchemaVisitor for Outline {
+    fn enter_field(&mut self, field: &Field) {
+      self.push(field.path.to_string());
+      self.depth += 1;
+    }
+
+    fn leave_field(&mut self, _field: &Field) {
+      self.depth -= 1;
+    }
+
+    fn enter_type(&mut self, field_type: &FieldType) {
+      self.push(field_type.bson_type.clone());
+    }
+
+    fn enter_document(&mut self, schema: &SchemaParser) {
+      self.push(format!("{{{}}}", schema.count));
+      self.depth += 1;
+    }
+
+    fn leave_document(&mut self, _schema: &SchemaParser) {
+      self.depth -= 1;
+    }
+
+    fn enter_array(&mut self, _field_type: &FieldType) {
+      self.push("[".to_string());
+      self.depth += 1;
+    }
+
+    fn leave_array(&mut self, _field_type: &FieldType) {
+      self.depth -= 1;
+    }
+  }
+
+  #[test]
+  fn it_visits_fields_types_documents_and_arrays() {
+    let mut schema_parser = SchemaParser::new();
+    schema_parser
+      .write_json(r#"{"name": "Nori", "owner": {"name": "Ana"}}"#)
+      .unwrap();
+    schema_parser
+      .write_json(r#"{"name": null, "visits": [{"vet": "Ben"}, 3]}"#)
+      .unwrap();
+    let mut outline = Outline::default();
+    accept(schema_parser.flush().fields(), &mut outline);
+    assert_eq!(
+      outline.lines,
+      vec![
+        "name",
+        "  Null",
+        "  String",
+        "owner",
+        "  Document",
+        "  {1}",
+        "    owner.name",
+        "      String",
+        "  Undefined",
+        "visits",
+        "  Array",
+        "  [",
+        "    Document",
+        "    {1}",
+        "      visits.vet",
+        "        String",
+        "    Int32",
+        "  Undefined",
+      ]
+    );
+  }
+
+  #[test]
+  fn it_calls_nothing_by_default() {
+    struct Nothing;
+    impl SchemaVisitor for Nothing {}
+
+    let mut schema_parser = SchemaParser::new();
+    schema_parser.write_json(r#"{"tags": [{"a": 1}]}"#).unwrap();
+    accept(schema_parser.flush().fields(), &mut Nothing);
+  }
+}
@@ -0,0 +1,170 @@
+use super::{Field, IndexMap};
+use crate::transform;
+use std::collections::HashSet;
+
+/// Name of the field that dynamic keys are coalesced into, i.e. `votes.$*`.
+pub static WILDCARD: &str = "$*";
+
+/// Takes the largest group of more than `threshold` sibling fields with the
+/// same types out of `fields`, and merges them into a single wildcard field.
+/// Such fields are most likely values used as keys, i.e. the user ids of
+/// `votes.<userId>: 1`. A wildcard field already in `fields`, i.e. of a
+/// restored Schema, is taken out too, whether or not there's a threshold.
+/// Returns None, leaving `fields` as they are, if there's nothing to merge.
+/// Fields must not be finalised yet.
+pub(crate) fn coalesce(
+  fields: &mut IndexMap<String, Field>,
+  threshold: Option<usize>,
+) -> Option<Field> {
+  let mut keys: HashSet<String> = fields
+    .values()
+    .filter(|field| &*field.name == WILDCARD)
+    .map(|field| field.path.to_string())
+    .collect();
+  if let Some(threshold) = threshold {
+    keys.extend(dynamic_keys(fields, threshold));
+  }
+  if keys.is_empty() {
+    return None;
+  }
+
+  let (dynamic, rest): (IndexMap<String, Field>, IndexMap<String, Field>) =
+    std::mem::replace(fields, IndexMap::new())
+      .into_iter()
+      .partition(|(key, _)| keys.contains(key));
+  *fields = rest;
+
+  let mut wildcard: Option<Field> = None;
+  for (_, mut field) in dynamic {
+    let prefix_len = field.path.len() - field.name.len();
+    let path = format!("{}{}", &field.path[..prefix_len], WILDCARD);
+    let key_path = field.path.clone();
+    transform::repath_field(&mut field, &key_path, &path);
+    field.name = WILDCARD.into();
+    match wildcard.as_mut() {
+      Some(wildcard) => wildcard.merge(field),
+      None => wildcard = Some(field),
+    }
+  }
+  wildcard
+}
+
+// Keys of the largest group of more than `threshold` fields with the same
+// types.
+fn dynamic_keys(
+  fields: &IndexMap<String, Field>,
+  threshold: usize,
+) -> Vec<String> {
+  let mut shapes: IndexMap<Vec<&str>, Vec<&str>> = IndexMap::new();
+  for (key, field) in fields {
+    let mut shape: Vec<&str> =
+      field.bson_types.iter().map(String::as_str).collect();
+    shape.sort();
+    shapes.entry(shape).or_insert_with(Vec::new).push(key);
+  }
+  shapes
+    .into_iter()
+    .map(|(_, keys)| keys)
+    .filter(|keys| keys.len() > threshold)
+    .max_by_key(|keys| keys.len())
+    .unwrap_or_default()
+    .into_iter()
+    .map(String::from)
+    .collect()
+}
+
+/// Finalises a wildcard field. A document can have many of the coalesced
+/// keys, so the field's types are relative to all their values, as with the
+/// types of array elements, and its count is the number of values. Which
+/// documents had any of the keys isn't known once they're merged, so it
+/// doesn't have a probability: it's left at 0.0, and the field is never
+/// required.
+pub(crate) fn finalise(field: &mut Field) {
+  let values = field.types().map(|field_type| field_type.count).sum();
+  field.count = values;
+  field.finalise_field(values);
+  field.probability = 0.0;
+  field.required = false;
+}
+
+/// Path of the wildcard field among the siblings of `path`, i.e. `votes.$*`
+/// for `votes.u1`.
+pub(crate) fn sibling_path(path: Option<String>) -> String {
+  Field::get_path(WILDCARD.to_string(), path)
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::SchemaParser;
+
+  fn schema_parser(documents: &[&str]) -> SchemaParser {
+    let mut schema_parser =
+      SchemaParser::builder().wildcard_threshold(3).build();
+    for json in documents {
+      schema_parser.write_json(json).unwrap();
+    }
+    schema_parser
+  }
+
+  #[test]
+  #[allow(clippy::float_cmp)]
+  fn it_coalesces_dynamic_keys() {
+    let schema = schema_parser(&[
+      r#"{"votes": {"u1": 1, "u2": -1, "u3": 1, "u4": 1, "note": "x"}}"#,
+      r#"{"votes": {"u5": 1}}"#,
+    ])
+    .flush();
+    let votes = schema.get_field("votes").unwrap().types["Document"]
+      .schema()
+      .unwrap();
+    let paths: Vec<&str> = votes.fields().map(|field| &*field.path).collect();
+    assert_eq!(paths, vec!["votes.note", "votes.$*"]);
+
+    let wildcard = schema.get_field("votes.$*").unwrap();
+    assert_eq!(&*wildcard.name, "$*");
+    assert_eq!(wildcard.count, 5);
+    assert_eq!(wildcard.probability, 0.0);
+    assert!(!wildcard.required);
+    let int = &wildcard.types["Int32"];
+    assert_eq!(int.count, 5);
+    assert_eq!(int.probability, 1.0);
+    assert_eq!(&*int.path, "votes.$*");
+  }
+
+  #[test]
+  fn it_coalesces_subdocuments() {
+    let schema = schema_parser(&[
+      r#"{"s1": {"start": 1}, "s2": {"start": 2}, "s3": {"end": 3}}"#,
+      r#"{"s4": {"start": 4}}"#,
+    ])
+    .flush();
+    let paths: Vec<&str> = schema.iter_fields().map(|(path, _)| path).collect();
+    assert_eq!(paths, vec!["$*", "$*.start", "$*.end"]);
+  }
+
+  #[test]
+  #[allow(clippy::float_cmp)]
+  fn it_finalises_restored_wildcards() {
+    let schema =
+      schema_parser(&[r#"{"u1": 1, "u2": 1, "u3": 1, "u4": 1}"#]).flush();
+    let union = schema.union(&schema);
+    assert_eq!(union.count, 2);
+    let wildcard = union.get_field("$*").unwrap();
+    assert_eq!(wildcard.count, 8);
+    assert_eq!(wildcard.probability, 0.0);
+    let int = &wildcard.types["Int32"];
+    assert_eq!(int.count, 8);
+    assert_eq!(int.probability, 1.0);
+  }
+
+  #[test]
+  fn it_leaves_keys_alone_below_the_threshold() {
+    let schema_parser = schema_parser(&[r#"{"a": 1, "b": 2, "c": 3}"#]);
+    assert_eq!(schema_parser.flush().fields().count(), 3);
+    let mut schema_parser = SchemaParser::new();
+    schema_parser
+      .write_json(r#"{"a": 1, "b": 2, "c": 3, "d": 4, "e": 5}"#)
+      .unwrap();
+    assert_eq!(schema_parser.flush().fields().count(), 5);
+  }
+}
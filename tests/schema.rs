@@ -1,3 +1,5 @@
+#![cfg(feature = "wasm")]
+
 use wasm_bindgen_test::*;
 use mongodb_schema_parser::SchemaParser;
 use web_sys::console;